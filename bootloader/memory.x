@@ -0,0 +1,17 @@
+MEMORY {
+    BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
+    /* up to the boot state sector, pico_ui_core::update::layout::PICO_LAYOUT */
+    FLASH : ORIGIN = 0x10000100, LENGTH = 32K - 0x100
+    /* the last 256 bytes hold the crash record of the firmware, kept over the reboot */
+    RAM   : ORIGIN = 0x20000000, LENGTH = 256K - 256
+}
+
+EXTERN(BOOT2_FIRMWARE)
+
+SECTIONS {
+    /* ### Boot loader */
+    .boot2 ORIGIN(BOOT2) :
+    {
+        KEEP(*(.boot2));
+    } > BOOT2
+} INSERT BEFORE .text;
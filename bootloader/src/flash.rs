@@ -0,0 +1,111 @@
+use core::ptr;
+use rp2040_hal::rom_data;
+use pico_ui_core::settings::flash::Flash;
+use pico_ui_core::update::layout::{PICO_LAYOUT, XIP_BASE};
+
+const SECTOR_BYTES: u32 = 4096;
+const PAGE_BYTES: usize = 256;
+/// Erases a sector at a time when the range is not 64 KB aligned, like the pico-sdk calls it
+const BLOCK_BYTES: u32 = 1 << 16;
+const BLOCK_ERASE_COMMAND: u8 = 0xd8;
+
+/// Flash up to the end of the update partition through the boot ROM routines, offsets are from
+/// the start of flash like in `PICO_LAYOUT`. Only one core runs, nothing has to be parked.
+pub struct BootFlash;
+
+#[derive(Debug, Clone, Copy)]
+pub enum BootFlashError {
+    OutOfBounds,
+}
+
+/// Looked up while flash is still readable, called from RAM
+struct RomFunctions {
+    connect_internal_flash: unsafe extern "C" fn(),
+    flash_exit_xip: unsafe extern "C" fn(),
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: unsafe extern "C" fn(),
+}
+
+enum Operation {
+    Erase(u32),
+    /// A page, the pointer is to RAM
+    Program(u32, *const u8),
+}
+
+impl BootFlash {
+    fn check_bounds(offset: u32, len: usize) -> Result<(), BootFlashError> {
+        if offset as usize + len > (PICO_LAYOUT.update + PICO_LAYOUT.partition_bytes) as usize {
+            return Err(BootFlashError::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    fn run(operation: Operation) {
+        let rom = RomFunctions {
+            connect_internal_flash: rom_data::connect_internal_flash::ptr(),
+            flash_exit_xip: rom_data::flash_exit_xip::ptr(),
+            flash_range_erase: rom_data::flash_range_erase::ptr(),
+            flash_range_program: rom_data::flash_range_program::ptr(),
+            flash_flush_cache: rom_data::flash_flush_cache::ptr(),
+        };
+        // our own boot2 sets up fast reads again afterwards
+        let mut boot2 = [0u32; 64];
+        unsafe { ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), boot2.len()) };
+        cortex_m::interrupt::free(|_| unsafe { flash_operation(&rom, &operation, boot2.as_ptr()) });
+    }
+}
+
+impl Flash for BootFlash {
+    type Error = BootFlashError;
+    const SECTOR_SIZE: u32 = SECTOR_BYTES;
+
+    fn sectors(&self) -> u32 {
+        (PICO_LAYOUT.update + PICO_LAYOUT.partition_bytes) / SECTOR_BYTES
+    }
+
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        Self::check_bounds(offset, buffer.len())?;
+        let source = (XIP_BASE + offset) as *const u8;
+        unsafe { ptr::copy_nonoverlapping(source, buffer.as_mut_ptr(), buffer.len()) };
+        Ok(())
+    }
+
+    fn erase(&mut self, sector: u32) -> Result<(), Self::Error> {
+        Self::check_bounds(sector * SECTOR_BYTES, SECTOR_BYTES as usize)?;
+        Self::run(Operation::Erase(sector * SECTOR_BYTES));
+        Ok(())
+    }
+
+    /// The ROM programs whole pages, bytes around `data` are programmed as 0xff which keeps them
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        Self::check_bounds(offset, data.len())?;
+        let mut written = 0;
+        while written < data.len() {
+            let address = offset as usize + written;
+            let page_start = address - address % PAGE_BYTES;
+            let in_page = (PAGE_BYTES - address % PAGE_BYTES).min(data.len() - written);
+            let mut page = [0xffu8; PAGE_BYTES];
+            page[address - page_start..address - page_start + in_page].copy_from_slice(&data[written..written + in_page]);
+            Self::run(Operation::Program(page_start as u32, page.as_ptr()));
+            written += in_page;
+        }
+        Ok(())
+    }
+}
+
+/// Nothing here may run from flash, XIP is off until boot2 ran again
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_operation(rom: &RomFunctions, operation: &Operation, boot2: *const u32) {
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    match *operation {
+        Operation::Erase(address) => (rom.flash_range_erase)(address, SECTOR_BYTES as usize, BLOCK_BYTES, BLOCK_ERASE_COMMAND),
+        Operation::Program(address, page) => (rom.flash_range_program)(address, page, PAGE_BYTES),
+    }
+    (rom.flash_flush_cache)();
+    // thumb bit set
+    let enter_xip: extern "C" fn() = core::mem::transmute(boot2 as usize + 1);
+    enter_xip();
+}
@@ -0,0 +1,68 @@
+//! Runs before the firmware on every boot. Installs an update the firmware staged and asked for,
+//! puts the previous firmware back when the new one reset before confirming itself, then starts
+//! the image in the active partition. The work itself is `pico_ui_core::update::swap`.
+#![no_std]
+#![no_main]
+
+mod flash;
+
+extern crate cortex_m;
+extern crate cortex_m_rt;
+extern crate rp2040_boot2;
+extern crate rp2040_hal;
+extern crate pico_ui_core;
+
+use core::panic::PanicInfo;
+use cortex_m::peripheral::SCB;
+use rp2040_hal::pac;
+use flash::BootFlash;
+use pico_ui_core::update::layout::{PICO_LAYOUT, XIP_BASE};
+use pico_ui_core::update::swap::{prepare_boot, BootAction};
+
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_GENERIC_03H;
+
+/// `WATCHDOG_TICK` cycles per microsecond, clk_ref still runs from the ring oscillator here
+const ROSC_TICK_CYCLES: u16 = 6;
+/// Longest watchdog period, about 8 s: the counter goes down by 2 per tick (RP2040-E1)
+const TRIAL_WATCHDOG_LOAD: u32 = 0xff_ffff;
+
+#[rp2040_hal::entry]
+fn main() -> ! {
+    let pac = pac::Peripherals::take().unwrap();
+
+    // still counting after a watchdog reset, a swap takes far longer than its period
+    pac.WATCHDOG.ctrl().modify(|_, w| w.enable().clear_bit());
+
+    // a swap cut by power loss goes on here on the next boot, the firmware is started anyway
+    // when the flash can't be read
+    let action = prepare_boot(&mut BootFlash, &PICO_LAYOUT).unwrap_or(BootAction::Run);
+    if action == BootAction::Trial {
+        start_trial_watchdog(&pac.WATCHDOG, &pac.PSM);
+    }
+    unsafe { start_firmware() }
+}
+
+/// A new image that hangs before its own watchdog runs still resets the board, the next boot
+/// rolls it back. The firmware takes the watchdog over with its own period.
+fn start_trial_watchdog(watchdog: &pac::WATCHDOG, psm: &pac::PSM) {
+    watchdog.tick().write(|w| unsafe { w.cycles().bits(ROSC_TICK_CYCLES).enable().set_bit() });
+    // everything but the oscillators, like `hal::Watchdog::start`
+    psm.wdsel().write(|w| unsafe { w.bits(0x0001_ffff) }.xosc().clear_bit().rosc().clear_bit());
+    watchdog.load().write(|w| unsafe { w.bits(TRIAL_WATCHDOG_LOAD) });
+    watchdog.ctrl().modify(|_, w| w.enable().set_bit());
+}
+
+/// Jump to the reset handler of the active image with its vector table and stack
+unsafe fn start_firmware() -> ! {
+    let vector_table = XIP_BASE + PICO_LAYOUT.active;
+    (*SCB::PTR).vtor.write(vector_table);
+    cortex_m::asm::bootload(vector_table as *const u32)
+}
+
+/// Every step of `prepare_boot` can be redone, starting over is the way out
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    SCB::sys_reset()
+}
@@ -0,0 +1,783 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{Read, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+use pico_ui_client::{
+    Alert, Bitmap, ClientError, DeviceEvent, EccLevel, FirmwareEvent, FirmwareImage, ImageError, KeyEvent, LedId, LedPattern,
+    LineStyle, LinkState, PicoClient, ScreenUpdate, SettingKey, StatsGroup, UartControl, UartEvent, UartFormat, UartParity, BAUD_RATE,
+    DEFAULT_TOAST_MS, HEARTBEAT_INTERVAL_MS,
+};
+
+use CliError;
+
+const DEFAULT_BEEP_HZ: u16 = 2000;
+const DEFAULT_BEEP_MS: u32 = 100;
+const DEFAULT_PING_TIMEOUT_MS: u64 = 1000;
+const LAYOUT_RESULT_TIMEOUT_MS: u64 = 1000;
+/// Added to the time the image data takes on the wire
+const IMAGE_RESULT_TIMEOUT_MS: u64 = 1000;
+/// Failures come back quickly, silence means the image is on screen
+const IMAGE_SHOW_TIMEOUT_MS: u64 = 300;
+const QR_RESULT_TIMEOUT_MS: u64 = 1000;
+/// Includes a flash sector erase when the store compacts
+const SETTING_RESULT_TIMEOUT_MS: u64 = 1000;
+const FIRMWARE_STATUS_TIMEOUT_MS: u64 = 1000;
+/// For all groups, they go out one after the other
+const STATS_TIMEOUT_MS: u64 = 1000;
+
+pub fn send_screen<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let mut update = ScreenUpdate::new();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--status" => {
+                let value = option_value(&mut args, "--status")?;
+                let (ip, battery) = value.rsplit_once(':')
+                    .ok_or_else(|| usage("--status expects <ip>:<battery>"))?;
+                update = update.status(ip, parse_number(battery, "--status battery")?);
+            }
+            "--title" => {
+                let value = option_value(&mut args, "--title")?;
+                let mut parts = value.rsplitn(3, ':');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(pages), Some(page), Some(title)) => {
+                        update = update.title(title, parse_number(page, "--title page")?, parse_number(pages, "--title pages")?);
+                    }
+                    _ => return Err(usage("--title expects <title>:<page>:<pages>")),
+                }
+            }
+            "--cursor" => {
+                update = update.cursor(parse_number(option_value(&mut args, "--cursor")?, "--cursor")?);
+            }
+            "--style" => {
+                let value = option_value(&mut args, "--style")?;
+                let (line, style) = value.split_once('|').unwrap_or((value, ""));
+                let style = LineStyle::parse(style)
+                    .map_err(|err| usage(&format!("--style {:?}: {:?}", value, err)))?;
+                update = update.line_style(parse_number(line, "--style line")?, style);
+            }
+            line => lines.push(line),
+        }
+    }
+    if !lines.is_empty() {
+        update = update.lines(&lines);
+    }
+    client.send_screen(&update)?;
+    Ok(())
+}
+
+/// `show-list [--title <title>] [--cursor <n>] <item>...`, `show-list --close`
+pub fn show_list<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let mut title = "";
+    let mut cursor = 0;
+    let mut items: Vec<&str> = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--close" => {
+                client.close_list()?;
+                return Ok(());
+            }
+            "--title" => title = option_value(&mut args, "--title")?,
+            "--cursor" => cursor = parse_number(option_value(&mut args, "--cursor")?, "--cursor")?,
+            item => items.push(item),
+        }
+    }
+    client.show_list(title, &items, cursor)?;
+    Ok(())
+}
+
+/// `widget <kind> [args]`, see usage for kinds
+pub fn widget<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let kind = args.first().map(String::as_str).unwrap_or("");
+    let args: Vec<&str> = args.iter().skip(1).map(String::as_str).collect();
+    let label = |index: usize| args.get(index).copied().unwrap_or("");
+    match (kind, args.len()) {
+        ("progress", 1..=2) => client.show_progress(parse_number(args[0], "percent")?, label(1))?,
+        ("gauge", 3..=4) => client.show_gauge(
+            parse_number(args[0], "value")?,
+            parse_number(args[1], "min")?,
+            parse_number(args[2], "max")?,
+            label(3),
+        )?,
+        ("dialog", 1) => client.ask(args[0])?,
+        ("spinner", 3..=5) => client.show_spinner(
+            parse_number(args[0], "value")?,
+            parse_number(args[1], "min")?,
+            parse_number(args[2], "max")?,
+            args.get(3).map(|step| parse_number(step, "step")).unwrap_or(Ok(1))?,
+            label(4),
+        )?,
+        ("toast", 1..=2) => client.toast(
+            args[0],
+            args.get(1).map(|ms| parse_number(ms, "duration")).unwrap_or(Ok(DEFAULT_TOAST_MS))?,
+        )?,
+        ("battery", 1) if args[0] == "off" => client.set_battery_icon(None)?,
+        ("battery", 1) => client.set_battery_icon(Some(parse_number(args[0], "percent")?))?,
+        ("close", 0) => client.close_widget()?,
+        _ => return Err(usage("widget expects progress, gauge, dialog, spinner, toast, battery or close with its arguments")),
+    }
+    Ok(())
+}
+
+/// `layout <node>...`, `layout --set <index> <property=value>...`, `layout --clear`.
+/// Nodes are sent as written (`text|0,0,128,16|text=hi`), the device answer is printed.
+pub fn layout<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    match args.first().map(String::as_str) {
+        None => return Err(usage("layout expects nodes, --set or --clear")),
+        Some("--clear") => {
+            client.clear_layout()?;
+            return Ok(());
+        }
+        Some("--set") => {
+            let index = parse_number(args.get(1).ok_or_else(|| usage("--set needs a node index"))?, "--set")?;
+            client.update_layout_node(index, &args[2..])?;
+        }
+        Some(_) => {
+            if args.iter().any(|node| node.contains(';')) {
+                return Err(usage("one node per argument, without ';'"));
+            }
+            client.send_line(&format!("layout={}", args.join(";")))?;
+        }
+    }
+
+    let sent = Instant::now();
+    while sent.elapsed() < Duration::from_millis(LAYOUT_RESULT_TIMEOUT_MS) {
+        match client.read_event() {
+            Ok(Some(DeviceEvent::LayoutAccepted)) => {
+                println!("layout accepted");
+                return Ok(());
+            }
+            Ok(Some(DeviceEvent::LayoutRejected(err))) => {
+                return Err(CliError::Device(format!("layout rejected: {}", err)));
+            }
+            Ok(_) => {}
+            Err(err) => report_error(err)?,
+        }
+    }
+    Err(CliError::Device(format!("no layout answer in {} ms", LAYOUT_RESULT_TIMEOUT_MS)))
+}
+
+/// `qr <text> [--ecc l|m|q|h]`, `qr --close`. The device encodes the text, its answer is printed.
+pub fn qr<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let mut text: Option<&str> = None;
+    let mut ecc = EccLevel::Medium;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--close" => {
+                client.close_qr()?;
+                return Ok(());
+            }
+            "--ecc" => {
+                let value = option_value(&mut args, "--ecc")?;
+                ecc = EccLevel::try_from(value).map_err(|_| usage(&format!("--ecc expects l, m, q or h: {}", value)))?;
+            }
+            other if text.is_none() => text = Some(other),
+            other => return Err(usage(&format!("unknown qr argument: {}", other))),
+        }
+    }
+    client.show_qr(text.ok_or_else(|| usage("qr expects text or --close"))?, ecc)?;
+
+    let sent = Instant::now();
+    while sent.elapsed() < Duration::from_millis(QR_RESULT_TIMEOUT_MS) {
+        match client.read_event() {
+            Ok(Some(DeviceEvent::QrShown)) => {
+                println!("qr shown");
+                return Ok(());
+            }
+            Ok(Some(DeviceEvent::QrFailed(err))) => return Err(CliError::Device(format!("qr failed: {}", err.code()))),
+            Ok(_) => {}
+            Err(err) => report_error(err)?,
+        }
+    }
+    Err(CliError::Device(format!("no qr answer in {} ms", QR_RESULT_TIMEOUT_MS)))
+}
+
+/// `image send <id> <file.ppm|file.pbm>`, `image show <id> <x> <y>`, `image hide <id|all>`, `image delete <id>`.
+/// Uploads resume once from where the device lost data.
+pub fn image<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["send", id, path] => {
+            let id = parse_number(id, "id")?;
+            let bytes = fs::read(path).map_err(|err| CliError::Device(format!("can not read {}: {}", path, err)))?;
+            let bitmap = Bitmap::from_netpbm(&bytes)?;
+            client.send_image(id, &bitmap)?;
+            // base64 and line framing, 10 bits per byte on the wire
+            let wire_ms = (bitmap.data_len() as u64 * 4 / 3 + 64) * 10 * 1000 / BAUD_RATE as u64;
+            let timeout = Duration::from_millis(wire_ms + IMAGE_RESULT_TIMEOUT_MS);
+            let mut resumed = false;
+            loop {
+                match wait_image_result(client, id, timeout)? {
+                    None => return Err(CliError::Device(format!("no image answer in {} ms", timeout.as_millis()))),
+                    Some(Ok(())) => {
+                        println!("image {} stored, {}x{}", id, bitmap.width(), bitmap.height());
+                        return Ok(());
+                    }
+                    Some(Err(err)) if !resumed => {
+                        eprintln!("image {}: {}, sending again", id, err);
+                        resumed = true;
+                        match err {
+                            ImageError::Offset(offset) | ImageError::Incomplete(offset) => {
+                                client.resume_image(id, &bitmap, offset as usize)?;
+                            }
+                            _ => client.send_image(id, &bitmap)?,
+                        }
+                    }
+                    Some(Err(err)) => return Err(CliError::Device(format!("image {} failed: {}", id, err))),
+                }
+            }
+        }
+        ["show", id, x, y] => {
+            let id = parse_number(id, "id")?;
+            client.show_image(id, parse_number(x, "x")?, parse_number(y, "y")?)?;
+            if let Some(Err(err)) = wait_image_result(client, id, Duration::from_millis(IMAGE_SHOW_TIMEOUT_MS))? {
+                return Err(CliError::Device(format!("image {} not shown: {}", id, err)));
+            }
+        }
+        ["hide", "all"] => client.hide_all_images()?,
+        ["hide", id] => client.hide_image(parse_number(id, "id")?)?,
+        ["delete", id] => client.delete_image(parse_number(id, "id")?)?,
+        _ => return Err(usage("image expects send, show, hide or delete with its arguments")),
+    }
+    Ok(())
+}
+
+/// Answer about image `id`, `None` when nothing came in `timeout`.
+/// An `offset` error is often followed by more of them for the chunks after it, the first one counts.
+fn wait_image_result<T: Read + Write>(
+    client: &mut PicoClient<T>,
+    id: u8,
+    timeout: Duration,
+) -> Result<Option<Result<(), ImageError>>, CliError> {
+    let sent = Instant::now();
+    let mut result = None;
+    while sent.elapsed() < timeout {
+        match client.read_event() {
+            Ok(Some(DeviceEvent::ImageStored(event_id))) if event_id == id => return Ok(Some(Ok(()))),
+            Ok(Some(DeviceEvent::ImageFailed(event_id, err))) if event_id == id => {
+                if !matches!(err, ImageError::Offset(_)) {
+                    return Ok(Some(result.unwrap_or(Err(err))));
+                }
+                result.get_or_insert(Err(err));
+            }
+            Ok(_) => {}
+            Err(err) => report_error(err)?,
+        }
+    }
+    Ok(result)
+}
+
+/// Print key events, one per line. Bad frames are reported on stderr and do not stop watching.
+/// Device heartbeats are printed only when the link state they carry changes.
+pub fn watch_keys<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let mut count: Option<u64> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => count = Some(parse_number(option_value(&mut args, "--count")?, "--count")?),
+            other => return Err(usage(&format!("unknown watch-keys argument: {}", other))),
+        }
+    }
+
+    let started = Instant::now();
+    let mut seen = 0;
+    let mut link_state: Option<LinkState> = None;
+    while count.is_none_or(|count| seen < count) {
+        match client.read_event() {
+            Ok(Some(DeviceEvent::Key(key))) => {
+                println!("{:>8.3}s  {}", started.elapsed().as_secs_f32(), format_key(&key));
+                seen += 1;
+            }
+            Ok(Some(DeviceEvent::Pong(token))) => println!("{:>8.3}s  pong {}", started.elapsed().as_secs_f32(), token),
+            Ok(Some(DeviceEvent::ListSelected(index))) => println!("{:>8.3}s  list selected {}", started.elapsed().as_secs_f32(), index),
+            Ok(Some(DeviceEvent::ListPage(page))) => println!("{:>8.3}s  list page {}", started.elapsed().as_secs_f32(), page + 1),
+            Ok(Some(DeviceEvent::DialogAnswer(answer))) => {
+                println!("{:>8.3}s  dialog {}", started.elapsed().as_secs_f32(), if answer { "yes" } else { "no" });
+            }
+            Ok(Some(DeviceEvent::SpinnerValue(value))) => println!("{:>8.3}s  spinner {}", started.elapsed().as_secs_f32(), value),
+            Ok(Some(DeviceEvent::LayoutAccepted)) => println!("{:>8.3}s  layout accepted", started.elapsed().as_secs_f32()),
+            Ok(Some(DeviceEvent::LayoutRejected(err))) => println!("{:>8.3}s  layout rejected: {}", started.elapsed().as_secs_f32(), err),
+            Ok(Some(DeviceEvent::ImageStored(id))) => println!("{:>8.3}s  image {} stored", started.elapsed().as_secs_f32(), id),
+            Ok(Some(DeviceEvent::ImageFailed(id, err))) => println!("{:>8.3}s  image {} failed: {}", started.elapsed().as_secs_f32(), id, err),
+            Ok(Some(DeviceEvent::QrShown)) => println!("{:>8.3}s  qr shown", started.elapsed().as_secs_f32()),
+            Ok(Some(DeviceEvent::QrFailed(err))) => println!("{:>8.3}s  qr failed: {}", started.elapsed().as_secs_f32(), err.code()),
+            Ok(Some(DeviceEvent::Restarted(reason))) => println!("{:>8.3}s  device restarted: {}", started.elapsed().as_secs_f32(), reason),
+            Ok(Some(DeviceEvent::Crashed(crash))) => println!("{:>8.3}s  device crashed: {}", started.elapsed().as_secs_f32(), crash),
+            Ok(Some(DeviceEvent::Setting(key, value))) => println!("{:>8.3}s  setting {} = {}", started.elapsed().as_secs_f32(), key.as_str(), value),
+            Ok(Some(DeviceEvent::SettingsReset)) => println!("{:>8.3}s  settings reset", started.elapsed().as_secs_f32()),
+            Ok(Some(DeviceEvent::SettingFailed(err))) => println!("{:>8.3}s  setting failed: {}", started.elapsed().as_secs_f32(), err),
+            Ok(Some(DeviceEvent::Firmware(event))) => println!("{:>8.3}s  firmware {}", started.elapsed().as_secs_f32(), event),
+            Ok(Some(DeviceEvent::FirmwareFailed(err))) => println!("{:>8.3}s  firmware failed: {}", started.elapsed().as_secs_f32(), err),
+            Ok(Some(DeviceEvent::Uart(UartEvent::BadValue))) => println!("{:>8.3}s  uart bad value", started.elapsed().as_secs_f32()),
+            Ok(Some(DeviceEvent::Uart(event))) => {
+                if let Some(format) = event.format() {
+                    println!("{:>8.3}s  uart {} {}", started.elapsed().as_secs_f32(), event.code(), format.to_text());
+                }
+            }
+            Ok(Some(DeviceEvent::Stats(report))) => println!("{:>8.3}s  stats {}", started.elapsed().as_secs_f32(), report.to_text()),
+            Ok(Some(DeviceEvent::Heartbeat { link, .. })) => {
+                if link_state != Some(link) {
+                    println!("{:>8.3}s  link {}", started.elapsed().as_secs_f32(), link.as_str());
+                    link_state = Some(link);
+                }
+            }
+            Ok(Some(DeviceEvent::Other(message))) => println!("{:>8.3}s  {:?}", started.elapsed().as_secs_f32(), message),
+            Ok(None) => {}
+            Err(err) => report_error(err)?,
+        }
+    }
+    Ok(())
+}
+
+pub fn set_led<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    match args {
+        [led, pattern] => {
+            let led = LedId::try_from(led.as_str()).map_err(|_| usage(&format!("unknown led: {}", led)))?;
+            let pattern = LedPattern::try_from(pattern.as_str()).map_err(|_| usage(&format!("bad pattern: {}", pattern)))?;
+            client.set_led(led, pattern)?;
+            Ok(())
+        }
+        _ => Err(usage("set-led expects <led> <pattern>")),
+    }
+}
+
+pub fn beep<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    match args.first().map(String::as_str) {
+        Some("--melody") => match args.get(1) {
+            Some(rtttl) => client.play_melody(rtttl)?,
+            None => return Err(usage("--melody needs an rtttl string")),
+        },
+        Some("--alert") => {
+            let alert = match args.get(1).map(String::as_str) {
+                Some("notify") => Alert::Notify,
+                Some("success") => Alert::Success,
+                Some("error") => Alert::Error,
+                _ => return Err(usage("--alert expects notify, success or error")),
+            };
+            client.alert(alert)?;
+        }
+        Some("--off") => client.stop_buzzer()?,
+        _ => {
+            let freq_hz = match args.first() {
+                Some(freq_hz) => parse_number(freq_hz, "frequency")?,
+                None => DEFAULT_BEEP_HZ,
+            };
+            let duration_ms = match args.get(1) {
+                Some(duration_ms) => parse_number(duration_ms, "duration")?,
+                None => DEFAULT_BEEP_MS,
+            };
+            client.beep(freq_hz, duration_ms)?;
+        }
+    }
+    Ok(())
+}
+
+/// Round trip time of `ping=<token>` / `pong=<token>`. Key events arriving meanwhile are skipped.
+pub fn ping<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let mut count: u32 = 1;
+    let mut timeout = Duration::from_millis(DEFAULT_PING_TIMEOUT_MS);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => count = parse_number(option_value(&mut args, "--count")?, "--count")?,
+            "--timeout" => timeout = Duration::from_millis(parse_number(option_value(&mut args, "--timeout")?, "--timeout")?),
+            other => return Err(usage(&format!("unknown ping argument: {}", other))),
+        }
+    }
+
+    let mut lost = 0;
+    for seq in 0..count {
+        let token = format!("{}-{}", process::id() % 10_000, seq);
+        let sent = Instant::now();
+        client.ping(&token)?;
+        let mut answered = false;
+        while sent.elapsed() < timeout {
+            match client.read_event() {
+                Ok(Some(DeviceEvent::Pong(pong))) if pong == token => {
+                    println!("pong {}: {:.1} ms", token, sent.elapsed().as_secs_f64() * 1000.0);
+                    answered = true;
+                    break;
+                }
+                Ok(_) => {}
+                Err(err) => report_error(err)?,
+            }
+        }
+        if !answered {
+            println!("ping {}: no answer in {} ms", token, timeout.as_millis());
+            lost += 1;
+        }
+    }
+    if lost > 0 {
+        return Err(CliError::Device(format!("{} of {} pings lost", lost, count)));
+    }
+    Ok(())
+}
+
+/// Send heartbeats every `HEARTBEAT_INTERVAL_MS` and print the ones coming back with the link state
+/// the device sees. Device heartbeats missing for 3 intervals are reported.
+pub fn heartbeat<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let mut count: Option<u64> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => count = Some(parse_number(option_value(&mut args, "--count")?, "--count")?),
+            other => return Err(usage(&format!("unknown heartbeat argument: {}", other))),
+        }
+    }
+
+    let interval = Duration::from_millis(HEARTBEAT_INTERVAL_MS);
+    let started = Instant::now();
+    let mut next_heartbeat = started;
+    let mut last_received = started;
+    let mut silence_reported = false;
+    let mut seen = 0;
+    while count.is_none_or(|count| seen < count) {
+        if Instant::now() >= next_heartbeat {
+            client.heartbeat()?;
+            next_heartbeat += interval;
+        }
+        match client.read_event() {
+            Ok(Some(DeviceEvent::Heartbeat { seq, link })) => {
+                println!("{:>8.3}s  hb {} {}", started.elapsed().as_secs_f32(), seq, link.as_str());
+                last_received = Instant::now();
+                silence_reported = false;
+                seen += 1;
+            }
+            Ok(Some(DeviceEvent::Restarted(reason))) => println!("{:>8.3}s  device restarted: {}", started.elapsed().as_secs_f32(), reason),
+            Ok(Some(DeviceEvent::Crashed(crash))) => println!("{:>8.3}s  device crashed: {}", started.elapsed().as_secs_f32(), crash),
+            Ok(_) => {}
+            Err(err) => report_error(err)?,
+        }
+        if !silence_reported && last_received.elapsed() > 3 * interval {
+            println!("{:>8.3}s  no heartbeat from the device for {} ms", started.elapsed().as_secs_f32(), last_received.elapsed().as_millis());
+            silence_reported = true;
+        }
+    }
+    Ok(())
+}
+
+/// `setting` prints all settings, `setting <name>` one, `setting <name> <value>` changes it,
+/// `setting --reset` goes back to defaults
+pub fn setting<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let parse_key = |name: &str| SettingKey::try_from(name).map_err(|_| usage(&format!("unknown setting: {}", name)));
+    match args.as_slice() {
+        [] => {
+            for key in SettingKey::ALL {
+                client.get_setting(key)?;
+                wait_setting(client, Some(key))?;
+            }
+        }
+        ["--reset"] => {
+            client.reset_settings()?;
+            wait_setting(client, None)?;
+        }
+        [name] => {
+            client.get_setting(parse_key(name)?)?;
+            wait_setting(client, Some(parse_key(name)?))?;
+        }
+        [name, value] => {
+            client.set_setting(parse_key(name)?, value)?;
+            wait_setting(client, Some(parse_key(name)?))?;
+        }
+        _ => return Err(usage("setting expects [<name> [<value>]] or --reset")),
+    }
+    Ok(())
+}
+
+/// Print the value of `key`, or the reset when `None`
+fn wait_setting<T: Read + Write>(client: &mut PicoClient<T>, key: Option<SettingKey>) -> Result<(), CliError> {
+    let sent = Instant::now();
+    while sent.elapsed() < Duration::from_millis(SETTING_RESULT_TIMEOUT_MS) {
+        match client.read_event() {
+            Ok(Some(DeviceEvent::Setting(event_key, value))) if Some(event_key) == key => {
+                println!("{} = {}", event_key.as_str(), value);
+                return Ok(());
+            }
+            Ok(Some(DeviceEvent::SettingsReset)) if key.is_none() => {
+                println!("settings reset");
+                return Ok(());
+            }
+            Ok(Some(DeviceEvent::SettingFailed(err))) => return Err(CliError::Device(format!("setting failed: {}", err))),
+            Ok(_) => {}
+            Err(err) => report_error(err)?,
+        }
+    }
+    Err(CliError::Device(format!("no setting answer in {} ms", SETTING_RESULT_TIMEOUT_MS)))
+}
+
+/// `firmware update <file.bin> [--no-apply]`, `firmware status`
+pub fn firmware<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["update", path, options @ ..] => {
+            let apply = match options {
+                [] => true,
+                ["--no-apply"] => false,
+                _ => return Err(usage("firmware update expects <file.bin> [--no-apply]")),
+            };
+            let data = fs::read(path).map_err(|err| CliError::Device(format!("can not read {}: {}", path, err)))?;
+            let image = FirmwareImage::new(data)?;
+            let total = image.data_len();
+            let mut last_percent = None;
+            client.update_firmware(&image, apply, |event| match event {
+                FirmwareEvent::Next(offset) => {
+                    let percent = offset as usize * 100 / total;
+                    if last_percent.is_none_or(|last| percent >= last + 10 || percent == 100) {
+                        println!("sent {} of {} bytes ({}%)", offset, total, percent);
+                        last_percent = Some(percent);
+                    }
+                }
+                FirmwareEvent::Verified => println!("image verified"),
+                FirmwareEvent::Applying => println!("rebooting, the bootloader installs the image"),
+                FirmwareEvent::Testing => println!("new firmware running on trial"),
+                FirmwareEvent::Confirmed => println!("new firmware confirmed"),
+                _ => {}
+            })?;
+            if !apply {
+                println!("staged, not installed");
+            }
+            Ok(())
+        }
+        ["status"] => {
+            client.firmware_status()?;
+            let sent = Instant::now();
+            while sent.elapsed() < Duration::from_millis(FIRMWARE_STATUS_TIMEOUT_MS) {
+                match client.read_event() {
+                    Ok(Some(DeviceEvent::Firmware(event))) => {
+                        println!("{}", event);
+                        return Ok(());
+                    }
+                    Ok(Some(DeviceEvent::FirmwareFailed(err))) => return Err(CliError::Device(format!("firmware status failed: {}", err))),
+                    Ok(_) => {}
+                    Err(err) => report_error(err)?,
+                }
+            }
+            Err(CliError::Device(format!("no firmware status in {} ms", FIRMWARE_STATUS_TIMEOUT_MS)))
+        }
+        _ => Err(usage("firmware expects update <file.bin> [--no-apply] or status")),
+    }
+}
+
+/// `uart <baud> [none|odd|even] [--save]`, parity stays `current` when left out
+pub fn uart<T: Read + Write + UartControl>(client: &mut PicoClient<T>, args: &[String], current: UartFormat) -> Result<(), CliError> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (format, save) = match args.as_slice() {
+        [baud, rest @ ..] => {
+            let baud = parse_number(baud, "baud")?;
+            let (parity, save) = match rest {
+                [] => (current.parity, false),
+                ["--save"] => (current.parity, true),
+                [parity] => (parse_parity(parity)?, false),
+                [parity, "--save"] => (parse_parity(parity)?, true),
+                _ => return Err(usage("uart expects <baud> [none|odd|even] [--save]")),
+            };
+            (UartFormat { baud, parity }, save)
+        }
+        _ => return Err(usage("uart expects <baud> [none|odd|even] [--save]")),
+    };
+    client.switch_uart(format)?;
+    if !save {
+        println!("uart now {} {} until the device restarts, pass --baud {} --parity {} to later commands",
+            format.baud, format.parity.as_str(), format.baud, format.parity.as_str());
+        return Ok(());
+    }
+    client.set_setting(SettingKey::Baud, &format.baud.to_string())?;
+    wait_setting(client, Some(SettingKey::Baud))?;
+    client.set_setting(SettingKey::Parity, format.parity.as_str())?;
+    wait_setting(client, Some(SettingKey::Parity))?;
+    Ok(())
+}
+
+/// `stats`, counters since the device booted, one `name = value` per line
+pub fn stats<T: Read + Write>(client: &mut PicoClient<T>, args: &[String]) -> Result<(), CliError> {
+    if !args.is_empty() {
+        return Err(usage("stats takes no arguments"));
+    }
+    client.request_stats()?;
+    let mut missing = StatsGroup::ALL.to_vec();
+    let sent = Instant::now();
+    while sent.elapsed() < Duration::from_millis(STATS_TIMEOUT_MS) {
+        match client.read_event() {
+            Ok(Some(DeviceEvent::Stats(report))) => {
+                for (name, value) in report.group.names().iter().zip(report.values.iter()) {
+                    println!("{} = {}", name, value);
+                }
+                missing.retain(|group| *group != report.group);
+                if missing.is_empty() {
+                    return Ok(());
+                }
+            }
+            Ok(_) => {}
+            Err(err) => report_error(err)?,
+        }
+    }
+    let missing: Vec<&str> = missing.iter().map(StatsGroup::as_str).collect();
+    Err(CliError::Device(format!("no {} stats in {} ms", missing.join(", "), STATS_TIMEOUT_MS)))
+}
+
+pub fn parse_parity(value: &str) -> Result<UartParity, CliError> {
+    UartParity::try_from(value).map_err(|_| usage(&format!("parity is none, odd or even: {}", value)))
+}
+
+/// Garbled lines from the device are printed and the wait goes on, other errors end the command
+fn report_error(err: ClientError) -> Result<(), CliError> {
+    match err {
+        ClientError::Protocol { line, error } => {
+            eprintln!("protocol error {:?} in {:?}", error, line);
+            Ok(())
+        }
+        err => Err(err.into()),
+    }
+}
+
+fn format_key(key: &KeyEvent) -> String {
+    let mut text = format!("{:<5}", format!("{:?}", key.code));
+    if key.held_ms == 0 {
+        text.push_str("  press");
+    } else if key.is_hold() {
+        text.push_str(&format!("  hold {} ms", key.held_ms));
+    } else {
+        text.push_str(&format!("  down {} ms", key.held_ms));
+    }
+    if let Some((w, h)) = key.screen_size {
+        text.push_str(&format!("  [{}x{}]", w, h));
+    }
+    text
+}
+
+fn option_value<'a, I: Iterator<Item = &'a String>>(args: &mut I, name: &str) -> Result<&'a str, CliError> {
+    args.next().map(String::as_str).ok_or_else(|| usage(&format!("{} needs a value", name)))
+}
+
+fn parse_number<N: std::str::FromStr>(value: &str, name: &str) -> Result<N, CliError> {
+    value.parse::<N>().map_err(|_| usage(&format!("{} is not a number: {}", name, value)))
+}
+
+fn usage(message: &str) -> CliError {
+    CliError::Usage(String::from(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pico_ui_client::{LoopbackDevice, Pico2PiMessageError, Settings};
+    use std::io;
+
+    /// Device that takes everything and never answers, or only with `garbage`
+    struct SilentDevice {
+        garbage: Vec<u8>,
+    }
+
+    impl Read for SilentDevice {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.garbage.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "silent"));
+            }
+            let count = buf.len().min(self.garbage.len());
+            buf[..count].copy_from_slice(&self.garbage.drain(..count).collect::<Vec<u8>>());
+            Ok(count)
+        }
+    }
+
+    impl Write for SilentDevice {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn loopback() -> PicoClient<LoopbackDevice> {
+        PicoClient::new(LoopbackDevice::new())
+    }
+
+    fn silent(garbage: &str) -> PicoClient<SilentDevice> {
+        PicoClient::new(SilentDevice { garbage: garbage.as_bytes().to_vec() })
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    #[test]
+    fn ping_is_answered() {
+        assert!(ping(&mut loopback(), &args(&["--count", "3"])).is_ok());
+    }
+
+    #[test]
+    fn ping_without_answer_fails() {
+        assert!(matches!(ping(&mut silent(""), &args(&["--timeout", "20"])), Err(CliError::Device(_))));
+    }
+
+    #[test]
+    fn garbled_lines_do_not_end_the_wait() {
+        let result = ping(&mut silent("garbage\r\nlen=1&x\r\n"), &args(&["--timeout", "20"]));
+        assert!(matches!(result, Err(CliError::Device(_))));
+    }
+
+    #[test]
+    fn ping_rejects_unknown_arguments() {
+        assert!(matches!(ping(&mut loopback(), &args(&["--fast"])), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn setting_is_changed_on_the_device() {
+        let mut client = loopback();
+        assert!(setting(&mut client, &args(&["brightness", "60"])).is_ok());
+        assert_eq!(client.get_mut().state().settings.brightness, 60);
+        assert!(setting(&mut client, &args(&[])).is_ok());
+        assert!(setting(&mut client, &args(&["--reset"])).is_ok());
+        assert_eq!(client.get_mut().state().settings.brightness, Settings::default().brightness);
+    }
+
+    #[test]
+    fn bad_setting_is_not_sent() {
+        assert!(matches!(setting(&mut loopback(), &args(&["brightness", "150"])), Err(CliError::Client(_))));
+        assert!(matches!(setting(&mut loopback(), &args(&["colour", "red"])), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn stats_waits_for_every_group() {
+        assert!(stats(&mut loopback(), &args(&[])).is_ok());
+        assert!(matches!(stats(&mut silent(""), &args(&[])), Err(CliError::Device(_))));
+        assert!(matches!(stats(&mut loopback(), &args(&["now"])), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn layout_answer_is_reported() {
+        assert!(layout(&mut loopback(), &args(&["text|0,0,128,16|text=hi"])).is_ok());
+        assert!(matches!(layout(&mut loopback(), &args(&["text|0,0,200,16|text=hi"])), Err(CliError::Device(_))));
+        assert!(matches!(layout(&mut loopback(), &args(&["text|0,0,8,8;box|0,0,8,8"])), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn screen_lines_reach_the_device() {
+        let mut client = loopback();
+        assert!(send_screen(&mut client, &args(&["--title", "Menu:1:2", "first", "second"])).is_ok());
+        let lines = &client.get_mut().state().lines;
+        assert!(lines.iter().flatten().any(|(text, _)| text == "second"));
+        assert!(matches!(send_screen(&mut client, &args(&["--title", "Menu"])), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn widget_arguments_are_checked() {
+        assert!(widget(&mut loopback(), &args(&["gauge", "5", "0", "10", "Temp"])).is_ok());
+        assert!(matches!(widget(&mut loopback(), &args(&["gauge", "5"])), Err(CliError::Usage(_))));
+        assert!(matches!(widget(&mut loopback(), &args(&["progress", "lots"])), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn protocol_errors_are_only_reported() {
+        let protocol = ClientError::Protocol { line: String::from("x"), error: Pico2PiMessageError::NotAFrame };
+        assert!(report_error(protocol).is_ok());
+        assert!(matches!(report_error(ClientError::NoAnswer("pong")), Err(CliError::Client(_))));
+    }
+}
@@ -0,0 +1,157 @@
+//! `picoui` - talk to the device from the Pi shell.
+//!
+//! ```bash
+//! picoui --port /dev/ttyAMA0 send-screen --status 192.168.1.10:87 --title Menu:1:3 --cursor 0 first second
+//! picoui show-list --title Menu one two three
+//! picoui image send 1 logo.ppm && picoui image show 1 32 32
+//! picoui watch-keys
+//! picoui set-led red blink:500
+//! picoui beep 2000 100
+//! picoui ping --count 5
+//! picoui heartbeat --count 10
+//! picoui setting brightness 60
+//! picoui firmware update firmware.bin
+//! picoui uart 921600 none && picoui --baud 921600 --parity none ping
+//! picoui stats
+//! ```
+//!
+//! Works the same against the simulator: start it with `--pty` and pass the printed path as `--port`.
+
+extern crate pico_ui_client;
+
+mod commands;
+
+use std::env;
+use std::fmt;
+use std::process;
+
+use pico_ui_client::{open_serial_with, ClientError, Settings, UartFormat};
+
+/// Used when neither `--port` nor `PICOUI_PORT` is given
+const DEFAULT_PORT: &str = "/dev/ttyAMA0";
+
+const COMMANDS: [&str; 15] = [
+    "send-screen", "show-list", "widget", "layout", "image", "qr", "watch-keys", "set-led", "beep", "ping", "heartbeat",
+    "setting", "firmware", "uart", "stats",
+];
+
+const USAGE: &str = "usage: picoui [--port <path>] <command> [args]
+
+  --port, -p <path>   serial device, default $PICOUI_PORT or /dev/ttyAMA0
+  --baud <rate>       uart rate of the device, default 115200
+  --parity <parity>   none, odd or even, default odd
+
+commands:
+  send-screen [--status <ip>:<battery>] [--title <title>:<page>:<pages>] [--cursor <n>]
+              [--style <line>[|fg=rrggbb|bg=rrggbb|font=small|align=center|bold=1|inverse=1|overflow=wrap]]... [line...]
+  show-list [--title <title>] [--cursor <n>] <item>... | show-list --close
+  widget progress <percent> [label] | widget gauge <value> <min> <max> [label]
+  widget dialog <question> | widget spinner <value> <min> <max> [step] [label]
+  widget toast <text> [ms] | widget battery <percent|off> | widget close
+  layout <kind|x,y,w,h[|prop=value]...>... | layout --set <index> <prop=value>... | layout --clear
+  image send <id> <file.ppm|file.pbm> | image show <id> <x> <y> | image hide <id|all> | image delete <id>
+  qr <text> [--ecc l|m|q|h] | qr --close
+  watch-keys [--count <n>]          print key events until interrupted or <n> events
+  set-led <green|blue1|blue2|red> <off|on|blink[:ms]|pulse[:ms]>
+  beep [<freq_hz> [<ms>]] | beep --melody <rtttl> | beep --alert <notify|success|error> | beep --off
+  ping [--count <n>] [--timeout <ms>]
+  heartbeat [--count <n>]           keep the link alive, print device heartbeats until interrupted or <n> of them
+  setting [<name> [<value>]] | setting --reset
+                                    show or change settings kept in device flash:
+                                    orientation, offset, brightness, baud, keymap, parity, uart_pins,
+                                    usb_mode
+  firmware update <file.bin> [--no-apply] | firmware status
+                                    install a firmware built for the bootloader, rolled back
+                                    when it does not confirm itself after the reboot
+  uart <baud> [none|odd|even] [--save]
+                                    switch the link to another format, back by itself when it does not
+                                    work; kept until the device restarts, or in settings with --save
+  stats                             print frame, error and timing counters since the device booted";
+
+pub enum CliError {
+    Usage(String),
+    Client(ClientError),
+    /// Command ran but the device did not behave, e.g. no pong
+    Device(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            CliError::Client(err) => write!(f, "{}", err),
+            CliError::Device(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<ClientError> for CliError {
+    fn from(err: ClientError) -> Self {
+        CliError::Client(err)
+    }
+}
+
+fn run() -> Result<(), CliError> {
+    let mut port = env::var("PICOUI_PORT").unwrap_or_else(|_| String::from(DEFAULT_PORT));
+    let defaults = Settings::default();
+    let mut format = UartFormat { baud: defaults.baud, parity: defaults.parity };
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.peek() {
+        match arg.as_str() {
+            "--port" | "-p" => {
+                args.next();
+                port = args.next().ok_or_else(|| CliError::Usage(String::from("--port needs a path")))?;
+            }
+            "--baud" => {
+                args.next();
+                let baud = args.next().ok_or_else(|| CliError::Usage(String::from("--baud needs a rate")))?;
+                format.baud = baud.parse().map_err(|_| CliError::Usage(format!("--baud is not a number: {}", baud)))?;
+            }
+            "--parity" => {
+                args.next();
+                let parity = args.next().ok_or_else(|| CliError::Usage(String::from("--parity needs a value")))?;
+                format.parity = commands::parse_parity(&parity)?;
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => break,
+        }
+    }
+    let command = args.next().ok_or_else(|| CliError::Usage(String::from("missing command")))?;
+    let args: Vec<String> = args.collect();
+    if !COMMANDS.contains(&command.as_str()) {
+        return Err(CliError::Usage(format!("unknown command: {}", command)));
+    }
+
+    let mut client = open_serial_with(&port, format)?;
+    match command.as_str() {
+        "send-screen" => commands::send_screen(&mut client, &args),
+        "show-list" => commands::show_list(&mut client, &args),
+        "widget" => commands::widget(&mut client, &args),
+        "layout" => commands::layout(&mut client, &args),
+        "image" => commands::image(&mut client, &args),
+        "qr" => commands::qr(&mut client, &args),
+        "watch-keys" => commands::watch_keys(&mut client, &args),
+        "set-led" => commands::set_led(&mut client, &args),
+        "beep" => commands::beep(&mut client, &args),
+        "ping" => commands::ping(&mut client, &args),
+        "heartbeat" => commands::heartbeat(&mut client, &args),
+        "setting" => commands::setting(&mut client, &args),
+        "firmware" => commands::firmware(&mut client, &args),
+        "uart" => commands::uart(&mut client, &args, format),
+        "stats" => commands::stats(&mut client, &args),
+        _ => unreachable!(),
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("picoui: {}", err);
+        process::exit(match err {
+            CliError::Usage(_) => 2,
+            _ => 1,
+        });
+    }
+}
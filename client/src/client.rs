@@ -0,0 +1,543 @@
+use std::convert::TryFrom;
+use std::io::{ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+use pico_ui_core::buzzer::melody::parse_rtttl;
+use pico_ui_core::leds::pattern::{LedId, LedPattern};
+use pico_ui_core::link::monitor::HEARTBEAT_INTERVAL_MS;
+use pico_ui_core::link::uart::{UartEvent, UartFormat, UART_CONFIRM_MS};
+use pico_ui_core::messages::pi_2_pico_message::RESERVED_CHARS;
+use pico_ui_core::messages::pico_2_pi_message::Pico2PiMessage;
+use pico_ui_core::qr::code::{EccLevel, QrCode, MAX_DATA_BYTES};
+use pico_ui_core::screen::lines::TITLE_CAPACITY;
+use pico_ui_core::screen::list::{ITEM_CAPACITY, LIST_CAPACITY};
+use pico_ui_core::settings::setting::{SettingKey, Settings};
+use pico_ui_core::update::error::UpdateError;
+use pico_ui_core::update::event::FirmwareEvent;
+use pico_ui_core::utils::base64;
+use pico_ui_core::widgets::{LABEL_CAPACITY, LABEL_CHARS};
+
+use error::ClientError;
+use event::DeviceEvent;
+use firmware::FirmwareImage;
+use image::Bitmap;
+use layout::ScreenLayout;
+use screen::ScreenUpdate;
+
+/// Wait for the answer to a firmware command, erasing a flash sector takes up to 400 ms
+const FIRMWARE_ANSWER_TIMEOUT_MS: u64 = 2_000;
+/// Times a chunk is sent again after a timeout or checksum error
+const FIRMWARE_RETRIES: u32 = 5;
+/// Bootloader swapping a whole partition, then the trial until the new firmware confirms itself
+const FIRMWARE_INSTALL_TIMEOUT_MS: u64 = 120_000;
+/// Wait for `uart=switching`
+const UART_ANSWER_TIMEOUT_MS: u64 = 1_000;
+/// Wait for `uart=ok` before sending `uart_confirm` again
+const UART_CONFIRM_RETRY_MS: u64 = 250;
+
+/// Built-in buzzer alerts
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Alert {
+    Notify,
+    Success,
+    Error,
+}
+
+impl Alert {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Alert::Notify => "notify",
+            Alert::Success => "success",
+            Alert::Error => "error",
+        }
+    }
+}
+
+/// Protocol client over any byte stream: serial port, pty of the simulator, TCP bridge.
+/// Reads should time out (or be non-blocking) for `read_event` to return `None` when idle.
+pub struct PicoClient<T: Read + Write> {
+    transport: T,
+    rx_buffer: Vec<u8>,
+    heartbeat_seq: u32,
+}
+
+/// Transport with a line format which can change, e.g. a serial port. Needed by
+/// `PicoClient::switch_uart`.
+pub trait UartControl {
+    fn uart_format(&self) -> Result<UartFormat, ClientError>;
+    fn set_uart_format(&mut self, format: UartFormat) -> Result<(), ClientError>;
+}
+
+impl<T: Read + Write> PicoClient<T> {
+    pub fn new(transport: T) -> Self {
+        PicoClient {
+            transport,
+            rx_buffer: Vec::new(),
+            heartbeat_seq: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// The byte stream, e.g. to press keys on a `LoopbackDevice`
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Send one line, `\r\n` is added
+    pub fn send_line(&mut self, line: &str) -> Result<(), ClientError> {
+        if line.contains(['\r', '\n']) {
+            return Err(ClientError::InvalidField { field: "line", reason: "contains line terminator" });
+        }
+        self.transport.write_all(line.as_bytes())?;
+        self.transport.write_all(b"\r\n")?;
+        self.transport.flush()?;
+        Ok(())
+    }
+
+    pub fn send_screen(&mut self, update: &ScreenUpdate) -> Result<(), ClientError> {
+        let frame = update.build()?.to_frame();
+        self.transport.write_all(frame.as_bytes())?;
+        self.transport.flush()?;
+        Ok(())
+    }
+
+    pub fn set_led(&mut self, led: LedId, pattern: LedPattern) -> Result<(), ClientError> {
+        self.send_line(&format!("led={}:{}", led, pattern))
+    }
+
+    pub fn beep(&mut self, freq_hz: u16, duration_ms: u32) -> Result<(), ClientError> {
+        self.send_line(&format!("beep={}:{}", freq_hz, duration_ms))
+    }
+
+    pub fn stop_buzzer(&mut self) -> Result<(), ClientError> {
+        self.send_line("beep=off")
+    }
+
+    /// Play RTTTL melody, e.g. `tune:d=8,o=5,b=120:c,e,g`. Checked with the device parser before sending.
+    pub fn play_melody(&mut self, rtttl: &str) -> Result<(), ClientError> {
+        if rtttl.contains('&') {
+            return Err(ClientError::InvalidField { field: "melody", reason: "contains protocol separator" });
+        }
+        parse_rtttl(rtttl).map_err(|_| ClientError::InvalidField { field: "melody", reason: "not a valid rtttl melody" })?;
+        self.send_line(&format!("melody={}", rtttl))
+    }
+
+    pub fn alert(&mut self, alert: Alert) -> Result<(), ClientError> {
+        self.send_line(&format!("alert={}", alert.as_str()))
+    }
+
+    pub fn set_key_click(&mut self, enabled: bool) -> Result<(), ClientError> {
+        self.send_line(if enabled { "keyclick=on" } else { "keyclick=off" })
+    }
+
+    pub fn set_backlight(&mut self, percent: u8, fade_ms: u32) -> Result<(), ClientError> {
+        self.send_line(&format!("backlight={}:{}", percent.min(100), fade_ms))
+    }
+
+    /// Dim after `dim_after_s` and switch backlight off after `off_after_s` without key presses, 0 disables
+    pub fn set_auto_dim(&mut self, dim_after_s: u32, off_after_s: u32, dim_percent: u8) -> Result<(), ClientError> {
+        self.send_line(&format!("autodim={}:{}:{}", dim_after_s, off_after_s, dim_percent.min(100)))
+    }
+
+    /// Show a menu of up to 32 items the device scrolls by itself.
+    /// Only `DeviceEvent::ListSelected` and `DeviceEvent::ListPage` come back while it is open.
+    pub fn show_list<S: AsRef<str>>(&mut self, title: &str, items: &[S], cursor: usize) -> Result<(), ClientError> {
+        if items.is_empty() || items.len() > LIST_CAPACITY {
+            return Err(ClientError::InvalidField { field: "list", reason: "must have 1 to 32 items" });
+        }
+        if cursor >= items.len() {
+            return Err(ClientError::InvalidField { field: "list_cursor", reason: "past the last item" });
+        }
+        list_field("list_title", title, TITLE_CAPACITY)?;
+        let mut line = String::from("list=");
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                line.push(',');
+            }
+            line.push_str(list_field("list item", item.as_ref(), ITEM_CAPACITY)?);
+        }
+        if !title.is_empty() {
+            line.push_str(&format!("&list_title={}", title));
+        }
+        line.push_str(&format!("&list_cursor={}", cursor));
+        self.send_line(&line)
+    }
+
+    /// Give data lines back to `send_screen`, also done by any screen update with lines or cursor
+    pub fn close_list(&mut self) -> Result<(), ClientError> {
+        self.send_line("list_close=1")
+    }
+
+    /// Progress bar over data lines, stays until `close_widget` or another widget
+    pub fn show_progress(&mut self, percent: u8, label: &str) -> Result<(), ClientError> {
+        label_field("label", label)?;
+        self.send_line(&format!("progress={}:{}", percent.min(100), label))
+    }
+
+    pub fn show_gauge(&mut self, value: i32, min: i32, max: i32, label: &str) -> Result<(), ClientError> {
+        label_field("label", label)?;
+        self.send_line(&format!("gauge={}:{}:{}:{}", value, min, max, label))
+    }
+
+    /// Yes/no question, answered with `DeviceEvent::DialogAnswer`
+    pub fn ask(&mut self, question: &str) -> Result<(), ClientError> {
+        label_field("question", question)?;
+        self.send_line(&format!("dialog={}", question))
+    }
+
+    /// Number picker, answered with `DeviceEvent::SpinnerValue`
+    pub fn show_spinner(&mut self, value: i32, min: i32, max: i32, step: i32, label: &str) -> Result<(), ClientError> {
+        label_field("label", label)?;
+        self.send_line(&format!("spinner={}:{}:{}:{}:{}", value, min, max, step.max(1), label))
+    }
+
+    /// Give data lines back to the screen lines
+    pub fn close_widget(&mut self) -> Result<(), ClientError> {
+        self.send_line("widget_close=1")
+    }
+
+    /// Notification hidden by the device after `duration_ms`
+    pub fn toast(&mut self, text: &str, duration_ms: u32) -> Result<(), ClientError> {
+        label_field("toast", text)?;
+        self.send_line(&format!("toast={}&toast_ms={}", text, duration_ms))
+    }
+
+    /// Battery icon in the status line, `None` hides it
+    pub fn set_battery_icon(&mut self, percent: Option<u8>) -> Result<(), ClientError> {
+        match percent {
+            Some(percent) => self.send_line(&format!("battery={}", percent.min(100))),
+            None => self.send_line("battery=off"),
+        }
+    }
+
+    /// QR code of `text` over the whole screen, encoded by the device.
+    /// Text with protocol separators goes base64 encoded. Answered with `DeviceEvent::QrShown` or `DeviceEvent::QrFailed`.
+    pub fn show_qr(&mut self, text: &str, ecc: EccLevel) -> Result<(), ClientError> {
+        // same encoder as the device, so it is refused here rather than there
+        QrCode::encode(text.as_bytes(), ecc)
+            .map_err(|_| ClientError::InvalidField { field: "qr", reason: "empty or too long for a QR code" })?;
+        if text.contains(&RESERVED_CHARS[..]) {
+            let mut encoded: heapless::String<{ MAX_DATA_BYTES.div_ceil(3) * 4 }> = heapless::String::new();
+            _ = base64::encode(text.as_bytes(), &mut encoded);
+            self.send_line(&format!("qr_b64={}&qr_ecc={}", encoded, ecc.as_str()))
+        } else {
+            self.send_line(&format!("qr={}&qr_ecc={}", text, ecc.as_str()))
+        }
+    }
+
+    pub fn close_qr(&mut self) -> Result<(), ClientError> {
+        self.send_line("qr_close=1")
+    }
+
+    /// Replace screen lines with a layout, widgets are still drawn on top.
+    /// Answered with `DeviceEvent::LayoutAccepted` or `DeviceEvent::LayoutRejected`.
+    pub fn send_layout(&mut self, layout: &ScreenLayout) -> Result<(), ClientError> {
+        self.send_line(&format!("layout={}", layout.build()?))
+    }
+
+    /// Change properties of one node, e.g. `&["text=12:30", "fg=ff0000"]`, answered like `send_layout`
+    pub fn update_layout_node<S: AsRef<str>>(&mut self, index: usize, properties: &[S]) -> Result<(), ClientError> {
+        let mut line = format!("layout_set={}", index);
+        for property in properties {
+            let property = property.as_ref();
+            if property.contains(&RESERVED_CHARS[..]) || property.contains(['|', ';']) {
+                return Err(ClientError::InvalidField { field: "layout property", reason: "contains protocol separator" });
+            }
+            line.push('|');
+            line.push_str(property);
+        }
+        self.send_line(&line)
+    }
+
+    /// Go back to screen lines
+    pub fn clear_layout(&mut self) -> Result<(), ClientError> {
+        self.send_line("layout_clear=1")
+    }
+
+    /// Upload `bitmap` into the device image cache as `id`, replacing what had the id before.
+    /// Answered with `DeviceEvent::ImageStored` or `DeviceEvent::ImageFailed`.
+    pub fn send_image(&mut self, id: u8, bitmap: &Bitmap) -> Result<(), ClientError> {
+        self.send_line(&format!("img_begin={}", bitmap.header(id)))?;
+        self.resume_image(id, bitmap, 0)
+    }
+
+    /// Send the rest of an upload, from the offset the device reported with
+    /// `ImageError::Offset` or `ImageError::Incomplete`
+    pub fn resume_image(&mut self, id: u8, bitmap: &Bitmap, offset: usize) -> Result<(), ClientError> {
+        for chunk in bitmap.chunks(id, offset) {
+            self.send_line(&format!("img_data={}", chunk))?;
+        }
+        self.send_line(&format!("img_end={}", bitmap.trailer(id)))
+    }
+
+    /// Put a cached image on screen with its top left corner at `x`, `y`, over lines and layout.
+    /// `DeviceEvent::ImageFailed` with `ImageError::Missing` means it has to be sent again.
+    pub fn show_image(&mut self, id: u8, x: i32, y: i32) -> Result<(), ClientError> {
+        self.send_line(&format!("img_show={}:{},{}", id, x, y))
+    }
+
+    /// Take an image off screen, it stays cached
+    pub fn hide_image(&mut self, id: u8) -> Result<(), ClientError> {
+        self.send_line(&format!("img_hide={}", id))
+    }
+
+    pub fn hide_all_images(&mut self) -> Result<(), ClientError> {
+        self.send_line("img_hide=all")
+    }
+
+    /// Drop an image from the device cache
+    pub fn delete_image(&mut self, id: u8) -> Result<(), ClientError> {
+        self.send_line(&format!("img_delete={}", id))
+    }
+
+    /// Ask the device to answer with `DeviceEvent::Pong(token)`, up to 16 chars
+    pub fn ping(&mut self, token: &str) -> Result<(), ClientError> {
+        if token.is_empty() || token.len() > 16 || token.contains(['&', ',', '=', '\r', '\n']) {
+            return Err(ClientError::InvalidField { field: "token", reason: "empty, too long or contains separators" });
+        }
+        self.send_line(&format!("ping={}", token))
+    }
+
+    /// Tell the device the Pi is alive. Call every `HEARTBEAT_INTERVAL_MS` while nothing else is sent,
+    /// after `LOST_AFTER_MS` of silence the device replaces the screen with "Pi not responding".
+    pub fn heartbeat(&mut self) -> Result<(), ClientError> {
+        self.heartbeat_seq = self.heartbeat_seq.wrapping_add(1);
+        self.send_line(&format!("hb={}", self.heartbeat_seq))
+    }
+
+    /// Change a setting, the device keeps it in flash. Answered with `DeviceEvent::Setting` once saved,
+    /// orientation, offset and baud take effect after the next reboot.
+    pub fn set_setting(&mut self, key: SettingKey, value: &str) -> Result<(), ClientError> {
+        if Settings::default().set(key, value).is_err() {
+            return Err(ClientError::InvalidField { field: key.as_str(), reason: "invalid value" });
+        }
+        self.send_line(&format!("setting_set={}:{}", key.as_str(), value))
+    }
+
+    /// Ask for the current value, answered with `DeviceEvent::Setting`
+    pub fn get_setting(&mut self, key: SettingKey) -> Result<(), ClientError> {
+        self.send_line(&format!("setting_get={}", key.as_str()))
+    }
+
+    /// Put all settings back to defaults, answered with `DeviceEvent::SettingsReset`
+    pub fn reset_settings(&mut self) -> Result<(), ClientError> {
+        self.send_line("setting_reset=1")
+    }
+
+    /// Stage `image` on the device chunk by chunk, each one waits for its answer and is sent again
+    /// when it got lost. With `apply` the device then reboots, its bootloader installs the image
+    /// and the new firmware confirms itself once it talked to the Pi for a while; a reset before
+    /// that rolls it back.
+    ///
+    /// Blocks until done, other frames from the device are dropped meanwhile. `progress` sees
+    /// `Ready`, `Next` per chunk and `Verified`, with `apply` also `Applying`, `Testing` and
+    /// `Confirmed`.
+    pub fn update_firmware(&mut self, image: &FirmwareImage, apply: bool, mut progress: impl FnMut(FirmwareEvent)) -> Result<(), ClientError> {
+        self.firmware_command(&format!("fw_begin={}", image.header()), FirmwareEvent::Ready, &mut progress)?;
+        let mut offset = 0;
+        let mut retries = 0;
+        while let Some(chunk) = image.chunk(offset) {
+            match self.firmware_answer(&format!("fw_data={}", chunk), |event| matches!(event, FirmwareEvent::Next(_)))? {
+                Some(Ok(FirmwareEvent::Next(next))) => {
+                    offset = next as usize;
+                    retries = 0;
+                    progress(FirmwareEvent::Next(next));
+                    continue;
+                }
+                // device has the data up to there, e.g. after an earlier answer got lost
+                Some(Err(UpdateError::Offset(expected))) => offset = expected as usize,
+                Some(Err(UpdateError::Checksum)) | Some(Ok(_)) | None => {}
+                Some(Err(err)) => return Err(ClientError::Update(err)),
+            }
+            retries += 1;
+            if retries > FIRMWARE_RETRIES {
+                return Err(ClientError::NoAnswer("fw=next"));
+            }
+        }
+        self.firmware_command("fw_end=1", FirmwareEvent::Verified, &mut progress)?;
+        if !apply {
+            return Ok(());
+        }
+        self.firmware_command("fw_apply=1", FirmwareEvent::Applying, &mut progress)?;
+
+        // status requests keep the link online, the new firmware only confirms itself then
+        let started = Instant::now();
+        let mut restarted = false;
+        let mut testing = false;
+        while started.elapsed() < Duration::from_millis(FIRMWARE_INSTALL_TIMEOUT_MS) {
+            self.send_line("fw_status=1")?;
+            let sent = Instant::now();
+            while sent.elapsed() < Duration::from_millis(HEARTBEAT_INTERVAL_MS) {
+                match self.read_event() {
+                    Ok(Some(DeviceEvent::Restarted(_))) => restarted = true,
+                    Ok(Some(DeviceEvent::Firmware(FirmwareEvent::Testing))) if !testing => {
+                        testing = true;
+                        progress(FirmwareEvent::Testing);
+                    }
+                    Ok(Some(DeviceEvent::Firmware(FirmwareEvent::Confirmed))) => {
+                        progress(FirmwareEvent::Confirmed);
+                        return Ok(());
+                    }
+                    Ok(Some(DeviceEvent::Firmware(FirmwareEvent::RolledBack))) => return Err(ClientError::RolledBack),
+                    // came back without a bootloader which installed the image
+                    Ok(Some(DeviceEvent::Firmware(FirmwareEvent::Idle))) if restarted => {
+                        return Err(ClientError::NoAnswer("fw=testing"));
+                    }
+                    Ok(_) | Err(ClientError::Protocol { .. }) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Err(ClientError::NoAnswer(if testing { "fw=confirmed" } else { "fw=testing" }))
+    }
+
+    /// Ask how the last update went or how far an upload is, answered with `DeviceEvent::Firmware`
+    pub fn firmware_status(&mut self) -> Result<(), ClientError> {
+        self.send_line("fw_status=1")
+    }
+
+    /// Ask for the diagnostics counters, answered with a `DeviceEvent::Stats` per `StatsGroup`
+    pub fn request_stats(&mut self) -> Result<(), ClientError> {
+        self.send_line("stats=1")
+    }
+
+    /// Send a firmware command which has to be answered with `expected`
+    fn firmware_command(&mut self, line: &str, expected: FirmwareEvent, progress: &mut impl FnMut(FirmwareEvent)) -> Result<(), ClientError> {
+        match self.firmware_answer(line, |event| event == expected)? {
+            Some(Ok(event)) => {
+                progress(event);
+                Ok(())
+            }
+            Some(Err(err)) => Err(ClientError::Update(err)),
+            None => Err(ClientError::NoAnswer(match expected {
+                FirmwareEvent::Ready => "fw=ready",
+                FirmwareEvent::Verified => "fw=verified",
+                _ => "fw=applying",
+            })),
+        }
+    }
+
+    /// Send `line` and wait for an `expected` answer or an error, `None` when there was none in time.
+    /// Answers to earlier commands, e.g. of another process still in the port buffer, are skipped.
+    fn firmware_answer(&mut self, line: &str, expected: impl Fn(FirmwareEvent) -> bool) -> Result<Option<Result<FirmwareEvent, UpdateError>>, ClientError> {
+        self.send_line(line)?;
+        let sent = Instant::now();
+        while sent.elapsed() < Duration::from_millis(FIRMWARE_ANSWER_TIMEOUT_MS) {
+            match self.read_event() {
+                Ok(Some(DeviceEvent::Firmware(event))) if expected(event) => return Ok(Some(Ok(event))),
+                Ok(Some(DeviceEvent::FirmwareFailed(err))) => return Ok(Some(Err(err))),
+                // a garbled answer is like a lost one
+                Ok(_) | Err(ClientError::Protocol { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Next event from the device, `None` when nothing complete was received before read timeout
+    pub fn read_event(&mut self) -> Result<Option<DeviceEvent>, ClientError> {
+        loop {
+            if let Some(line) = self.take_line() {
+                if line.is_empty() {
+                    continue;
+                }
+                return match Pico2PiMessage::try_from(line.as_str()) {
+                    Ok(message) => Ok(Some(DeviceEvent::from(message))),
+                    Err(error) => Err(ClientError::Protocol { line, error }),
+                };
+            }
+
+            let mut buffer = [0u8; 256];
+            match self.transport.read(&mut buffer) {
+                Ok(0) => return Ok(None),
+                Ok(count) => self.rx_buffer.extend_from_slice(&buffer[..count]),
+                Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::WouldBlock => {
+                    return Ok(None);
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        let end = self.rx_buffer.windows(2).position(|x| x == b"\r\n")?;
+        let line: Vec<u8> = self.rx_buffer.drain(..end + 2).take(end).collect();
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+impl<T: Read + Write + UartControl> PicoClient<T> {
+    /// Move the link to `format` until the device restarts: the device announces the change at
+    /// the old format, both sides change over and the Pi confirms at the new one. When that does
+    /// not come through the device goes back by itself, so does the transport, and this fails
+    /// with `NoAnswer("uart=ok")`. Other frames from the device are dropped meanwhile.
+    ///
+    /// Save `baud` and `parity` with `set_setting` to boot with the format.
+    pub fn switch_uart(&mut self, format: UartFormat) -> Result<(), ClientError> {
+        let previous = self.transport.uart_format()?;
+        self.send_line(&format!("uart_switch={}", format.to_text()))?;
+        match self.uart_answer(UART_ANSWER_TIMEOUT_MS, |event| event == UartEvent::Switching(format) || event == UartEvent::BadValue)? {
+            Some(UartEvent::Switching(_)) => {}
+            Some(UartEvent::BadValue) => {
+                return Err(ClientError::InvalidField { field: "uart", reason: "rate or parity not supported by the device" });
+            }
+            _ => return Err(ClientError::NoAnswer("uart=switching")),
+        }
+        self.transport.set_uart_format(format)?;
+        // whatever came in during the change over is garbage
+        self.rx_buffer.clear();
+
+        let changed = Instant::now();
+        while changed.elapsed() < Duration::from_millis(UART_CONFIRM_MS / 2) {
+            self.send_line("uart_confirm=1")?;
+            if let Some(UartEvent::Ok(confirmed)) = self.uart_answer(UART_CONFIRM_RETRY_MS, |event| matches!(event, UartEvent::Ok(_)))? {
+                if confirmed == format {
+                    return Ok(());
+                }
+            }
+        }
+
+        self.transport.set_uart_format(previous)?;
+        self.rx_buffer.clear();
+        // returns once the device is back too
+        self.uart_answer(2 * UART_CONFIRM_MS, |event| matches!(event, UartEvent::Fallback(_)))?;
+        Err(ClientError::NoAnswer("uart=ok"))
+    }
+
+    /// Wait for an `expected` uart answer, `None` when there was none in time
+    fn uart_answer(&mut self, timeout_ms: u64, expected: impl Fn(UartEvent) -> bool) -> Result<Option<UartEvent>, ClientError> {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_millis(timeout_ms) {
+            match self.read_event() {
+                Ok(Some(DeviceEvent::Uart(event))) if expected(event) => return Ok(Some(event)),
+                // garbled while the two sides use different formats
+                Ok(_) | Err(ClientError::Protocol { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Free text value with device side capacity in bytes
+fn list_field<'a>(field: &'static str, value: &'a str, max_bytes: usize) -> Result<&'a str, ClientError> {
+    if value.contains(&RESERVED_CHARS[..]) || value.contains(',') {
+        return Err(ClientError::InvalidField { field, reason: "contains protocol separator" });
+    }
+    if value.len() > max_bytes {
+        return Err(ClientError::InvalidField { field, reason: "too long" });
+    }
+    Ok(value)
+}
+
+/// Free text value the device draws in one piece, cut by characters as well
+fn label_field<'a>(field: &'static str, value: &'a str) -> Result<&'a str, ClientError> {
+    if value.chars().count() > LABEL_CHARS {
+        return Err(ClientError::InvalidField { field, reason: "too long" });
+    }
+    list_field(field, value, LABEL_CAPACITY)
+}
@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use pico_ui_core::layout::error::LayoutError;
+use pico_ui_core::messages::pico_2_pi_message::Pico2PiMessageError;
+use pico_ui_core::update::error::UpdateError;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    /// Value can't be sent: too long for the device buffers or contains protocol separators
+    InvalidField { field: &'static str, reason: &'static str },
+    /// Layout node the device would reject before looking at geometry
+    Layout(LayoutError),
+    /// Line from the device is not a valid frame
+    Protocol { line: String, error: Pico2PiMessageError },
+    /// Device refused a firmware update command
+    Update(UpdateError),
+    /// Update was installed but did not confirm itself, the previous firmware runs again
+    RolledBack,
+    /// Device did not give the expected answer in time, with the frame waited for
+    NoAnswer(&'static str),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(err) => write!(f, "io error: {}", err),
+            ClientError::InvalidField { field, reason } => write!(f, "invalid {}: {}", field, reason),
+            ClientError::Layout(err) => write!(f, "invalid layout: {}", err),
+            ClientError::Protocol { line, error } => write!(f, "bad frame {:?}: {:?}", line, error),
+            ClientError::Update(err) => write!(f, "firmware update failed: {}", err),
+            ClientError::RolledBack => f.write_str("new firmware did not confirm itself, rolled back"),
+            ClientError::NoAnswer(frame) => write!(f, "no {} answer from the device", frame),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::Io(err)
+    }
+}
@@ -0,0 +1,139 @@
+use pico_ui_core::crash::record::CrashRecord;
+use pico_ui_core::device::reset::ResetReason;
+use pico_ui_core::diagnostics::counters::StatsReport;
+use pico_ui_core::images::error::ImageError;
+use pico_ui_core::input::keyboard_codes::KeyboardCodes;
+use pico_ui_core::input::key_hold::HOLD_REPEAT_AFTER_MS;
+use pico_ui_core::layout::error::LayoutError;
+use pico_ui_core::link::monitor::LinkState;
+use pico_ui_core::link::uart::UartEvent;
+use pico_ui_core::messages::pico_2_pi_message::Pico2PiMessage;
+use pico_ui_core::qr::code::QrError;
+use pico_ui_core::settings::setting::{SettingKey, SettingsError};
+use pico_ui_core::update::error::UpdateError;
+use pico_ui_core::update::event::FirmwareEvent;
+
+/// Key reported by the device. Short presses are reported on release with `held_ms` 0,
+/// held keys are repeated every 250 ms with growing `held_ms`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct KeyEvent {
+    pub code: KeyboardCodes,
+    pub held_ms: u64,
+    /// Display width and height as reported by the device
+    pub screen_size: Option<(i32, i32)>,
+}
+
+impl KeyEvent {
+    pub fn is_hold(&self) -> bool {
+        self.held_ms > HOLD_REPEAT_AFTER_MS
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DeviceEvent {
+    Key(KeyEvent),
+    /// Answer to `PicoClient::ping` with the same token
+    Pong(String),
+    /// `Ok` pressed on the on-device list, index of the item
+    ListSelected(usize),
+    /// On-device list scrolled to another page, 0 based
+    ListPage(usize),
+    /// Dialog answered, true for yes
+    DialogAnswer(bool),
+    /// Spinner value confirmed with `Ok`
+    SpinnerValue(i32),
+    /// Layout or layout node update is on screen
+    LayoutAccepted,
+    /// Layout or layout node update was refused, the previous one stays on screen
+    LayoutRejected(LayoutError),
+    /// Upload of the image with this id is complete
+    ImageStored(u8),
+    /// Image command failed, see `ImageError` for what to send again
+    ImageFailed(u8, ImageError),
+    /// QR code is on screen
+    QrShown,
+    /// QR code was not shown, the previous screen stays
+    QrFailed(QrError),
+    /// Device restarted since the Pi last talked to it, sent once in answer to the first line after boot
+    Restarted(ResetReason),
+    /// Device panicked. Sent right before the reboot and once more after it, with `Restarted`.
+    Crashed(CrashRecord),
+    /// Sent by the device every second with how it sees the link
+    Heartbeat { seq: u32, link: LinkState },
+    /// Current value of a setting, after it was saved or asked for
+    Setting(SettingKey, String),
+    /// Settings are back to defaults
+    SettingsReset,
+    /// Setting not changed, or changed but not saved to flash
+    SettingFailed(SettingsError),
+    /// Progress of a firmware update, also `Testing` or `RolledBack` once after the reboot
+    Firmware(FirmwareEvent),
+    /// Firmware update command refused
+    FirmwareFailed(UpdateError),
+    /// Answer to `uart_switch` and `uart_confirm`, or the switch fell back
+    Uart(UartEvent),
+    /// One group of diagnostics counters, three of them answer `PicoClient::request_stats`
+    Stats(StatsReport),
+    /// Valid frame without anything this client knows about
+    Other(Box<Pico2PiMessage>),
+}
+
+impl From<Pico2PiMessage> for DeviceEvent {
+    fn from(message: Pico2PiMessage) -> Self {
+        match message.keyboard_codes {
+            Some(code) => DeviceEvent::Key(KeyEvent {
+                code,
+                held_ms: message.keypress_ms,
+                screen_size: message.wh.map(|[w, h]| (w, h)),
+            }),
+            None => if let Some(token) = &message.pong {
+                DeviceEvent::Pong(String::from(token.as_str()))
+            } else if let Some(index) = message.list_selected {
+                DeviceEvent::ListSelected(index)
+            } else if let Some(page) = message.list_page {
+                DeviceEvent::ListPage(page)
+            } else if let Some(answer) = message.dialog_answer {
+                DeviceEvent::DialogAnswer(answer)
+            } else if let Some(value) = message.spinner_value {
+                DeviceEvent::SpinnerValue(value)
+            } else if let Some(result) = message.layout_result {
+                match result {
+                    Ok(()) => DeviceEvent::LayoutAccepted,
+                    Err(err) => DeviceEvent::LayoutRejected(err),
+                }
+            } else if let Some((id, result)) = message.image_result {
+                match result {
+                    Ok(()) => DeviceEvent::ImageStored(id),
+                    Err(err) => DeviceEvent::ImageFailed(id, err),
+                }
+            } else if let Some(result) = message.qr_result {
+                match result {
+                    Ok(()) => DeviceEvent::QrShown,
+                    Err(err) => DeviceEvent::QrFailed(err),
+                }
+            } else if let Some(reset_reason) = message.reset_reason {
+                DeviceEvent::Restarted(reset_reason)
+            } else if let Some(crash) = message.crash {
+                DeviceEvent::Crashed(crash)
+            } else if let (Some(seq), Some(link)) = (message.heartbeat, message.link_state) {
+                DeviceEvent::Heartbeat { seq, link }
+            } else if let Some((key, value)) = &message.setting {
+                DeviceEvent::Setting(*key, String::from(value.as_str()))
+            } else if message.settings_reset {
+                DeviceEvent::SettingsReset
+            } else if let Some(err) = message.setting_error {
+                DeviceEvent::SettingFailed(err)
+            } else if let Some(event) = message.firmware {
+                DeviceEvent::Firmware(event)
+            } else if let Some(err) = message.firmware_error {
+                DeviceEvent::FirmwareFailed(err)
+            } else if let Some(event) = message.uart {
+                DeviceEvent::Uart(event)
+            } else if let Some(report) = message.stats {
+                DeviceEvent::Stats(report)
+            } else {
+                DeviceEvent::Other(Box::new(message))
+            },
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::convert::TryInto;
+
+use heapless::String as HString;
+
+use pico_ui_core::update::layout::PICO_LAYOUT;
+use pico_ui_core::update::receiver::CHUNK_BYTES;
+use pico_ui_core::utils::base64;
+use pico_ui_core::utils::crc32::crc32;
+
+use error::ClientError;
+
+/// Raw bytes per `fw_data` line, 1368 characters once encoded
+pub const FIRMWARE_CHUNK_BYTES: usize = CHUNK_BYTES;
+
+/// Firmware for `PicoClient::update_firmware`: the raw binary of the firmware built for the
+/// bootloader, `objcopy -O binary` of the elf
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FirmwareImage {
+    data: Vec<u8>,
+}
+
+impl FirmwareImage {
+    /// Checked like the device does before installing it: fits the partition and starts with a
+    /// vector table for the address the bootloader starts it from
+    pub fn new(data: Vec<u8>) -> Result<Self, ClientError> {
+        let bad_image = |reason| ClientError::InvalidField { field: "firmware", reason };
+        if data.is_empty() {
+            return Err(bad_image("empty"));
+        }
+        if data.len() > PICO_LAYOUT.partition_bytes as usize {
+            return Err(bad_image("larger than the update partition"));
+        }
+        let vector_table = data.get(..8).and_then(|table| table.try_into().ok()).ok_or(bad_image("no vector table"))?;
+        if !PICO_LAYOUT.check_vector_table(vector_table) {
+            return Err(bad_image("not linked to run after the bootloader, use the .bin of a bootloader build"));
+        }
+        Ok(FirmwareImage { data })
+    }
+
+    /// Bytes sent to the device
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// `fw_begin` value
+    pub(crate) fn header(&self) -> String {
+        format!("{}:{:08x}", self.data.len(), crc32(&self.data))
+    }
+
+    /// `fw_data` value of the chunk at `offset`, `None` past the end
+    pub(crate) fn chunk(&self, offset: usize) -> Option<String> {
+        let end = (offset + FIRMWARE_CHUNK_BYTES).min(self.data.len());
+        let chunk = self.data.get(offset..end).filter(|chunk| !chunk.is_empty())?;
+        let mut encoded: HString<1368> = HString::new();
+        // chunk size keeps the encoded text within capacity
+        _ = base64::encode(chunk, &mut encoded);
+        Some(format!("{}:{:08x}:{}", offset, crc32(chunk), encoded))
+    }
+}
@@ -0,0 +1,157 @@
+use embedded_graphics_core::geometry::Size;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::{IntoStorage, RgbColor};
+use heapless::String as HString;
+
+use pico_ui_core::images::cache::IMAGE_POOL_BYTES;
+use pico_ui_core::images::format::{ImageFormat, MAX_IMAGE_SIDE};
+use pico_ui_core::utils::base64;
+use pico_ui_core::utils::crc32::crc32;
+
+use error::ClientError;
+
+/// Raw bytes per `img_data` line, 1024 characters once encoded
+pub const IMAGE_CHUNK_BYTES: usize = 768;
+
+/// Image for `PicoClient::send_image`, pixel data in the device format
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Bitmap {
+    size: Size,
+    format: ImageFormat,
+    data: Vec<u8>,
+}
+
+impl Bitmap {
+    /// Pixels row by row, `width * height` of them
+    pub fn rgb565(width: u32, height: u32, pixels: &[Rgb565]) -> Result<Self, ClientError> {
+        if pixels.len() as u64 != width as u64 * height as u64 {
+            return Err(ClientError::InvalidField { field: "bitmap", reason: "pixel count differs from size" });
+        }
+        let data = pixels.iter().flat_map(|pixel| pixel.into_storage().to_be_bytes()).collect();
+        Self::new(Size::new(width, height), ImageFormat::Rgb565, data)
+    }
+
+    /// Two colour image, `true` pixels are drawn with `fg`, the rest with `bg`
+    pub fn mono(width: u32, height: u32, pixels: &[bool], fg: Rgb565, bg: Rgb565) -> Result<Self, ClientError> {
+        if pixels.len() as u64 != width as u64 * height as u64 {
+            return Err(ClientError::InvalidField { field: "bitmap", reason: "pixel count differs from size" });
+        }
+        let mut data = Vec::new();
+        for row in pixels.chunks(width.max(1) as usize) {
+            for byte_pixels in row.chunks(8) {
+                let byte = byte_pixels.iter().enumerate()
+                    .fold(0u8, |byte, (index, set)| if *set { byte | 0x80 >> index } else { byte });
+                data.push(byte);
+            }
+        }
+        Self::new(Size::new(width, height), ImageFormat::Mono { fg, bg }, data)
+    }
+
+    /// Binary netpbm: `P6` colour with maxval up to 255 or `P4` bitmap. Bitmap ink is drawn
+    /// white on black, use `mono` for other colours.
+    pub fn from_netpbm(bytes: &[u8]) -> Result<Self, ClientError> {
+        let bad_file = |reason| ClientError::InvalidField { field: "netpbm", reason };
+        let mut rest = bytes;
+        let magic = header_token(&mut rest).ok_or(bad_file("no header"))?;
+        let width = header_number(&mut rest).ok_or(bad_file("bad width"))?;
+        let height = header_number(&mut rest).ok_or(bad_file("bad height"))?;
+        if width > MAX_IMAGE_SIDE || height > MAX_IMAGE_SIDE {
+            return Err(bad_file("larger than the device display"));
+        }
+        match magic.as_slice() {
+            b"P6" => {
+                let max = header_number(&mut rest).filter(|max| (1..=255).contains(max)).ok_or(bad_file("maxval not 1..255"))?;
+                // one whitespace after the header, then pixels
+                let data = rest.get(1..).ok_or(bad_file("no pixel data"))?;
+                let scale = |value: u8| (value as u32 * 255 / max) as u8;
+                let pixels: Vec<Rgb565> = data.chunks_exact(3)
+                    .take((width * height) as usize)
+                    .map(|rgb| Rgb565::new(scale(rgb[0]) >> 3, scale(rgb[1]) >> 2, scale(rgb[2]) >> 3))
+                    .collect();
+                Self::rgb565(width, height, &pixels).map_err(|_| bad_file("truncated pixel data"))
+            }
+            b"P4" => {
+                let data = rest.get(1..).ok_or(bad_file("no pixel data"))?;
+                let stride = width.div_ceil(8) as usize;
+                if data.len() < stride * height as usize {
+                    return Err(bad_file("truncated pixel data"));
+                }
+                let format = ImageFormat::Mono { fg: Rgb565::WHITE, bg: Rgb565::BLACK };
+                Self::new(Size::new(width, height), format, data[..stride * height as usize].to_vec())
+            }
+            _ => Err(bad_file("only binary P6 and P4 are supported")),
+        }
+    }
+
+    fn new(size: Size, format: ImageFormat, data: Vec<u8>) -> Result<Self, ClientError> {
+        if data.is_empty() {
+            return Err(ClientError::InvalidField { field: "bitmap", reason: "empty" });
+        }
+        if size.width > MAX_IMAGE_SIDE || size.height > MAX_IMAGE_SIDE {
+            return Err(ClientError::InvalidField { field: "bitmap", reason: "larger than the device display" });
+        }
+        if data.len() > IMAGE_POOL_BYTES {
+            return Err(ClientError::InvalidField { field: "bitmap", reason: "larger than the device image cache" });
+        }
+        Ok(Bitmap { size, format, data })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.size.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size.height
+    }
+
+    /// Bytes sent to the device
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// `img_begin` value
+    pub(crate) fn header(&self, id: u8) -> String {
+        format!("{}:{}x{}:{}", id, self.size.width, self.size.height, self.format.encode())
+    }
+
+    /// `img_data` values from `offset` on
+    pub(crate) fn chunks(&self, id: u8, offset: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut chunk_offset = offset.min(self.data.len());
+        for chunk in self.data[chunk_offset..].chunks(IMAGE_CHUNK_BYTES) {
+            let mut encoded: HString<1024> = HString::new();
+            // chunk size keeps the encoded text within capacity
+            _ = base64::encode(chunk, &mut encoded);
+            chunks.push(format!("{}:{}:{}", id, chunk_offset, encoded));
+            chunk_offset += chunk.len();
+        }
+        chunks
+    }
+
+    /// `img_end` value
+    pub(crate) fn trailer(&self, id: u8) -> String {
+        format!("{}:{:08x}", id, crc32(&self.data))
+    }
+}
+
+/// Next whitespace separated token, `#` comments skipped
+fn header_token(rest: &mut &[u8]) -> Option<Vec<u8>> {
+    loop {
+        while rest.first()?.is_ascii_whitespace() {
+            *rest = &rest[1..];
+        }
+        if rest[0] != b'#' {
+            break;
+        }
+        let line_end = rest.iter().position(|x| *x == b'\n')?;
+        *rest = &rest[line_end..];
+    }
+    let end = rest.iter().position(|x| x.is_ascii_whitespace()).unwrap_or(rest.len());
+    let token = rest[..end].to_vec();
+    *rest = &rest[end..];
+    Some(token)
+}
+
+fn header_number(rest: &mut &[u8]) -> Option<u32> {
+    std::str::from_utf8(&header_token(rest)?).ok()?.parse().ok()
+}
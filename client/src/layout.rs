@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use pico_ui_core::layout::node::{Align, Font, Icon, NodeKind};
+use pico_ui_core::layout::tree::{Layout, LAYOUT_CAPACITY};
+use pico_ui_core::messages::pi_2_pico_message::RESERVED_CHARS;
+
+use error::ClientError;
+
+/// Characters with a meaning inside `layout=`, see `pico_ui_core::layout::node::Node`
+const NODE_SEPARATORS: [char; 2] = [';', '|'];
+
+/// One node of a `ScreenLayout`. Rectangles are relative to the parent node.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LayoutNode {
+    kind: NodeKind,
+    rect: (i32, i32, u32, u32),
+    parent: Option<usize>,
+    fg: Option<(u8, u8, u8)>,
+    bg: Option<(u8, u8, u8)>,
+    font: Option<Font>,
+    align: Option<Align>,
+    text: Option<String>,
+    value: Option<i32>,
+    icon: Option<Icon>,
+}
+
+impl LayoutNode {
+    fn new(kind: NodeKind, x: i32, y: i32, width: u32, height: u32) -> Self {
+        LayoutNode {
+            kind,
+            rect: (x, y, width, height),
+            parent: None,
+            fg: None,
+            bg: None,
+            font: None,
+            align: None,
+            text: None,
+            value: None,
+            icon: None,
+        }
+    }
+
+    /// Container for other nodes, draws only its background
+    pub fn region(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self::new(NodeKind::Region, x, y, width, height)
+    }
+
+    /// Single line of text, vertically centered
+    pub fn text(x: i32, y: i32, width: u32, height: u32, text: &str) -> Self {
+        Self::new(NodeKind::Text, x, y, width, height).with_text(text)
+    }
+
+    /// Items with the `cursor` one highlighted, the device scrolls to keep it visible
+    pub fn list<S: AsRef<str>>(x: i32, y: i32, width: u32, height: u32, items: &[S], cursor: usize) -> Self {
+        let items: Vec<&str> = items.iter().map(|x| x.as_ref()).collect();
+        let mut node = Self::new(NodeKind::List, x, y, width, height).with_text(&items.join(","));
+        node.value = Some(cursor as i32);
+        node
+    }
+
+    pub fn bar(x: i32, y: i32, width: u32, height: u32, percent: u8) -> Self {
+        let mut node = Self::new(NodeKind::Bar, x, y, width, height);
+        node.value = Some(percent.min(100) as i32);
+        node
+    }
+
+    /// `value` sets the charge of `Icon::Battery`
+    pub fn icon(x: i32, y: i32, width: u32, height: u32, icon: Icon) -> Self {
+        let mut node = Self::new(NodeKind::Icon, x, y, width, height);
+        node.icon = Some(icon);
+        node
+    }
+
+    /// Index of an earlier node in the same layout
+    pub fn parent(mut self, index: usize) -> Self {
+        self.parent = Some(index);
+        self
+    }
+
+    pub fn fg(mut self, r: u8, g: u8, b: u8) -> Self {
+        self.fg = Some((r, g, b));
+        self
+    }
+
+    pub fn bg(mut self, r: u8, g: u8, b: u8) -> Self {
+        self.bg = Some((r, g, b));
+        self
+    }
+
+    pub fn font(mut self, font: Font) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    pub fn value(mut self, value: i32) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    /// `<kind>|<x>,<y>,<w>,<h>|<property>=<value>...`
+    fn encode(&self) -> Result<String, ClientError> {
+        let (x, y, width, height) = self.rect;
+        let mut node = format!("{}|{},{},{},{}", self.kind.as_str(), x, y, width, height);
+        if let Some(parent) = self.parent {
+            _ = write!(node, "|parent={}", parent);
+        }
+        if let Some((r, g, b)) = self.fg {
+            _ = write!(node, "|fg={:02x}{:02x}{:02x}", r, g, b);
+        }
+        if let Some((r, g, b)) = self.bg {
+            _ = write!(node, "|bg={:02x}{:02x}{:02x}", r, g, b);
+        }
+        if let Some(font) = self.font {
+            _ = write!(node, "|font={}", font.as_str());
+        }
+        if let Some(align) = self.align {
+            _ = write!(node, "|align={}", align.as_str());
+        }
+        if let Some(text) = &self.text {
+            if text.contains(&RESERVED_CHARS[..]) || text.contains(&NODE_SEPARATORS[..]) {
+                return Err(ClientError::InvalidField { field: "layout text", reason: "contains protocol separator" });
+            }
+            let key = if self.kind == NodeKind::List { "items" } else { "text" };
+            _ = write!(node, "|{}={}", key, text);
+        }
+        if let Some(value) = self.value {
+            _ = write!(node, "|value={}", value);
+        }
+        if let Some(icon) = self.icon {
+            _ = write!(node, "|icon={}", icon.as_str());
+        }
+        Ok(node)
+    }
+}
+
+/// Builder of a screen layout replacing screen lines, see `PicoClient::send_layout`.
+/// Geometry is checked by the device against its display, the answer is
+/// `DeviceEvent::LayoutAccepted` or `DeviceEvent::LayoutRejected`.
+#[derive(Debug, Default, Clone)]
+pub struct ScreenLayout {
+    nodes: Vec<LayoutNode>,
+}
+
+impl ScreenLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of the added node, used by children as `parent` and by `PicoClient::update_layout_node`
+    pub fn add(&mut self, node: LayoutNode) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Value of `layout=`, parsed with the device code so only geometry errors are left for the device
+    pub fn build(&self) -> Result<String, ClientError> {
+        if self.nodes.len() > LAYOUT_CAPACITY {
+            return Err(ClientError::InvalidField { field: "layout", reason: "at most 16 nodes" });
+        }
+        let mut nodes = Vec::new();
+        for node in &self.nodes {
+            nodes.push(node.encode()?);
+        }
+        let layout = nodes.join(";");
+        Layout::parse(&layout).map_err(ClientError::Layout)?;
+        Ok(layout)
+    }
+}
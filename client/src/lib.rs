@@ -0,0 +1,64 @@
+//! Raspberry Pi side client of the device protocol.
+//!
+//! ```no_run
+//! use pico_ui_client::{DeviceEvent, ScreenUpdate};
+//!
+//! let mut client = pico_ui_client::open_serial("/dev/ttyAMA0").unwrap();
+//! client.send_screen(&ScreenUpdate::new()
+//!     .status("192.168.1.10", 87)
+//!     .title("Menu", 1, 3)
+//!     .lines(&["first", "second"])
+//!     .cursor(0)).unwrap();
+//! loop {
+//!     if let Some(DeviceEvent::Key(key)) = client.read_event().unwrap() {
+//!         println!("{:?} held {} ms", key.code, key.held_ms);
+//!     }
+//! }
+//! ```
+
+extern crate heapless;
+extern crate embedded_graphics_core;
+extern crate pico_ui_core;
+#[cfg(feature = "serial")]
+extern crate serialport;
+
+mod client;
+mod error;
+mod event;
+mod firmware;
+mod image;
+mod layout;
+mod loopback;
+mod screen;
+#[cfg(feature = "serial")]
+mod serial;
+
+pub use client::{Alert, PicoClient, UartControl};
+pub use error::ClientError;
+pub use event::{DeviceEvent, KeyEvent};
+pub use firmware::{FirmwareImage, FIRMWARE_CHUNK_BYTES};
+pub use image::{Bitmap, IMAGE_CHUNK_BYTES};
+pub use layout::{LayoutNode, ScreenLayout};
+pub use loopback::{LoopbackDevice, LOOPBACK_STEP_MS};
+pub use screen::ScreenUpdate;
+#[cfg(feature = "serial")]
+pub use serial::{open_serial, open_serial_with, BAUD_RATE};
+
+pub use embedded_graphics_core::pixelcolor::{Rgb565, RgbColor};
+pub use pico_ui_core::crash::record::CrashRecord;
+pub use pico_ui_core::device::reset::ResetReason;
+pub use pico_ui_core::diagnostics::counters::{StatsGroup, StatsReport};
+pub use pico_ui_core::images::error::ImageError;
+pub use pico_ui_core::input::keyboard_codes::KeyboardCodes;
+pub use pico_ui_core::layout::error::LayoutError;
+pub use pico_ui_core::layout::node::{Align, Font, Icon};
+pub use pico_ui_core::leds::pattern::{LedId, LedPattern};
+pub use pico_ui_core::messages::pico_2_pi_message::Pico2PiMessageError;
+pub use pico_ui_core::link::monitor::{LinkState, HEARTBEAT_INTERVAL_MS, LOST_AFTER_MS};
+pub use pico_ui_core::link::uart::{UartEvent, UartFormat, UartParity, UartPins, UART_CONFIRM_MS};
+pub use pico_ui_core::qr::code::{EccLevel, QrError};
+pub use pico_ui_core::screen::style::{LineStyle, Overflow};
+pub use pico_ui_core::settings::setting::{DisplayOrientation, SettingKey, Settings, SettingsError, BAUD_RATES};
+pub use pico_ui_core::update::error::UpdateError;
+pub use pico_ui_core::update::event::FirmwareEvent;
+pub use pico_ui_core::widgets::toast::DEFAULT_TOAST_MS;
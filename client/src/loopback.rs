@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+
+use embedded_graphics_core::geometry::Size;
+use pico_ui_core::device::reset::ResetReason;
+use pico_ui_core::device::state::DeviceState;
+use pico_ui_core::input::keyboard_codes::KeyboardCodes;
+use pico_ui_core::messages::receiver::LineReceiver;
+use pico_ui_core::settings::setting::Settings;
+use pico_ui_core::update::boot_state::FirmwareStatus;
+
+/// Time the device moves on with every read
+pub const LOOPBACK_STEP_MS: u64 = 10;
+
+/// The device state machine in-process behind `Read + Write`, the same code the firmware runs
+/// between its uart and screen. Lets Pi applications and this crate be tested without hardware
+/// or the simulator: `PicoClient::new(LoopbackDevice::new())`.
+///
+/// Time is simulated and moves on by `LOOPBACK_STEP_MS` with every read, reads with nothing to
+/// give fail with `WouldBlock` like a serial port timing out. Settings are kept in memory,
+/// firmware updates and uart switches are not carried out.
+pub struct LoopbackDevice {
+    state: DeviceState,
+    receiver: LineReceiver,
+    now_us: u64,
+    /// Key held down until the time, in us
+    pressed: Option<(KeyboardCodes, u64)>,
+    tx: VecDeque<u8>,
+}
+
+impl LoopbackDevice {
+    /// Device after power on with default settings and a 128x128 display
+    pub fn new() -> Self {
+        Self::with_settings(Settings::default())
+    }
+
+    pub fn with_settings(settings: Settings) -> Self {
+        let now_us = 1_000;
+        LoopbackDevice {
+            state: DeviceState::new(now_us, Size::new(128, 128), ResetReason::PowerOn, None, settings, FirmwareStatus::Idle),
+            receiver: LineReceiver::new(),
+            now_us,
+            pressed: None,
+            tx: VecDeque::new(),
+        }
+    }
+
+    /// What the device shows and knows, as core1 would draw it
+    pub fn state(&self) -> &DeviceState {
+        &self.state
+    }
+
+    /// Press a physical key for `hold_ms`, released by the reads after
+    pub fn press(&mut self, key: KeyboardCodes, hold_ms: u64) {
+        self.pressed = Some((key, self.now_us + hold_ms * 1_000));
+    }
+
+    /// Let the device run for `ms` without reading what it sends
+    pub fn advance(&mut self, ms: u64) {
+        for _ in 0..ms.div_ceil(LOOPBACK_STEP_MS) {
+            self.step();
+        }
+    }
+
+    /// One pass of the firmware loop
+    fn step(&mut self) {
+        self.now_us += LOOPBACK_STEP_MS * 1_000;
+        if self.pressed.is_some_and(|(_, release_at)| self.now_us >= release_at) {
+            self.pressed = None;
+        }
+        self.state.on_keys(self.pressed.map(|(key, _)| key), self.now_us);
+        self.state.update(self.now_us);
+        while let Some(key) = self.state.take_unsaved_setting() {
+            self.state.on_setting_saved(key, true);
+        }
+        while let Some(frame) = self.state.poll_outgoing(self.now_us) {
+            self.tx.extend(frame.as_bytes());
+        }
+    }
+}
+
+impl Default for LoopbackDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Read for LoopbackDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.tx.is_empty() {
+            self.step();
+        }
+        if self.tx.is_empty() {
+            return Err(io::Error::new(ErrorKind::WouldBlock, "nothing from the device"));
+        }
+        let count = buf.len().min(self.tx.len());
+        for (target, byte) in buf.iter_mut().zip(self.tx.drain(..count)) {
+            *target = byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Write for LoopbackDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            match self.receiver.push_byte(byte) {
+                Ok(Some(line)) => self.state.handle_line(line, self.now_us),
+                Ok(None) => {}
+                // counted like the firmware does, the next line is fine again
+                Err(err) => self.state.diagnostics.on_receive_error(err),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use heapless::{String, Vec};
+use pico_ui_core::messages::pi_2_pico_message::{Pi2PicoMessage, RESERVED_CHARS};
+use pico_ui_core::layout::node::Font;
+use pico_ui_core::screen::lines::{DATA_LINES_COUNT, LINES_COUNT};
+use pico_ui_core::screen::style::LineStyle;
+
+use error::ClientError;
+
+/// Builder of a screen update: status line, title with paginator, data lines, cursor and line styles.
+/// Parts which are not set are left as they are on the device.
+#[derive(Debug, Default, Clone)]
+pub struct ScreenUpdate {
+    cursor_index: Option<usize>,
+    status: Option<(std::string::String, u8)>,
+    title: Option<(std::string::String, u32, u32)>,
+    lines: Option<std::vec::Vec<std::string::String>>,
+    line_styles: std::vec::Vec<(usize, LineStyle)>,
+}
+
+impl ScreenUpdate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlight data line with given index (0 is the first data line)
+    pub fn cursor(mut self, index: usize) -> Self {
+        self.cursor_index = Some(index);
+        self
+    }
+
+    /// First line: ip address and battery percent
+    pub fn status(mut self, ip: &str, battery_percent: u8) -> Self {
+        self.status = Some((ip.to_string(), battery_percent));
+        self
+    }
+
+    /// Last line: title and `page/pages` paginator
+    pub fn title(mut self, title: &str, page: u32, pages: u32) -> Self {
+        self.title = Some((title.to_string(), page, pages));
+        self
+    }
+
+    /// Data lines, up to 8. Missing lines are cleared on the device.
+    pub fn lines<S: AsRef<str>>(mut self, lines: &[S]) -> Self {
+        self.lines = Some(lines.iter().map(|x| x.as_ref().to_string()).collect());
+        self
+    }
+
+    /// Style of a screen line, 0 is the status line, 1-8 data lines, 9 the title.
+    /// The device keeps it for the line until another style is sent, `LineStyle::default()` resets it.
+    pub fn line_style(mut self, line: usize, style: LineStyle) -> Self {
+        self.line_styles.retain(|(styled_line, _)| *styled_line != line);
+        self.line_styles.push((line, style));
+        self
+    }
+
+    /// Validate against device limits and convert to the shared message type
+    pub fn build(&self) -> Result<Pi2PicoMessage, ClientError> {
+        let mut message = Pi2PicoMessage::default();
+
+        if let Some(cursor_index) = self.cursor_index {
+            if cursor_index >= DATA_LINES_COUNT {
+                return Err(invalid("cursor_index", "must be less than 8"));
+            }
+            message.cursor_index = Some(cursor_index as isize);
+        }
+
+        if let Some((ip, battery_percent)) = &self.status {
+            message.ip_and_battery = Some((
+                field("ip", ip, &['/'])?,
+                field("battery", &(*battery_percent).min(100).to_string(), &[])?,
+            ));
+        }
+
+        if let Some((title, page, pages)) = &self.title {
+            message.title_and_paginator = Some((
+                field("title", title, &['/'])?,
+                field("paginator", &format!("{}/{}", page, pages), &[])?,
+            ));
+        }
+
+        if let Some(lines) = &self.lines {
+            if lines.len() > DATA_LINES_COUNT {
+                return Err(invalid("data_lines", "at most 8 lines fit on the screen"));
+            }
+            let mut data_lines = Vec::new();
+            for line in lines {
+                let data_line = if line.is_empty() { None } else { Some(field("data_line", line, &[','])?) };
+                _ = data_lines.push(data_line);
+            }
+            message.data_lines = Some(data_lines);
+        }
+
+        if !self.line_styles.is_empty() {
+            let mut line_styles = Vec::new();
+            for (line, style) in &self.line_styles {
+                if *line >= LINES_COUNT {
+                    return Err(invalid("line_style", "line must be less than 10"));
+                }
+                if style.font == Font::Large {
+                    return Err(invalid("line_style", "large font does not fit into a line"));
+                }
+                _ = line_styles.push((*line, *style));
+            }
+            message.line_styles = Some(line_styles);
+        }
+
+        Ok(message)
+    }
+}
+
+fn invalid(field: &'static str, reason: &'static str) -> ClientError {
+    ClientError::InvalidField { field, reason }
+}
+
+/// Copy value into device sized string, rejecting separators instead of silently breaking the frame
+fn field<const N: usize>(name: &'static str, value: &str, separators: &[char]) -> Result<String<N>, ClientError> {
+    if value.contains(&RESERVED_CHARS[..]) || value.contains(separators) {
+        return Err(invalid(name, "contains protocol separator"));
+    }
+    value.parse::<String<N>>().map_err(|_| invalid(name, "too long"))
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use serialport::{DataBits, Parity, SerialPort, StopBits};
+
+use pico_ui_core::link::uart::{UartFormat, UartParity};
+use pico_ui_core::settings::setting::Settings;
+
+use client::{PicoClient, UartControl};
+use error::ClientError;
+
+/// Device uart rate with default settings, see `Settings`
+pub const BAUD_RATE: u32 = 115_200;
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Open device connected to serial port, e.g. `/dev/ttyAMA0`, `/dev/serial0` or simulator pty.
+/// The port is not locked, so one process can watch keys while others send screens.
+pub fn open_serial(path: &str) -> Result<PicoClient<Box<dyn SerialPort>>, ClientError> {
+    let defaults = Settings::default();
+    open_serial_with(path, UartFormat { baud: defaults.baud, parity: defaults.parity })
+}
+
+/// `open_serial` for a device with other `baud` and `parity` settings, or switched with
+/// `PicoClient::switch_uart`
+pub fn open_serial_with(path: &str, format: UartFormat) -> Result<PicoClient<Box<dyn SerialPort>>, ClientError> {
+    let builder = serialport::new(path, format.baud)
+        .data_bits(DataBits::Eight)
+        .parity(serial_parity(format.parity))
+        .stop_bits(StopBits::One)
+        .timeout(READ_TIMEOUT);
+    #[cfg(unix)]
+    let builder = builder.exclusive(false);
+    let port = builder.open().map_err(|err| ClientError::Io(err.into()))?;
+    Ok(PicoClient::new(port))
+}
+
+impl UartControl for Box<dyn SerialPort> {
+    fn uart_format(&self) -> Result<UartFormat, ClientError> {
+        let baud = self.baud_rate().map_err(|err| ClientError::Io(err.into()))?;
+        let parity = match self.parity().map_err(|err| ClientError::Io(err.into()))? {
+            Parity::None => UartParity::None,
+            Parity::Odd => UartParity::Odd,
+            Parity::Even => UartParity::Even,
+        };
+        Ok(UartFormat { baud, parity })
+    }
+
+    fn set_uart_format(&mut self, format: UartFormat) -> Result<(), ClientError> {
+        self.set_baud_rate(format.baud).map_err(|err| ClientError::Io(err.into()))?;
+        self.set_parity(serial_parity(format.parity)).map_err(|err| ClientError::Io(err.into()))
+    }
+}
+
+fn serial_parity(parity: UartParity) -> Parity {
+    match parity {
+        UartParity::None => Parity::None,
+        UartParity::Odd => Parity::Odd,
+        UartParity::Even => Parity::Even,
+    }
+}
@@ -0,0 +1,155 @@
+extern crate pico_ui_client;
+
+use pico_ui_client::{
+    ClientError, DeviceEvent, KeyboardCodes, LoopbackDevice, PicoClient, ResetReason, ScreenUpdate, SettingKey, SettingsError,
+    StatsGroup,
+};
+
+/// Reads to wait for an answer, each one moves the device on by `LOOPBACK_STEP_MS`
+const MAX_READS: usize = 500;
+
+fn connect() -> PicoClient<LoopbackDevice> {
+    PicoClient::new(LoopbackDevice::new())
+}
+
+/// First event `matches` accepts, others are skipped like heartbeats
+fn wait_for(client: &mut PicoClient<LoopbackDevice>, matches: impl Fn(&DeviceEvent) -> bool) -> DeviceEvent {
+    for _ in 0..MAX_READS {
+        if let Some(event) = client.read_event().unwrap() {
+            if matches(&event) {
+                return event;
+            }
+        }
+    }
+    panic!("no matching event in {} reads", MAX_READS);
+}
+
+#[test]
+fn first_line_is_answered_with_the_reset_reason() {
+    let mut client = connect();
+    client.ping("boot").unwrap();
+    assert_eq!(wait_for(&mut client, |_| true), DeviceEvent::Restarted(ResetReason::PowerOn));
+    assert_eq!(wait_for(&mut client, |_| true), DeviceEvent::Pong(String::from("boot")));
+}
+
+#[test]
+fn idle_device_reads_nothing() {
+    let mut client = connect();
+    assert_eq!(client.read_event().unwrap(), None);
+}
+
+#[test]
+fn screen_update_reaches_the_lines() {
+    let mut client = connect();
+    client.send_screen(&ScreenUpdate::new().title("Menu", 1, 3).lines(&["first", "second"])).unwrap();
+    let lines = &client.get_mut().state().lines;
+    let texts: Vec<&str> = lines.iter().flatten().map(|(text, _)| text.as_str()).collect();
+    assert!(texts.contains(&"first"));
+    assert!(texts.contains(&"second"));
+}
+
+#[test]
+fn key_press_is_reported_on_release() {
+    let mut client = connect();
+    client.get_mut().press(KeyboardCodes::Up, 100);
+    match wait_for(&mut client, |event| matches!(event, DeviceEvent::Key(_))) {
+        DeviceEvent::Key(key) => {
+            assert_eq!(key.code, KeyboardCodes::Up);
+            assert!(!key.is_hold());
+            assert_eq!(key.screen_size, Some((128, 128)));
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn held_key_repeats_as_hold() {
+    let mut client = connect();
+    client.get_mut().press(KeyboardCodes::Ok, 2_000);
+    match wait_for(&mut client, |event| matches!(event, DeviceEvent::Key(_))) {
+        DeviceEvent::Key(key) => assert!(key.is_hold()),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn list_is_scrolled_and_chosen_on_the_device() {
+    let mut client = connect();
+    client.show_list("Pick", &["one", "two", "three"], 0).unwrap();
+    client.get_mut().press(KeyboardCodes::Down, 50);
+    client.get_mut().advance(400);
+    client.get_mut().press(KeyboardCodes::Ok, 50);
+    let event = wait_for(&mut client, |event| matches!(event, DeviceEvent::ListSelected(_) | DeviceEvent::Key(_)));
+    assert_eq!(event, DeviceEvent::ListSelected(1));
+}
+
+#[test]
+fn dialog_is_answered() {
+    let mut client = connect();
+    client.ask("Sure?").unwrap();
+    client.get_mut().press(KeyboardCodes::Right, 50);
+    client.get_mut().advance(400);
+    client.get_mut().press(KeyboardCodes::Ok, 50);
+    assert_eq!(wait_for(&mut client, |event| matches!(event, DeviceEvent::DialogAnswer(_))), DeviceEvent::DialogAnswer(true));
+}
+
+#[test]
+fn setting_is_saved_and_answered() {
+    let mut client = connect();
+    client.set_setting(SettingKey::Brightness, "60").unwrap();
+    let event = wait_for(&mut client, |event| matches!(event, DeviceEvent::Setting(..) | DeviceEvent::SettingFailed(_)));
+    assert_eq!(event, DeviceEvent::Setting(SettingKey::Brightness, String::from("60")));
+    assert_eq!(client.get_mut().state().settings.brightness, 60);
+}
+
+#[test]
+fn bad_setting_is_refused() {
+    let mut client = connect();
+    assert!(matches!(client.set_setting(SettingKey::Brightness, "150"), Err(ClientError::InvalidField { .. })));
+    // the device checks again for clients that don't
+    client.send_line("setting_set=brightness:150").unwrap();
+    let event = wait_for(&mut client, |event| matches!(event, DeviceEvent::Setting(..) | DeviceEvent::SettingFailed(_)));
+    assert_eq!(event, DeviceEvent::SettingFailed(SettingsError::BadValue(SettingKey::Brightness)));
+}
+
+#[test]
+fn stats_come_in_every_group() {
+    let mut client = connect();
+    client.ping("x").unwrap();
+    client.request_stats().unwrap();
+    let mut groups = Vec::new();
+    while groups.len() < StatsGroup::ALL.len() {
+        match wait_for(&mut client, |event| matches!(event, DeviceEvent::Stats(_))) {
+            DeviceEvent::Stats(report) => {
+                assert_eq!(report.values.len(), report.group.names().len());
+                if report.group == StatsGroup::Frames {
+                    // ping and stats
+                    assert_eq!(report.values[0], 2);
+                }
+                groups.push(report.group);
+            }
+            _ => unreachable!(),
+        }
+    }
+    assert_eq!(groups, StatsGroup::ALL.to_vec());
+}
+
+#[test]
+fn invalid_fields_are_not_sent() {
+    let mut client = connect();
+    assert!(matches!(client.send_line("a\r\nb"), Err(ClientError::InvalidField { .. })));
+    assert!(matches!(client.ping("way too long for the device"), Err(ClientError::InvalidField { .. })));
+    assert_eq!(client.get_mut().state().diagnostics.frames_rx, 0);
+}
+
+#[test]
+fn cyrillic_text_fits_as_many_characters_as_latin() {
+    let mut client = connect();
+    client.show_list("Главное меню ПК", &["Настройки экрана и с", "Выход"], 0).unwrap();
+    client.ask("Сохранить настройки?").unwrap();
+    client.toast("Настройки сохранены", 500).unwrap();
+    assert!(matches!(client.ask("Сохранить настройки??"), Err(ClientError::InvalidField { .. })));
+    assert!(matches!(client.show_progress(10, "Label longer than twenty"), Err(ClientError::InvalidField { .. })));
+    assert_eq!(client.get_mut().state().diagnostics.frames_rx, 3);
+    assert_eq!(client.get_mut().state().diagnostics.parse_errors, 0);
+}
@@ -0,0 +1,205 @@
+/// Default fade length for brightness changes requested by the Pi
+pub const DEFAULT_FADE_MS: u32 = 500;
+/// Waking up on key press is instant, the key press must not go by in the dark
+const WAKE_FADE_MS: u32 = 0;
+/// Dimming and switching off are slow, user can notice it and press a key
+const DIM_FADE_MS: u32 = 1500;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BacklightState {
+    Active,
+    Dimmed,
+    Off,
+}
+
+#[derive(Clone, Copy)]
+struct Fade {
+    from: u8,
+    to: u8,
+    started_at_ms: u64,
+    duration_ms: u32,
+}
+
+impl Fade {
+    fn level_at(&self, now_ms: u64) -> u8 {
+        let elapsed = now_ms.saturating_sub(self.started_at_ms);
+        if self.duration_ms == 0 || elapsed >= self.duration_ms as u64 {
+            return self.to;
+        }
+        let from = self.from as i64;
+        let to = self.to as i64;
+        (from + (to - from) * elapsed as i64 / self.duration_ms as i64) as u8
+    }
+}
+
+/// Backlight brightness (percent, 0..=100) with fades and inactivity based auto-dim/off.
+/// Hardware independent, driven by timestamps from the core0 loop.
+pub struct BacklightPolicy {
+    brightness: u8,
+    dim_level: u8,
+    /// inactivity before dimming, `None` - never
+    dim_after_ms: Option<u32>,
+    /// inactivity before switching off, `None` - never
+    off_after_ms: Option<u32>,
+    last_activity_ms: u64,
+    fade: Fade,
+    next_fade_ms: u32,
+}
+
+impl BacklightPolicy {
+    pub fn new(brightness: u8, now_ms: u64) -> Self {
+        let brightness = brightness.min(100);
+        BacklightPolicy {
+            brightness,
+            dim_level: 10,
+            dim_after_ms: None,
+            off_after_ms: None,
+            last_activity_ms: now_ms,
+            fade: Fade { from: 0, to: brightness, started_at_ms: now_ms, duration_ms: DEFAULT_FADE_MS },
+            next_fade_ms: DEFAULT_FADE_MS,
+        }
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// Set brightness of the active (not dimmed) backlight
+    pub fn set_brightness(&mut self, brightness: u8, fade_ms: u32, now_ms: u64) {
+        self.brightness = brightness.min(100);
+        self.next_fade_ms = fade_ms;
+        // explicit request from the Pi counts as activity, otherwise it could be invisible
+        self.last_activity_ms = now_ms;
+    }
+
+    /// Configure auto-dim. Zero disables the corresponding step.
+    pub fn set_auto_dim(&mut self, dim_after_ms: u32, off_after_ms: u32, dim_level: u8) {
+        self.dim_after_ms = if dim_after_ms > 0 { Some(dim_after_ms) } else { None };
+        self.off_after_ms = if off_after_ms > 0 { Some(off_after_ms) } else { None };
+        self.dim_level = dim_level.min(100);
+    }
+
+    /// Key press or other user activity. Returns true if backlight was dimmed or off.
+    pub fn on_activity(&mut self, now_ms: u64) -> bool {
+        let was_inactive = self.state(now_ms) != BacklightState::Active;
+        self.last_activity_ms = now_ms;
+        if was_inactive {
+            self.next_fade_ms = WAKE_FADE_MS;
+        }
+        was_inactive
+    }
+
+    pub fn state(&self, now_ms: u64) -> BacklightState {
+        let idle_ms = now_ms.saturating_sub(self.last_activity_ms);
+        if self.off_after_ms.is_some_and(|off| idle_ms >= off as u64) {
+            return BacklightState::Off;
+        }
+        if self.dim_after_ms.is_some_and(|dim| idle_ms >= dim as u64) {
+            return BacklightState::Dimmed;
+        }
+        BacklightState::Active
+    }
+
+    /// Current brightness in percent
+    pub fn level(&mut self, now_ms: u64) -> u8 {
+        let target = match self.state(now_ms) {
+            BacklightState::Active => self.brightness,
+            BacklightState::Dimmed => self.dim_level.min(self.brightness),
+            BacklightState::Off => 0,
+        };
+        if target != self.fade.to {
+            let duration_ms = if target < self.fade.to && self.state(now_ms) != BacklightState::Active {
+                DIM_FADE_MS
+            } else {
+                self.next_fade_ms
+            };
+            self.fade = Fade {
+                from: self.fade.level_at(now_ms),
+                to: target,
+                started_at_ms: now_ms,
+                duration_ms,
+            };
+            self.next_fade_ms = DEFAULT_FADE_MS;
+        }
+        self.fade.level_at(now_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 80 % after the power-on fade, dims to 25 % after 10 s and goes off after 30 s
+    fn policy() -> BacklightPolicy {
+        let mut policy = BacklightPolicy::new(80, 0);
+        policy.set_auto_dim(10_000, 30_000, 25);
+        policy
+    }
+
+    #[test]
+    fn fades_interpolate_linearly() {
+        let mut policy = policy();
+        assert_eq!(policy.level(0), 0);
+        assert_eq!(policy.level(125), 20);
+        assert_eq!(policy.level(250), 40);
+        assert_eq!(policy.level(DEFAULT_FADE_MS as u64), 80);
+        policy.set_brightness(20, 1_000, 1_000);
+        assert_eq!(policy.level(1_000), 80);
+        assert_eq!(policy.level(1_500), 50);
+        assert_eq!(policy.level(2_000), 20);
+        assert_eq!(policy.brightness(), 20);
+    }
+
+    #[test]
+    fn fade_changing_target_midway_starts_from_the_current_level() {
+        let mut policy = policy();
+        assert_eq!(policy.level(250), 40);
+        policy.set_brightness(100, 100, 250);
+        assert_eq!(policy.level(250), 40);
+        assert_eq!(policy.level(300), 70);
+        assert_eq!(policy.level(350), 100);
+    }
+
+    #[test]
+    fn inactivity_dims_then_switches_off() {
+        let mut policy = policy();
+        assert_eq!(policy.level(9_999), 80);
+        assert_eq!(policy.state(9_999), BacklightState::Active);
+        assert_eq!(policy.state(10_000), BacklightState::Dimmed);
+        assert_eq!(policy.level(10_000), 80);
+        assert_eq!(policy.level(10_750), 53);
+        assert_eq!(policy.level(10_000 + DIM_FADE_MS as u64), 25);
+        assert_eq!(policy.state(30_000), BacklightState::Off);
+        assert_eq!(policy.level(30_000), 25);
+        assert_eq!(policy.level(30_000 + DIM_FADE_MS as u64), 0);
+    }
+
+    #[test]
+    fn key_press_wakes_to_full_at_once() {
+        let mut policy = policy();
+        policy.level(30_000);
+        assert_eq!(policy.level(40_000), 0);
+        assert!(policy.on_activity(40_000));
+        assert_eq!(policy.state(40_000), BacklightState::Active);
+        assert_eq!(policy.level(40_000), 80);
+        // a key press while active only restarts the timeout
+        assert!(!policy.on_activity(45_000));
+        assert_eq!(policy.state(54_999), BacklightState::Active);
+    }
+
+    #[test]
+    fn settings_take_effect_right_away() {
+        let mut policy = policy();
+        assert_eq!(policy.state(20_000), BacklightState::Dimmed);
+        // the Pi setting brightness counts as activity
+        policy.set_brightness(60, 0, 20_000);
+        assert_eq!(policy.level(20_000), 60);
+        // dimming never brightens a dark backlight
+        policy.set_auto_dim(1_000, 0, 90);
+        assert_eq!(policy.level(21_000), 60);
+        assert_eq!(policy.state(u64::MAX), BacklightState::Dimmed);
+        policy.set_auto_dim(0, 0, 10);
+        assert_eq!(policy.state(u64::MAX), BacklightState::Active);
+        assert_eq!(BacklightPolicy::new(150, 0).brightness(), 100);
+    }
+}
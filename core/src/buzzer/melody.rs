@@ -0,0 +1,240 @@
+use core::convert::TryFrom;
+use core::ops::RangeInclusive;
+use heapless::Vec;
+
+pub const MAX_NOTES: usize = 64;
+
+/// Single note of a melody. `freq_hz == 0` is a pause.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Note {
+    pub freq_hz: u16,
+    pub duration_ms: u32,
+}
+
+pub type Melody = Vec<Note, MAX_NOTES>;
+
+/// Built-in alerts the Pi can trigger by name (`alert=notify`)
+pub const ALERT_NOTIFY: &str = "notify:d=16,o=6,b=180:c,e,g";
+pub const ALERT_SUCCESS: &str = "success:d=8,o=6,b=160:c,g,2c7";
+pub const ALERT_ERROR: &str = "error:d=4,o=4,b=120:8a,8p,8a,8p,2f";
+pub const BOOT_MELODY: &str = "boot:d=16,o=5,b=200:c,e,g,c6";
+
+/// Note lengths from a whole note to a 1/32
+const DURATIONS: RangeInclusive<u32> = 1..=32;
+/// Octaves the buzzer plays, `OCTAVE_4_HZ` shifted up to three times
+const OCTAVES: RangeInclusive<u8> = 4..=7;
+
+/// Frequencies of octave 4, C4..B4
+const OCTAVE_4_HZ: [u16; 12] = [262, 277, 294, 311, 330, 349, 370, 392, 415, 440, 466, 494];
+
+/// Parse RTTTL (ring tone text transfer language) string, e.g. `name:d=4,o=5,b=120:8c,8d,e.,2p,c6`
+pub fn parse_rtttl(data: &str) -> Result<Melody, RtttlError> {
+    let mut sections = data.splitn(3, ':');
+    let _name = sections.next().ok_or(RtttlError::MissingSection)?;
+    let defaults = sections.next().ok_or(RtttlError::MissingSection)?;
+    let notes = sections.next().ok_or(RtttlError::MissingSection)?;
+
+    let mut default_duration = 4u32;
+    let mut default_octave = 6u8;
+    let mut bpm = 63u32;
+    for default in defaults.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        let (key, value) = match default.find('=') {
+            Some(index) => (&default[..index], &default[index + 1..]),
+            None => return Err(RtttlError::BadDefault),
+        };
+        let value = value.parse::<u32>().map_err(|_| RtttlError::BadDefault)?;
+        match key {
+            "d" if DURATIONS.contains(&value) => default_duration = value,
+            "o" => {
+                default_octave = u8::try_from(value).ok()
+                    .filter(|octave| OCTAVES.contains(octave))
+                    .ok_or(RtttlError::BadDefault)?;
+            }
+            "b" => bpm = value,
+            _ => return Err(RtttlError::BadDefault),
+        }
+    }
+    if bpm == 0 {
+        return Err(RtttlError::BadDefault);
+    }
+    let whole_note_ms = 60_000 * 4 / bpm;
+
+    let mut melody = Vec::new();
+    for note in notes.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        let bytes = note.as_bytes();
+        let mut i = 0;
+
+        let mut duration = 0u32;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            duration = duration.checked_mul(10)
+                .and_then(|duration| duration.checked_add((bytes[i] - b'0') as u32))
+                .ok_or(RtttlError::BadNote)?;
+            i += 1;
+        }
+        if i == 0 {
+            duration = default_duration;
+        } else if !DURATIONS.contains(&duration) {
+            return Err(RtttlError::BadNote);
+        }
+
+        let semitone: Option<usize> = match bytes.get(i).map(|x| x.to_ascii_lowercase()) {
+            Some(b'c') => Some(0),
+            Some(b'd') => Some(2),
+            Some(b'e') => Some(4),
+            Some(b'f') => Some(5),
+            Some(b'g') => Some(7),
+            Some(b'a') => Some(9),
+            Some(b'b') | Some(b'h') => Some(11),
+            Some(b'p') => None,
+            _ => return Err(RtttlError::BadNote),
+        };
+        i += 1;
+
+        let mut sharp = false;
+        if bytes.get(i) == Some(&b'#') {
+            sharp = true;
+            i += 1;
+        }
+        // dot can be placed before or after octave
+        let mut dotted = false;
+        if bytes.get(i) == Some(&b'.') {
+            dotted = true;
+            i += 1;
+        }
+        let mut octave = default_octave;
+        if let Some(digit) = bytes.get(i).filter(|x| x.is_ascii_digit()) {
+            octave = digit - b'0';
+            if !OCTAVES.contains(&octave) {
+                return Err(RtttlError::BadNote);
+            }
+            i += 1;
+        }
+        if bytes.get(i) == Some(&b'.') {
+            dotted = true;
+            i += 1;
+        }
+        if i != bytes.len() {
+            return Err(RtttlError::BadNote);
+        }
+
+        let mut duration_ms = whole_note_ms / duration;
+        if dotted {
+            duration_ms += duration_ms / 2;
+        }
+        let freq_hz = match semitone {
+            Some(semitone) => note_frequency(semitone + sharp as usize, octave),
+            None => 0,
+        };
+        melody.push(Note { freq_hz, duration_ms }).map_err(|_| RtttlError::TooLong)?;
+    }
+    Ok(melody)
+}
+
+/// Frequency of semitone (0 = C, 12 = C of next octave) in octave
+fn note_frequency(semitone: usize, octave: u8) -> u16 {
+    let octave = octave + (semitone / 12) as u8;
+    let base = OCTAVE_4_HZ[semitone % 12] as u32;
+    let freq = if octave >= 4 {
+        base << (octave - 4).min(4)
+    } else {
+        base >> (4 - octave).min(4)
+    };
+    freq as u16
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RtttlError {
+    MissingSection,
+    BadDefault,
+    BadNote,
+    TooLong,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(data: &str) -> Melody {
+        parse_rtttl(data).unwrap()
+    }
+
+    #[test]
+    fn defaults_apply_to_plain_notes() {
+        assert_eq!(notes("tune:d=4,o=5,b=120:c").as_slice(), &[Note { freq_hz: 524, duration_ms: 500 }]);
+        // d=4, o=6, b=63 without a defaults section
+        assert_eq!(notes("tune::c").as_slice(), &[Note { freq_hz: 1048, duration_ms: 952 }]);
+    }
+
+    #[test]
+    fn note_overrides_duration_and_octave() {
+        assert_eq!(notes("tune:d=4,o=5,b=120:8c,2a6").as_slice(), &[
+            Note { freq_hz: 524, duration_ms: 250 },
+            Note { freq_hz: 1760, duration_ms: 1000 },
+        ]);
+    }
+
+    #[test]
+    fn dot_lengthens_by_half_before_or_after_octave() {
+        assert_eq!(notes("tune:d=4,o=5,b=120:8c.,c.6,c6.").as_slice(), &[
+            Note { freq_hz: 524, duration_ms: 375 },
+            Note { freq_hz: 1048, duration_ms: 750 },
+            Note { freq_hz: 1048, duration_ms: 750 },
+        ]);
+    }
+
+    #[test]
+    fn sharps_and_the_top_of_the_range() {
+        assert_eq!(notes("tune:d=4,o=5,b=120:a#,f#4").as_slice(), &[
+            Note { freq_hz: 932, duration_ms: 500 },
+            Note { freq_hz: 370, duration_ms: 500 },
+        ]);
+        // B sharp in the highest octave is C of the next one
+        assert_eq!(notes("tune:o=7:b#")[0].freq_hz, 4192);
+    }
+
+    #[test]
+    fn pauses_are_silent() {
+        assert_eq!(notes("tune:d=4,o=5,b=120:2p,p.").as_slice(), &[
+            Note { freq_hz: 0, duration_ms: 1000 },
+            Note { freq_hz: 0, duration_ms: 750 },
+        ]);
+    }
+
+    #[test]
+    fn built_in_melodies_parse() {
+        for melody in [ALERT_NOTIFY, ALERT_SUCCESS, ALERT_ERROR, BOOT_MELODY] {
+            assert!(parse_rtttl(melody).is_ok());
+        }
+    }
+
+    #[test]
+    fn malformed_strings_are_rejected() {
+        assert_eq!(parse_rtttl("tune:d=4"), Err(RtttlError::MissingSection));
+        assert_eq!(parse_rtttl("tune:d:c"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune:q=1:c"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune:b=0:c"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune::x"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune::cc"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune::8"), Err(RtttlError::BadNote));
+        let too_long = ["tune::", &"c,".repeat(MAX_NOTES + 1)].concat();
+        assert_eq!(parse_rtttl(&too_long), Err(RtttlError::TooLong));
+    }
+
+    #[test]
+    fn numbers_out_of_range_are_rejected() {
+        assert_eq!(parse_rtttl("::4294967296"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune::99999999999999999999c"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune::0c"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune::64c"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune::c3"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune::c8"), Err(RtttlError::BadNote));
+        assert_eq!(parse_rtttl("tune:d=1,o=255,b=1:99999999999999999999"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune:d=0:c"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune:d=33:c"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune:o=3:c"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune:o=8:c"), Err(RtttlError::BadDefault));
+        assert_eq!(parse_rtttl("tune:b=99999999999:c"), Err(RtttlError::BadDefault));
+    }
+}
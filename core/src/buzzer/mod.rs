@@ -0,0 +1,3 @@
+pub mod melody;
+pub mod player;
+pub mod tone;
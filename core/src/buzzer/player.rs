@@ -0,0 +1,178 @@
+use buzzer::melody::{Melody, Note};
+use heapless::Vec;
+
+/// Length of the key-click feedback tone
+const KEY_CLICK: Note = Note { freq_hz: 4000, duration_ms: 8 };
+/// Silence between melody notes, so repeated notes are audible as separate ones
+const NOTE_GAP_MS: u32 = 10;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BuzzerOutput {
+    Silent,
+    Tone(u16),
+}
+
+/// Non-blocking melody player.
+/// Call `update` from the main loop with the current time and apply returned output changes to the PWM.
+pub struct BuzzerPlayer {
+    melody: Melody,
+    index: usize,
+    note_started_at_ms: u64,
+    in_gap: bool,
+    playing: bool,
+    output: BuzzerOutput,
+    pub key_click_enabled: bool,
+}
+
+impl BuzzerPlayer {
+    pub fn new() -> Self {
+        BuzzerPlayer {
+            melody: Vec::new(),
+            index: 0,
+            note_started_at_ms: 0,
+            in_gap: false,
+            playing: false,
+            output: BuzzerOutput::Silent,
+            key_click_enabled: true,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Replace whatever is playing with a single tone
+    pub fn play_tone(&mut self, freq_hz: u16, duration_ms: u32, now_ms: u64) {
+        let mut melody = Vec::new();
+        _ = melody.push(Note { freq_hz, duration_ms });
+        self.play(melody, now_ms);
+    }
+
+    /// Replace whatever is playing with a melody
+    pub fn play(&mut self, melody: Melody, now_ms: u64) {
+        self.melody = melody;
+        self.index = 0;
+        self.in_gap = false;
+        self.note_started_at_ms = now_ms;
+        self.playing = !self.melody.is_empty();
+    }
+
+    /// Short click on key press. Does not interrupt melodies and alerts.
+    pub fn key_click(&mut self, now_ms: u64) {
+        if self.key_click_enabled && !self.playing {
+            self.play_tone(KEY_CLICK.freq_hz, KEY_CLICK.duration_ms, now_ms);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.melody.clear();
+        self.playing = false;
+    }
+
+    /// Advance playback. Returns new output only when it differs from the previous one.
+    pub fn update(&mut self, now_ms: u64) -> Option<BuzzerOutput> {
+        let wanted = self.advance(now_ms);
+        if wanted == self.output {
+            return None;
+        }
+        self.output = wanted;
+        Some(wanted)
+    }
+
+    fn advance(&mut self, now_ms: u64) -> BuzzerOutput {
+        while self.playing {
+            let note = self.melody[self.index];
+            let elapsed = now_ms.saturating_sub(self.note_started_at_ms);
+            if !self.in_gap {
+                if elapsed < note.duration_ms as u64 {
+                    return match note.freq_hz {
+                        0 => BuzzerOutput::Silent,
+                        freq_hz => BuzzerOutput::Tone(freq_hz),
+                    };
+                }
+                self.note_started_at_ms += note.duration_ms as u64;
+                self.in_gap = self.index + 1 < self.melody.len();
+                if !self.in_gap {
+                    self.stop();
+                }
+            } else {
+                if elapsed < NOTE_GAP_MS as u64 {
+                    return BuzzerOutput::Silent;
+                }
+                self.note_started_at_ms += NOTE_GAP_MS as u64;
+                self.in_gap = false;
+                self.index += 1;
+            }
+        }
+        BuzzerOutput::Silent
+    }
+}
+
+impl Default for BuzzerPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn melody(notes: &[(u16, u32)]) -> Melody {
+        notes.iter().map(|&(freq_hz, duration_ms)| Note { freq_hz, duration_ms }).collect()
+    }
+
+    #[test]
+    fn notes_play_for_their_duration_with_gaps_between() {
+        let mut player = BuzzerPlayer::new();
+        player.play(melody(&[(440, 100), (0, 50), (523, 100)]), 1_000);
+        assert_eq!(player.update(1_000), Some(BuzzerOutput::Tone(440)));
+        assert_eq!(player.update(1_099), None);
+        assert_eq!(player.update(1_100), Some(BuzzerOutput::Silent));
+        // the gap and the pause after it are both silent
+        assert_eq!(player.update(1_159), None);
+        assert_eq!(player.update(1_169), None);
+        assert_eq!(player.update(1_170), Some(BuzzerOutput::Tone(523)));
+        assert_eq!(player.update(1_269), None);
+        assert!(player.is_playing());
+        // no gap after the last note
+        assert_eq!(player.update(1_270), Some(BuzzerOutput::Silent));
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn late_update_catches_up_without_drift() {
+        let mut player = BuzzerPlayer::new();
+        player.play(melody(&[(440, 100), (523, 100), (659, 100)]), 0);
+        assert_eq!(player.update(205), Some(BuzzerOutput::Tone(523)));
+        // third note starts 100 + 10 + 100 + 10 ms in, not counted from the late update
+        assert_eq!(player.update(219), Some(BuzzerOutput::Silent));
+        assert_eq!(player.update(220), Some(BuzzerOutput::Tone(659)));
+        assert_eq!(player.update(10_000), Some(BuzzerOutput::Silent));
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn key_click_does_not_interrupt_a_melody() {
+        let mut player = BuzzerPlayer::new();
+        player.play_tone(440, 100, 0);
+        player.key_click(10);
+        assert_eq!(player.update(20), Some(BuzzerOutput::Tone(440)));
+        player.stop();
+        assert_eq!(player.update(30), Some(BuzzerOutput::Silent));
+        player.key_click(40);
+        assert_eq!(player.update(40), Some(BuzzerOutput::Tone(KEY_CLICK.freq_hz)));
+        assert_eq!(player.update(48), Some(BuzzerOutput::Silent));
+        player.key_click_enabled = false;
+        player.key_click(50);
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn empty_melody_stays_silent() {
+        let mut player = BuzzerPlayer::new();
+        player.play(Vec::new(), 0);
+        assert!(!player.is_playing());
+        assert_eq!(player.update(0), None);
+    }
+}
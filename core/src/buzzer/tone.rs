@@ -0,0 +1,26 @@
+/// PWM slice settings producing a square wave of given frequency
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ToneDivider {
+    pub div_int: u8,
+    pub top: u16,
+}
+
+/// Calculate clock divider and wrap value, so `sys_hz / (div_int * (top + 1))` is close to `freq_hz`.
+/// Returns `None` when frequency is out of what PWM can do.
+pub fn tone_divider(freq_hz: u32, sys_hz: u32) -> Option<ToneDivider> {
+    if freq_hz == 0 || freq_hz > sys_hz / 2 {
+        return None;
+    }
+    let cycles = sys_hz / freq_hz;
+    // smallest divider that fits period into 16 bit counter, keeps resolution best
+    let div = cycles.div_ceil(0x10000);
+    let div = div.max(1);
+    if div > 255 {
+        return None;
+    }
+    let top = cycles / div - 1;
+    Some(ToneDivider {
+        div_int: div as u8,
+        top: top as u16,
+    })
+}
@@ -0,0 +1,3 @@
+pub mod record;
+pub mod store;
+pub mod screen;
@@ -0,0 +1,122 @@
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use heapless::String;
+
+/// Tail of the source path kept in a record, the file name is the useful part
+pub const CRASH_FILE_LEN: usize = 24;
+/// Start of the panic message kept in a record, the whole `crash=` frame has to fit in 80 bytes
+pub const CRASH_MESSAGE_LEN: usize = 32;
+
+/// What is known about a panic: where it happened and the start of its message.
+/// Cores are numbered like `jobs::core0` (uart and keys) and `jobs::core1` (screen).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CrashRecord {
+    pub core: u8,
+    pub file: String<CRASH_FILE_LEN>,
+    pub line: u32,
+    pub message: String<CRASH_MESSAGE_LEN>,
+}
+
+impl CrashRecord {
+    pub fn from_panic(info: &PanicInfo, core: u8) -> Self {
+        let (file, line) = match info.location() {
+            Some(location) => (location.file(), location.line()),
+            None => ("unknown", 0),
+        };
+        Self::new(core, file, line, format_args!("{}", info.message()))
+    }
+
+    /// File and message are cut to fit, characters which would break the frame are replaced
+    pub fn new(core: u8, file: &str, line: u32, message: fmt::Arguments) -> Self {
+        // long paths keep their end
+        let skip = file.chars().count().saturating_sub(CRASH_FILE_LEN);
+        let mut record = CrashRecord { core, file: String::new(), line, message: String::new() };
+        for c in file.chars().skip(skip) {
+            _ = record.file.push(if matches!(c, ':' | '&') { '_' } else { c });
+        }
+        _ = Truncating(&mut record.message).write_fmt(message);
+        record
+    }
+
+    /// `<core>:<file>:<line>:<message>`, the `crash=` value and the stored form
+    pub fn to_text(&self) -> String<72> {
+        let mut text = String::new();
+        _ = write!(text, "{}:{}:{}:{}", self.core, self.file, self.line, self.message);
+        text
+    }
+
+    /// Reverse of `to_text`, the message may contain `:`
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.splitn(4, ':');
+        let core = parts.next()?.parse::<u8>().ok()?;
+        let file = parts.next()?.parse::<String<CRASH_FILE_LEN>>().ok()?;
+        let line = parts.next()?.parse::<u32>().ok()?;
+        let message = parts.next()?.parse::<String<CRASH_MESSAGE_LEN>>().ok()?;
+        Some(CrashRecord { core, file, line, message })
+    }
+}
+
+impl fmt::Display for CrashRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "core{} panicked at {}:{}: {}", self.core, self.file, self.line, self.message)
+    }
+}
+
+/// Keeps what fits, line breaks and `&` would break the frame and become spaces
+struct Truncating<'a, const N: usize>(&'a mut String<N>);
+
+impl<'a, const N: usize> Write for Truncating<'a, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let c = if matches!(c, '&' | '\r' | '\n') { ' ' } else { c };
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_parses_back() {
+        let record = CrashRecord::new(1, "src/jobs.rs", 142, format_args!("index {} out of range: {}", 9, 8));
+        assert_eq!(record.to_text().as_str(), "1:src/jobs.rs:142:index 9 out of range: 8");
+        assert_eq!(CrashRecord::parse(record.to_text().as_str()), Some(record));
+    }
+
+    #[test]
+    fn long_path_keeps_its_end() {
+        let record = CrashRecord::new(0, "/home/build/.cargo/registry/src/heapless-0.7.6/src/vec.rs", 7, format_args!("x"));
+        assert_eq!(record.file.as_str(), "eapless-0.7.6/src/vec.rs");
+        assert_eq!(record.file.len(), CRASH_FILE_LEN);
+    }
+
+    #[test]
+    fn long_message_is_cut() {
+        let record = CrashRecord::new(0, "a.rs", 1, format_args!("{}", "0123456789".repeat(5)));
+        assert_eq!(record.message.as_str(), &"0123456789".repeat(4)[..CRASH_MESSAGE_LEN]);
+        // the longest record still fits the `crash=` value
+        let longest = CrashRecord::new(255, &"x".repeat(40), u32::MAX, format_args!("{}", "y".repeat(40)));
+        assert_eq!(CrashRecord::parse(longest.to_text().as_str()), Some(longest));
+    }
+
+    #[test]
+    fn frame_separators_are_replaced() {
+        let record = CrashRecord::new(0, "C:\\src&x.rs", 3, format_args!("a&b\r\nc:d"));
+        assert_eq!(record.file.as_str(), "C_\\src_x.rs");
+        assert_eq!(record.message.as_str(), "a b  c:d");
+        assert_eq!(CrashRecord::parse(record.to_text().as_str()), Some(record));
+    }
+
+    #[test]
+    fn malformed_text_does_not_parse() {
+        assert_eq!(CrashRecord::parse(""), None);
+        assert_eq!(CrashRecord::parse("x:a.rs:1:m"), None);
+        assert_eq!(CrashRecord::parse("0:a.rs:line:m"), None);
+        assert_eq!(CrashRecord::parse("0:a.rs:1"), None);
+    }
+}
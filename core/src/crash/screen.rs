@@ -0,0 +1,48 @@
+use core::fmt::Write;
+use embedded_graphics::text::{Alignment, Baseline};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::Point;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use heapless::String;
+use crash::record::CrashRecord;
+use screen::text::{draw_text, FONT_SET_10X20, FONT_SET_6X10};
+
+/// How long the panic handler keeps the crash screen before rebooting
+pub const CRASH_SCREEN_MS: u32 = 5000;
+/// Rows of the 6x10 font, location and message wrap at the display width
+const ROW_HEIGHT: i32 = 11;
+
+/// Red screen with the panic location and message, drawn by the panic handler before the reboot
+pub fn draw_crash<D>(display: &mut D, record: &CrashRecord) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let screen = display.bounding_box();
+    display.clear(Rgb565::RED)?;
+    draw_text(display, "PANIC", Point::new(screen.center().x, 4), &FONT_SET_10X20, Rgb565::WHITE,
+              Alignment::Center, Baseline::Top)?;
+
+    let columns = (screen.size.width / FONT_SET_6X10.character_width()) as usize;
+    let mut location: String<48> = String::new();
+    _ = write!(location, "core{} {}:{}", record.core, record.file, record.line);
+    let mut y = 30;
+    y = draw_wrapped(display, location.as_str(), columns, y, Rgb565::YELLOW)?;
+    draw_wrapped(display, record.message.as_str(), columns, y + ROW_HEIGHT / 2, Rgb565::WHITE)?;
+    Ok(())
+}
+
+/// Returns where the next row starts
+fn draw_wrapped<D>(display: &mut D, text: &str, columns: usize, mut y: i32, color: Rgb565) -> Result<i32, D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let mut rest = text;
+    while !rest.is_empty() {
+        let split = rest.char_indices().nth(columns).map_or(rest.len(), |(index, _)| index);
+        draw_text(display, &rest[..split], Point::new(2, y), &FONT_SET_6X10, color, Alignment::Left, Baseline::Top)?;
+        rest = &rest[split..];
+        y += ROW_HEIGHT;
+    }
+    Ok(y)
+}
@@ -0,0 +1,89 @@
+use crash::record::CrashRecord;
+use utils::crc32::crc32;
+
+/// Size of the memory kept over resets for one record
+pub const CRASH_STORE_BYTES: usize = 128;
+const CRASH_MAGIC: u32 = 0x4352_5348;
+/// magic, crc32 of the text, text length, all little endian u32
+const HEADER_BYTES: usize = 12;
+
+/// Put the record into memory which survives a reset. After a power cycle the memory holds noise,
+/// magic and checksum tell it from a record.
+pub fn save(store: &mut [u8; CRASH_STORE_BYTES], record: &CrashRecord) {
+    let text = record.to_text();
+    let text = text.as_bytes();
+    store[0..4].copy_from_slice(&CRASH_MAGIC.to_le_bytes());
+    store[4..8].copy_from_slice(&crc32(text).to_le_bytes());
+    store[8..12].copy_from_slice(&(text.len() as u32).to_le_bytes());
+    store[HEADER_BYTES..HEADER_BYTES + text.len()].copy_from_slice(text);
+}
+
+/// Record saved before the last reset, if any. The store is cleared, so it is reported once.
+pub fn take(store: &mut [u8; CRASH_STORE_BYTES]) -> Option<CrashRecord> {
+    let word = |offset: usize| u32::from_le_bytes([store[offset], store[offset + 1], store[offset + 2], store[offset + 3]]);
+    let (magic, checksum, len) = (word(0), word(4), word(8) as usize);
+    store[0..4].copy_from_slice(&[0; 4]);
+    if magic != CRASH_MAGIC || len > CRASH_STORE_BYTES - HEADER_BYTES {
+        return None;
+    }
+    let text = &store[HEADER_BYTES..HEADER_BYTES + len];
+    if crc32(text) != checksum {
+        return None;
+    }
+    CrashRecord::parse(core::str::from_utf8(text).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> CrashRecord {
+        CrashRecord::new(1, "src/screen/render.rs", 88, format_args!("attempt to divide by zero"))
+    }
+
+    fn saved() -> [u8; CRASH_STORE_BYTES] {
+        let mut store = [0xa5; CRASH_STORE_BYTES];
+        save(&mut store, &record());
+        store
+    }
+
+    #[test]
+    fn record_is_taken_once() {
+        let mut store = saved();
+        assert_eq!(take(&mut store), Some(record()));
+        assert_eq!(take(&mut store), None);
+    }
+
+    #[test]
+    fn noise_is_no_record() {
+        for fill in [0x00, 0xff, 0xa5] {
+            assert_eq!(take(&mut [fill; CRASH_STORE_BYTES]), None);
+        }
+    }
+
+    #[test]
+    fn damaged_record_is_dropped() {
+        let mut bad_crc = saved();
+        bad_crc[4] ^= 1;
+        assert_eq!(take(&mut bad_crc), None);
+        let mut bad_text = saved();
+        bad_text[HEADER_BYTES + 3] ^= 0x40;
+        assert_eq!(take(&mut bad_text), None);
+        let mut too_long = saved();
+        too_long[8..12].copy_from_slice(&(CRASH_STORE_BYTES as u32).to_le_bytes());
+        assert_eq!(take(&mut too_long), None);
+        let mut shorter = saved();
+        shorter[8] -= 1;
+        assert_eq!(take(&mut shorter), None);
+        // dropped records are cleared as well
+        assert_eq!(u32::from_le_bytes([bad_crc[0], bad_crc[1], bad_crc[2], bad_crc[3]]), 0);
+    }
+
+    #[test]
+    fn longest_record_fits_the_store() {
+        let longest = CrashRecord::new(255, &"x".repeat(40), u32::MAX, format_args!("{}", "y".repeat(40)));
+        let mut store = [0; CRASH_STORE_BYTES];
+        save(&mut store, &longest);
+        assert_eq!(take(&mut store), Some(longest));
+    }
+}
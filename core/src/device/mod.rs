@@ -0,0 +1,3 @@
+pub mod state;
+pub mod reset;
+pub mod supervisor;
@@ -0,0 +1,56 @@
+use core::fmt;
+
+/// Why the device started, reported to the Pi on first contact after boot
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetReason {
+    PowerOn,
+    /// RUN pin pulled low, e.g. a reset button
+    ResetPin,
+    /// Restarted by a debug probe
+    Debugger,
+    /// Watchdog reset requested by the firmware itself
+    Software,
+    /// Watchdog was not fed, with the core which stopped checking in when the supervisor knew it
+    Watchdog(Option<u8>),
+}
+
+impl ResetReason {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ResetReason::PowerOn => "power_on",
+            ResetReason::ResetPin => "reset_pin",
+            ResetReason::Debugger => "debugger",
+            ResetReason::Software => "software",
+            ResetReason::Watchdog(_) => "watchdog",
+        }
+    }
+
+    pub fn stalled_core(&self) -> Option<u8> {
+        match self {
+            ResetReason::Watchdog(core) => *core,
+            _ => None,
+        }
+    }
+
+    /// Reverse of `code` and `stalled_core`, used on the Pi side
+    pub fn from_code(code: &str, stalled_core: Option<u8>) -> Option<Self> {
+        match (code, stalled_core) {
+            ("power_on", None) => Some(ResetReason::PowerOn),
+            ("reset_pin", None) => Some(ResetReason::ResetPin),
+            ("debugger", None) => Some(ResetReason::Debugger),
+            ("software", None) => Some(ResetReason::Software),
+            ("watchdog", core) => Some(ResetReason::Watchdog(core)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResetReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.stalled_core() {
+            Some(core) => write!(f, "{} (core{} stalled)", self.code(), core),
+            None => f.write_str(self.code()),
+        }
+    }
+}
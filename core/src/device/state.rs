@@ -0,0 +1,841 @@
+use core::convert::TryFrom;
+use embedded_graphics_core::geometry::Size;
+use heapless::{Deque, String};
+use backlight::policy::{BacklightPolicy, DEFAULT_FADE_MS};
+use buzzer::melody::{parse_rtttl, BOOT_MELODY};
+use buzzer::player::BuzzerPlayer;
+use crash::record::CrashRecord;
+use device::reset::ResetReason;
+use diagnostics::counters::{bump, Diagnostics, StatsGroup};
+use images::cache::ImageCache;
+use images::error::ImageError;
+use input::key_hold::{KeyHoldState, HOLD_REPEAT_AFTER_MS};
+use input::keyboard_codes::KeyboardCodes;
+use layout::error::LayoutError;
+use layout::tree::Layout;
+use leds::controller::{LedController, StatusPattern};
+use link::monitor::{LinkMonitor, LinkState};
+use link::uart::{UartChange, UartEvent, UartFormat, UartSwitch};
+use messages::pi_2_pico_backlight::Pi2PicoBacklight;
+use messages::pi_2_pico_buzzer::{BuzzerCommand, Pi2PicoBuzzer};
+use messages::pi_2_pico_firmware::{Pi2PicoFirmware, Pi2PicoFirmwareError};
+use messages::pi_2_pico_image::{ImageCommand, Pi2PicoImage, Pi2PicoImageError};
+use messages::pi_2_pico_layout::{Pi2PicoLayout, Pi2PicoLayoutError};
+use messages::pi_2_pico_led::Pi2PicoLed;
+use messages::pi_2_pico_list::Pi2PicoList;
+use messages::pi_2_pico_message::{Pi2PicoMessage, Pi2PicoMessageError};
+use messages::pi_2_pico_ping::Pi2PicoPing;
+use messages::pi_2_pico_settings::{Pi2PicoSettings, Pi2PicoSettingsError};
+use messages::pi_2_pico_stats::Pi2PicoStats;
+use messages::pi_2_pico_test::Pi2PicoTest;
+use messages::pi_2_pico_uart::{Pi2PicoUart, Pi2PicoUartError};
+use messages::pi_2_pico_widget::Pi2PicoWidget;
+use messages::pico_2_pi_message::Pico2PiMessage;
+use screen::lines::{apply_pi_2_pico_message, ScreenLines, DATA_LINES_COUNT, FIRST_DATA_LINE, LINES_COUNT};
+use screen::render::ScreenContent;
+use screen::list::{ListEvent, ListWidget};
+use screen::style::{LineStyle, LineStyles};
+use settings::setting::{SettingKey, Settings, SettingsError};
+use update::boot_state::FirmwareStatus;
+use update::error::UpdateError;
+use update::event::FirmwareEvent;
+use update::receiver::FirmwareCommand;
+use widgets::layer::{WidgetEvent, Widgets};
+use widgets::toast::Toast;
+
+/// Uptime with the Pi online after which a firmware on trial confirms itself
+pub const CONFIRM_AFTER_MS: u64 = 10_000;
+/// Physical left and right held together this long toggle the debug screen
+pub const DEBUG_CHORD_MS: u64 = 1_000;
+/// Values on the debug screen are updated this often, so it is not redrawn every loop
+const DEBUG_REFRESH_MS: u64 = 1_000;
+
+/// Everything core0 knows about the device, without peripherals.
+/// core0 feeds it with pins state, uart lines and timer ticks (us) and applies results to hardware,
+/// the simulator does the same with stdin and a framebuffer.
+pub struct DeviceState {
+    pub lines: ScreenLines,
+    /// Set by the Pi per line, kept when line text changes
+    pub line_styles: LineStyles,
+    pub led_controller: LedController,
+    pub buzzer_player: BuzzerPlayer,
+    pub backlight_policy: BacklightPolicy,
+    /// On-device menu, owns data lines and keys while open
+    pub list: Option<ListWidget>,
+    /// Drawn over lines, dialog and spinner take keys before the list
+    pub widgets: Widgets,
+    /// Replaces lines on screen while set, widgets are still drawn on top
+    pub layout: Option<Layout>,
+    /// Bitmaps uploaded by the Pi, placed ones are drawn over lines and layout, under widgets
+    pub images: ImageCache,
+    /// Heartbeats and silence of the Pi, the offline screen is drawn while the link is lost
+    pub link: LinkMonitor,
+    /// Uart format asked for by the Pi, the firmware applies it through `take_uart_change`
+    pub uart: UartSwitch,
+    /// Loaded from flash on boot, changed by the Pi. Key map and brightness apply at once,
+    /// the firmware applies the rest on the next boot.
+    pub settings: Settings,
+    /// Layouts are validated against it, also reported with key frames
+    display_size: Size,
+    key_hold: KeyHoldState,
+    /// Current key went to the list or a widget, its release is not reported even if the widget closed
+    key_handled_locally: bool,
+    /// Mapped key held down for the USB keyboard, not one the list or a widget took
+    keyboard_key: Option<KeyboardCodes>,
+    /// Reported to the Pi on first contact, then cleared
+    reset_reason: Option<ResetReason>,
+    /// Panic before the last reboot, reported with the reset reason
+    crash: Option<CrashRecord>,
+    /// Bit per `SettingKey` changed and not saved to flash yet
+    unsaved_settings: u8,
+    /// Bit per `SettingKey` whose value is sent to the Pi once saved
+    report_saved_settings: u8,
+    /// Trial or rollback of the last update, reported with the reset reason
+    firmware_report: Option<FirmwareEvent>,
+    /// New firmware on trial, confirmed once it ran `CONFIRM_AFTER_MS` and the Pi is online
+    firmware_on_trial: bool,
+    confirm_firmware: bool,
+    /// Rollback was reported, the firmware marks it so it is reported once
+    rollback_reported: bool,
+    /// Firmware command from the Pi, run by the firmware with the flash
+    firmware_command: Option<FirmwareCommand>,
+    /// Update applied, the firmware reboots once the answer is sent
+    reboot_pending: bool,
+    /// Counters since boot, the firmware adds loop and redraw times
+    pub diagnostics: Diagnostics,
+    /// Bit per `StatsGroup` still to send in answer to `stats`
+    stats_pending: u8,
+    /// Copy of `diagnostics` shown while the debug screen is on
+    debug_screen: Option<Diagnostics>,
+    debug_refreshed_ms: u64,
+    /// Debug chord held since, toggled once per hold
+    chord_since: Option<u64>,
+    chord_toggled: bool,
+    /// Replies waiting for the uart, oldest first
+    outgoing: Deque<String<100>, 4>,
+}
+
+impl DeviceState {
+    pub fn new(now_us: u64, display_size: Size, reset_reason: ResetReason, crash: Option<CrashRecord>, settings: Settings, firmware: FirmwareStatus) -> Self {
+        let now_ms = now_us / 1_000;
+        let mut lines: ScreenLines = Default::default();
+        lines[5] = Some((String::from("hw! core1"), false));
+
+        let mut led_controller = LedController::new();
+        led_controller.show_status(StatusPattern::Boot, now_ms);
+
+        let mut buzzer_player = BuzzerPlayer::new();
+        if let Ok(melody) = parse_rtttl(BOOT_MELODY) {
+            buzzer_player.play(melody, now_ms);
+        }
+
+        DeviceState {
+            lines,
+            line_styles: [LineStyle::default(); LINES_COUNT],
+            led_controller,
+            buzzer_player,
+            backlight_policy: BacklightPolicy::new(settings.brightness, now_ms),
+            list: None,
+            widgets: Widgets::new(),
+            layout: None,
+            images: ImageCache::new(),
+            link: LinkMonitor::new(now_ms),
+            uart: UartSwitch::new(UartFormat { baud: settings.baud, parity: settings.parity }),
+            settings,
+            display_size,
+            key_hold: KeyHoldState::new(now_us),
+            key_handled_locally: false,
+            keyboard_key: None,
+            reset_reason: Some(reset_reason),
+            crash,
+            unsaved_settings: 0,
+            report_saved_settings: 0,
+            firmware_report: match firmware {
+                FirmwareStatus::Testing => Some(FirmwareEvent::Testing),
+                FirmwareStatus::RolledBack => Some(FirmwareEvent::RolledBack),
+                _ => None,
+            },
+            firmware_on_trial: firmware == FirmwareStatus::Testing,
+            confirm_firmware: false,
+            rollback_reported: false,
+            firmware_command: None,
+            reboot_pending: false,
+            diagnostics: Diagnostics::default(),
+            stats_pending: 0,
+            debug_screen: None,
+            debug_refreshed_ms: 0,
+            chord_since: None,
+            chord_toggled: false,
+            outgoing: Deque::new(),
+        }
+    }
+
+    /// Handle complete line received from the Pi (without `\r\n`)
+    pub fn handle_line(&mut self, text_buffer: &String<2048>, now_us: u64) {
+        let now_ms = now_us / 1_000;
+        bump(&mut self.diagnostics.frames_rx);
+        // Pi is talking to us, boot is over
+        self.led_controller.clear_status(StatusPattern::Boot);
+        if self.link.on_contact(now_ms).is_some() {
+            self.led_controller.clear_status(StatusPattern::LinkLost);
+        }
+        // the Pi may have missed the boot, it hears why the device restarted once it talks to it
+        if let Some(reset_reason) = self.reset_reason.take() {
+            let message = Pico2PiMessage {
+                reset_reason: Some(reset_reason),
+                ..Default::default()
+            };
+            self.queue_outgoing(message.to_frame());
+        }
+        if let Some(crash) = self.crash.take() {
+            let message = Pico2PiMessage {
+                crash: Some(crash),
+                ..Default::default()
+            };
+            self.queue_outgoing(message.to_frame());
+        }
+        if let Some(event) = self.firmware_report.take() {
+            self.rollback_reported = event == FirmwareEvent::RolledBack;
+            self.queue_firmware_result(Ok(Some(event)));
+        }
+
+        // a bare `stats` is no `key=value` line, still not a parse error
+        let stats_request = Pi2PicoStats::try_from(text_buffer).is_ok();
+
+        match Pi2PicoMessage::try_from(text_buffer) {
+            Ok(message) => {
+                // Pi draws data lines itself again
+                if message.data_lines.is_some() || message.cursor_index.is_some() {
+                    self.list = None;
+                }
+                apply_pi_2_pico_message(&mut self.lines, &message);
+                if let Some(line_styles) = &message.line_styles {
+                    for (line, style) in line_styles {
+                        self.line_styles[*line] = *style;
+                    }
+                }
+            }
+            Err(err) => {
+                if matches!(err, Pi2PicoMessageError::ParseError) && !stats_request {
+                    bump(&mut self.diagnostics.parse_errors);
+                }
+                error!("Error reading Pi2PicoMessage: {:?}", err);
+            }
+        }
+
+        if let Ok(list_message) = Pi2PicoList::try_from(text_buffer) {
+            if list_message.close {
+                self.list = None;
+                for line in &mut self.lines[FIRST_DATA_LINE..FIRST_DATA_LINE + DATA_LINES_COUNT] {
+                    *line = None;
+                }
+            }
+            if let Some(list) = list_message.open {
+                list.apply(&mut self.lines);
+                self.list = Some(list);
+            }
+        }
+
+        if let Ok(widget_message) = Pi2PicoWidget::try_from(text_buffer) {
+            if widget_message.close {
+                self.widgets.active = None;
+            }
+            if let Some(widget) = widget_message.widget {
+                self.widgets.active = Some(widget);
+            }
+            if let Some((text, duration_ms)) = widget_message.toast {
+                self.widgets.toast = Some(Toast::new(text, duration_ms, now_ms));
+            }
+            if let Some(battery) = widget_message.battery {
+                self.widgets.battery = battery;
+            }
+            if widget_message.qr_close {
+                self.widgets.qr = None;
+            }
+            if let Some(qr) = widget_message.qr {
+                let qr_result = match qr {
+                    Ok(code) => {
+                        self.widgets.qr = Some(code);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                };
+                let message = Pico2PiMessage {
+                    qr_result: Some(qr_result),
+                    ..Default::default()
+                };
+                self.queue_outgoing(message.to_frame());
+            }
+        }
+
+        match Pi2PicoLayout::try_from(text_buffer) {
+            Ok(layout_message) => {
+                if layout_message.clear {
+                    self.layout = None;
+                }
+                if let Some(layout) = layout_message.layout {
+                    let result = layout.validate(self.display_size);
+                    if result.is_ok() {
+                        self.layout = Some(layout);
+                    }
+                    self.queue_layout_result(result);
+                }
+                if let Some((index, properties)) = layout_message.update {
+                    let display_size = self.display_size;
+                    let result = match &mut self.layout {
+                        Some(layout) => layout.update(index, properties.as_str(), display_size),
+                        None => Err(LayoutError::UnknownNode(LayoutError::node_index(index))),
+                    };
+                    self.queue_layout_result(result);
+                }
+            }
+            Err(Pi2PicoLayoutError::Layout(err)) => self.queue_layout_result(Err(err)),
+            Err(_) => {}
+        }
+
+        match Pi2PicoImage::try_from(text_buffer) {
+            Ok(image_message) => {
+                for command in image_message.commands {
+                    self.handle_image_command(command);
+                }
+            }
+            Err(Pi2PicoImageError::Image(id, err)) => self.queue_image_result(id, Err(err)),
+            Err(_) => {}
+        }
+
+        if let Ok(led_message) = Pi2PicoLed::try_from(text_buffer) {
+            for (led, pattern) in led_message.commands {
+                self.led_controller.set(led, pattern, now_ms);
+            }
+        }
+
+        if let Ok(backlight_message) = Pi2PicoBacklight::try_from(text_buffer) {
+            if let Some((dim_after_ms, off_after_ms, dim_level)) = backlight_message.auto_dim {
+                self.backlight_policy.set_auto_dim(dim_after_ms, off_after_ms, dim_level);
+            }
+            if let Some((brightness, fade_ms)) = backlight_message.brightness_and_fade {
+                self.backlight_policy.set_brightness(brightness, fade_ms, now_ms);
+            }
+        }
+
+        if let Ok(buzzer_message) = Pi2PicoBuzzer::try_from(text_buffer) {
+            match buzzer_message.command {
+                BuzzerCommand::Tone { freq_hz, duration_ms } => {
+                    self.buzzer_player.play_tone(freq_hz, duration_ms, now_ms);
+                }
+                BuzzerCommand::Melody(melody) | BuzzerCommand::Alert(melody) => {
+                    self.buzzer_player.play(melody, now_ms);
+                }
+                BuzzerCommand::KeyClick(enabled) => {
+                    self.buzzer_player.key_click_enabled = enabled;
+                }
+                BuzzerCommand::Stop => self.buzzer_player.stop(),
+            }
+        }
+
+        match Pi2PicoSettings::try_from(text_buffer) {
+            Ok(settings_message) => {
+                if settings_message.reset {
+                    self.settings = Settings::default();
+                    self.backlight_policy.set_brightness(self.settings.brightness, DEFAULT_FADE_MS, now_ms);
+                    for key in SettingKey::ALL {
+                        self.unsaved_settings |= 1 << key.store_key();
+                    }
+                    let message = Pico2PiMessage {
+                        settings_reset: true,
+                        ..Default::default()
+                    };
+                    self.queue_outgoing(message.to_frame());
+                }
+                if let Some((key, value)) = settings_message.set {
+                    match self.settings.set(key, value.as_str()) {
+                        Ok(()) => {
+                            if key == SettingKey::Brightness {
+                                self.backlight_policy.set_brightness(self.settings.brightness, DEFAULT_FADE_MS, now_ms);
+                            }
+                            self.unsaved_settings |= 1 << key.store_key();
+                            self.report_saved_settings |= 1 << key.store_key();
+                        }
+                        Err(err) => self.queue_setting_error(err),
+                    }
+                }
+                if let Some(key) = settings_message.get {
+                    self.queue_setting(key);
+                }
+            }
+            Err(Pi2PicoSettingsError::Setting(err)) => self.queue_setting_error(err),
+            Err(_) => {}
+        }
+
+        match Pi2PicoFirmware::try_from(text_buffer) {
+            Ok(firmware) => self.firmware_command = Some(firmware.command),
+            Err(Pi2PicoFirmwareError::Update(err)) => self.queue_firmware_result(Err(err)),
+            Err(_) => {}
+        }
+
+        match Pi2PicoUart::try_from(text_buffer) {
+            Ok(uart_message) => {
+                if let Some((baud, parity)) = uart_message.switch {
+                    let parity = parity.unwrap_or(self.uart.current().parity);
+                    let event = self.uart.request(UartFormat { baud, parity });
+                    self.queue_uart_event(event);
+                }
+                if uart_message.confirm {
+                    let event = self.uart.confirm();
+                    self.queue_uart_event(event);
+                }
+            }
+            Err(Pi2PicoUartError::BadValue) => self.queue_uart_event(UartEvent::BadValue),
+            Err(_) => {}
+        }
+
+        if let Ok(ping) = Pi2PicoPing::try_from(text_buffer) {
+            let message = Pico2PiMessage {
+                pong: Some(ping.token),
+                ..Default::default()
+            };
+            self.queue_outgoing(message.to_frame());
+        }
+
+        if stats_request {
+            for group in StatsGroup::ALL {
+                self.stats_pending |= 1 << group as u8;
+            }
+        }
+
+        if let Ok(val) = Pi2PicoTest::try_from(text_buffer) {
+            if !val.kc.is_empty() {
+                self.lines[1] = Some((String::from(val.kc.as_str()), false));
+            }
+        }
+    }
+
+    /// Only failures and finished uploads are answered, chunks are not acknowledged one by one
+    fn handle_image_command(&mut self, command: ImageCommand) {
+        let failure = match command {
+            ImageCommand::Begin { id, size, format } => self.images.begin(id, size, format).err().map(|err| (id, err)),
+            ImageCommand::Data { id, offset, data } => self.images.write(id, offset, data).err().map(|err| (id, err)),
+            ImageCommand::End { id, checksum } => {
+                let result = self.images.finish(id, checksum);
+                if result == Err(ImageError::Checksum) {
+                    bump(&mut self.diagnostics.crc_failures);
+                }
+                self.queue_image_result(id, result);
+                None
+            }
+            ImageCommand::Show { id, position } => self.images.show(id, position).err().map(|err| (id, err)),
+            ImageCommand::Hide(id) => {
+                self.images.hide(id);
+                None
+            }
+            ImageCommand::HideAll => {
+                self.images.hide_all();
+                None
+            }
+            ImageCommand::Delete(id) => {
+                self.images.delete(id);
+                None
+            }
+        };
+        if let Some((id, err)) = failure {
+            self.queue_image_result(id, Err(err));
+        }
+    }
+
+    /// What core1 draws
+    pub fn screen(&self) -> ScreenContent<'_> {
+        ScreenContent {
+            lines: &self.lines,
+            styles: &self.line_styles,
+            widgets: &self.widgets,
+            layout: self.layout.as_ref(),
+            images: &self.images,
+            link: &self.link,
+            debug: self.debug_screen.as_ref(),
+        }
+    }
+
+    /// Setting to write to flash, the firmware saves it and calls `on_setting_saved`
+    pub fn take_unsaved_setting(&mut self) -> Option<SettingKey> {
+        let key = SettingKey::ALL.iter().copied().find(|key| self.unsaved_settings & (1 << key.store_key()) != 0)?;
+        self.unsaved_settings &= !(1 << key.store_key());
+        Some(key)
+    }
+
+    /// Answers `setting_set` once the value is in flash, failures are always reported
+    pub fn on_setting_saved(&mut self, key: SettingKey, saved: bool) {
+        let reported = self.report_saved_settings & (1 << key.store_key()) != 0;
+        self.report_saved_settings &= !(1 << key.store_key());
+        if !saved {
+            error!("Setting {} not saved", key.as_str());
+            self.queue_setting_error(SettingsError::Storage(key));
+        } else if reported {
+            self.queue_setting(key);
+        }
+    }
+
+    /// Firmware command to run with the flash, the firmware calls `on_firmware_result` with the
+    /// outcome of `UpdateReceiver::handle`
+    pub fn take_firmware_command(&mut self) -> Option<FirmwareCommand> {
+        if self.confirm_firmware {
+            self.confirm_firmware = false;
+            return Some(FirmwareCommand::Confirm);
+        }
+        if self.rollback_reported {
+            self.rollback_reported = false;
+            return Some(FirmwareCommand::RollbackReported);
+        }
+        self.firmware_command.take()
+    }
+
+    pub fn on_firmware_result(&mut self, result: Result<Option<FirmwareEvent>, UpdateError>) {
+        if let Err(err) = result {
+            error!("Firmware command failed: {:?}", err);
+        }
+        if result == Err(UpdateError::Checksum) {
+            bump(&mut self.diagnostics.crc_failures);
+        }
+        if result == Ok(Some(FirmwareEvent::Applying)) {
+            self.reboot_pending = true;
+        }
+        self.queue_firmware_result(result);
+    }
+
+    /// Update applied and its answer sent, the firmware reboots into the bootloader
+    pub fn wants_reboot(&self) -> bool {
+        self.reboot_pending && self.outgoing.is_empty()
+    }
+
+    /// Uart format to apply before sending anything else. Only once everything queued before it
+    /// went out at the old format, `uart=fallback` is queued after going back.
+    pub fn take_uart_change(&mut self, now_us: u64) -> Option<UartChange> {
+        if !self.outgoing.is_empty() {
+            return None;
+        }
+        let change = self.uart.take_change(now_us / 1_000)?;
+        if let UartChange::Fallback(format) = change {
+            error!("uart_switch not confirmed, back to {}", format.baud);
+            self.queue_uart_event(UartEvent::Fallback(format));
+        }
+        Some(change)
+    }
+
+    /// Uart read failed (break, overrun, parity, framing), counted by `exchange`
+    pub fn on_uart_error(&mut self, now_us: u64) {
+        self.led_controller.show_status_for(StatusPattern::Error, now_us / 1_000, 1000);
+        self.link.on_error();
+    }
+
+    /// Currently pressed physical key, reported through the key map. Called every loop
+    pub fn on_keys(&mut self, pressed: Option<KeyboardCodes>, now_us: u64) {
+        let now_ms = now_us / 1_000;
+        let pressed = pressed.map(|physical| self.settings.map_key(physical));
+        if self.key_hold.update(pressed, now_us) {
+            self.buzzer_player.key_click(now_ms);
+            self.backlight_policy.on_activity(now_ms);
+            self.key_handled_locally = self.handles_keys_locally();
+            if let (Some(keycode), true) = (pressed, self.key_handled_locally) {
+                self.local_key(keycode);
+            }
+        }
+        self.keyboard_key = pressed.filter(|_| !self.key_handled_locally);
+    }
+
+    /// Physical left and right both pressed, called every loop. The firmware passes no key to
+    /// `on_keys` meanwhile, so the chord does not reach the Pi.
+    pub fn on_debug_chord(&mut self, held: bool, now_us: u64) {
+        let now_ms = now_us / 1_000;
+        if !held {
+            self.chord_since = None;
+            self.chord_toggled = false;
+            return;
+        }
+        let since = *self.chord_since.get_or_insert(now_ms);
+        if !self.chord_toggled && now_ms - since >= DEBUG_CHORD_MS {
+            self.chord_toggled = true;
+            self.debug_screen = match self.debug_screen {
+                Some(_) => None,
+                None => Some(self.diagnostics),
+            };
+            self.debug_refreshed_ms = now_ms;
+        }
+    }
+
+    /// Key the USB keyboard holds down, the host repeats it while held like any keyboard
+    pub fn keyboard_key(&self) -> Option<KeyboardCodes> {
+        self.keyboard_key
+    }
+
+    /// Time based updates which don't depend on input, call every loop
+    pub fn update(&mut self, now_us: u64) {
+        let now_ms = now_us / 1_000;
+        self.diagnostics.uptime_s = (now_ms / 1_000) as u32;
+        if self.debug_screen.is_some() && now_ms - self.debug_refreshed_ms >= DEBUG_REFRESH_MS {
+            self.debug_screen = Some(self.diagnostics);
+            self.debug_refreshed_ms = now_ms;
+        }
+        self.widgets.update(now_ms);
+        self.link.update(now_ms);
+        self.uart.update(now_ms);
+        // also takes over again after a uart error blink
+        if self.link.state() == LinkState::Lost {
+            self.led_controller.show_status(StatusPattern::LinkLost, now_ms);
+        }
+        if self.firmware_on_trial && now_ms >= CONFIRM_AFTER_MS && self.link.state() == LinkState::Online {
+            self.firmware_on_trial = false;
+            self.confirm_firmware = true;
+        }
+    }
+
+    /// Keys handled on the device, not sent to the Pi as key codes
+    fn handles_keys_locally(&self) -> bool {
+        self.widgets.takes_keys() || self.list.is_some()
+    }
+
+    fn local_key(&mut self, keycode: KeyboardCodes) {
+        if self.widgets.takes_keys() {
+            let message = match self.widgets.on_key(keycode) {
+                Some(WidgetEvent::DialogAnswer(answer)) => Pico2PiMessage {
+                    dialog_answer: Some(answer),
+                    ..Default::default()
+                },
+                Some(WidgetEvent::SpinnerValue(value)) => Pico2PiMessage {
+                    spinner_value: Some(value),
+                    ..Default::default()
+                },
+                None => return,
+            };
+            self.queue_outgoing(message.to_frame());
+        } else {
+            self.list_key(keycode);
+        }
+    }
+
+    /// Move list cursor and queue selection or page change for the Pi
+    fn list_key(&mut self, keycode: KeyboardCodes) {
+        let list = match &mut self.list {
+            Some(list) => list,
+            None => return,
+        };
+        let event = list.on_key(keycode);
+        list.apply(&mut self.lines);
+        let message = match event {
+            Some(ListEvent::Selected(index)) => Pico2PiMessage {
+                list_selected: Some(index),
+                ..Default::default()
+            },
+            Some(ListEvent::PageChanged(page)) => Pico2PiMessage {
+                list_page: Some(page),
+                ..Default::default()
+            },
+            None => return,
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    /// Frame to send to the Pi, if any. Call when uart is writable.
+    pub fn poll_outgoing(&mut self, now_us: u64) -> Option<String<100>> {
+        if let Some(frame) = self.poll_key_frame(now_us) {
+            self.queue_outgoing(frame);
+        }
+        if let Some(seq) = self.link.poll_heartbeat(now_us / 1_000) {
+            let message = Pico2PiMessage {
+                heartbeat: Some(seq),
+                link_state: Some(self.link.state()),
+                ..Default::default()
+            };
+            self.queue_outgoing(message.to_frame());
+        }
+        // a group at a time, so replies and key frames don't push the others out
+        if self.outgoing.is_empty() {
+            self.queue_stats();
+        }
+        let frame = self.outgoing.pop_front();
+        if frame.is_some() {
+            bump(&mut self.diagnostics.frames_tx);
+        }
+        frame
+    }
+
+    fn queue_stats(&mut self) {
+        let group = match StatsGroup::ALL.iter().copied().find(|group| self.stats_pending & (1 << *group as u8) != 0) {
+            Some(group) => group,
+            None => return,
+        };
+        self.stats_pending &= !(1 << group as u8);
+        let message = Pico2PiMessage {
+            stats: Some(self.diagnostics.report(group)),
+            ..Default::default()
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    fn queue_uart_event(&mut self, event: UartEvent) {
+        let message = Pico2PiMessage {
+            uart: Some(event),
+            ..Default::default()
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    fn queue_layout_result(&mut self, result: Result<(), LayoutError>) {
+        let message = Pico2PiMessage {
+            layout_result: Some(result),
+            ..Default::default()
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    fn queue_image_result(&mut self, id: u8, result: Result<(), ImageError>) {
+        let message = Pico2PiMessage {
+            image_result: Some((id, result)),
+            ..Default::default()
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    fn queue_setting(&mut self, key: SettingKey) {
+        let message = Pico2PiMessage {
+            setting: Some((key, self.settings.value(key))),
+            ..Default::default()
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    fn queue_setting_error(&mut self, err: SettingsError) {
+        let message = Pico2PiMessage {
+            setting_error: Some(err),
+            ..Default::default()
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    fn queue_firmware_result(&mut self, result: Result<Option<FirmwareEvent>, UpdateError>) {
+        let message = match result {
+            Ok(None) => return,
+            Ok(Some(event)) => Pico2PiMessage {
+                firmware: Some(event),
+                ..Default::default()
+            },
+            Err(err) => Pico2PiMessage {
+                firmware_error: Some(err),
+                ..Default::default()
+            },
+        };
+        self.queue_outgoing(message.to_frame());
+    }
+
+    /// Drops the oldest reply when the Pi sends faster than uart drains
+    fn queue_outgoing(&mut self, frame: String<100>) {
+        if self.outgoing.is_full() {
+            self.outgoing.pop_front();
+            bump(&mut self.diagnostics.frames_dropped);
+        }
+        self.outgoing.push_back(frame).ok();
+    }
+
+    fn poll_key_frame(&mut self, now_us: u64) -> Option<String<100>> {
+        let (keycode, keypress_ms) = self.key_hold.poll(now_us)?;
+        // list or widget handled the key on key down, held keys repeat
+        if self.key_handled_locally {
+            if keypress_ms > HOLD_REPEAT_AFTER_MS && keycode != KeyboardCodes::Ok && self.handles_keys_locally() {
+                self.local_key(keycode);
+            }
+            return None;
+        }
+
+        let message = Pico2PiMessage {
+            wh: Some([self.display_size.width as i32, self.display_size.height as i32]),
+            keyboard_codes: Some(keycode),
+            keypress_ms,
+            ..Default::default()
+        };
+        Some(message.to_frame())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diagnostics::counters::StatsReport;
+    use heapless::Vec;
+    use utils::test_fixtures::device;
+
+    /// Stats frames among everything waiting to go out
+    fn stats_frames(device_state: &mut DeviceState) -> usize {
+        let mut count = 0;
+        while let Some(frame) = device_state.poll_outgoing(2_000) {
+            if frame.contains("&stats=") {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn bare_stats_is_answered_without_a_parse_error() {
+        let mut device_state = device();
+        device_state.handle_line(&String::from("stats"), 2_000);
+        assert_eq!(device_state.diagnostics.parse_errors, 0);
+        assert_eq!(stats_frames(&mut device_state), StatsGroup::ALL.len());
+    }
+
+    #[test]
+    fn stats_answer_carries_the_counters() {
+        let mut device_state = device();
+        device_state.handle_line(&String::from("garbage"), 2_000);
+        device_state.handle_line(&String::from("stats=1"), 2_000);
+        let mut reports = Vec::<StatsReport, 3>::new();
+        while let Some(frame) = device_state.poll_outgoing(2_000) {
+            if let Some(report) = Pico2PiMessage::try_from(frame.as_str()).ok().and_then(|message| message.stats) {
+                reports.push(report).unwrap();
+            }
+        }
+        let groups: Vec<StatsGroup, 3> = reports.iter().map(|report| report.group).collect();
+        assert_eq!(groups.as_slice(), &StatsGroup::ALL);
+        // lines received, then crc failures, overruns, line errors and parse errors
+        assert_eq!(reports[0].values[0], 2);
+        assert_eq!(reports[1].values.as_slice(), &[0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn oversized_melody_numbers_are_refused() {
+        let mut device_state = device();
+        for line in ["melody=::4294967296", "melody=tune:d=1,o=255,b=1:99999999999999999999", "melody=tune:o=7:b#"] {
+            device_state.handle_line(&String::from(line), 2_000);
+        }
+        device_state.update(3_000);
+    }
+
+    #[test]
+    fn lines_nobody_accepts_are_parse_errors() {
+        let mut device_state = device();
+        device_state.handle_line(&String::from("statistics"), 2_000);
+        device_state.handle_line(&String::from("cursor_index=x"), 2_000);
+        assert_eq!(device_state.diagnostics.parse_errors, 2);
+        assert_eq!(stats_frames(&mut device_state), 0);
+    }
+
+    #[test]
+    fn longer_key_codes_leave_the_lines_alone() {
+        let mut device_state = device();
+        device_state.handle_line(&String::from("kc=ab"), 2_000);
+        assert_eq!(device_state.lines[1], None);
+        device_state.handle_line(&String::from("kc=a"), 2_000);
+        assert_eq!(device_state.lines[1], Some((String::from("a"), false)));
+    }
+
+    #[test]
+    fn layout_indices_past_a_byte_are_unknown_nodes() {
+        let mut device_state = device();
+        device_state.handle_line(&String::from("layout=text|0,0,64,12"), 2_000);
+        device_state.handle_line(&String::from("layout_set=256|text=x"), 2_000);
+        let mut results = Vec::<Result<(), LayoutError>, 2>::new();
+        while let Some(frame) = device_state.poll_outgoing(2_000) {
+            if let Some(result) = Pico2PiMessage::try_from(frame.as_str()).ok().and_then(|message| message.layout_result) {
+                results.push(result).unwrap();
+            }
+        }
+        assert_eq!(results.as_slice(), &[Ok(()), Err(LayoutError::UnknownNode(u8::MAX))]);
+    }
+}
@@ -0,0 +1,131 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Supervised loops: core0 handles uart and keys, core1 draws the screen
+pub const SUPERVISED_CORES: usize = 2;
+/// A core which did not check in for this long is hung, the watchdog is not fed any more
+pub const CHECK_IN_TIMEOUT_MS: u64 = 1000;
+
+/// Counter a core bumps once per loop. Only the owning core writes it, so a plain load and store
+/// is enough on the M0+, which has no atomic read-modify-write.
+pub struct CheckIn(AtomicU32);
+
+impl CheckIn {
+    pub const fn new() -> Self {
+        CheckIn(AtomicU32::new(0))
+    }
+
+    pub fn check_in(&self) {
+        self.0.store(self.0.load(Ordering::Relaxed).wrapping_add(1), Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for CheckIn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decides when the hardware watchdog may be fed: only while every core keeps checking in.
+/// Runs in the core0 loop, so a hung core0 stops feeding by itself.
+/// Driven by timestamps, the watchdog itself is up to the caller.
+pub struct Supervisor {
+    last_counts: [u32; SUPERVISED_CORES],
+    last_change_ms: [u64; SUPERVISED_CORES],
+    /// First core found hung. Kept even if it recovers, the reset is already on its way.
+    stalled_core: Option<u8>,
+}
+
+impl Supervisor {
+    pub fn new(now_ms: u64) -> Self {
+        Supervisor {
+            last_counts: [0; SUPERVISED_CORES],
+            last_change_ms: [now_ms; SUPERVISED_CORES],
+            stalled_core: None,
+        }
+    }
+
+    /// Call every core0 loop. Returns true when the watchdog should be fed.
+    pub fn update(&mut self, check_ins: &[CheckIn; SUPERVISED_CORES], now_ms: u64) -> bool {
+        for (core, check_in) in check_ins.iter().enumerate() {
+            let count = check_in.count();
+            if count != self.last_counts[core] {
+                self.last_counts[core] = count;
+                self.last_change_ms[core] = now_ms;
+            } else if self.stalled_core.is_none() && now_ms.saturating_sub(self.last_change_ms[core]) > CHECK_IN_TIMEOUT_MS {
+                self.stalled_core = Some(core as u8);
+            }
+        }
+        self.stalled_core.is_none()
+    }
+
+    pub fn stalled_core(&self) -> Option<u8> {
+        self.stalled_core
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cores_checking_in_keep_the_watchdog_fed() {
+        let check_ins = [CheckIn::new(), CheckIn::new()];
+        let mut supervisor = Supervisor::new(0);
+        for now_ms in (0..20_000).step_by(100) {
+            check_ins[0].check_in();
+            check_ins[1].check_in();
+            assert!(supervisor.update(&check_ins, now_ms));
+        }
+        assert_eq!(supervisor.stalled_core(), None);
+    }
+
+    #[test]
+    fn core_that_stops_checking_in_is_reported_after_the_timeout() {
+        let check_ins = [CheckIn::new(), CheckIn::new()];
+        let mut supervisor = Supervisor::new(0);
+        check_ins[0].check_in();
+        check_ins[1].check_in();
+        assert!(supervisor.update(&check_ins, 500));
+        // only core0 goes on
+        for now_ms in (600..=1_500).step_by(100) {
+            check_ins[0].check_in();
+            assert!(supervisor.update(&check_ins, now_ms));
+        }
+        check_ins[0].check_in();
+        assert!(!supervisor.update(&check_ins, 1_501));
+        assert_eq!(supervisor.stalled_core(), Some(1));
+        // a reset is on its way, recovering does not call it off
+        check_ins[1].check_in();
+        assert!(!supervisor.update(&check_ins, 1_600));
+        assert_eq!(supervisor.stalled_core(), Some(1));
+    }
+
+    #[test]
+    fn core_that_never_checks_in_is_reported() {
+        let check_ins = [CheckIn::new(), CheckIn::new()];
+        let mut supervisor = Supervisor::new(10_000);
+        check_ins[1].check_in();
+        assert!(supervisor.update(&check_ins, 10_000 + CHECK_IN_TIMEOUT_MS));
+        check_ins[1].check_in();
+        assert!(!supervisor.update(&check_ins, 10_001 + CHECK_IN_TIMEOUT_MS));
+        assert_eq!(supervisor.stalled_core(), Some(0));
+    }
+
+    #[test]
+    fn counter_wraparound_is_a_check_in() {
+        let check_in = CheckIn(AtomicU32::new(u32::MAX));
+        check_in.check_in();
+        assert_eq!(check_in.count(), 0);
+        let check_ins = [check_in, CheckIn::new()];
+        let mut supervisor = Supervisor::new(0);
+        supervisor.last_counts = [u32::MAX, 0];
+        check_ins[1].check_in();
+        assert!(supervisor.update(&check_ins, 900));
+        check_ins[1].check_in();
+        assert!(supervisor.update(&check_ins, 1_800));
+    }
+}
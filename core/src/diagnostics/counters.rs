@@ -0,0 +1,226 @@
+use core::convert::TryFrom;
+use core::fmt::Write;
+use heapless::{String, Vec};
+use link::transport::TransportError;
+use messages::receiver::ReceiverError;
+
+/// Values of `Diagnostics` sent together, `stats=<group>:<value>:...`. Three frames, all of them
+/// do not fit in one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StatsGroup {
+    Frames,
+    Errors,
+    Time,
+}
+
+impl StatsGroup {
+    pub const ALL: [StatsGroup; 3] = [StatsGroup::Frames, StatsGroup::Errors, StatsGroup::Time];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatsGroup::Frames => "frames",
+            StatsGroup::Errors => "errors",
+            StatsGroup::Time => "time",
+        }
+    }
+
+    /// Names of the values in the order they are sent
+    pub fn names(&self) -> &'static [&'static str] {
+        match self {
+            StatsGroup::Frames => &["frames_rx", "frames_tx", "frames_dropped"],
+            StatsGroup::Errors => &["crc_failures", "overruns", "line_errors", "parse_errors"],
+            StatsGroup::Time => &["uptime_s", "loop_us", "loop_max_us", "redraw_us", "redraw_max_us"],
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for StatsGroup {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        StatsGroup::ALL.iter().copied().find(|group| group.as_str() == value).ok_or(())
+    }
+}
+
+/// One `stats=` frame, `values` named by `StatsGroup::names`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StatsReport {
+    pub group: StatsGroup,
+    pub values: Vec<u32, 5>,
+}
+
+impl StatsReport {
+    pub fn to_text(&self) -> String<64> {
+        let mut text: String<64> = String::new();
+        text.push_str(self.group.as_str()).unwrap();
+        for value in &self.values {
+            _ = write!(text, ":{}", value);
+        }
+        text
+    }
+
+    /// Reverse of `to_text`, used on the Pi side
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(':');
+        let group = StatsGroup::try_from(parts.next()?).ok()?;
+        let mut values = Vec::new();
+        for part in parts {
+            values.push(part.parse::<u32>().ok()?).ok()?;
+        }
+        if values.len() != group.names().len() {
+            return None;
+        }
+        Some(StatsReport { group, values })
+    }
+}
+
+/// Counted since boot, so the Pi can see what goes wrong without a debug probe. Sent in answer
+/// to `stats=1` and shown on the debug screen. Counters wrap around.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Diagnostics {
+    /// Lines received from the Pi
+    pub frames_rx: u32,
+    /// Frames sent to the Pi
+    pub frames_tx: u32,
+    /// Frames dropped because the Pi sent faster than the link drained
+    pub frames_dropped: u32,
+    /// Image and firmware data whose CRC-32 differs
+    pub crc_failures: u32,
+    /// Bytes lost: uart overrun, line longer than the receive buffer, I2C buffer full
+    pub overruns: u32,
+    /// Uart break, parity and framing errors
+    pub line_errors: u32,
+    /// Bad UTF-8 and lines which are not `key=value` pairs
+    pub parse_errors: u32,
+    pub loop_us: u32,
+    pub loop_max_us: u32,
+    /// Last redraw which changed the display
+    pub redraw_us: u32,
+    pub redraw_max_us: u32,
+    pub uptime_s: u32,
+}
+
+impl Diagnostics {
+    pub fn on_transport_error(&mut self, err: TransportError) {
+        match err {
+            TransportError::Overrun => bump(&mut self.overruns),
+            TransportError::Break | TransportError::Parity | TransportError::Framing => bump(&mut self.line_errors),
+        }
+    }
+
+    pub fn on_receive_error(&mut self, err: ReceiverError) {
+        match err {
+            ReceiverError::Utf8 => bump(&mut self.parse_errors),
+            ReceiverError::Overflow => bump(&mut self.overruns),
+        }
+    }
+
+    /// Time of one pass of the loop handling the link
+    pub fn record_loop(&mut self, us: u32) {
+        self.loop_us = us;
+        self.loop_max_us = self.loop_max_us.max(us);
+    }
+
+    pub fn record_redraw(&mut self, us: u32) {
+        self.redraw_us = us;
+        self.redraw_max_us = self.redraw_max_us.max(us);
+    }
+
+    pub fn report(&self, group: StatsGroup) -> StatsReport {
+        let values: &[u32] = match group {
+            StatsGroup::Frames => &[self.frames_rx, self.frames_tx, self.frames_dropped],
+            StatsGroup::Errors => &[self.crc_failures, self.overruns, self.line_errors, self.parse_errors],
+            StatsGroup::Time => &[self.uptime_s, self.loop_us, self.loop_max_us, self.redraw_us, self.redraw_max_us],
+        };
+        StatsReport { group, values: Vec::from_slice(values).unwrap() }
+    }
+}
+
+pub fn bump(counter: &mut u32) {
+    *counter = counter.wrapping_add(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use messages::pico_2_pi_message::Pico2PiMessage;
+
+    #[test]
+    fn errors_go_to_their_counters() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.on_transport_error(TransportError::Overrun);
+        for err in [TransportError::Break, TransportError::Parity, TransportError::Framing] {
+            diagnostics.on_transport_error(err);
+        }
+        diagnostics.on_receive_error(ReceiverError::Utf8);
+        diagnostics.on_receive_error(ReceiverError::Overflow);
+        assert_eq!((diagnostics.overruns, diagnostics.line_errors, diagnostics.parse_errors), (2, 3, 1));
+        assert_eq!(diagnostics.report(StatsGroup::Errors).values.as_slice(), &[0, 2, 3, 1]);
+    }
+
+    #[test]
+    fn times_keep_the_last_and_the_longest() {
+        let mut diagnostics = Diagnostics::default();
+        for us in [300, 900, 200] {
+            diagnostics.record_loop(us);
+            diagnostics.record_redraw(us * 10);
+        }
+        diagnostics.uptime_s = 42;
+        assert_eq!(diagnostics.report(StatsGroup::Time).values.as_slice(), &[42, 200, 900, 2_000, 9_000]);
+    }
+
+    #[test]
+    fn counters_wrap_around() {
+        let mut counter = u32::MAX;
+        bump(&mut counter);
+        assert_eq!(counter, 0);
+    }
+
+    #[test]
+    fn report_text_parses_back() {
+        let diagnostics = Diagnostics { frames_rx: 12, frames_tx: 7, frames_dropped: 1, ..Default::default() };
+        let report = diagnostics.report(StatsGroup::Frames);
+        assert_eq!(report.to_text().as_str(), "frames:12:7:1");
+        assert_eq!(StatsReport::parse(report.to_text().as_str()), Some(report));
+        for group in StatsGroup::ALL {
+            assert_eq!(group.names().len(), diagnostics.report(group).values.len());
+            assert_eq!(StatsGroup::try_from(group.as_str()), Ok(group));
+        }
+    }
+
+    #[test]
+    fn largest_reports_fit_a_frame() {
+        let diagnostics = Diagnostics {
+            frames_rx: u32::MAX,
+            frames_tx: u32::MAX,
+            frames_dropped: u32::MAX,
+            crc_failures: u32::MAX,
+            overruns: u32::MAX,
+            line_errors: u32::MAX,
+            parse_errors: u32::MAX,
+            loop_us: u32::MAX,
+            loop_max_us: u32::MAX,
+            redraw_us: u32::MAX,
+            redraw_max_us: u32::MAX,
+            uptime_s: u32::MAX,
+        };
+        for group in StatsGroup::ALL {
+            let report = diagnostics.report(group);
+            let frame = Pico2PiMessage { stats: Some(report.clone()), ..Default::default() }.to_frame();
+            let text = ["&stats=", report.to_text().as_str(), "\r\n"].concat();
+            assert!(frame.ends_with(text.as_str()), "{}", frame);
+            let parsed = Pico2PiMessage::try_from(frame.as_str()).ok().unwrap();
+            assert_eq!(parsed.stats, Some(report));
+        }
+    }
+
+    #[test]
+    fn malformed_reports_do_not_parse() {
+        assert_eq!(StatsReport::parse("frames:1:2"), None);
+        assert_eq!(StatsReport::parse("frames:1:2:3:4"), None);
+        assert_eq!(StatsReport::parse("frames:1:x:3"), None);
+        assert_eq!(StatsReport::parse("bytes:1:2:3"), None);
+        assert_eq!(StatsReport::parse("time:1:2:3:4:5:6"), None);
+    }
+}
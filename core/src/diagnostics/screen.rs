@@ -0,0 +1,72 @@
+use core::fmt::Write;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics::text::{Alignment, Baseline};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use diagnostics::counters::Diagnostics;
+use screen::text::{draw_text, FONT_SET_6X12};
+
+const ROW_HEIGHT: i32 = 12;
+/// Values start after the longest label
+const VALUE_X: i32 = 8 * 6;
+
+/// Debug screen over everything else while it is toggled on. Labels are drawn on `first_draw`
+/// only, values every call.
+pub fn draw_diagnostics<D>(display: &mut D, diagnostics: &Diagnostics, first_draw: bool) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let screen = display.bounding_box();
+    let rows: [(&str, String<24>); 9] = [
+        ("up", uptime(diagnostics.uptime_s)),
+        ("rx/tx", pair(diagnostics.frames_rx, diagnostics.frames_tx, "")),
+        ("dropped", number(diagnostics.frames_dropped)),
+        ("crc", number(diagnostics.crc_failures)),
+        ("overrun", number(diagnostics.overruns)),
+        ("line", number(diagnostics.line_errors)),
+        ("parse", number(diagnostics.parse_errors)),
+        ("loop", pair(diagnostics.loop_us, diagnostics.loop_max_us, " us")),
+        ("redraw", pair(diagnostics.redraw_us / 1_000, diagnostics.redraw_max_us / 1_000, " ms")),
+    ];
+    if first_draw {
+        display.clear(Rgb565::BLACK)?;
+        draw_text(display, "Diagnostics", Point::new(screen.center().x, 0), &FONT_SET_6X12, Rgb565::YELLOW,
+                  Alignment::Center, Baseline::Top)?;
+    }
+    for (index, (label, value)) in rows.iter().enumerate() {
+        let y = ROW_HEIGHT * (index as i32 + 1);
+        if first_draw {
+            draw_text(display, label, Point::new(0, y), &FONT_SET_6X12, Rgb565::CYAN, Alignment::Left, Baseline::Top)?;
+        }
+        let value_area = Rectangle::new(Point::new(VALUE_X, y), Size::new(screen.size.width.saturating_sub(VALUE_X as u32), ROW_HEIGHT as u32));
+        value_area.into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK)).draw(display)?;
+        draw_text(display, value.as_str(), Point::new(VALUE_X, y), &FONT_SET_6X12, Rgb565::WHITE, Alignment::Left, Baseline::Top)?;
+    }
+    Ok(())
+}
+
+fn number(value: u32) -> String<24> {
+    let mut text = String::new();
+    _ = write!(text, "{}", value);
+    text
+}
+
+/// `last/max unit`
+fn pair(first: u32, second: u32, unit: &str) -> String<24> {
+    let mut text = String::new();
+    _ = write!(text, "{}/{}{}", first, second, unit);
+    text
+}
+
+/// `3:12:05`
+fn uptime(seconds: u32) -> String<24> {
+    let mut text = String::new();
+    _ = write!(text, "{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    text
+}
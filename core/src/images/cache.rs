@@ -0,0 +1,325 @@
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::Vec;
+use images::error::ImageError;
+use images::format::ImageFormat;
+use utils::base64;
+use utils::crc32::crc32;
+
+/// Images kept at once
+pub const IMAGE_SLOTS: usize = 16;
+/// Pixel data of all cached images, a 64x64 RGB565 image fills it
+pub const IMAGE_POOL_BYTES: usize = 8 * 1024;
+/// Images on screen at once
+pub const PLACED_IMAGES: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct ImageSlot {
+    id: u8,
+    size: Size,
+    format: ImageFormat,
+    /// Data is `pool[start..start + len]`
+    start: usize,
+    len: usize,
+    /// Bytes received so far, chunks come in order
+    received: usize,
+    /// All data received and checksum matched
+    complete: bool,
+    /// Value of `use_counter` when last uploaded or shown, the lowest one is evicted first
+    last_used: u32,
+}
+
+/// Images uploaded by the Pi, referenced by an id the Pi chooses. The Pi sends an icon once
+/// and shows it by id after, when the device answers `missing` the image was evicted and is sent again.
+///
+/// Data of all images shares one byte pool, kept compact so free space is always at the end.
+/// When an upload does not fit, least recently used images not on screen are evicted.
+#[derive(Clone)]
+pub struct ImageCache {
+    slots: Vec<ImageSlot, IMAGE_SLOTS>,
+    pool: [u8; IMAGE_POOL_BYTES],
+    /// Images on screen with their top left corner, drawn in this order
+    placed: Vec<(u8, Point), PLACED_IMAGES>,
+    /// Changes whenever something on screen changes, so the renderer does not compare pixel data
+    revision: u32,
+    use_counter: u32,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageCache {
+    pub fn new() -> Self {
+        ImageCache {
+            slots: Vec::new(),
+            pool: [0; IMAGE_POOL_BYTES],
+            placed: Vec::new(),
+            revision: 0,
+            use_counter: 0,
+        }
+    }
+
+    /// Start an upload, an image with the same id is replaced and taken off screen
+    pub fn begin(&mut self, id: u8, size: Size, format: ImageFormat) -> Result<(), ImageError> {
+        let len = format.data_len(size).ok_or(ImageError::TooLarge)?;
+        if len == 0 {
+            return Err(ImageError::BadHeader);
+        }
+        if len > IMAGE_POOL_BYTES {
+            return Err(ImageError::TooLarge);
+        }
+        self.delete(id);
+        while self.slots.is_full() || self.free_bytes() < len {
+            self.evict()?;
+        }
+        self.compact();
+
+        let start = self.used_bytes();
+        let last_used = self.next_use();
+        self.slots.push(ImageSlot { id, size, format, start, len, received: 0, complete: false, last_used })
+            .map_err(|_| ImageError::NoSpace)
+    }
+
+    /// Append a base64 chunk at `offset`. A chunk received before is ignored,
+    /// so the Pi can resend from the offset reported with `ImageError::Offset`.
+    pub fn write(&mut self, id: u8, offset: usize, data: &str) -> Result<(), ImageError> {
+        let slot = self.slots.iter_mut().find(|slot| slot.id == id && !slot.complete)
+            .ok_or(ImageError::Missing)?;
+        if offset < slot.received {
+            return Ok(());
+        }
+        if offset > slot.received {
+            return Err(ImageError::Offset(slot.received as u32));
+        }
+        let target = &mut self.pool[slot.start + slot.received..slot.start + slot.len];
+        let written = base64::decode(data, target).map_err(|_| ImageError::BadData)?;
+        slot.received += written;
+        Ok(())
+    }
+
+    /// Complete an upload. On checksum mismatch the data is dropped and the upload starts over from 0.
+    pub fn finish(&mut self, id: u8, checksum: u32) -> Result<(), ImageError> {
+        let slot = self.slots.iter_mut().find(|slot| slot.id == id)
+            .ok_or(ImageError::Missing)?;
+        if slot.complete {
+            return Ok(());
+        }
+        if slot.received < slot.len {
+            return Err(ImageError::Incomplete(slot.received as u32));
+        }
+        if crc32(&self.pool[slot.start..slot.start + slot.len]) != checksum {
+            slot.received = 0;
+            return Err(ImageError::Checksum);
+        }
+        slot.complete = true;
+        Ok(())
+    }
+
+    /// Put a complete image on screen, moving it when it is there already
+    pub fn show(&mut self, id: u8, position: Point) -> Result<(), ImageError> {
+        let last_used = self.next_use();
+        let slot = self.slots.iter_mut().find(|slot| slot.id == id && slot.complete)
+            .ok_or(ImageError::Missing)?;
+        slot.last_used = last_used;
+        self.revision = self.revision.wrapping_add(1);
+        match self.placed.iter_mut().find(|(placed_id, _)| *placed_id == id) {
+            Some(placed) => placed.1 = position,
+            None => self.placed.push((id, position)).map_err(|_| ImageError::NoSpace)?,
+        }
+        Ok(())
+    }
+
+    /// Take off screen, the image stays in the cache
+    pub fn hide(&mut self, id: u8) {
+        let placed_count = self.placed.len();
+        self.placed.retain(|(placed_id, _)| *placed_id != id);
+        if self.placed.len() != placed_count {
+            self.revision = self.revision.wrapping_add(1);
+        }
+    }
+
+    /// Take every image off screen
+    pub fn hide_all(&mut self) {
+        if !self.placed.is_empty() {
+            self.placed.clear();
+            self.revision = self.revision.wrapping_add(1);
+        }
+    }
+
+    /// Drop from the cache and from screen
+    pub fn delete(&mut self, id: u8) {
+        self.hide(id);
+        if let Some(index) = self.slots.iter().position(|slot| slot.id == id) {
+            self.slots.remove(index);
+        }
+    }
+
+    pub fn placed(&self) -> &[(u8, Point)] {
+        &self.placed
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    /// Draw images on screen in order, later ones on top. `fill_contiguous` streams
+    /// the pixels, the ST7735 driver sends them to the display in one buffered write.
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        self.draw_over(display, None)
+    }
+
+    /// Draw only images overlapping `area`, for parts of the screen drawn again under them
+    pub fn draw_over<D>(&self, display: &mut D, area: Option<&Rectangle>) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        for (id, position) in &self.placed {
+            let slot = match self.slots.iter().find(|slot| slot.id == *id && slot.complete) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            let rect = Rectangle::new(*position, slot.size);
+            if area.is_some_and(|area| area.intersection(&rect).is_zero_sized()) {
+                continue;
+            }
+            let data = &self.pool[slot.start..slot.start + slot.len];
+            let width = slot.size.width;
+            let pixels = (0..slot.size.width as usize * slot.size.height as usize)
+                .map(|index| slot.format.pixel(data, width, index));
+            display.fill_contiguous(&rect, pixels)?;
+        }
+        Ok(())
+    }
+
+    fn next_use(&mut self) -> u32 {
+        self.use_counter = self.use_counter.wrapping_add(1);
+        self.use_counter
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.slots.iter().map(|slot| slot.len).sum()
+    }
+
+    fn free_bytes(&self) -> usize {
+        IMAGE_POOL_BYTES - self.used_bytes()
+    }
+
+    /// Drop the least recently used image which is not on screen
+    fn evict(&mut self) -> Result<(), ImageError> {
+        let placed = &self.placed;
+        let index = self.slots.iter().enumerate()
+            .filter(|(_, slot)| !placed.iter().any(|(id, _)| *id == slot.id))
+            .min_by_key(|(_, slot)| slot.last_used)
+            .map(|(index, _)| index)
+            .ok_or(ImageError::NoSpace)?;
+        self.slots.remove(index);
+        Ok(())
+    }
+
+    /// Move data down over the gaps left by removed images, slots stay ordered by `start`
+    fn compact(&mut self) {
+        let mut next_start = 0;
+        for slot in self.slots.iter_mut() {
+            if slot.start != next_start {
+                self.pool.copy_within(slot.start..slot.start + slot.len, next_start);
+                slot.start = next_start;
+            }
+            next_start += slot.len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics_core::prelude::RgbColor;
+    use heapless::String;
+    use utils::test_display::TestDisplay;
+
+    const MONO: ImageFormat = ImageFormat::Mono { fg: Rgb565::WHITE, bg: Rgb565::BLACK };
+
+    fn encode(data: &[u8]) -> String<64> {
+        let mut encoded = String::new();
+        base64::encode(data, &mut encoded).unwrap();
+        encoded
+    }
+
+    /// 8x2 mono image, left half of the top row set
+    fn upload(cache: &mut ImageCache, id: u8) {
+        let data = [0xf0, 0x00];
+        cache.begin(id, Size::new(8, 2), MONO).unwrap();
+        cache.write(id, 0, encode(&data).as_str()).unwrap();
+        cache.finish(id, crc32(&data)).unwrap();
+    }
+
+    #[test]
+    fn sizes_are_checked() {
+        let mut cache = ImageCache::new();
+        assert_eq!(cache.begin(1, Size::new(0, 8), ImageFormat::Rgb565), Err(ImageError::BadHeader));
+        assert_eq!(cache.begin(1, Size::new(65, 64), ImageFormat::Rgb565), Err(ImageError::TooLarge));
+        assert_eq!(cache.begin(1, Size::new(u32::MAX, u32::MAX), ImageFormat::Rgb565), Err(ImageError::TooLarge));
+        assert_eq!(cache.begin(1, Size::new(65536, 32768), ImageFormat::Rgb565), Err(ImageError::TooLarge));
+        assert_eq!(cache.begin(1, Size::new(64, 64), ImageFormat::Rgb565), Ok(()));
+    }
+
+    #[test]
+    fn uploaded_image_is_drawn() {
+        let mut cache = ImageCache::new();
+        upload(&mut cache, 7);
+        cache.show(7, Point::new(2, 1)).unwrap();
+        let mut display: TestDisplay<12, 4> = TestDisplay::new();
+        cache.draw(&mut display).unwrap();
+        display.assert_rows(0, &[
+            "............",
+            "..####......",
+            "............",
+            "............",
+        ]);
+    }
+
+    #[test]
+    fn chunks_must_continue_the_data() {
+        let mut cache = ImageCache::new();
+        cache.begin(1, Size::new(8, 2), MONO).unwrap();
+        assert_eq!(cache.write(1, 1, encode(&[0]).as_str()), Err(ImageError::Offset(0)));
+        cache.write(1, 0, encode(&[0xf0]).as_str()).unwrap();
+        // resent chunk is ignored
+        cache.write(1, 0, encode(&[0xff]).as_str()).unwrap();
+        assert_eq!(cache.finish(1, crc32(&[0xf0, 0])), Err(ImageError::Incomplete(1)));
+        assert_eq!(cache.write(1, 1, encode(&[0, 0]).as_str()), Err(ImageError::BadData));
+        cache.write(1, 1, encode(&[0]).as_str()).unwrap();
+        assert_eq!(cache.finish(1, crc32(&[0xf0, 0])), Ok(()));
+    }
+
+    #[test]
+    fn bad_checksum_starts_over() {
+        let mut cache = ImageCache::new();
+        cache.begin(1, Size::new(8, 2), MONO).unwrap();
+        cache.write(1, 0, encode(&[1, 2]).as_str()).unwrap();
+        assert_eq!(cache.finish(1, 0), Err(ImageError::Checksum));
+        assert_eq!(cache.show(1, Point::zero()), Err(ImageError::Missing));
+        assert_eq!(cache.write(1, 2, encode(&[0]).as_str()), Err(ImageError::Offset(0)));
+    }
+
+    #[test]
+    fn images_on_screen_are_not_evicted() {
+        let mut cache = ImageCache::new();
+        upload(&mut cache, 1);
+        cache.show(1, Point::zero()).unwrap();
+        upload(&mut cache, 2);
+        // needs the whole pool
+        assert_eq!(cache.begin(3, Size::new(64, 64), ImageFormat::Rgb565), Err(ImageError::NoSpace));
+        assert_eq!(cache.show(1, Point::zero()), Ok(()));
+        cache.hide_all();
+        assert_eq!(cache.begin(3, Size::new(64, 64), ImageFormat::Rgb565), Ok(()));
+        assert_eq!(cache.show(1, Point::zero()), Err(ImageError::Missing));
+    }
+}
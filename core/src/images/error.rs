@@ -0,0 +1,70 @@
+use core::fmt;
+
+/// Why an image command failed, reported back to the Pi as `img=<id>:<code>[:<bytes>]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ImageError {
+    /// `img_begin` is not `<id>:<w>x<h>:<format>`, the image is empty or a side is past `MAX_IMAGE_SIDE`
+    BadHeader,
+    /// Bigger than the whole cache
+    TooLarge,
+    /// Cache full of images on screen
+    NoSpace,
+    /// Id not in the cache or not complete yet, the Pi sends the image again
+    Missing,
+    /// Chunk does not continue the received data, carries the offset expected instead
+    Offset(u32),
+    /// Bad base64 or more data than the image has
+    BadData,
+    /// `img_end` before all data came, carries the number of bytes received
+    Incomplete(u32),
+    /// CRC-32 of the received data differs, data is dropped
+    Checksum,
+}
+
+impl ImageError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ImageError::BadHeader => "bad_header",
+            ImageError::TooLarge => "too_large",
+            ImageError::NoSpace => "no_space",
+            ImageError::Missing => "missing",
+            ImageError::Offset(_) => "offset",
+            ImageError::BadData => "bad_data",
+            ImageError::Incomplete(_) => "incomplete",
+            ImageError::Checksum => "checksum",
+        }
+    }
+
+    /// Byte count of `Offset` and `Incomplete`
+    pub fn bytes(&self) -> Option<u32> {
+        match *self {
+            ImageError::Offset(bytes) | ImageError::Incomplete(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Reverse of `code` and `bytes`, used on the Pi side
+    pub fn from_code(code: &str, bytes: Option<u32>) -> Option<Self> {
+        match (code, bytes) {
+            ("bad_header", _) => Some(ImageError::BadHeader),
+            ("too_large", _) => Some(ImageError::TooLarge),
+            ("no_space", _) => Some(ImageError::NoSpace),
+            ("missing", _) => Some(ImageError::Missing),
+            ("offset", Some(bytes)) => Some(ImageError::Offset(bytes)),
+            ("bad_data", _) => Some(ImageError::BadData),
+            ("incomplete", Some(bytes)) => Some(ImageError::Incomplete(bytes)),
+            ("checksum", _) => Some(ImageError::Checksum),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bytes() {
+            Some(bytes) => write!(f, "{} at byte {}", self.code(), bytes),
+            None => f.write_str(self.code()),
+        }
+    }
+}
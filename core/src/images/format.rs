@@ -0,0 +1,124 @@
+use core::fmt::Write;
+use embedded_graphics_core::geometry::Size;
+use embedded_graphics_core::pixelcolor::raw::RawU16;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use heapless::String;
+use utils::color::{hex_color, parse_color};
+
+/// Longest side of an image, the largest ST7735 panel is 160 pixels
+pub const MAX_IMAGE_SIDE: u32 = 160;
+
+/// Pixel data of a cached image, rows top to bottom
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ImageFormat {
+    /// Two bytes per pixel, big endian, as the ST7735 takes them
+    Rgb565,
+    /// One bit per pixel, most significant bit on the left, every row starts a new byte.
+    /// Set bits are `fg`, clear bits `bg`.
+    Mono { fg: Rgb565, bg: Rgb565 },
+}
+
+impl ImageFormat {
+    /// `rgb565` or `mono[:<fg>:<bg>]` with `rrggbb` colours, mono is white on black by default
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(':');
+        let format = match parts.next()? {
+            "rgb565" => ImageFormat::Rgb565,
+            "mono" => match (parts.next(), parts.next()) {
+                (None, None) => ImageFormat::Mono { fg: Rgb565::WHITE, bg: Rgb565::BLACK },
+                (Some(fg), Some(bg)) => ImageFormat::Mono { fg: parse_color(fg)?, bg: parse_color(bg)? },
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(format)
+    }
+
+    /// Opposite of `parse`
+    pub fn encode(&self) -> String<20> {
+        let mut value: String<20> = String::new();
+        match self {
+            ImageFormat::Rgb565 => _ = value.push_str("rgb565"),
+            ImageFormat::Mono { fg, bg } => _ = write!(value, "mono:{}:{}", hex_color(*fg), hex_color(*bg)),
+        }
+        value
+    }
+
+    /// Bytes of pixel data for an image of `size`, `None` when the count overflows usize
+    pub fn data_len(&self, size: Size) -> Option<usize> {
+        let (width, height) = (size.width as usize, size.height as usize);
+        match self {
+            ImageFormat::Rgb565 => width.checked_mul(height)?.checked_mul(2),
+            ImageFormat::Mono { .. } => width.div_ceil(8).checked_mul(height),
+        }
+    }
+
+    /// Colour of pixel `index`, counted row by row
+    pub fn pixel(&self, data: &[u8], width: u32, index: usize) -> Rgb565 {
+        match *self {
+            ImageFormat::Rgb565 => {
+                let raw = u16::from_be_bytes([data[index * 2], data[index * 2 + 1]]);
+                RawU16::new(raw).into()
+            }
+            ImageFormat::Mono { fg, bg } => {
+                let width = width as usize;
+                let (row, column) = (index / width, index % width);
+                let byte = data[row * width.div_ceil(8) + column / 8];
+                if byte & (0x80 >> (column % 8)) != 0 { fg } else { bg }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONO: ImageFormat = ImageFormat::Mono { fg: Rgb565::WHITE, bg: Rgb565::BLACK };
+
+    #[test]
+    fn data_len_of_formats() {
+        assert_eq!(ImageFormat::Rgb565.data_len(Size::new(16, 8)), Some(256));
+        assert_eq!(MONO.data_len(Size::new(9, 3)), Some(6));
+        assert_eq!(MONO.data_len(Size::new(0, 3)), Some(0));
+    }
+
+    #[test]
+    fn data_len_does_not_wrap() {
+        let huge = Size::new(u32::MAX, u32::MAX);
+        assert_eq!(ImageFormat::Rgb565.data_len(huge), None);
+        if usize::BITS == 32 {
+            // 2^31 pixels, wrapped to 0 bytes before
+            assert_eq!(ImageFormat::Rgb565.data_len(Size::new(65536, 32768)), None);
+            assert_eq!(MONO.data_len(huge), None);
+        }
+    }
+
+    #[test]
+    fn format_round_trip() {
+        let colored = ImageFormat::Mono { fg: Rgb565::RED, bg: Rgb565::BLUE };
+        for format in [ImageFormat::Rgb565, MONO, colored] {
+            assert_eq!(ImageFormat::parse(format.encode().as_str()), Some(format));
+        }
+        assert_eq!(ImageFormat::parse("mono"), Some(MONO));
+        assert_eq!(ImageFormat::parse("mono:ffffff"), None);
+        assert_eq!(ImageFormat::parse("rgb565:x"), None);
+        assert_eq!(ImageFormat::parse("rgb888"), None);
+    }
+
+    #[test]
+    fn pixels_row_by_row() {
+        assert_eq!(ImageFormat::Rgb565.pixel(&[0x00, 0x00, 0xf8, 0x00], 2, 1), Rgb565::RED);
+        // 9 pixels wide, the second row starts a new byte
+        let data = [0b1000_0000, 0b1000_0000, 0b0100_0000, 0];
+        assert_eq!(MONO.pixel(&data, 9, 0), Rgb565::WHITE);
+        assert_eq!(MONO.pixel(&data, 9, 1), Rgb565::BLACK);
+        assert_eq!(MONO.pixel(&data, 9, 8), Rgb565::WHITE);
+        assert_eq!(MONO.pixel(&data, 9, 10), Rgb565::WHITE);
+        assert_eq!(MONO.pixel(&data, 9, 9), Rgb565::BLACK);
+    }
+}
@@ -0,0 +1,131 @@
+use input::keyboard_codes::KeyboardCodes;
+
+/// How often pending key events are reported to the Pi
+pub const SEND_INTERVAL_US: u64 = 250_000;
+/// Held key is repeated to the Pi after this time
+pub const HOLD_REPEAT_AFTER_MS: u64 = 1000;
+
+/// Key press/hold state machine.
+/// Key is reported on release, or repeatedly every `SEND_INTERVAL_US` while held longer than
+/// `HOLD_REPEAT_AFTER_MS`, together with how long it is held.
+pub struct KeyHoldState {
+    keycode_to_send_option: Option<KeyboardCodes>,
+    keydown_code_option: Option<KeyboardCodes>,
+    keydown_timestamp: u64,
+    timestamp: u64,
+}
+
+impl KeyHoldState {
+    pub fn new(now_us: u64) -> Self {
+        KeyHoldState {
+            keycode_to_send_option: None,
+            keydown_code_option: None,
+            keydown_timestamp: 0,
+            timestamp: now_us,
+        }
+    }
+
+    /// Feed currently pressed key (`None` - nothing pressed). Returns true on new key down.
+    pub fn update(&mut self, pressed: Option<KeyboardCodes>, now_us: u64) -> bool {
+        let mut new_keydown = false;
+        self.keydown_code_option = pressed;
+        match pressed {
+            Some(ckc) => {
+                if self.keydown_timestamp == 0 {
+                    self.keydown_timestamp = now_us;
+                    new_keydown = true;
+                }
+                self.keycode_to_send_option = Some(ckc);
+            }
+            None => {
+                self.keydown_timestamp = 0u64;
+            }
+        }
+        new_keydown
+    }
+
+    /// Run every loop. Every `SEND_INTERVAL_US` returns key and hold time in ms if it should be sent.
+    pub fn poll(&mut self, now_us: u64) -> Option<(KeyboardCodes, u64)> {
+        if now_us - self.timestamp <= SEND_INTERVAL_US {
+            return None;
+        }
+        self.timestamp = now_us;
+
+        let mut is_keys_the_same = false;
+        if self.keydown_code_option.is_some() {
+            is_keys_the_same = self.keydown_code_option.eq(&self.keycode_to_send_option);
+        }
+
+        let mut kd_ms = 0;
+        if self.keydown_timestamp > 0 {
+            kd_ms = (now_us - self.keydown_timestamp) / 1_000;
+        }
+        let keyup_or_keypress_more_than_sec =
+            self.keydown_code_option.is_none() || is_keys_the_same && kd_ms > HOLD_REPEAT_AFTER_MS;
+
+        if keyup_or_keypress_more_than_sec {
+            if let Some(keycode) = self.keycode_to_send_option.take() {
+                return Some((keycode, kd_ms));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_US: u64 = 1_000_000;
+
+    #[test]
+    fn tap_is_reported_after_release() {
+        let mut state = KeyHoldState::new(START_US);
+        assert!(state.update(Some(KeyboardCodes::Ok), START_US + 10_000));
+        assert_eq!(state.poll(START_US + 100_000), None);
+        assert!(!state.update(None, START_US + 200_000));
+        assert_eq!(state.poll(START_US + 300_000), Some((KeyboardCodes::Ok, 0)));
+        // reported once
+        assert_eq!(state.poll(START_US + 600_000), None);
+    }
+
+    #[test]
+    fn polls_at_most_every_send_interval() {
+        let mut state = KeyHoldState::new(START_US);
+        state.update(Some(KeyboardCodes::Up), START_US);
+        state.update(None, START_US + 10_000);
+        assert_eq!(state.poll(START_US + SEND_INTERVAL_US), None);
+        assert_eq!(state.poll(START_US + SEND_INTERVAL_US + 1), Some((KeyboardCodes::Up, 0)));
+    }
+
+    #[test]
+    fn key_down_is_reported_once() {
+        let mut state = KeyHoldState::new(START_US);
+        assert!(state.update(Some(KeyboardCodes::Down), START_US));
+        assert!(!state.update(Some(KeyboardCodes::Down), START_US + 1_000));
+        assert!(!state.update(Some(KeyboardCodes::Down), START_US + 2_000));
+    }
+
+    #[test]
+    fn held_key_repeats_with_hold_time() {
+        let mut state = KeyHoldState::new(START_US);
+        state.update(Some(KeyboardCodes::Left), START_US);
+        for held_us in [300_000, 600_000, 1_000_000] {
+            state.update(Some(KeyboardCodes::Left), START_US + held_us);
+            assert_eq!(state.poll(START_US + held_us), None);
+        }
+        state.update(Some(KeyboardCodes::Left), START_US + 1_300_000);
+        assert_eq!(state.poll(START_US + 1_300_000), Some((KeyboardCodes::Left, 1_300)));
+        state.update(Some(KeyboardCodes::Left), START_US + 1_600_000);
+        assert_eq!(state.poll(START_US + 1_600_000), Some((KeyboardCodes::Left, 1_600)));
+    }
+
+    #[test]
+    fn last_pressed_key_is_reported() {
+        let mut state = KeyHoldState::new(START_US);
+        state.update(Some(KeyboardCodes::Left), START_US);
+        state.update(Some(KeyboardCodes::Right), START_US + 100_000);
+        state.update(None, START_US + 200_000);
+        assert_eq!(state.poll(START_US + 300_000), Some((KeyboardCodes::Right, 0)));
+    }
+}
@@ -0,0 +1,54 @@
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeyboardCodes {
+    Up,
+    Down,
+    Left,
+    Right,
+    Ok,
+}
+impl KeyboardCodes {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            KeyboardCodes::Up => b'u',
+            KeyboardCodes::Down => b'd',
+            KeyboardCodes::Left => b'l',
+            KeyboardCodes::Right => b'r',
+            KeyboardCodes::Ok => b'o',
+        }
+    }
+
+    /// Usage id on the USB HID keyboard page: arrows and Enter
+    pub fn hid_usage(&self) -> u8 {
+        match self {
+            KeyboardCodes::Up => 0x52,
+            KeyboardCodes::Down => 0x51,
+            KeyboardCodes::Left => 0x50,
+            KeyboardCodes::Right => 0x4f,
+            KeyboardCodes::Ok => 0x28,
+        }
+    }
+
+    //obsolete
+    pub fn as_char(&self) -> char {
+        match self {
+            KeyboardCodes::Up => 'u',
+            KeyboardCodes::Down => 'd',
+            KeyboardCodes::Left => 'l',
+            KeyboardCodes::Right => 'r',
+            KeyboardCodes::Ok => 'o',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<KeyboardCodes> {
+        match c {
+            'u' => Some(KeyboardCodes::Up),
+            'd' => Some(KeyboardCodes::Down),
+            'l' => Some(KeyboardCodes::Left),
+            'r' => Some(KeyboardCodes::Right),
+            'o' => Some(KeyboardCodes::Ok),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod keyboard_codes;
+pub mod key_hold;
@@ -0,0 +1,81 @@
+use core::convert::TryFrom;
+use core::fmt;
+
+/// Why a layout was rejected, reported back to the Pi as `layout=<code>[:<node>]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LayoutError {
+    /// More nodes than `LAYOUT_CAPACITY`
+    TooManyNodes,
+    /// `layout_set` without a layout or with index past the last node
+    UnknownNode(u8),
+    UnknownKind(u8),
+    /// Rectangle is not `x,y,w,h` or has zero size
+    BadGeometry(u8),
+    /// Unknown property, bad value or text too long
+    BadProperty(u8),
+    /// Parent is not an earlier node
+    BadParent(u8),
+    /// Nesting deeper than `MAX_DEPTH`
+    TooDeep(u8),
+    /// Node does not fit into its parent or the display
+    OutOfBounds(u8),
+}
+
+impl LayoutError {
+    /// Node index as reported, indices past `u8` show as `u8::MAX`, never a real node
+    pub fn node_index(index: usize) -> u8 {
+        u8::try_from(index).unwrap_or(u8::MAX)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            LayoutError::TooManyNodes => "too_many_nodes",
+            LayoutError::UnknownNode(_) => "unknown_node",
+            LayoutError::UnknownKind(_) => "unknown_kind",
+            LayoutError::BadGeometry(_) => "bad_geometry",
+            LayoutError::BadProperty(_) => "bad_property",
+            LayoutError::BadParent(_) => "bad_parent",
+            LayoutError::TooDeep(_) => "too_deep",
+            LayoutError::OutOfBounds(_) => "out_of_bounds",
+        }
+    }
+
+    /// Index of the offending node
+    pub fn node(&self) -> Option<u8> {
+        match *self {
+            LayoutError::TooManyNodes => None,
+            LayoutError::UnknownNode(node)
+            | LayoutError::UnknownKind(node)
+            | LayoutError::BadGeometry(node)
+            | LayoutError::BadProperty(node)
+            | LayoutError::BadParent(node)
+            | LayoutError::TooDeep(node)
+            | LayoutError::OutOfBounds(node) => Some(node),
+        }
+    }
+
+    /// Reverse of `code` and `node`, used on the Pi side
+    pub fn from_code(code: &str, node: Option<u8>) -> Option<Self> {
+        match (code, node) {
+            ("too_many_nodes", _) => Some(LayoutError::TooManyNodes),
+            ("unknown_node", Some(node)) => Some(LayoutError::UnknownNode(node)),
+            ("unknown_kind", Some(node)) => Some(LayoutError::UnknownKind(node)),
+            ("bad_geometry", Some(node)) => Some(LayoutError::BadGeometry(node)),
+            ("bad_property", Some(node)) => Some(LayoutError::BadProperty(node)),
+            ("bad_parent", Some(node)) => Some(LayoutError::BadParent(node)),
+            ("too_deep", Some(node)) => Some(LayoutError::TooDeep(node)),
+            ("out_of_bounds", Some(node)) => Some(LayoutError::OutOfBounds(node)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.node() {
+            Some(node) => write!(f, "{} at node {}", self.code(), node),
+            None => f.write_str(self.code()),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod error;
+pub mod node;
+pub mod tree;
+pub mod render;
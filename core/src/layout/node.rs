@@ -0,0 +1,302 @@
+use core::convert::TryFrom;
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::text::Alignment;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use layout::error::LayoutError;
+use screen::text::{FontSet, FONT_SET_10X20, FONT_SET_6X10, FONT_SET_6X12};
+use utils::color::parse_color;
+
+/// Largest coordinate or size, far past any display. Keeps the sums over `MAX_DEPTH` nested
+/// rectangles in `Layout::validate` inside i32.
+pub const MAX_EXTENT: i64 = 0x7fff;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NodeKind {
+    /// Container, fills its background and holds children
+    Region,
+    Text,
+    /// `,` separated items, `value` is the highlighted one, scrolled to stay visible
+    List,
+    /// Horizontal bar, `value` is percent
+    Bar,
+    Icon,
+}
+
+impl NodeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeKind::Region => "region",
+            NodeKind::Text => "text",
+            NodeKind::List => "list",
+            NodeKind::Bar => "bar",
+            NodeKind::Icon => "icon",
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for NodeKind {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "region" => Ok(NodeKind::Region),
+            "text" => Ok(NodeKind::Text),
+            "list" => Ok(NodeKind::List),
+            "bar" => Ok(NodeKind::Bar),
+            "icon" => Ok(NodeKind::Icon),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Font {
+    /// 6x10
+    Small,
+    /// 6x12, same as screen lines
+    Normal,
+    /// 10x20
+    Large,
+}
+
+impl Font {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Font::Small => "small",
+            Font::Normal => "normal",
+            Font::Large => "large",
+        }
+    }
+
+    pub fn font_set(&self) -> &'static FontSet {
+        match self {
+            Font::Small => &FONT_SET_6X10,
+            Font::Normal => &FONT_SET_6X12,
+            Font::Large => &FONT_SET_10X20,
+        }
+    }
+
+    /// Metrics, text is drawn with `font_set`
+    pub fn mono_font(&self) -> &'static MonoFont<'static> {
+        &self.font_set().latin1
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Font {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "small" => Ok(Font::Small),
+            "normal" => Ok(Font::Normal),
+            "large" => Ok(Font::Large),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Align::Left => "left",
+            Align::Center => "center",
+            Align::Right => "right",
+        }
+    }
+
+    pub fn alignment(&self) -> Alignment {
+        match self {
+            Align::Left => Alignment::Left,
+            Align::Center => Alignment::Center,
+            Align::Right => Alignment::Right,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Align {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "left" => Ok(Align::Left),
+            "center" => Ok(Align::Center),
+            "right" => Ok(Align::Right),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Icon {
+    /// `value` is charge percent
+    Battery,
+    Check,
+    Cross,
+    Dot,
+}
+
+impl Icon {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Icon::Battery => "battery",
+            Icon::Check => "check",
+            Icon::Cross => "cross",
+            Icon::Dot => "dot",
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Icon {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "battery" => Ok(Icon::Battery),
+            "check" => Ok(Icon::Check),
+            "cross" => Ok(Icon::Cross),
+            "dot" => Ok(Icon::Dot),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One region or widget of a layout: `<kind>|<x>,<y>,<w>,<h>[|<property>=<value>]...`
+/// Properties: `parent=<index>`, `fg=<rrggbb>`, `bg=<rrggbb>`, `font=small|normal|large`,
+/// `align=left|center|right`, `text=<text>`, `items=<item>,<item>`, `value=<number>`,
+/// `icon=battery|check|cross|dot` and `rect=<x>,<y>,<w>,<h>` for updates.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub parent: Option<usize>,
+    /// Relative to the parent, or to the display for top level nodes
+    pub rect: Rectangle,
+    pub fg: Rgb565,
+    pub bg: Option<Rgb565>,
+    pub font: Font,
+    pub align: Align,
+    /// Text, or list items separated by `,`
+    pub text: String<64>,
+    /// Bar percent, list cursor or battery percent
+    pub value: i32,
+    pub icon: Icon,
+}
+
+impl Node {
+    pub fn parse(index: usize, value: &str) -> Result<Node, LayoutError> {
+        let node_index = LayoutError::node_index(index);
+        let mut parts = value.split('|');
+        let kind = NodeKind::try_from(parts.next().unwrap_or(""))
+            .map_err(|_| LayoutError::UnknownKind(node_index))?;
+        let rect = parse_rect(node_index, parts.next().unwrap_or(""))?;
+        let mut node = Node {
+            kind,
+            parent: None,
+            rect,
+            fg: Rgb565::WHITE,
+            bg: None,
+            font: Font::Normal,
+            align: Align::Left,
+            text: String::new(),
+            value: 0,
+            icon: Icon::Dot,
+        };
+        for property in parts {
+            node.apply_property(index, property)?;
+        }
+        Ok(node)
+    }
+
+    /// Apply one `<property>=<value>`
+    pub fn apply_property(&mut self, index: usize, property: &str) -> Result<(), LayoutError> {
+        let node_index = LayoutError::node_index(index);
+        let bad_property = LayoutError::BadProperty(node_index);
+        let (key, value) = property.split_once('=').ok_or(bad_property)?;
+        match key {
+            "rect" => self.rect = parse_rect(node_index, value)?,
+            "parent" => self.parent = Some(value.parse::<usize>().map_err(|_| LayoutError::BadParent(node_index))?),
+            "fg" => self.fg = parse_color(value).ok_or(bad_property)?,
+            "bg" => self.bg = Some(parse_color(value).ok_or(bad_property)?),
+            "font" => self.font = Font::try_from(value).map_err(|_| bad_property)?,
+            "align" => self.align = Align::try_from(value).map_err(|_| bad_property)?,
+            "text" | "items" => self.text = value.parse::<String<64>>().map_err(|_| bad_property)?,
+            "value" => self.value = value.parse::<i32>().map_err(|_| bad_property)?,
+            "icon" => self.icon = Icon::try_from(value).map_err(|_| bad_property)?,
+            _ => return Err(bad_property),
+        }
+        Ok(())
+    }
+}
+
+/// `x,y,w,h`, width and height can't be 0. Values past `MAX_EXTENT` are out of bounds.
+fn parse_rect(node_index: u8, value: &str) -> Result<Rectangle, LayoutError> {
+    let bad_geometry = LayoutError::BadGeometry(node_index);
+    let mut numbers = value.split(',');
+    let mut next = || numbers.next().and_then(|number| number.parse::<i64>().ok()).ok_or(bad_geometry);
+    let (x, y, w, h) = (next()?, next()?, next()?, next()?);
+    if w <= 0 || h <= 0 || numbers.next().is_some() {
+        return Err(bad_geometry);
+    }
+    let extent = -MAX_EXTENT..=MAX_EXTENT;
+    if !extent.contains(&x) || !extent.contains(&y) || w > MAX_EXTENT || h > MAX_EXTENT {
+        return Err(LayoutError::OutOfBounds(node_index));
+    }
+    Ok(Rectangle::new(Point::new(x as i32, y as i32), Size::new(w as u32, h as u32)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_is_parsed() {
+        let node = Node::parse(0, "text|4,8,100,12|text=hi|parent=0").unwrap();
+        assert_eq!(node.rect, Rectangle::new(Point::new(4, 8), Size::new(100, 12)));
+        assert_eq!(node.text.as_str(), "hi");
+        assert_eq!(node.parent, Some(0));
+    }
+
+    #[test]
+    fn bad_rect_is_rejected() {
+        assert_eq!(Node::parse(2, "text|0,0,0,10"), Err(LayoutError::BadGeometry(2)));
+        assert_eq!(Node::parse(2, "text|0,0,-4,10"), Err(LayoutError::BadGeometry(2)));
+        assert_eq!(Node::parse(2, "text|0,0,10"), Err(LayoutError::BadGeometry(2)));
+        assert_eq!(Node::parse(2, "text|0,0,10,10,10"), Err(LayoutError::BadGeometry(2)));
+        assert_eq!(Node::parse(2, "text|a,0,10,10"), Err(LayoutError::BadGeometry(2)));
+        assert_eq!(Node::parse(2, "text|0,0,99999999999999999999,10"), Err(LayoutError::BadGeometry(2)));
+    }
+
+    #[test]
+    fn extreme_rect_is_out_of_bounds() {
+        assert_eq!(Node::parse(1, "region|2147483647,0,10,10"), Err(LayoutError::OutOfBounds(1)));
+        assert_eq!(Node::parse(1, "region|0,-2147483648,10,10"), Err(LayoutError::OutOfBounds(1)));
+        assert_eq!(Node::parse(1, "region|0,0,4294967295,10"), Err(LayoutError::OutOfBounds(1)));
+        assert_eq!(Node::parse(1, "region|0,0,10,32768"), Err(LayoutError::OutOfBounds(1)));
+        assert!(Node::parse(1, "region|32767,-32767,32767,32767").is_ok());
+    }
+
+    #[test]
+    fn properties_are_applied() {
+        let mut node = Node::parse(0, "bar|0,0,50,8").unwrap();
+        node.apply_property(0, "value=40").unwrap();
+        assert_eq!(node.value, 40);
+        assert_eq!(node.apply_property(0, "rect=2147483647,0,1,1"), Err(LayoutError::OutOfBounds(0)));
+        assert_eq!(node.apply_property(0, "colour=red"), Err(LayoutError::BadProperty(0)));
+        assert_eq!(node.apply_property(0, "value"), Err(LayoutError::BadProperty(0)));
+        assert_eq!(node.apply_property(256, "value"), Err(LayoutError::BadProperty(u8::MAX)));
+    }
+}
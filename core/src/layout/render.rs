@@ -0,0 +1,124 @@
+use embedded_graphics::draw_target::DrawTargetExt;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle};
+use embedded_graphics::text::Baseline;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use layout::node::{Align, Icon, Node, NodeKind};
+use layout::tree::Layout;
+use screen::text::draw_text;
+use widgets::battery::draw_battery;
+
+/// Draw validated layout, every node is clipped to its rectangle
+pub fn draw_layout<D>(display: &mut D, layout: &Layout) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    for (index, node) in layout.nodes.iter().enumerate() {
+        let rect = layout.absolute_rect(index);
+        let mut clipped = display.clipped(&rect);
+        if let Some(bg) = node.bg {
+            rect.into_styled(PrimitiveStyle::with_fill(bg)).draw(&mut clipped)?;
+        }
+        match node.kind {
+            NodeKind::Region => {}
+            NodeKind::Text => draw_node_text(&mut clipped, node, node.text.as_str(), rect, node.fg)?,
+            NodeKind::List => draw_list(&mut clipped, node, rect)?,
+            NodeKind::Bar => draw_bar(&mut clipped, node, rect)?,
+            NodeKind::Icon => draw_icon(&mut clipped, node, rect)?,
+        }
+    }
+    Ok(())
+}
+
+/// One line of text vertically centered in `rect`
+fn draw_node_text<D>(display: &mut D, node: &Node, text: &str, rect: Rectangle, color: Rgb565) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let x = match node.align {
+        Align::Left => rect.top_left.x + 1,
+        Align::Center => rect.center().x,
+        Align::Right => rect.top_left.x + rect.size.width as i32 - 1,
+    };
+    draw_text(
+        display,
+        text,
+        Point::new(x, rect.center().y),
+        node.font.font_set(),
+        color,
+        node.align.alignment(),
+        Baseline::Middle,
+    )
+}
+
+fn draw_list<D>(display: &mut D, node: &Node, rect: Rectangle) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let row_height = node.font.mono_font().character_size.height + 2;
+    let visible_rows = (rect.size.height / row_height).max(1) as usize;
+    let cursor = node.value.max(0) as usize;
+    let first_row = (cursor + 1).saturating_sub(visible_rows);
+    let background = node.bg.unwrap_or(Rgb565::BLACK);
+    for (row, item) in node.text.split(',').skip(first_row).take(visible_rows).enumerate() {
+        let row_rect = Rectangle::new(
+            rect.top_left + Point::new(0, (row as u32 * row_height) as i32),
+            Size::new(rect.size.width, row_height),
+        );
+        let color = if first_row + row == cursor {
+            row_rect.into_styled(PrimitiveStyle::with_fill(node.fg)).draw(display)?;
+            background
+        } else {
+            node.fg
+        };
+        draw_node_text(display, node, item, row_rect, color)?;
+    }
+    Ok(())
+}
+
+fn draw_bar<D>(display: &mut D, node: &Node, rect: Rectangle) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    rect.into_styled(PrimitiveStyle::with_stroke(node.fg, 1)).draw(display)?;
+    let inner_width = rect.size.width.saturating_sub(4);
+    let filled_width = inner_width * node.value.clamp(0, 100) as u32 / 100;
+    Rectangle::new(rect.top_left + Point::new(2, 2), Size::new(filled_width, rect.size.height.saturating_sub(4)))
+        .into_styled(PrimitiveStyle::with_fill(node.fg))
+        .draw(display)?;
+    Ok(())
+}
+
+fn draw_icon<D>(display: &mut D, node: &Node, rect: Rectangle) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let side = rect.size.width.min(rect.size.height) as i32;
+    let top_left = rect.top_left;
+    let stroke = PrimitiveStyle::with_stroke(node.fg, 2);
+    match node.icon {
+        Icon::Battery => draw_battery(display, top_left, node.value.clamp(0, 100) as u8)?,
+        Icon::Check => {
+            let middle = top_left + Point::new(side / 3, side - 2);
+            Line::new(top_left + Point::new(1, side / 2), middle).into_styled(stroke).draw(display)?;
+            Line::new(middle, top_left + Point::new(side - 1, 1)).into_styled(stroke).draw(display)?;
+        }
+        Icon::Cross => {
+            Line::new(top_left + Point::new(1, 1), top_left + Point::new(side - 2, side - 2))
+                .into_styled(stroke).draw(display)?;
+            Line::new(top_left + Point::new(side - 2, 1), top_left + Point::new(1, side - 2))
+                .into_styled(stroke).draw(display)?;
+        }
+        Icon::Dot => {
+            Circle::new(top_left, side as u32)
+                .into_styled(PrimitiveStyle::with_fill(node.fg))
+                .draw(display)?;
+        }
+    }
+    Ok(())
+}
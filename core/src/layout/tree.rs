@@ -0,0 +1,144 @@
+use core::mem::replace;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::Vec;
+use layout::error::LayoutError;
+use layout::node::Node;
+
+pub const LAYOUT_CAPACITY: usize = 16;
+/// Top level node has depth 1
+pub const MAX_DEPTH: usize = 4;
+
+/// Screen described by the Pi as a tree of nodes. Parents go before their children,
+/// so drawing in order paints containers first.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Layout {
+    pub nodes: Vec<Node, LAYOUT_CAPACITY>,
+}
+
+impl Layout {
+    /// `<node>;<node>;...`, see `Node::parse`. Call `validate` before drawing.
+    pub fn parse(value: &str) -> Result<Layout, LayoutError> {
+        let mut layout = Layout::default();
+        for (index, node) in value.split(';').filter(|node| !node.is_empty()).enumerate() {
+            let node = Node::parse(index, node)?;
+            layout.nodes.push(node).map_err(|_| LayoutError::TooManyNodes)?;
+        }
+        Ok(layout)
+    }
+
+    /// Check parents, nesting depth and that every node fits into its parent and the display
+    pub fn validate(&self, display_size: Size) -> Result<(), LayoutError> {
+        for (index, node) in self.nodes.iter().enumerate() {
+            let node_index = LayoutError::node_index(index);
+            let mut depth = 1;
+            let mut ancestor = node.parent;
+            while let Some(parent) = ancestor {
+                if parent >= index {
+                    return Err(LayoutError::BadParent(node_index));
+                }
+                depth += 1;
+                if depth > MAX_DEPTH {
+                    return Err(LayoutError::TooDeep(node_index));
+                }
+                ancestor = self.nodes[parent].parent;
+            }
+
+            let container = match node.parent {
+                Some(parent) => self.absolute_rect(parent),
+                None => Rectangle::new(Point::zero(), display_size),
+            };
+            let rect = self.absolute_rect(index);
+            if container.intersection(&rect) != rect {
+                return Err(LayoutError::OutOfBounds(node_index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Node rectangle in display coordinates
+    pub fn absolute_rect(&self, index: usize) -> Rectangle {
+        let node = &self.nodes[index];
+        let mut top_left = node.rect.top_left;
+        let mut ancestor = node.parent;
+        while let Some(parent) = ancestor {
+            top_left += self.nodes[parent].rect.top_left;
+            ancestor = self.nodes[parent].parent;
+        }
+        Rectangle::new(top_left, node.rect.size)
+    }
+
+    /// `layout_set=<index>|<property>=<value>|...`, node is left as it was when the result does not validate
+    pub fn update(&mut self, index: usize, properties: &str, display_size: Size) -> Result<(), LayoutError> {
+        let mut node = self.nodes.get(index).ok_or(LayoutError::UnknownNode(LayoutError::node_index(index)))?.clone();
+        for property in properties.split('|').filter(|property| !property.is_empty()) {
+            node.apply_property(index, property)?;
+        }
+        let previous = replace(&mut self.nodes[index], node);
+        if let Err(err) = self.validate(display_size) {
+            self.nodes[index] = previous;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISPLAY: Size = Size::new(128, 128);
+
+    fn validate(value: &str) -> Result<(), LayoutError> {
+        Layout::parse(value)?.validate(DISPLAY)
+    }
+
+    #[test]
+    fn nested_layout_is_valid() {
+        assert_eq!(validate("region|0,16,128,96;text|4,4,120,12|parent=0;bar|4,20,120,8|parent=0"), Ok(()));
+        let layout = Layout::parse("region|0,16,128,96;text|4,4,120,12|parent=0").unwrap();
+        assert_eq!(layout.absolute_rect(1), Rectangle::new(Point::new(4, 20), Size::new(120, 12)));
+    }
+
+    #[test]
+    fn nodes_must_fit() {
+        assert_eq!(validate("text|0,0,129,10"), Err(LayoutError::OutOfBounds(0)));
+        assert_eq!(validate("region|0,0,64,64;text|60,0,10,10|parent=0"), Err(LayoutError::OutOfBounds(1)));
+        assert_eq!(validate("text|-1,0,10,10"), Err(LayoutError::OutOfBounds(0)));
+    }
+
+    #[test]
+    fn extreme_coordinates_do_not_overflow() {
+        assert_eq!(validate("region|2147483647,0,10,10"), Err(LayoutError::OutOfBounds(0)));
+        assert_eq!(validate("region|-2147483648,-2147483648,4294967295,4294967295"), Err(LayoutError::OutOfBounds(0)));
+        // largest accepted values, summed over the deepest nesting
+        let deepest = "region|32767,32767,32767,32767;\
+            region|32767,32767,32767,32767|parent=0;\
+            region|32767,32767,32767,32767|parent=1;\
+            region|32767,32767,32767,32767|parent=2";
+        assert_eq!(validate(deepest), Err(LayoutError::OutOfBounds(0)));
+        let negative = "region|0,0,128,128;region|-32767,-32767,32767,32767|parent=0";
+        assert_eq!(validate(negative), Err(LayoutError::OutOfBounds(1)));
+    }
+
+    #[test]
+    fn parents_and_depth_are_checked() {
+        assert_eq!(validate("text|0,0,10,10|parent=0"), Err(LayoutError::BadParent(0)));
+        assert_eq!(validate("text|0,0,10,10|parent=1;text|0,0,10,10"), Err(LayoutError::BadParent(0)));
+        let too_deep = "region|0,0,64,64;region|0,0,32,32|parent=0;region|0,0,16,16|parent=1;\
+            region|0,0,8,8|parent=2;region|0,0,4,4|parent=3";
+        assert_eq!(validate(too_deep), Err(LayoutError::TooDeep(4)));
+    }
+
+    #[test]
+    fn update_keeps_the_node_when_invalid() {
+        let mut layout = Layout::parse("text|0,0,64,12|text=hi").unwrap();
+        assert_eq!(layout.update(0, "rect=100,0,64,12", DISPLAY), Err(LayoutError::OutOfBounds(0)));
+        assert_eq!(layout.update(0, "rect=2147483647,0,64,12", DISPLAY), Err(LayoutError::OutOfBounds(0)));
+        assert_eq!(layout.nodes[0].rect, Rectangle::new(Point::zero(), Size::new(64, 12)));
+        assert_eq!(layout.update(0, "text=ok|rect=8,8,64,12", DISPLAY), Ok(()));
+        assert_eq!(layout.nodes[0].text.as_str(), "ok");
+        assert_eq!(layout.update(3, "text=x", DISPLAY), Err(LayoutError::UnknownNode(3)));
+        assert_eq!(layout.update(256, "text=x", DISPLAY), Err(LayoutError::UnknownNode(u8::MAX)));
+    }
+}
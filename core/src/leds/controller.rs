@@ -0,0 +1,178 @@
+use leds::pattern::{LedId, LedPattern};
+
+/// Built-in patterns for device states. While one is active it overrides patterns set by the Pi.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StatusPattern {
+    /// all LEDs breathing until the first message from the Pi
+    Boot,
+    /// red slow blink, everything else off
+    LinkLost,
+    /// red fast blink
+    Error,
+}
+
+impl StatusPattern {
+    fn pattern_for(&self, led: LedId) -> LedPattern {
+        match (self, led) {
+            (StatusPattern::Boot, _) => LedPattern::Pulse { period_ms: 1500 },
+            (StatusPattern::LinkLost, LedId::Red) => LedPattern::Blink { period_ms: 2000 },
+            (StatusPattern::LinkLost, _) => LedPattern::Off,
+            (StatusPattern::Error, LedId::Red) => LedPattern::Blink { period_ms: 200 },
+            (StatusPattern::Error, _) => LedPattern::Off,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct LedSlot {
+    pattern: LedPattern,
+    started_at_ms: u64,
+}
+
+struct ActiveStatus {
+    status: StatusPattern,
+    started_at_ms: u64,
+    until_ms: Option<u64>,
+}
+
+/// Time driven pattern engine for the status LEDs.
+/// Knows nothing about hardware: feed it timestamps and apply `levels` to the PWM outputs.
+pub struct LedController {
+    slots: [LedSlot; 4],
+    status: Option<ActiveStatus>,
+}
+
+impl LedController {
+    pub fn new() -> Self {
+        LedController {
+            slots: [LedSlot { pattern: LedPattern::Off, started_at_ms: 0 }; 4],
+            status: None,
+        }
+    }
+
+    /// Set pattern requested by the Pi. Pattern phase starts at `now_ms`.
+    pub fn set(&mut self, led: LedId, pattern: LedPattern, now_ms: u64) {
+        self.slots[led.index()] = LedSlot { pattern, started_at_ms: now_ms };
+    }
+
+    pub fn pattern(&self, led: LedId) -> LedPattern {
+        self.slots[led.index()].pattern
+    }
+
+    /// Show built-in pattern until `clear_status` is called
+    pub fn show_status(&mut self, status: StatusPattern, now_ms: u64) {
+        if self.status.as_ref().map(|s| s.status) == Some(status) {
+            return;
+        }
+        self.status = Some(ActiveStatus { status, started_at_ms: now_ms, until_ms: None });
+    }
+
+    /// Show built-in pattern for `duration_ms`, then fall back to the Pi patterns
+    pub fn show_status_for(&mut self, status: StatusPattern, now_ms: u64, duration_ms: u32) {
+        self.status = Some(ActiveStatus {
+            status,
+            started_at_ms: now_ms,
+            until_ms: Some(now_ms + duration_ms as u64),
+        });
+    }
+
+    pub fn clear_status(&mut self, status: StatusPattern) {
+        if self.status.as_ref().map(|s| s.status) == Some(status) {
+            self.status = None;
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusPattern> {
+        self.status.as_ref().map(|s| s.status)
+    }
+
+    /// Brightness of every LED (indexed by `LedId::index`) at `now_ms`
+    pub fn levels(&mut self, now_ms: u64) -> [u8; 4] {
+        if let Some(until_ms) = self.status.as_ref().and_then(|s| s.until_ms) {
+            if now_ms >= until_ms {
+                self.status = None;
+            }
+        }
+
+        let mut levels = [0u8; 4];
+        for led in LedId::ALL.iter() {
+            let (pattern, started_at_ms) = match &self.status {
+                Some(active) => (active.status.pattern_for(*led), active.started_at_ms),
+                None => {
+                    let slot = self.slots[led.index()];
+                    (slot.pattern, slot.started_at_ms)
+                }
+            };
+            levels[led.index()] = pattern.brightness_at(now_ms.saturating_sub(started_at_ms));
+        }
+        levels
+    }
+}
+
+impl Default for LedController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: usize = 3;
+
+    fn controller() -> LedController {
+        let mut controller = LedController::new();
+        controller.set(LedId::Green, LedPattern::On, 0);
+        controller.set(LedId::Red, LedPattern::Blink { period_ms: 1000 }, 100);
+        controller
+    }
+
+    #[test]
+    fn pi_patterns_run_from_when_they_were_set() {
+        let mut controller = controller();
+        assert_eq!(controller.levels(100), [255, 0, 0, 255]);
+        assert_eq!(controller.levels(600), [255, 0, 0, 0]);
+        assert_eq!(controller.levels(1_100), [255, 0, 0, 255]);
+        assert_eq!(controller.pattern(LedId::Red), LedPattern::Blink { period_ms: 1000 });
+    }
+
+    #[test]
+    fn boot_status_breathes_on_every_led() {
+        let mut controller = controller();
+        controller.show_status(StatusPattern::Boot, 1_000);
+        assert_eq!(controller.levels(1_000), [0; 4]);
+        assert_eq!(controller.levels(1_750), [255; 4]);
+        assert_eq!(controller.levels(2_125), [127; 4]);
+    }
+
+    #[test]
+    fn status_overrides_the_pi_patterns_until_cleared() {
+        let mut controller = controller();
+        controller.show_status(StatusPattern::LinkLost, 5_000);
+        assert_eq!(controller.levels(5_000), [0, 0, 0, 255]);
+        assert_eq!(controller.levels(6_000), [0, 0, 0, 0]);
+        // showing the same status again keeps its phase
+        controller.show_status(StatusPattern::LinkLost, 6_000);
+        assert_eq!(controller.levels(6_000), [0, 0, 0, 0]);
+        // another status is not cleared by mistake
+        controller.clear_status(StatusPattern::Boot);
+        assert_eq!(controller.status(), Some(StatusPattern::LinkLost));
+        controller.clear_status(StatusPattern::LinkLost);
+        assert_eq!(controller.status(), None);
+        assert_eq!(controller.levels(6_100), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn timed_status_expires() {
+        let mut controller = controller();
+        controller.show_status_for(StatusPattern::Error, 2_000, 500);
+        assert_eq!(controller.levels(2_000)[RED], 255);
+        assert_eq!(controller.levels(2_100)[RED], 0);
+        assert_eq!(controller.levels(2_499)[0], 0);
+        assert_eq!(controller.status(), Some(StatusPattern::Error));
+        assert_eq!(controller.levels(2_500), [255, 0, 0, 255]);
+        assert_eq!(controller.status(), None);
+    }
+}
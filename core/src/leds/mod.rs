@@ -0,0 +1,2 @@
+pub mod pattern;
+pub mod controller;
@@ -0,0 +1,182 @@
+use core::convert::TryFrom;
+use core::fmt;
+
+/// Status LEDs wired on gpio2..gpio5
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LedId {
+    Green,
+    Blue1,
+    Blue2,
+    Red,
+}
+
+impl LedId {
+    pub const ALL: [LedId; 4] = [LedId::Green, LedId::Blue1, LedId::Blue2, LedId::Red];
+
+    pub fn index(&self) -> usize {
+        match self {
+            LedId::Green => 0,
+            LedId::Blue1 => 1,
+            LedId::Blue2 => 2,
+            LedId::Red => 3,
+        }
+    }
+}
+
+impl fmt::Display for LedId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LedId::Green => "green",
+            LedId::Blue1 => "blue1",
+            LedId::Blue2 => "blue2",
+            LedId::Red => "red",
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a str> for LedId {
+    type Error = LedPatternError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "green" => Ok(LedId::Green),
+            "blue1" => Ok(LedId::Blue1),
+            "blue2" => Ok(LedId::Blue2),
+            "red" => Ok(LedId::Red),
+            _ => Err(LedPatternError::UnknownLed),
+        }
+    }
+}
+
+/// What a single LED should do over time.
+/// Periods are full cycle length in ms (on + off for blink, up + down for pulse).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LedPattern {
+    Off,
+    On,
+    Blink { period_ms: u32 },
+    Pulse { period_ms: u32 },
+}
+
+impl LedPattern {
+    /// Brightness (0..=255) of the pattern `elapsed_ms` after it was started.
+    /// Pure function of time, so the same pattern always looks the same for the same timestamp.
+    pub fn brightness_at(&self, elapsed_ms: u64) -> u8 {
+        match *self {
+            LedPattern::Off => 0,
+            LedPattern::On => 255,
+            LedPattern::Blink { period_ms } => {
+                if period_ms == 0 {
+                    return 255;
+                }
+                let phase = elapsed_ms % period_ms as u64;
+                if phase < (period_ms / 2) as u64 { 255 } else { 0 }
+            }
+            LedPattern::Pulse { period_ms } => {
+                if period_ms < 2 {
+                    return 255;
+                }
+                // triangle wave 0 -> 255 -> 0
+                let half = (period_ms / 2) as u64;
+                let phase = elapsed_ms % period_ms as u64;
+                let level = if phase < half {
+                    phase * 255 / half
+                } else {
+                    (period_ms as u64 - phase) * 255 / (period_ms as u64 - half)
+                };
+                level.min(255) as u8
+            }
+        }
+    }
+}
+
+/// Same format as parsed by `try_from`
+impl fmt::Display for LedPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedPattern::Off => f.write_str("off"),
+            LedPattern::On => f.write_str("on"),
+            LedPattern::Blink { period_ms } => write!(f, "blink:{}", period_ms),
+            LedPattern::Pulse { period_ms } => write!(f, "pulse:{}", period_ms),
+        }
+    }
+}
+
+/// Parse pattern part of `led=<led>:<pattern>[:<period_ms>]`, e.g. `blink:500`, `pulse:2000`, `on`
+impl<'a> TryFrom<&'a str> for LedPattern {
+    type Error = LedPatternError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let mut parts = value.split(':');
+        let name = parts.next().unwrap_or("");
+        let period_ms = match parts.next() {
+            Some(period) => Some(period.parse::<u32>().map_err(|_| LedPatternError::BadPeriod)?),
+            None => None,
+        };
+        match name {
+            "off" => Ok(LedPattern::Off),
+            "on" => Ok(LedPattern::On),
+            "blink" => Ok(LedPattern::Blink { period_ms: period_ms.unwrap_or(1000) }),
+            "pulse" => Ok(LedPattern::Pulse { period_ms: period_ms.unwrap_or(2000) }),
+            _ => Err(LedPatternError::UnknownPattern),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LedPatternError {
+    UnknownLed,
+    UnknownPattern,
+    BadPeriod,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use heapless::String;
+
+    #[test]
+    fn blink_is_on_for_the_first_half_of_each_period() {
+        let blink = LedPattern::Blink { period_ms: 1000 };
+        assert_eq!(blink.brightness_at(0), 255);
+        assert_eq!(blink.brightness_at(499), 255);
+        assert_eq!(blink.brightness_at(500), 0);
+        assert_eq!(blink.brightness_at(999), 0);
+        assert_eq!(blink.brightness_at(1_000), 255);
+        assert_eq!(blink.brightness_at(u64::MAX), 0);
+        assert_eq!(LedPattern::Blink { period_ms: 0 }.brightness_at(123), 255);
+    }
+
+    #[test]
+    fn pulse_rises_and_falls_linearly() {
+        let pulse = LedPattern::Pulse { period_ms: 2000 };
+        assert_eq!(pulse.brightness_at(0), 0);
+        assert_eq!(pulse.brightness_at(500), 127);
+        assert_eq!(pulse.brightness_at(1_000), 255);
+        assert_eq!(pulse.brightness_at(1_500), 127);
+        assert_eq!(pulse.brightness_at(2_000), 0);
+        // odd periods spend the extra ms going down
+        let odd = LedPattern::Pulse { period_ms: 3 };
+        assert_eq!([odd.brightness_at(0), odd.brightness_at(1), odd.brightness_at(2)], [0, 255, 127]);
+        assert_eq!(LedPattern::Pulse { period_ms: 1 }.brightness_at(5), 255);
+    }
+
+    #[test]
+    fn steady_patterns_ignore_time() {
+        assert_eq!(LedPattern::On.brightness_at(12_345), 255);
+        assert_eq!(LedPattern::Off.brightness_at(12_345), 0);
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for pattern in [LedPattern::Off, LedPattern::On, LedPattern::Blink { period_ms: 250 }, LedPattern::Pulse { period_ms: 3000 }] {
+            let mut text: String<16> = String::new();
+            write!(text, "{}", pattern).unwrap();
+            assert_eq!(LedPattern::try_from(text.as_str()), Ok(pattern));
+        }
+    }
+}
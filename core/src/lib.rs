@@ -0,0 +1,34 @@
+//! Hardware independent part of the device: uart protocol, screen model, input state machines,
+//! LED/buzzer/backlight logic and rendering onto any `DrawTarget`.
+//!
+//! Used by the RP2040 firmware and by the desktop simulator.
+#![no_std]
+// messages are parsed with `match (key, value)`, even when only one key is known yet
+#![allow(clippy::single_match)]
+
+extern crate embedded_graphics;
+extern crate embedded_graphics_core;
+extern crate heapless;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+
+#[macro_use]
+mod log;
+
+pub mod utils;
+pub mod messages;
+pub mod input;
+pub mod screen;
+pub mod device;
+pub mod leds;
+pub mod buzzer;
+pub mod backlight;
+pub mod widgets;
+pub mod layout;
+pub mod images;
+pub mod qr;
+pub mod link;
+pub mod crash;
+pub mod settings;
+pub mod update;
+pub mod diagnostics;
@@ -0,0 +1,237 @@
+use heapless::Deque;
+use link::transport::{Transport, TransportError};
+
+/// 7-bit address the device answers on as an I2C target
+pub const I2C_ADDRESS: u8 = 0x42;
+
+/// Read, 3 bytes: flags, then the number of bytes waiting in `REG_EVENTS`, low byte first
+pub const REG_STATUS: u8 = 0x00;
+/// Write: lines from the Pi, the same `\r\n` terminated messages as on the uart
+pub const REG_SCREEN: u8 = 0x10;
+/// Read: frames for the Pi, key events and answers to `REG_SCREEN` lines, as on the uart.
+/// Bytes past the ones waiting read as 0.
+pub const REG_EVENTS: u8 = 0x20;
+
+/// `REG_STATUS` flag: `REG_EVENTS` has bytes to read, the interrupt pin is asserted meanwhile
+pub const STATUS_EVENTS_PENDING: u8 = 0x01;
+/// `REG_STATUS` flag: lines written to `REG_SCREEN` are not processed yet, more would be lost
+pub const STATUS_SCREEN_FULL: u8 = 0x02;
+
+const RX_BYTES: usize = 512;
+const TX_BYTES: usize = 512;
+/// Longest frame `DeviceState::poll_outgoing` gives
+const MAX_FRAME_BYTES: usize = 100;
+/// Room left in `REG_SCREEN` below which `STATUS_SCREEN_FULL` is set
+const SCREEN_FULL_BELOW: usize = 32;
+
+/// Register map of the I2C target. The first byte the Pi writes in a transaction selects the
+/// register, further bytes go to it and reads come from it, also after a repeated start.
+/// As a `Transport` the two byte streams carry the protocol like the uart does.
+pub struct I2cRegisters {
+    register: u8,
+    /// Transaction started, the next written byte is a register
+    select_next: bool,
+    /// Taken when `REG_STATUS` is selected so the bytes read together agree
+    status: [u8; 3],
+    status_index: usize,
+    rx: Deque<u8, RX_BYTES>,
+    /// Bytes written to `REG_SCREEN` while it was full, reported once as an overrun
+    rx_lost: bool,
+    tx: Deque<u8, TX_BYTES>,
+}
+
+impl I2cRegisters {
+    pub fn new() -> Self {
+        I2cRegisters {
+            register: REG_STATUS,
+            select_next: false,
+            status: [0; 3],
+            status_index: 0,
+            rx: Deque::new(),
+            rx_lost: false,
+            tx: Deque::new(),
+        }
+    }
+
+    /// Start condition, not a repeated start
+    pub fn on_start(&mut self) {
+        self.select_next = true;
+    }
+
+    /// Byte the Pi wrote
+    pub fn on_write(&mut self, byte: u8) {
+        if self.select_next {
+            self.select_next = false;
+            self.select(byte);
+            return;
+        }
+        // the other registers are read only
+        if self.register == REG_SCREEN && self.rx.push_back(byte).is_err() {
+            self.rx_lost = true;
+        }
+    }
+
+    /// Byte the Pi reads next
+    pub fn on_read(&mut self) -> u8 {
+        match self.register {
+            REG_STATUS => {
+                let byte = self.status.get(self.status_index).copied().unwrap_or(0);
+                self.status_index += 1;
+                byte
+            }
+            REG_EVENTS => self.tx.pop_front().unwrap_or(0),
+            _ => 0xff,
+        }
+    }
+
+    /// Level of the interrupt pin: asserted while `REG_EVENTS` has bytes
+    pub fn events_pending(&self) -> bool {
+        !self.tx.is_empty()
+    }
+
+    fn select(&mut self, register: u8) {
+        self.register = register;
+        if register == REG_STATUS {
+            let mut flags = 0;
+            if self.events_pending() {
+                flags |= STATUS_EVENTS_PENDING;
+            }
+            if self.rx.capacity() - self.rx.len() < SCREEN_FULL_BELOW {
+                flags |= STATUS_SCREEN_FULL;
+            }
+            let pending = self.tx.len() as u16;
+            self.status = [flags, pending as u8, (pending >> 8) as u8];
+            self.status_index = 0;
+        }
+    }
+}
+
+impl Default for I2cRegisters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for I2cRegisters {
+    fn read_byte(&mut self) -> Option<Result<u8, TransportError>> {
+        if self.rx_lost {
+            self.rx_lost = false;
+            return Some(Err(TransportError::Overrun));
+        }
+        self.rx.pop_front().map(Ok)
+    }
+
+    fn is_writable(&self) -> bool {
+        self.tx.capacity() - self.tx.len() >= MAX_FRAME_BYTES
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.tx.push_back(byte).is_err() {
+                error!("I2C events full, frame cut");
+                return;
+            }
+        }
+    }
+
+    /// The Pi reads when it wants, the firmware waits for it with its own timeout
+    fn flush(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(registers: &mut I2cRegisters, register: u8, bytes: &[u8]) {
+        registers.on_start();
+        registers.on_write(register);
+        for &byte in bytes {
+            registers.on_write(byte);
+        }
+    }
+
+    fn read<const N: usize>(registers: &mut I2cRegisters, register: u8) -> [u8; N] {
+        write(registers, register, &[]);
+        let mut bytes = [0; N];
+        for byte in &mut bytes {
+            *byte = registers.on_read();
+        }
+        bytes
+    }
+
+    fn received(registers: &mut I2cRegisters) -> heapless::Vec<Result<u8, TransportError>, 600> {
+        let mut bytes = heapless::Vec::new();
+        while let Some(byte) = registers.read_byte() {
+            bytes.push(byte).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn screen_writes_become_the_received_stream() {
+        let mut registers = I2cRegisters::new();
+        write(&mut registers, REG_SCREEN, b"ping=");
+        write(&mut registers, REG_SCREEN, b"1\r\n");
+        let expected: heapless::Vec<_, 8> = b"ping=1\r\n".iter().map(|byte| Ok(*byte)).collect();
+        assert_eq!(received(&mut registers).as_slice(), expected.as_slice());
+        assert_eq!(registers.read_byte(), None);
+    }
+
+    #[test]
+    fn status_reports_the_waiting_events() {
+        let mut registers = I2cRegisters::new();
+        assert_eq!(read::<3>(&mut registers, REG_STATUS), [0, 0, 0]);
+        assert!(!registers.events_pending());
+        registers.write_all(&[b'x'; 300]);
+        assert!(registers.events_pending());
+        // 300 bytes low byte first, reading past the status gives 0
+        assert_eq!(read::<4>(&mut registers, REG_STATUS), [STATUS_EVENTS_PENDING, 44, 1, 0]);
+    }
+
+    #[test]
+    fn events_read_in_order_then_zeros() {
+        let mut registers = I2cRegisters::new();
+        registers.write_all(b"len=7&pong=1\r\n");
+        assert_eq!(&read::<6>(&mut registers, REG_EVENTS), b"len=7&");
+        // a repeated start reads on from the same register
+        assert_eq!(registers.on_read(), b'p');
+        assert_eq!(&read::<9>(&mut registers, REG_EVENTS), b"ong=1\r\n\0\0");
+        assert!(!registers.events_pending());
+    }
+
+    #[test]
+    fn read_only_and_unknown_registers() {
+        let mut registers = I2cRegisters::new();
+        write(&mut registers, REG_EVENTS, b"ignored");
+        write(&mut registers, REG_STATUS, b"ignored");
+        assert_eq!(registers.read_byte(), None);
+        assert_eq!(read::<2>(&mut registers, 0x30), [0xff, 0xff]);
+        assert_eq!(read::<1>(&mut registers, REG_SCREEN), [0xff]);
+    }
+
+    #[test]
+    fn full_screen_register_is_flagged_and_overrun_reported_once() {
+        let mut registers = I2cRegisters::new();
+        write(&mut registers, REG_SCREEN, &[b'a'; RX_BYTES - SCREEN_FULL_BELOW]);
+        assert_eq!(read::<1>(&mut registers, REG_STATUS), [0]);
+        write(&mut registers, REG_SCREEN, b"b");
+        assert_eq!(read::<1>(&mut registers, REG_STATUS), [STATUS_SCREEN_FULL]);
+        write(&mut registers, REG_SCREEN, &[b'c'; SCREEN_FULL_BELOW + 10]);
+        let received = received(&mut registers);
+        assert_eq!(received[0], Err(TransportError::Overrun));
+        assert_eq!(received.len(), RX_BYTES + 1);
+        assert!(received[1..].iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn frames_wait_while_events_are_full() {
+        let mut registers = I2cRegisters::new();
+        registers.write_all(&[b'x'; TX_BYTES - MAX_FRAME_BYTES]);
+        assert!(registers.is_writable());
+        registers.write_all(b"y");
+        assert!(!registers.is_writable());
+        // a frame that does not fit is cut at the end
+        registers.write_all(&[b'z'; MAX_FRAME_BYTES]);
+        assert_eq!(read::<4>(&mut registers, REG_STATUS)[1..3], [(TX_BYTES % 256) as u8, (TX_BYTES / 256) as u8]);
+    }
+}
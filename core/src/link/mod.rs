@@ -0,0 +1,6 @@
+pub mod i2c;
+pub mod monitor;
+pub mod screen;
+pub mod transport;
+pub mod uart;
+pub mod usb;
@@ -0,0 +1,248 @@
+use core::convert::TryFrom;
+
+/// Device heartbeat period, the Pi should send `hb=<n>` at the same rate when it has nothing else to say
+pub const HEARTBEAT_INTERVAL_MS: u64 = 1000;
+/// Silence after which the link is degraded: a couple of Pi heartbeats went missing
+pub const DEGRADED_AFTER_MS: u64 = 3000;
+/// Silence after which the Pi is considered gone and the offline screen is shown
+pub const LOST_AFTER_MS: u64 = 10_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkState {
+    /// Nothing received since boot, the Pi may still be booting
+    Connecting,
+    Online,
+    /// Pi is late or the uart reported errors since the last good line
+    Degraded,
+    /// Pi is silent for `LOST_AFTER_MS`, stale screen is replaced with the offline screen
+    Lost,
+}
+
+impl LinkState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkState::Connecting => "connecting",
+            LinkState::Online => "online",
+            LinkState::Degraded => "degraded",
+            LinkState::Lost => "lost",
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for LinkState {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "connecting" => Ok(LinkState::Connecting),
+            "online" => Ok(LinkState::Online),
+            "degraded" => Ok(LinkState::Degraded),
+            "lost" => Ok(LinkState::Lost),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Link health from the device side. Any line from the Pi counts as contact,
+/// `hb=<n>` only keeps the link alive while the Pi has nothing else to send.
+/// Driven by timestamps from the core0 loop, knows nothing about the uart.
+#[derive(Clone)]
+pub struct LinkMonitor {
+    state: LinkState,
+    last_contact_ms: Option<u64>,
+    next_heartbeat_ms: u64,
+    heartbeat_seq: u32,
+}
+
+impl LinkMonitor {
+    pub fn new(now_ms: u64) -> Self {
+        LinkMonitor {
+            state: LinkState::Connecting,
+            last_contact_ms: None,
+            next_heartbeat_ms: now_ms.saturating_add(HEARTBEAT_INTERVAL_MS),
+            heartbeat_seq: 0,
+        }
+    }
+
+    pub fn state(&self) -> LinkState {
+        self.state
+    }
+
+    /// Line received from the Pi. Returns the new state when it changed.
+    pub fn on_contact(&mut self, now_ms: u64) -> Option<LinkState> {
+        self.last_contact_ms = Some(now_ms);
+        self.set_state(LinkState::Online)
+    }
+
+    /// Uart error, the link is degraded until the next good line
+    pub fn on_error(&mut self) -> Option<LinkState> {
+        match self.state {
+            LinkState::Online => self.set_state(LinkState::Degraded),
+            _ => None,
+        }
+    }
+
+    /// Check for silence, call every loop. Returns the new state when it changed.
+    pub fn update(&mut self, now_ms: u64) -> Option<LinkState> {
+        let silent_ms = self.silent_ms(now_ms)?;
+        if silent_ms >= LOST_AFTER_MS {
+            self.set_state(LinkState::Lost)
+        } else if silent_ms >= DEGRADED_AFTER_MS && self.state == LinkState::Online {
+            self.set_state(LinkState::Degraded)
+        } else {
+            None
+        }
+    }
+
+    /// Sequence number of the heartbeat to send when one is due. Heartbeats go out in every state,
+    /// so a Pi coming back hears the device before it says anything.
+    pub fn poll_heartbeat(&mut self, now_ms: u64) -> Option<u32> {
+        if now_ms < self.next_heartbeat_ms {
+            return None;
+        }
+        // a long blocked loop should not produce a burst of heartbeats
+        self.next_heartbeat_ms = now_ms.saturating_add(HEARTBEAT_INTERVAL_MS);
+        self.heartbeat_seq = self.heartbeat_seq.wrapping_add(1);
+        Some(self.heartbeat_seq)
+    }
+
+    /// Time since the last line from the Pi, `None` before the first one
+    pub fn silent_ms(&self, now_ms: u64) -> Option<u64> {
+        self.last_contact_ms.map(|last_contact_ms| now_ms.saturating_sub(last_contact_ms))
+    }
+
+    /// Time since the last contact while the link is lost
+    pub fn lost_for_ms(&self, now_ms: u64) -> Option<u64> {
+        match self.state {
+            LinkState::Lost => self.silent_ms(now_ms),
+            _ => None,
+        }
+    }
+
+    fn set_state(&mut self, state: LinkState) -> Option<LinkState> {
+        if self.state == state {
+            return None;
+        }
+        self.state = state;
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monitor which heard the Pi at `now_ms`
+    fn online(now_ms: u64) -> LinkMonitor {
+        let mut monitor = LinkMonitor::new(now_ms);
+        monitor.on_contact(now_ms);
+        monitor
+    }
+
+    #[test]
+    fn connecting_until_first_contact() {
+        let mut monitor = LinkMonitor::new(0);
+        assert_eq!(monitor.update(60_000), None);
+        assert_eq!(monitor.state(), LinkState::Connecting);
+        assert_eq!(monitor.silent_ms(60_000), None);
+        assert_eq!(monitor.on_error(), None);
+        assert_eq!(monitor.on_contact(60_000), Some(LinkState::Online));
+        assert_eq!(monitor.on_contact(60_001), None);
+    }
+
+    #[test]
+    fn silence_degrades_then_loses_the_link() {
+        let mut monitor = online(1_000);
+        assert_eq!(monitor.update(1_000 + DEGRADED_AFTER_MS - 1), None);
+        assert_eq!(monitor.update(1_000 + DEGRADED_AFTER_MS), Some(LinkState::Degraded));
+        assert_eq!(monitor.update(1_000 + LOST_AFTER_MS - 1), None);
+        assert_eq!(monitor.lost_for_ms(1_000 + LOST_AFTER_MS - 1), None);
+        assert_eq!(monitor.update(1_000 + LOST_AFTER_MS), Some(LinkState::Lost));
+        assert_eq!(monitor.update(1_000 + LOST_AFTER_MS + 5_000), None);
+        assert_eq!(monitor.lost_for_ms(1_000 + LOST_AFTER_MS + 5_000), Some(LOST_AFTER_MS + 5_000));
+    }
+
+    #[test]
+    fn long_stall_goes_straight_to_lost() {
+        let mut monitor = online(0);
+        assert_eq!(monitor.update(LOST_AFTER_MS * 3), Some(LinkState::Lost));
+    }
+
+    #[test]
+    fn any_line_recovers_the_link() {
+        let mut monitor = online(0);
+        monitor.update(LOST_AFTER_MS);
+        assert_eq!(monitor.on_contact(LOST_AFTER_MS + 1), Some(LinkState::Online));
+        assert_eq!(monitor.lost_for_ms(LOST_AFTER_MS + 1), None);
+        // silence counts from the new contact
+        assert_eq!(monitor.update(LOST_AFTER_MS + DEGRADED_AFTER_MS), None);
+        assert_eq!(monitor.update(LOST_AFTER_MS + 1 + DEGRADED_AFTER_MS), Some(LinkState::Degraded));
+        assert_eq!(monitor.on_contact(LOST_AFTER_MS + 1 + DEGRADED_AFTER_MS), Some(LinkState::Online));
+    }
+
+    #[test]
+    fn uart_errors_degrade_until_the_next_good_line() {
+        let mut monitor = online(0);
+        assert_eq!(monitor.on_error(), Some(LinkState::Degraded));
+        assert_eq!(monitor.on_error(), None);
+        assert_eq!(monitor.update(100), None);
+        assert_eq!(monitor.on_contact(200), Some(LinkState::Online));
+        monitor.update(LOST_AFTER_MS + 200);
+        // lost stays lost, errors are no contact
+        assert_eq!(monitor.on_error(), None);
+        assert_eq!(monitor.state(), LinkState::Lost);
+    }
+
+    #[test]
+    fn heartbeats_once_per_interval() {
+        let mut monitor = LinkMonitor::new(0);
+        assert_eq!(monitor.poll_heartbeat(HEARTBEAT_INTERVAL_MS - 1), None);
+        assert_eq!(monitor.poll_heartbeat(HEARTBEAT_INTERVAL_MS), Some(1));
+        assert_eq!(monitor.poll_heartbeat(HEARTBEAT_INTERVAL_MS), None);
+        // a blocked loop gets one heartbeat, not a burst
+        assert_eq!(monitor.poll_heartbeat(10 * HEARTBEAT_INTERVAL_MS), Some(2));
+        assert_eq!(monitor.poll_heartbeat(10 * HEARTBEAT_INTERVAL_MS + 1), None);
+        assert_eq!(monitor.poll_heartbeat(11 * HEARTBEAT_INTERVAL_MS), Some(3));
+        // also while lost
+        monitor.on_contact(0);
+        monitor.update(LOST_AFTER_MS * 2);
+        assert_eq!(monitor.poll_heartbeat(LOST_AFTER_MS * 2), Some(4));
+    }
+
+    #[test]
+    fn heartbeat_sequence_wraps() {
+        let mut monitor = LinkMonitor::new(0);
+        monitor.heartbeat_seq = u32::MAX - 1;
+        assert_eq!(monitor.poll_heartbeat(HEARTBEAT_INTERVAL_MS), Some(u32::MAX));
+        assert_eq!(monitor.poll_heartbeat(2 * HEARTBEAT_INTERVAL_MS), Some(0));
+        assert_eq!(monitor.poll_heartbeat(3 * HEARTBEAT_INTERVAL_MS), Some(1));
+    }
+
+    #[test]
+    fn clock_at_the_end_of_its_range() {
+        let end = u64::MAX - 500;
+        let mut monitor = LinkMonitor::new(end);
+        monitor.on_contact(end);
+        assert_eq!(monitor.poll_heartbeat(u64::MAX - 1), None);
+        assert_eq!(monitor.poll_heartbeat(u64::MAX), Some(1));
+        assert_eq!(monitor.update(u64::MAX), None);
+        assert_eq!(monitor.silent_ms(u64::MAX), Some(500));
+    }
+
+    #[test]
+    fn time_going_backwards_is_no_silence() {
+        let mut monitor = online(5_000);
+        assert_eq!(monitor.silent_ms(4_000), Some(0));
+        assert_eq!(monitor.update(4_000), None);
+        assert_eq!(monitor.state(), LinkState::Online);
+    }
+
+    #[test]
+    fn state_names_round_trip() {
+        for state in [LinkState::Connecting, LinkState::Online, LinkState::Degraded, LinkState::Lost] {
+            assert_eq!(LinkState::try_from(state.as_str()), Ok(state));
+        }
+        assert_eq!(LinkState::try_from("up"), Err(()));
+    }
+}
@@ -0,0 +1,49 @@
+use core::fmt::Write;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics::text::{Alignment, Baseline};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use screen::text::{draw_text, FONT_SET_6X12};
+
+/// Replaces everything while the link is lost, so stale data is not taken for live.
+/// Static text is drawn on `first_draw` only, the time since last contact every call.
+pub fn draw_link_lost<D>(display: &mut D, silent_ms: u64, first_draw: bool) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let screen = display.bounding_box();
+    let center = screen.center();
+    if first_draw {
+        display.clear(Rgb565::BLACK)?;
+        draw_text(display, "Pi not responding", center - Point::new(0, 16), &FONT_SET_6X12, Rgb565::RED,
+                  Alignment::Center, Baseline::Middle)?;
+        draw_text(display, "last contact", center + Point::new(0, 4), &FONT_SET_6X12, Rgb565::WHITE,
+                  Alignment::Center, Baseline::Middle)?;
+    }
+
+    let elapsed_area = Rectangle::with_center(center + Point::new(0, 18), Size::new(screen.size.width, 12));
+    elapsed_area.into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK)).draw(display)?;
+    draw_text(display, elapsed(silent_ms / 1_000).as_str(), elapsed_area.center(), &FONT_SET_6X12,
+              Rgb565::WHITE, Alignment::Center, Baseline::Middle)?;
+    Ok(())
+}
+
+/// `42 s ago`, `12:05 ago`, `3:12:05 ago`
+fn elapsed(seconds: u64) -> String<20> {
+    let mut text = String::new();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    _ = if hours > 0 {
+        write!(text, "{}:{:02}:{:02} ago", hours, minutes, seconds)
+    } else if minutes > 0 {
+        write!(text, "{}:{:02} ago", minutes, seconds)
+    } else {
+        write!(text, "{} s ago", seconds)
+    };
+    text
+}
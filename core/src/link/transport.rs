@@ -0,0 +1,237 @@
+use device::state::DeviceState;
+use link::uart::{UartChange, UartFormat};
+use messages::receiver::LineReceiver;
+
+/// Byte lost on the line, only uarts report them
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TransportError {
+    Break,
+    Overrun,
+    Parity,
+    Framing,
+}
+
+impl TransportError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransportError::Break => "break",
+            TransportError::Overrun => "overrun",
+            TransportError::Parity => "parity",
+            TransportError::Framing => "framing",
+        }
+    }
+}
+
+/// Byte stream the protocol runs over: the uart, USB CDC, or whichever of them the Pi uses.
+/// Framing and messages are the same for all of them, see `exchange`.
+pub trait Transport {
+    /// Next received byte, `None` when there is nothing yet
+    fn read_byte(&mut self) -> Option<Result<u8, TransportError>>;
+
+    /// A frame can go out now
+    fn is_writable(&self) -> bool;
+
+    /// Send a whole frame, may block until it is out. The uart always waits for its FIFO, USB gives
+    /// up after a timeout and drops the rest, the link monitor notices a Pi that stopped reading.
+    fn write_all(&mut self, bytes: &[u8]);
+
+    /// Wait until everything written went out, before a format change or a reboot
+    fn flush(&mut self);
+
+    /// Rate and parity from `uart_switch`, transports without them ignore it
+    fn set_format(&mut self, _format: UartFormat) {}
+}
+
+/// Bytes read in one turn at most, keeps a Pi sending without pause from starving the loop
+pub const MAX_READ_PER_EXCHANGE: usize = 256;
+
+/// One turn of the link, called every loop: the received bytes up to `MAX_READ_PER_EXCHANGE`
+/// in, at most one frame out, then the uart format change that frame announced.
+pub fn exchange<T: Transport>(transport: &mut T, receiver: &mut LineReceiver, device_state: &mut DeviceState, now_us: u64) {
+    for _ in 0..MAX_READ_PER_EXCHANGE {
+        match transport.read_byte() {
+            Some(Ok(byte)) => match receiver.push_byte(byte) {
+                Ok(Some(line)) => device_state.handle_line(line, now_us),
+                Ok(None) => {}
+                Err(err) => {
+                    error!("Error receiving byte {:?}", err);
+                    device_state.diagnostics.on_receive_error(err);
+                }
+            },
+            Some(Err(err)) => {
+                error!("Error reading {}", err.as_str());
+                device_state.diagnostics.on_transport_error(err);
+                device_state.on_uart_error(now_us);
+            }
+            None => break,
+        }
+    }
+
+    if transport.is_writable() {
+        if let Some(frame) = device_state.poll_outgoing(now_us) {
+            debug!("Message to send: {}", frame.as_str());
+            transport.write_all(frame.as_bytes());
+        }
+    }
+
+    // `uart=switching` is out, or the Pi never confirmed the new format
+    if let Some(change) = device_state.take_uart_change(now_us) {
+        let format = change.format();
+        match change {
+            UartChange::Try(_) => info!("uart now {} {}", format.baud, format.parity.as_str()),
+            UartChange::Fallback(_) => info!("uart not confirmed, back to {} {}", format.baud, format.parity.as_str()),
+        }
+        transport.flush();
+        transport.set_format(format);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heapless::Vec;
+    use link::usb::{PacketBuffer, PACKET_SIZE};
+    use utils::test_fixtures::device;
+
+    /// Bytes waiting to be read, frames written
+    struct TestTransport {
+        input: Vec<Result<u8, TransportError>, 2048>,
+        read: usize,
+        output: Vec<u8, 1024>,
+    }
+
+    impl TestTransport {
+        fn new(input: &[u8]) -> Self {
+            TestTransport { input: input.iter().map(|byte| Ok(*byte)).collect(), read: 0, output: Vec::new() }
+        }
+
+        fn left(&self) -> usize {
+            self.input.len() - self.read
+        }
+    }
+
+    impl Transport for TestTransport {
+        fn read_byte(&mut self) -> Option<Result<u8, TransportError>> {
+            let byte = *self.input.get(self.read)?;
+            self.read += 1;
+            Some(byte)
+        }
+
+        fn is_writable(&self) -> bool {
+            true
+        }
+
+        fn write_all(&mut self, bytes: &[u8]) {
+            self.output.extend_from_slice(bytes).unwrap();
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn whole_lines_in_one_turn() {
+        let mut transport = TestTransport::new(b"ping=a\r\nping=b\r\n");
+        let (mut receiver, mut device_state) = (LineReceiver::new(), device());
+        exchange(&mut transport, &mut receiver, &mut device_state, 2_000);
+        assert_eq!(transport.left(), 0);
+        assert_eq!(device_state.diagnostics.frames_rx, 2);
+        // one frame per turn, the reset reason goes first
+        assert!(transport.output.starts_with(b"len="));
+    }
+
+    /// USB serial class with packets queued by the host, reads them like the firmware `UsbPort`
+    struct UsbTestTransport {
+        rx: PacketBuffer,
+        packets: Vec<Vec<u8, PACKET_SIZE>, 8>,
+        next: usize,
+        polls: usize,
+    }
+
+    impl UsbTestTransport {
+        fn new(input: &[u8]) -> Self {
+            let packets = input.chunks(PACKET_SIZE).map(|packet| Vec::from_slice(packet).unwrap()).collect();
+            UsbTestTransport { rx: PacketBuffer::new(), packets, next: 0, polls: 0 }
+        }
+    }
+
+    impl Transport for UsbTestTransport {
+        fn read_byte(&mut self) -> Option<Result<u8, TransportError>> {
+            let (packets, next, polls) = (&self.packets, &mut self.next, &mut self.polls);
+            self.rx.read_byte(|buffer| {
+                *polls += 1;
+                let packet = match packets.get(*next) {
+                    Some(packet) => packet,
+                    None => return 0,
+                };
+                *next += 1;
+                buffer[..packet.len()].copy_from_slice(packet);
+                packet.len()
+            })
+        }
+
+        fn is_writable(&self) -> bool {
+            true
+        }
+
+        fn write_all(&mut self, _bytes: &[u8]) {}
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn usb_packets_back_to_back_in_one_turn() {
+        // a line over three packets, the last one short
+        let mut input = [b'x'; 150];
+        input[..5].copy_from_slice(b"ping=");
+        input[148..].copy_from_slice(b"\r\n");
+        let mut transport = UsbTestTransport::new(&input);
+        let (mut receiver, mut device_state) = (LineReceiver::new(), device());
+        exchange(&mut transport, &mut receiver, &mut device_state, 2_000);
+        assert_eq!(device_state.diagnostics.frames_rx, 1);
+        // a poll per packet and one finding nothing more, not one per byte
+        assert_eq!((transport.next, transport.polls), (3, 4));
+    }
+
+    #[test]
+    fn usb_packets_beyond_the_cap_wait_for_the_next_turn() {
+        let mut input = [b'x'; 5 * PACKET_SIZE];
+        input[5 * PACKET_SIZE - 2..].copy_from_slice(b"\r\n");
+        let mut transport = UsbTestTransport::new(&input);
+        let (mut receiver, mut device_state) = (LineReceiver::new(), device());
+        exchange(&mut transport, &mut receiver, &mut device_state, 2_000);
+        assert_eq!((transport.next, transport.polls), (MAX_READ_PER_EXCHANGE / PACKET_SIZE, 4));
+        exchange(&mut transport, &mut receiver, &mut device_state, 3_000);
+        assert_eq!((transport.next, transport.polls), (5, 6));
+        assert_eq!(device_state.diagnostics.frames_rx, 1);
+    }
+
+    #[test]
+    fn reading_stops_at_the_cap() {
+        let mut input = [b'x'; MAX_READ_PER_EXCHANGE + 100];
+        input[MAX_READ_PER_EXCHANGE + 98..].copy_from_slice(b"\r\n");
+        let mut transport = TestTransport::new(&input);
+        let (mut receiver, mut device_state) = (LineReceiver::new(), device());
+        exchange(&mut transport, &mut receiver, &mut device_state, 2_000);
+        assert_eq!(transport.left(), 100);
+        exchange(&mut transport, &mut receiver, &mut device_state, 3_000);
+        assert_eq!(transport.left(), 0);
+        assert_eq!(device_state.diagnostics.frames_rx, 1);
+    }
+
+    #[test]
+    fn uart_errors_do_not_stop_reading() {
+        let mut transport = TestTransport::new(b"");
+        for item in [Err(TransportError::Overrun), Ok(b'p'), Err(TransportError::Framing)] {
+            transport.input.push(item).unwrap();
+        }
+        for byte in b"ing=1\r\n" {
+            transport.input.push(Ok(*byte)).unwrap();
+        }
+        let (mut receiver, mut device_state) = (LineReceiver::new(), device());
+        exchange(&mut transport, &mut receiver, &mut device_state, 2_000);
+        assert_eq!(transport.left(), 0);
+        assert_eq!(device_state.diagnostics.overruns, 1);
+        assert_eq!(device_state.diagnostics.line_errors, 1);
+    }
+}
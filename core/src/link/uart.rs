@@ -0,0 +1,228 @@
+use core::convert::TryFrom;
+use heapless::String;
+use settings::setting::BAUD_RATES;
+
+/// Time the Pi has to answer a `uart_switch` with `uart_confirm` at the new format
+pub const UART_CONFIRM_MS: u64 = 2000;
+
+/// Parity bit of the uart frames, always 8 data bits and 1 stop bit
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UartParity {
+    None,
+    Odd,
+    Even,
+}
+
+impl UartParity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UartParity::None => "none",
+            UartParity::Odd => "odd",
+            UartParity::Even => "even",
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for UartParity {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "none" => Ok(UartParity::None),
+            "odd" => Ok(UartParity::Odd),
+            "even" => Ok(UartParity::Even),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Pins the firmware talks to the Pi on, pairs not used by anything else on the board
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UartPins {
+    /// UART0, tx gpio16, rx gpio17
+    Uart0Gp16,
+    /// UART0, tx gpio0, rx gpio1
+    Uart0Gp0,
+    /// UART1, tx gpio8, rx gpio9
+    Uart1Gp8,
+}
+
+impl UartPins {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UartPins::Uart0Gp16 => "uart0_gp16",
+            UartPins::Uart0Gp0 => "uart0_gp0",
+            UartPins::Uart1Gp8 => "uart1_gp8",
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for UartPins {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "uart0_gp16" => Ok(UartPins::Uart0Gp16),
+            "uart0_gp0" => Ok(UartPins::Uart0Gp0),
+            "uart1_gp8" => Ok(UartPins::Uart1Gp8),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Rate and parity, `<baud>:<parity>` in the protocol
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UartFormat {
+    pub baud: u32,
+    pub parity: UartParity,
+}
+
+impl UartFormat {
+    /// Reverse of `to_text`, only `BAUD_RATES`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (baud, parity) = value.split_once(':')?;
+        let baud = baud.parse::<u32>().ok().filter(|baud| BAUD_RATES.contains(baud))?;
+        let parity = UartParity::try_from(parity).ok()?;
+        Some(UartFormat { baud, parity })
+    }
+
+    pub fn to_text(&self) -> String<16> {
+        let mut text: String<16> = String::new();
+        text.push_str(String::<10>::from(self.baud).as_str()).unwrap();
+        text.push(':').unwrap();
+        text.push_str(self.parity.as_str()).unwrap();
+        text
+    }
+}
+
+/// Answers to `uart_switch` and `uart_confirm`, `uart=<code>[:<baud>:<parity>]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UartEvent {
+    /// Sent at the old format, the device changes over right after it
+    Switching(UartFormat),
+    /// Confirmed at this format, it stays until the next boot
+    Ok(UartFormat),
+    /// No `uart_confirm` in time, sent at the old format which is back
+    Fallback(UartFormat),
+    /// Rate or parity the device does not support, nothing changed
+    BadValue,
+}
+
+impl UartEvent {
+    pub fn code(&self) -> &'static str {
+        match self {
+            UartEvent::Switching(_) => "switching",
+            UartEvent::Ok(_) => "ok",
+            UartEvent::Fallback(_) => "fallback",
+            UartEvent::BadValue => "bad_value",
+        }
+    }
+
+    pub fn format(&self) -> Option<UartFormat> {
+        match *self {
+            UartEvent::Switching(format) | UartEvent::Ok(format) | UartEvent::Fallback(format) => Some(format),
+            UartEvent::BadValue => None,
+        }
+    }
+
+    /// Reverse of `code` and `format`, used on the Pi side
+    pub fn from_code(code: &str, format: Option<UartFormat>) -> Option<Self> {
+        match (code, format) {
+            ("switching", Some(format)) => Some(UartEvent::Switching(format)),
+            ("ok", Some(format)) => Some(UartEvent::Ok(format)),
+            ("fallback", Some(format)) => Some(UartEvent::Fallback(format)),
+            ("bad_value", None) => Some(UartEvent::BadValue),
+            _ => None,
+        }
+    }
+}
+
+/// Format the firmware has to put the uart in, from `UartSwitch::take_change`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UartChange {
+    /// New format, the Pi has `UART_CONFIRM_MS` to confirm it
+    Try(UartFormat),
+    /// The previous one, `uart=fallback` goes out once the uart is back in it
+    Fallback(UartFormat),
+}
+
+impl UartChange {
+    pub fn format(&self) -> UartFormat {
+        match *self {
+            UartChange::Try(format) | UartChange::Fallback(format) => format,
+        }
+    }
+}
+
+enum SwitchState {
+    Idle,
+    /// `uart=switching` is queued, the format changes once it is sent
+    Announced { previous: UartFormat, target: UartFormat },
+    /// Running at the new format, waiting for `uart_confirm`
+    Trying { previous: UartFormat, since_ms: u64 },
+    /// Not confirmed in time
+    Expired { previous: UartFormat },
+}
+
+/// Rate change negotiated with the Pi: announce at the old format, change, wait for the Pi to
+/// confirm at the new one, else go back. Boot always starts with the format from the settings.
+pub struct UartSwitch {
+    current: UartFormat,
+    state: SwitchState,
+}
+
+impl UartSwitch {
+    pub fn new(current: UartFormat) -> Self {
+        UartSwitch { current, state: SwitchState::Idle }
+    }
+
+    /// What the uart runs at, or is about to once `take_change` was applied
+    pub fn current(&self) -> UartFormat {
+        self.current
+    }
+
+    /// `uart_switch` from the Pi. A request at a format still on trial proves it works.
+    pub fn request(&mut self, target: UartFormat) -> UartEvent {
+        self.state = SwitchState::Announced { previous: self.current, target };
+        UartEvent::Switching(target)
+    }
+
+    /// `uart_confirm` from the Pi, answered with the format also when nothing was on trial
+    pub fn confirm(&mut self) -> UartEvent {
+        if let SwitchState::Trying { .. } = self.state {
+            self.state = SwitchState::Idle;
+        }
+        UartEvent::Ok(self.current)
+    }
+
+    /// Call every loop
+    pub fn update(&mut self, now_ms: u64) {
+        if let SwitchState::Trying { previous, since_ms } = self.state {
+            if now_ms.saturating_sub(since_ms) >= UART_CONFIRM_MS {
+                self.state = SwitchState::Expired { previous };
+            }
+        }
+    }
+
+    /// Format to apply now, call once the frames before it are sent
+    pub fn take_change(&mut self, now_ms: u64) -> Option<UartChange> {
+        match self.state {
+            SwitchState::Announced { previous, target } => {
+                self.current = target;
+                self.state = SwitchState::Trying { previous, since_ms: now_ms };
+                Some(UartChange::Try(target))
+            }
+            SwitchState::Expired { previous } => {
+                self.current = previous;
+                self.state = SwitchState::Idle;
+                Some(UartChange::Fallback(previous))
+            }
+            SwitchState::Idle | SwitchState::Trying { .. } => None,
+        }
+    }
+}
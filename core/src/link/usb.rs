@@ -0,0 +1,121 @@
+use core::convert::TryFrom;
+use link::transport::TransportError;
+
+/// What the firmware built with the `usb` feature shows the Pi over USB, from the `usb_mode` setting
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UsbMode {
+    /// CDC serial port with the protocol
+    Serial,
+    /// Keyboard only, arrows and Enter for the buttons, needs no software on the Pi
+    Keyboard,
+    /// Both in one composite device
+    KeyboardSerial,
+}
+
+impl UsbMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsbMode::Serial => "serial",
+            UsbMode::Keyboard => "keyboard",
+            UsbMode::KeyboardSerial => "keyboard_serial",
+        }
+    }
+
+    pub fn has_serial(&self) -> bool {
+        *self != UsbMode::Keyboard
+    }
+
+    pub fn has_keyboard(&self) -> bool {
+        *self != UsbMode::Serial
+    }
+}
+
+impl<'a> TryFrom<&'a str> for UsbMode {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "serial" => Ok(UsbMode::Serial),
+            "keyboard" => Ok(UsbMode::Keyboard),
+            "keyboard_serial" => Ok(UsbMode::KeyboardSerial),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Largest full speed CDC bulk packet
+pub const PACKET_SIZE: usize = 64;
+
+/// Last packet read from the USB serial class, handed out a byte at a time. The device is only
+/// polled once the packet is drained, so `exchange` takes a whole packet in without a poll per byte.
+pub struct PacketBuffer {
+    buffer: [u8; PACKET_SIZE],
+    start: usize,
+    end: usize,
+}
+
+impl PacketBuffer {
+    pub fn new() -> Self {
+        PacketBuffer { buffer: [0; PACKET_SIZE], start: 0, end: 0 }
+    }
+
+    /// Next byte of the packet. A drained packet is refilled by `poll_and_read` first, it polls the
+    /// device and returns the number of bytes it read into the buffer.
+    pub fn read_byte<F>(&mut self, poll_and_read: F) -> Option<Result<u8, TransportError>>
+    where
+        F: FnOnce(&mut [u8]) -> usize,
+    {
+        if self.start == self.end {
+            // USB retries what does not fit, nothing gets lost on the way
+            self.start = 0;
+            self.end = poll_and_read(&mut self.buffer).min(PACKET_SIZE);
+        }
+        if self.start == self.end {
+            return None;
+        }
+        let byte = self.buffer[self.start];
+        self.start += 1;
+        Some(Ok(byte))
+    }
+}
+
+impl Default for PacketBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_is_polled_once_per_packet() {
+        let mut buffer = PacketBuffer::new();
+        let mut polls = 0;
+        let mut read = |packet: &[u8]| {
+            let mut polls_here = 0;
+            let byte = buffer.read_byte(|into| {
+                polls_here += 1;
+                into[..packet.len()].copy_from_slice(packet);
+                packet.len()
+            });
+            polls += polls_here;
+            byte
+        };
+        assert_eq!(read(b"ab"), Some(Ok(b'a')));
+        assert_eq!(read(b"unused"), Some(Ok(b'b')));
+        assert_eq!(read(b""), None);
+        assert_eq!(read(b"c"), Some(Ok(b'c')));
+        assert_eq!(polls, 3);
+    }
+
+    #[test]
+    fn mode_names_parse_back() {
+        for mode in [UsbMode::Serial, UsbMode::Keyboard, UsbMode::KeyboardSerial] {
+            assert_eq!(UsbMode::try_from(mode.as_str()), Ok(mode));
+        }
+        assert_eq!(UsbMode::try_from("mouse"), Err(()));
+    }
+}
@@ -0,0 +1,38 @@
+//! defmt logging when `defmt` feature is enabled, nothing otherwise.
+//! Host builds (simulator, tests) have no defmt global logger to link against.
+
+macro_rules! println {
+    ($fmt:literal $(, $arg:expr)*) => {{
+        #[cfg(feature = "defmt")]
+        defmt::println!($fmt $(, $arg)*);
+        #[cfg(not(feature = "defmt"))]
+        { $(let _ = &$arg;)* }
+    }};
+}
+
+macro_rules! error {
+    ($fmt:literal $(, $arg:expr)*) => {{
+        #[cfg(feature = "defmt")]
+        defmt::error!($fmt $(, $arg)*);
+        #[cfg(not(feature = "defmt"))]
+        { $(let _ = &$arg;)* }
+    }};
+}
+
+macro_rules! info {
+    ($fmt:literal $(, $arg:expr)*) => {{
+        #[cfg(feature = "defmt")]
+        defmt::info!($fmt $(, $arg)*);
+        #[cfg(not(feature = "defmt"))]
+        { $(let _ = &$arg;)* }
+    }};
+}
+
+macro_rules! debug {
+    ($fmt:literal $(, $arg:expr)*) => {{
+        #[cfg(feature = "defmt")]
+        defmt::debug!($fmt $(, $arg)*);
+        #[cfg(not(feature = "defmt"))]
+        { $(let _ = &$arg;)* }
+    }};
+}
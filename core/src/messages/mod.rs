@@ -0,0 +1,16 @@
+pub mod pi_2_pico_message;
+pub mod pi_2_pico_test;
+pub mod pi_2_pico_led;
+pub mod pi_2_pico_buzzer;
+pub mod pi_2_pico_backlight;
+pub mod pi_2_pico_ping;
+pub mod pi_2_pico_list;
+pub mod pi_2_pico_widget;
+pub mod pi_2_pico_layout;
+pub mod pi_2_pico_image;
+pub mod pi_2_pico_settings;
+pub mod pi_2_pico_firmware;
+pub mod pi_2_pico_uart;
+pub mod pi_2_pico_stats;
+pub mod pico_2_pi_message;
+pub mod receiver;
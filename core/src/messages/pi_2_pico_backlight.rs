@@ -0,0 +1,97 @@
+use core::convert::TryFrom;
+use heapless::String;
+use backlight::policy::DEFAULT_FADE_MS;
+use utils::string_to_kv::string_to_kv;
+
+pub struct Pi2PicoBacklight {
+    /// `backlight=<percent>[:<fade_ms>]`
+    pub brightness_and_fade: Option<(u8, u32)>,
+    /// `autodim=<dim_after_s>:<off_after_s>[:<dim_percent>]`, 0 disables dim or off step
+    pub auto_dim: Option<(u32, u32, u8)>,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoBacklight {
+    type Error = Pi2PicoBacklightError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut pi2_pico_backlight = Pi2PicoBacklight {
+            brightness_and_fade: None,
+            auto_dim: None,
+        };
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("backlight", backlight) => {
+                            let mut backlight_iter = backlight.split(':');
+                            let brightness = backlight_iter.next().unwrap_or("")
+                                .parse::<u8>().map_err(|_| Pi2PicoBacklightError::BadValue)?;
+                            let fade_ms = match backlight_iter.next() {
+                                Some(fade_ms) => fade_ms.parse::<u32>().map_err(|_| Pi2PicoBacklightError::BadValue)?,
+                                None => DEFAULT_FADE_MS,
+                            };
+                            pi2_pico_backlight.brightness_and_fade = Some((brightness.min(100), fade_ms));
+                        }
+                        ("autodim", autodim) => {
+                            let mut autodim_iter = autodim.split(':');
+                            let dim_after_s = autodim_iter.next().unwrap_or("")
+                                .parse::<u32>().map_err(|_| Pi2PicoBacklightError::BadValue)?;
+                            let off_after_s = autodim_iter.next().unwrap_or("0")
+                                .parse::<u32>().map_err(|_| Pi2PicoBacklightError::BadValue)?;
+                            let dim_percent = autodim_iter.next().unwrap_or("10")
+                                .parse::<u8>().map_err(|_| Pi2PicoBacklightError::BadValue)?;
+                            pi2_pico_backlight.auto_dim = Some((dim_after_s.saturating_mul(1000), off_after_s.saturating_mul(1000), dim_percent));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoBacklightError::ParseError);
+            }
+        }
+        if pi2_pico_backlight.brightness_and_fade.is_none() && pi2_pico_backlight.auto_dim.is_none() {
+            return Err(Pi2PicoBacklightError::StringMismatch);
+        }
+        Ok(pi2_pico_backlight)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoBacklightError {
+    StringMismatch,
+    ParseError,
+    BadValue,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoBacklight, Pi2PicoBacklightError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn parses_brightness_with_default_fade() {
+        let message = parse("backlight=60").ok().unwrap();
+        assert_eq!(message.brightness_and_fade, Some((60, DEFAULT_FADE_MS)));
+        assert_eq!(parse("backlight=150:0").ok().unwrap().brightness_and_fade, Some((100, 0)));
+    }
+
+    #[test]
+    fn parses_auto_dim_in_ms() {
+        let message = parse("autodim=30:120:20").ok().unwrap();
+        assert_eq!(message.auto_dim, Some((30_000, 120_000, 20)));
+        assert_eq!(parse("autodim=30").ok().unwrap().auto_dim, Some((30_000, 0, 10)));
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(matches!(parse("backlight=bright"), Err(Pi2PicoBacklightError::BadValue)));
+        assert!(matches!(parse("autodim=1:x"), Err(Pi2PicoBacklightError::BadValue)));
+        assert!(matches!(parse("beep=100"), Err(Pi2PicoBacklightError::StringMismatch)));
+    }
+}
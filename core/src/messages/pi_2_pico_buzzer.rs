@@ -0,0 +1,102 @@
+use core::convert::TryFrom;
+use heapless::String;
+use buzzer::melody::{parse_rtttl, Melody, RtttlError, ALERT_ERROR, ALERT_NOTIFY, ALERT_SUCCESS};
+use utils::string_to_kv::string_to_kv;
+
+pub enum BuzzerCommand {
+    /// `beep=<freq_hz>:<duration_ms>`
+    Tone { freq_hz: u16, duration_ms: u32 },
+    /// `melody=<rtttl>`
+    Melody(Melody),
+    /// `alert=notify|success|error`
+    Alert(Melody),
+    /// `keyclick=on|off`
+    KeyClick(bool),
+    /// `beep=off`
+    Stop,
+}
+
+pub struct Pi2PicoBuzzer {
+    pub command: BuzzerCommand,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoBuzzer {
+    type Error = Pi2PicoBuzzerError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    let command = match x {
+                        ("beep", "off") => BuzzerCommand::Stop,
+                        ("beep", beep) => {
+                            let mut beep_iter = beep.split(':');
+                            let freq_hz = beep_iter.next().unwrap_or("")
+                                .parse::<u16>().map_err(|_| Pi2PicoBuzzerError::BadTone)?;
+                            let duration_ms = beep_iter.next().unwrap_or("100")
+                                .parse::<u32>().map_err(|_| Pi2PicoBuzzerError::BadTone)?;
+                            BuzzerCommand::Tone { freq_hz, duration_ms }
+                        }
+                        ("melody", melody) => {
+                            BuzzerCommand::Melody(parse_rtttl(melody).map_err(Pi2PicoBuzzerError::Rtttl)?)
+                        }
+                        ("alert", alert) => {
+                            let rtttl = match alert {
+                                "notify" => ALERT_NOTIFY,
+                                "success" => ALERT_SUCCESS,
+                                "error" => ALERT_ERROR,
+                                _ => return Err(Pi2PicoBuzzerError::UnknownAlert),
+                            };
+                            BuzzerCommand::Alert(parse_rtttl(rtttl).map_err(Pi2PicoBuzzerError::Rtttl)?)
+                        }
+                        ("keyclick", keyclick) => BuzzerCommand::KeyClick(keyclick == "on"),
+                        _ => continue,
+                    };
+                    return Ok(Pi2PicoBuzzer { command });
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoBuzzerError::ParseError);
+            }
+        }
+        Err(Pi2PicoBuzzerError::StringMismatch)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoBuzzerError {
+    StringMismatch,
+    ParseError,
+    BadTone,
+    UnknownAlert,
+    Rtttl(RtttlError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoBuzzer, Pi2PicoBuzzerError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert!(matches!(parse("beep=2000:50").ok().unwrap().command, BuzzerCommand::Tone { freq_hz: 2000, duration_ms: 50 }));
+        assert!(matches!(parse("beep=440").ok().unwrap().command, BuzzerCommand::Tone { freq_hz: 440, duration_ms: 100 }));
+        assert!(matches!(parse("beep=off").ok().unwrap().command, BuzzerCommand::Stop));
+        assert!(matches!(parse("keyclick=on").ok().unwrap().command, BuzzerCommand::KeyClick(true)));
+        assert!(matches!(parse("alert=success").ok().unwrap().command, BuzzerCommand::Alert(_)));
+        assert!(matches!(parse("melody=tune:d=8,o=5,b=120:c,e,g").ok().unwrap().command, BuzzerCommand::Melody(_)));
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        assert!(matches!(parse("beep=loud"), Err(Pi2PicoBuzzerError::BadTone)));
+        assert!(matches!(parse("alert=panic"), Err(Pi2PicoBuzzerError::UnknownAlert)));
+        assert!(matches!(parse("melody=tune"), Err(Pi2PicoBuzzerError::Rtttl(_))));
+        assert!(matches!(parse("led=red:on"), Err(Pi2PicoBuzzerError::StringMismatch)));
+    }
+}
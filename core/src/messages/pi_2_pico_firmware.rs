@@ -0,0 +1,72 @@
+use core::convert::TryFrom;
+use heapless::{String, Vec};
+use update::error::UpdateError;
+use update::receiver::{FirmwareCommand, CHUNK_BYTES};
+use utils::base64;
+use utils::string_to_kv::string_to_kv;
+
+/// Firmware update, one command per line and one line at a time, the Pi waits for the answer:
+/// * `fw_begin=<len>:<crc32 hex>` starts an upload of the whole image, answered `fw=ready`
+/// * `fw_data=<offset>:<crc32 hex of the chunk>:<base64>` next chunk of at most `CHUNK_BYTES`,
+///   answered `fw=next:<offset>`
+/// * `fw_end=1` checks the staged image, answered `fw=verified`
+/// * `fw_apply=1` installs it, answered `fw=applying` before the reboot
+/// * `fw_status=1` answered with the state of the update, see `FirmwareEvent`
+///
+/// Failures are answered with `fw_error=<code>[:<bytes>]`, see `UpdateError`.
+pub struct Pi2PicoFirmware {
+    pub command: FirmwareCommand,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoFirmware {
+    type Error = Pi2PicoFirmwareError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut command = None;
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("fw_begin", begin) => {
+                            let bad_header = Pi2PicoFirmwareError::Update(UpdateError::BadHeader);
+                            let (len, checksum) = begin.split_once(':').ok_or(bad_header)?;
+                            let len = len.parse::<u32>().map_err(|_| bad_header)?;
+                            let checksum = u32::from_str_radix(checksum, 16).map_err(|_| bad_header)?;
+                            command = Some(FirmwareCommand::Begin { len, checksum });
+                        }
+                        ("fw_data", data) => {
+                            let bad_data = Pi2PicoFirmwareError::Update(UpdateError::BadData);
+                            let (offset, rest) = data.split_once(':').ok_or(bad_data)?;
+                            let (checksum, data) = rest.split_once(':').ok_or(bad_data)?;
+                            let offset = offset.parse::<u32>().map_err(|_| bad_data)?;
+                            let checksum = u32::from_str_radix(checksum, 16).map_err(|_| bad_data)?;
+                            let mut chunk = [0u8; CHUNK_BYTES];
+                            let len = base64::decode(data, &mut chunk).map_err(|_| bad_data)?;
+                            let data = Vec::from_slice(&chunk[..len]).map_err(|_| bad_data)?;
+                            command = Some(FirmwareCommand::Data { offset, checksum, data });
+                        }
+                        ("fw_end", _) => command = Some(FirmwareCommand::End),
+                        ("fw_apply", _) => command = Some(FirmwareCommand::Apply),
+                        ("fw_status", _) => command = Some(FirmwareCommand::Status),
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoFirmwareError::ParseError);
+            }
+        }
+        let command = command.ok_or(Pi2PicoFirmwareError::StringMismatch)?;
+        Ok(Pi2PicoFirmware { command })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoFirmwareError {
+    StringMismatch,
+    ParseError,
+    /// Reported back to the Pi
+    Update(UpdateError),
+}
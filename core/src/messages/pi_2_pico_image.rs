@@ -0,0 +1,164 @@
+use core::convert::TryFrom;
+use embedded_graphics_core::geometry::{Point, Size};
+use heapless::{String, Vec};
+use images::error::ImageError;
+use images::format::{ImageFormat, MAX_IMAGE_SIDE};
+use utils::string_to_kv::string_to_kv;
+
+/// Image upload and placement, commands run in the order they come, so a small icon
+/// can be uploaded and shown with one line:
+/// * `img_begin=<id>:<w>x<h>:rgb565|mono[:<fg>:<bg>]` starts an upload, replaces an image with the same id
+/// * `img_data=<id>:<offset>:<base64>` next chunk of pixel data
+/// * `img_end=<id>:<crc32 hex>` checks the data, answered with `img=<id>:ok`
+/// * `img_show=<id>:<x>,<y>` puts a cached image on screen
+/// * `img_hide=<id>|all` takes it off screen, it stays cached
+/// * `img_delete=<id>` drops it from the cache
+///
+/// Failures are answered with `img=<id>:<error code>[:<bytes>]`, see `ImageError`.
+pub struct Pi2PicoImage<'a> {
+    pub commands: Vec<ImageCommand<'a>, 10>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ImageCommand<'a> {
+    Begin { id: u8, size: Size, format: ImageFormat },
+    Data { id: u8, offset: usize, data: &'a str },
+    End { id: u8, checksum: u32 },
+    Show { id: u8, position: Point },
+    Hide(u8),
+    HideAll,
+    Delete(u8),
+}
+
+impl<'a> TryFrom<&'a String<2048>> for Pi2PicoImage<'a> {
+    type Error = Pi2PicoImageError;
+
+    fn try_from(value: &'a String<2048>) -> Result<Self, Self::Error> {
+        let mut pi2_pico_image = Pi2PicoImage {
+            commands: Vec::new(),
+        };
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    let command = match x {
+                        ("img_begin", begin) => {
+                            let (id, rest) = split_id(begin)?;
+                            let (size, format) = rest.split_once(':').ok_or(Pi2PicoImageError::Image(id, ImageError::BadHeader))?;
+                            let size = parse_size(size).ok_or(Pi2PicoImageError::Image(id, ImageError::BadHeader))?;
+                            let format = ImageFormat::parse(format).ok_or(Pi2PicoImageError::Image(id, ImageError::BadHeader))?;
+                            ImageCommand::Begin { id, size, format }
+                        }
+                        ("img_data", data) => {
+                            let (id, rest) = split_id(data)?;
+                            let (offset, data) = rest.split_once(':').ok_or(Pi2PicoImageError::Image(id, ImageError::BadData))?;
+                            let offset = offset.parse::<usize>().map_err(|_| Pi2PicoImageError::Image(id, ImageError::BadData))?;
+                            ImageCommand::Data { id, offset, data }
+                        }
+                        ("img_end", end) => {
+                            let (id, checksum) = split_id(end)?;
+                            let checksum = u32::from_str_radix(checksum, 16)
+                                .map_err(|_| Pi2PicoImageError::Image(id, ImageError::Checksum))?;
+                            ImageCommand::End { id, checksum }
+                        }
+                        ("img_show", show) => {
+                            let (id, position) = split_id(show)?;
+                            let position = parse_point(position).ok_or(Pi2PicoImageError::BadValue)?;
+                            ImageCommand::Show { id, position }
+                        }
+                        ("img_hide", "all") => ImageCommand::HideAll,
+                        ("img_hide", id) => ImageCommand::Hide(parse_id(id)?),
+                        ("img_delete", id) => ImageCommand::Delete(parse_id(id)?),
+                        _ => continue,
+                    };
+                    pi2_pico_image.commands.push(command).map_err(|_| Pi2PicoImageError::ParseError)?;
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoImageError::ParseError);
+            }
+        }
+        if pi2_pico_image.commands.is_empty() {
+            return Err(Pi2PicoImageError::StringMismatch);
+        }
+        Ok(pi2_pico_image)
+    }
+}
+
+fn parse_id(value: &str) -> Result<u8, Pi2PicoImageError> {
+    value.parse::<u8>().map_err(|_| Pi2PicoImageError::BadValue)
+}
+
+/// `<id>:<rest>`
+fn split_id(value: &str) -> Result<(u8, &str), Pi2PicoImageError> {
+    let (id, rest) = value.split_once(':').ok_or(Pi2PicoImageError::BadValue)?;
+    Ok((parse_id(id)?, rest))
+}
+
+/// `<w>x<h>`, sides up to `MAX_IMAGE_SIDE`
+fn parse_size(value: &str) -> Option<Size> {
+    let (width, height) = value.split_once('x')?;
+    let side = |value: &str| value.parse::<u32>().ok().filter(|side| *side <= MAX_IMAGE_SIDE);
+    Some(Size::new(side(width)?, side(height)?))
+}
+
+/// `<x>,<y>`
+fn parse_point(value: &str) -> Option<Point> {
+    let (x, y) = value.split_once(',')?;
+    Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoImageError {
+    StringMismatch,
+    ParseError,
+    /// Id or position is not a number, nothing to report back
+    BadValue,
+    /// Reported back to the Pi
+    Image(u8, ImageError),
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use self::std::boxed::Box;
+
+    fn parse(line: &str) -> Result<Vec<ImageCommand<'static>, 10>, Pi2PicoImageError> {
+        // leaked so the commands can borrow from the line after the function returns
+        let line: &'static String<2048> = Box::leak(Box::new(String::from(line)));
+        Pi2PicoImage::try_from(line).map(|image| image.commands)
+    }
+
+    #[test]
+    fn upload_commands() {
+        let commands = parse("img_begin=3:16x8:rgb565&img_data=3:0:AAAA&img_end=3:1a2b&img_show=3:10,-4").ok().unwrap();
+        assert_eq!(commands.as_slice(), &[
+            ImageCommand::Begin { id: 3, size: Size::new(16, 8), format: ImageFormat::Rgb565 },
+            ImageCommand::Data { id: 3, offset: 0, data: "AAAA" },
+            ImageCommand::End { id: 3, checksum: 0x1a2b },
+            ImageCommand::Show { id: 3, position: Point::new(10, -4) },
+        ]);
+        let commands = parse("img_hide=all&img_hide=2&img_delete=4").ok().unwrap();
+        assert_eq!(commands.as_slice(), &[ImageCommand::HideAll, ImageCommand::Hide(2), ImageCommand::Delete(4)]);
+    }
+
+    #[test]
+    fn size_is_bounded_by_the_display() {
+        assert!(parse("img_begin=1:160x160:rgb565").is_ok());
+        for line in ["img_begin=1:161x1:rgb565", "img_begin=1:1x161:mono", "img_begin=1:4294967295x4294967295:rgb565",
+            "img_begin=1:65536x32768:rgb565", "img_begin=1:-1x4:rgb565", "img_begin=1:4x4"] {
+            assert!(matches!(parse(line), Err(Pi2PicoImageError::Image(1, ImageError::BadHeader))), "{}", line);
+        }
+    }
+
+    #[test]
+    fn broken_commands() {
+        assert!(matches!(parse("img_data=1:x:AAAA"), Err(Pi2PicoImageError::Image(1, ImageError::BadData))));
+        assert!(matches!(parse("img_end=1:xyz"), Err(Pi2PicoImageError::Image(1, ImageError::Checksum))));
+        assert!(matches!(parse("img_show=300:0,0"), Err(Pi2PicoImageError::BadValue)));
+        assert!(matches!(parse("img_show=1:0"), Err(Pi2PicoImageError::BadValue)));
+        assert!(matches!(parse("ping=1"), Err(Pi2PicoImageError::StringMismatch)));
+    }
+}
@@ -0,0 +1,65 @@
+use core::convert::TryFrom;
+use heapless::String;
+use layout::error::LayoutError;
+use layout::tree::Layout;
+use utils::string_to_kv::string_to_kv;
+
+/// * `layout=<node>;<node>;...` replaces screen lines with a layout, see `layout::node::Node`
+/// * `layout_set=<index>|<property>=<value>|...` changes one node of the current layout
+/// * `layout_clear=1` goes back to screen lines
+///
+/// Device answers both with `layout=ok` or `layout=<error code>[:<node>]`
+pub struct Pi2PicoLayout {
+    pub layout: Option<Layout>,
+    pub update: Option<(usize, String<256>)>,
+    pub clear: bool,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoLayout {
+    type Error = Pi2PicoLayoutError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut pi2_pico_layout = Pi2PicoLayout {
+            layout: None,
+            update: None,
+            clear: false,
+        };
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("layout", layout) => {
+                            pi2_pico_layout.layout = Some(Layout::parse(layout).map_err(Pi2PicoLayoutError::Layout)?);
+                        }
+                        ("layout_set", update) => {
+                            let (index, properties) = update.split_once('|').unwrap_or((update, ""));
+                            let index = index.parse::<usize>()
+                                .map_err(|_| Pi2PicoLayoutError::Layout(LayoutError::UnknownNode(u8::MAX)))?;
+                            let properties = properties.parse::<String<256>>()
+                                .map_err(|_| Pi2PicoLayoutError::Layout(LayoutError::BadProperty(LayoutError::node_index(index))))?;
+                            pi2_pico_layout.update = Some((index, properties));
+                        }
+                        ("layout_clear", _) => pi2_pico_layout.clear = true,
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoLayoutError::ParseError);
+            }
+        }
+        if pi2_pico_layout.layout.is_none() && pi2_pico_layout.update.is_none() && !pi2_pico_layout.clear {
+            return Err(Pi2PicoLayoutError::StringMismatch);
+        }
+        Ok(pi2_pico_layout)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoLayoutError {
+    StringMismatch,
+    ParseError,
+    /// Reported back to the Pi
+    Layout(LayoutError),
+}
@@ -0,0 +1,77 @@
+use core::convert::TryFrom;
+use heapless::{String, Vec};
+use leds::pattern::{LedId, LedPattern, LedPatternError};
+use utils::string_to_kv::string_to_kv;
+
+/// `led=<led>:<pattern>[:<period_ms>]`, key can be repeated, e.g. `led=red:blink:500&led=green:on`
+pub struct Pi2PicoLed {
+    pub commands: Vec<(LedId, LedPattern), 4>,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoLed {
+    type Error = Pi2PicoLedError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut commands = Vec::new();
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("led", led) => {
+                            let (led_name, pattern) = match led.find(':') {
+                                Some(index) => (&led[..index], &led[index + 1..]),
+                                None => return Err(Pi2PicoLedError::Pattern(LedPatternError::UnknownPattern)),
+                            };
+                            let led_id = LedId::try_from(led_name).map_err(Pi2PicoLedError::Pattern)?;
+                            let pattern = LedPattern::try_from(pattern).map_err(Pi2PicoLedError::Pattern)?;
+                            commands.push((led_id, pattern)).map_err(|_| Pi2PicoLedError::TooManyCommands)?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoLedError::ParseError);
+            }
+        }
+        if commands.is_empty() {
+            return Err(Pi2PicoLedError::StringMismatch);
+        }
+        Ok(Pi2PicoLed { commands })
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoLedError {
+    StringMismatch,
+    ParseError,
+    TooManyCommands,
+    Pattern(LedPatternError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoLed, Pi2PicoLedError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn parses_every_led_command() {
+        let message = parse("led=red:on&led=green:off").ok().unwrap();
+        assert_eq!(message.commands.len(), 2);
+        assert_eq!(message.commands[0], (LedId::Red, LedPattern::On));
+        assert_eq!(message.commands[1], (LedId::Green, LedPattern::Off));
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        assert!(matches!(parse("led=red"), Err(Pi2PicoLedError::Pattern(_))));
+        assert!(matches!(parse("led=purple:on"), Err(Pi2PicoLedError::Pattern(_))));
+        assert!(matches!(parse("led=red:on&led=red:on&led=red:on&led=red:on&led=red:on"), Err(Pi2PicoLedError::TooManyCommands)));
+        assert!(matches!(parse("ping=1"), Err(Pi2PicoLedError::StringMismatch)));
+    }
+}
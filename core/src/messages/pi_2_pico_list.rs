@@ -0,0 +1,85 @@
+use core::convert::TryFrom;
+use heapless::{String, Vec};
+use screen::lines::TITLE_CAPACITY;
+use screen::list::{ListWidget, ITEM_CAPACITY, LIST_CAPACITY};
+use utils::string_to_kv::string_to_kv;
+use utils::truncate::truncated;
+
+/// `list=<item>,<item>,...[&list_title=<title>][&list_cursor=<index>]` opens list widget,
+/// `list_close=1` gives data lines back to `Pi2PicoMessage`
+pub struct Pi2PicoList {
+    pub open: Option<ListWidget>,
+    pub close: bool,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoList {
+    type Error = Pi2PicoListError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut items: Option<Vec<String<ITEM_CAPACITY>, LIST_CAPACITY>> = None;
+        let mut title: String<TITLE_CAPACITY> = String::new();
+        let mut cursor = 0;
+        let mut close = false;
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("list", list) => {
+                            let mut list_items = Vec::new();
+                            for item in list.split(',') {
+                                list_items.push(truncated(item)).map_err(|_| Pi2PicoListError::TooManyItems)?;
+                            }
+                            items = Some(list_items);
+                        }
+                        ("list_title", list_title) => title = truncated(list_title),
+                        ("list_cursor", list_cursor) => {
+                            cursor = list_cursor.parse::<usize>().map_err(|_| Pi2PicoListError::BadCursor)?;
+                        }
+                        ("list_close", _) => close = true,
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoListError::ParseError);
+            }
+        }
+        if items.is_none() && !close {
+            return Err(Pi2PicoListError::StringMismatch);
+        }
+        Ok(Pi2PicoList {
+            open: items.map(|items| ListWidget::new(title, items, cursor)),
+            close,
+        })
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoListError {
+    StringMismatch,
+    ParseError,
+    TooManyItems,
+    BadCursor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoList, Pi2PicoListError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn cyrillic_title_and_items_are_kept_whole() {
+        let (title, item) = ("Главное меню ПК", "Настройки экрана и с");
+        assert_eq!((title.chars().count(), item.chars().count()), (15, 20));
+        let list = parse(&["list=", item, ",Выход&list_title=", title].concat()).ok().unwrap();
+        let mut items = Vec::new();
+        _ = items.push(String::from(item));
+        _ = items.push(String::from("Выход"));
+        assert_eq!(list.open, Some(ListWidget::new(String::from(title), items, 0)));
+    }
+}
@@ -0,0 +1,211 @@
+use core::convert::TryFrom;
+use heapless::{String, Vec};
+use screen::lines::{LINES_COUNT, LINE_CAPACITY, TITLE_CAPACITY};
+use screen::style::{LineStyle, LineStyleError};
+use utils::string_to_kv::string_to_kv;
+use utils::truncate::truncated;
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Pi2PicoMessage {
+    pub cursor_index: Option<isize>,
+    pub ip_and_battery: Option<(String<TITLE_CAPACITY>, String<3>)>, //IP/battery %
+    pub title_and_paginator: Option<(String<TITLE_CAPACITY>, String<5>)>, //first header title, second page/total pages
+    pub data_lines: Option<Vec<Option<String<LINE_CAPACITY>>, 8>>,
+    /// Screen line index (0 is the status line) and its new style
+    pub line_styles: Option<Vec<(usize, LineStyle), LINES_COUNT>>,
+}
+
+
+/// Characters separating keys and lines, not allowed inside any value.
+/// `data_lines` can't contain `,` and ip or title can't contain `/` as well.
+pub const RESERVED_CHARS: [char; 3] = ['&', '\r', '\n'];
+
+impl Pi2PicoMessage {
+    /// Encode into the `\r\n` terminated line parsed by `try_from`. Used on the Pi side.
+    /// Values must not contain `RESERVED_CHARS`, there is no escaping in the protocol.
+    pub fn to_frame(&self) -> String<2048> {
+        let mut frame: String<2048> = String::new();
+        if let Some(cursor_index) = self.cursor_index {
+            push_key(&mut frame, "cursor_index");
+            _ = frame.push_str(String::<20>::from(cursor_index as i64).as_str());
+        }
+        if let Some((ip, battery)) = &self.ip_and_battery {
+            push_key(&mut frame, "ip_and_battery");
+            _ = frame.push_str(ip.as_str());
+            _ = frame.push('/');
+            _ = frame.push_str(battery.as_str());
+        }
+        if let Some((title, paginator)) = &self.title_and_paginator {
+            push_key(&mut frame, "title_and_paginator");
+            _ = frame.push_str(title.as_str());
+            _ = frame.push('/');
+            _ = frame.push_str(paginator.as_str());
+        }
+        if let Some(data_lines) = &self.data_lines {
+            push_key(&mut frame, "data_lines");
+            for (index, data_line) in data_lines.iter().enumerate() {
+                if index > 0 {
+                    _ = frame.push(',');
+                }
+                if let Some(data_line) = data_line {
+                    _ = frame.push_str(data_line.as_str());
+                }
+            }
+        }
+        if let Some(line_styles) = &self.line_styles {
+            push_key(&mut frame, "line_style");
+            for (index, (line, style)) in line_styles.iter().enumerate() {
+                if index > 0 {
+                    _ = frame.push(';');
+                }
+                _ = frame.push_str(String::<20>::from(*line as u32).as_str());
+                _ = frame.push_str(style.encode().as_str());
+            }
+        }
+        _ = frame.push_str("\r\n");
+        frame
+    }
+}
+
+fn push_key(frame: &mut String<2048>, key: &str) {
+    if !frame.is_empty() {
+        _ = frame.push('&');
+    }
+    _ = frame.push_str(key);
+    _ = frame.push('=');
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoMessage {
+    type Error = Pi2PicoMessageError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut pi2_pico_message = Pi2PicoMessage {
+            cursor_index: None,
+            ip_and_battery: None,
+            title_and_paginator: None,
+            data_lines: None,
+            line_styles: None,
+        };
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("cursor_index", cursor_index) => {
+                            pi2_pico_message.cursor_index = Some(cursor_index.parse::<isize>()
+                                .map_err(|_| Pi2PicoMessageError::ParseError)?);
+                        }
+                        ("ip_and_battery", ip_and_battery) => {
+                            let mut ip_and_battery_iter = ip_and_battery.split("/");
+                            let ip = ip_and_battery_iter.next().unwrap_or("");
+                            let battery = ip_and_battery_iter.next().unwrap_or("");
+                            pi2_pico_message.ip_and_battery = Some((truncated(ip), truncated(battery)));
+                        }
+                        ("title_and_paginator", title_and_paginator) => {
+                            let mut title_and_paginator_iter = title_and_paginator.split("/");
+                            let title = title_and_paginator_iter.next().unwrap_or("");
+                            // paginator itself is `page/total`
+                            let paginator = &title_and_paginator[title.len()..].trim_start_matches('/');
+                            pi2_pico_message.title_and_paginator = Some((truncated(title), truncated(paginator)));
+                        }
+                        ("data_lines", data_lines) => {
+                            let data_lines_iter = data_lines.split(",");
+                            let data_lines = data_lines_iter
+                                .take(8)
+                                .map(|x| if x.is_empty() { None } else { Some(truncated(x)) })
+                                .collect::<Vec<Option<String<LINE_CAPACITY>>, 8>>();
+                            pi2_pico_message.data_lines = Some(data_lines);
+                        }
+                        ("line_style", line_styles) => {
+                            pi2_pico_message.line_styles = Some(parse_line_styles(line_styles)
+                                .map_err(Pi2PicoMessageError::LineStyle)?);
+                        }
+
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoMessageError::ParseError);
+            }
+        }
+        if pi2_pico_message.cursor_index.is_none()
+            && pi2_pico_message.ip_and_battery.is_none()
+            && pi2_pico_message.title_and_paginator.is_none()
+            && pi2_pico_message.data_lines.is_none()
+            && pi2_pico_message.line_styles.is_none() {
+            return Err(Pi2PicoMessageError::StringMismatch);
+        }
+        Ok(pi2_pico_message)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoMessageError{
+    StringMismatch,
+    ParseError,
+    LineStyle(LineStyleError),
+}
+
+/// `<line>[|<property>=<value>]...;<line>...`, see `LineStyle::parse`
+fn parse_line_styles(value: &str) -> Result<Vec<(usize, LineStyle), LINES_COUNT>, LineStyleError> {
+    let mut line_styles = Vec::new();
+    for line_style in value.split(';').filter(|line_style| !line_style.is_empty()) {
+        let (line, style) = line_style.split_once('|').unwrap_or((line_style, ""));
+        let line = line.parse::<usize>().map_err(|_| LineStyleError::BadLine)?;
+        if line >= LINES_COUNT {
+            return Err(LineStyleError::BadLine);
+        }
+        line_styles.push((line, LineStyle::parse(style)?)).map_err(|_| LineStyleError::BadLine)?;
+    }
+    Ok(line_styles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoMessage, Pi2PicoMessageError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn parses_screen_fields() {
+        let message = parse("cursor_index=2&ip_and_battery=10.0.0.1/87&title_and_paginator=Menu/1/3&data_lines=one,,three").ok().unwrap();
+        assert_eq!(message.cursor_index, Some(2));
+        assert_eq!(message.ip_and_battery, Some((String::from("10.0.0.1"), String::from("87"))));
+        assert_eq!(message.title_and_paginator, Some((String::from("Menu"), String::from("1/3"))));
+        let data_lines = message.data_lines.unwrap();
+        assert_eq!(data_lines.as_slice(), &[Some(String::from("one")), None, Some(String::from("three"))]);
+    }
+
+    #[test]
+    fn cyrillic_title_is_kept_whole() {
+        let title = "Главное меню ПК";
+        assert_eq!(title.chars().count(), 15);
+        let message = parse(&["title_and_paginator=", title, "/1/3"].concat()).ok().unwrap();
+        assert_eq!(message.title_and_paginator, Some((String::from(title), String::from("1/3"))));
+    }
+
+    #[test]
+    fn frame_parses_back() {
+        let message = parse("cursor_index=-1&title_and_paginator=Menu/2/5&data_lines=a,b").ok().unwrap();
+        let frame = message.to_frame();
+        assert!(frame.ends_with("\r\n"));
+        let parsed = parse(frame.trim_end_matches("\r\n")).ok().unwrap();
+        assert_eq!(parsed, message);
+    }
+
+    #[test]
+    fn other_messages_do_not_match() {
+        assert!(matches!(parse("ping=abc"), Err(Pi2PicoMessageError::StringMismatch)));
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(matches!(parse("stats"), Err(Pi2PicoMessageError::ParseError)));
+        assert!(matches!(parse("cursor_index=x"), Err(Pi2PicoMessageError::ParseError)));
+        assert!(matches!(parse("line_style=99|bold=1"), Err(Pi2PicoMessageError::LineStyle(_))));
+    }
+}
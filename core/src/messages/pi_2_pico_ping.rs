@@ -0,0 +1,71 @@
+use core::convert::TryFrom;
+use heapless::String;
+use utils::string_to_kv::string_to_kv;
+
+/// `ping=<token>`, answered with `pong=<token>` frame. Token is up to 16 chars without `&` and `,`
+pub struct Pi2PicoPing {
+    pub token: String<16>,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoPing {
+    type Error = Pi2PicoPingError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("ping", token) => {
+                            if token.contains(',') {
+                                return Err(Pi2PicoPingError::BadToken);
+                            }
+                            let token = token.parse::<String<16>>().map_err(|_| Pi2PicoPingError::BadToken)?;
+                            return Ok(Pi2PicoPing { token });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoPingError::ParseError);
+            }
+        }
+        Err(Pi2PicoPingError::StringMismatch)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoPingError {
+    StringMismatch,
+    ParseError,
+    BadToken,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoPing, Pi2PicoPingError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn parses_token() {
+        assert_eq!(parse("ping=abc").ok().unwrap().token.as_str(), "abc");
+        assert_eq!(parse("hb=1&ping=").ok().unwrap().token.as_str(), "");
+    }
+
+    #[test]
+    fn rejects_bad_tokens() {
+        assert!(matches!(parse("ping=a,b"), Err(Pi2PicoPingError::BadToken)));
+        assert!(matches!(parse("ping=12345678901234567"), Err(Pi2PicoPingError::BadToken)));
+    }
+
+    #[test]
+    fn other_messages_do_not_match() {
+        assert!(matches!(parse("cursor_index=1"), Err(Pi2PicoPingError::StringMismatch)));
+        assert!(matches!(parse("ping"), Err(Pi2PicoPingError::ParseError)));
+    }
+}
@@ -0,0 +1,66 @@
+use core::convert::TryFrom;
+use heapless::String;
+use settings::setting::{SettingKey, SettingsError};
+use utils::string_to_kv::string_to_kv;
+
+/// * `setting_set=<name>:<value>` changes a setting and saves it to flash
+/// * `setting_get=<name>` asks for the current value
+/// * `setting_reset=1` goes back to defaults
+///
+/// Device answers `setting_set` and `setting_get` with `setting=<name>:<value>` and
+/// `setting_reset` with `setting_reset=ok`, failures with `setting_error=<code>[:<name>]`
+pub struct Pi2PicoSettings {
+    pub set: Option<(SettingKey, String<24>)>,
+    pub get: Option<SettingKey>,
+    pub reset: bool,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoSettings {
+    type Error = Pi2PicoSettingsError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut pi2_pico_settings = Pi2PicoSettings {
+            set: None,
+            get: None,
+            reset: false,
+        };
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("setting_set", setting) => {
+                            let (name, value) = setting.split_once(':').unwrap_or((setting, ""));
+                            let key = SettingKey::try_from(name)
+                                .map_err(|_| Pi2PicoSettingsError::Setting(SettingsError::Unknown))?;
+                            let value = value.parse::<String<24>>()
+                                .map_err(|_| Pi2PicoSettingsError::Setting(SettingsError::BadValue(key)))?;
+                            pi2_pico_settings.set = Some((key, value));
+                        }
+                        ("setting_get", name) => {
+                            pi2_pico_settings.get = Some(SettingKey::try_from(name)
+                                .map_err(|_| Pi2PicoSettingsError::Setting(SettingsError::Unknown))?);
+                        }
+                        ("setting_reset", _) => pi2_pico_settings.reset = true,
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoSettingsError::ParseError);
+            }
+        }
+        if pi2_pico_settings.set.is_none() && pi2_pico_settings.get.is_none() && !pi2_pico_settings.reset {
+            return Err(Pi2PicoSettingsError::StringMismatch);
+        }
+        Ok(pi2_pico_settings)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoSettingsError {
+    StringMismatch,
+    ParseError,
+    /// Reported back to the Pi
+    Setting(SettingsError),
+}
@@ -0,0 +1,60 @@
+use core::convert::TryFrom;
+use heapless::String;
+use utils::string_to_kv::string_to_kv;
+
+/// `stats=1` or a bare `stats`, answered with one `stats=<group>:<value>:...` frame per `StatsGroup`
+pub struct Pi2PicoStats;
+
+impl TryFrom<&String<2048>> for Pi2PicoStats {
+    type Error = Pi2PicoStatsError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        if value.as_str() == "stats" {
+            return Ok(Pi2PicoStats);
+        }
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("stats", _) => return Ok(Pi2PicoStats),
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoStatsError::ParseError);
+            }
+        }
+        Err(Pi2PicoStatsError::StringMismatch)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoStatsError {
+    StringMismatch,
+    ParseError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoStats, Pi2PicoStatsError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn bare_and_valued_requests_match() {
+        assert!(parse("stats").is_ok());
+        assert!(parse("stats=1").is_ok());
+        assert!(parse("ping=1&stats").is_ok());
+    }
+
+    #[test]
+    fn other_lines_do_not_match() {
+        assert!(matches!(parse("ping=1"), Err(Pi2PicoStatsError::StringMismatch)));
+        assert!(matches!(parse("statistics"), Err(Pi2PicoStatsError::ParseError)));
+    }
+}
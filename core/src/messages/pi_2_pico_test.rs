@@ -0,0 +1,50 @@
+use core::convert::TryFrom;
+use heapless::String;
+use utils::string_to_kv::string_to_kv;
+
+/// `kc=<char>`, shows the key code on the second line. A single byte, longer codes are refused
+pub struct Pi2PicoTest {
+    pub kc: String<1>,
+}
+impl TryFrom<&String<2048>> for Pi2PicoTest {
+    type Error = ();
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        if let Ok(kv) = string_to_kv::<10>(value) {
+            for x in kv {
+                match x {
+                    ("kc", kc) => {
+                        let kc = kc.parse::<String<1>>().map_err(|_| ())?;
+                        return Ok(Pi2PicoTest { kc });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Pi2PicoTest {
+            kc: String::from(""), //default
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoTest, ()> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn parses_single_byte_key_code() {
+        assert_eq!(parse("kc=a").unwrap().kc.as_str(), "a");
+        assert_eq!(parse("ping=1").unwrap().kc.as_str(), "");
+    }
+
+    #[test]
+    fn refuses_longer_key_codes() {
+        assert!(parse("kc=ab").is_err());
+        assert!(parse("kc=ж").is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use core::convert::TryFrom;
+use heapless::String;
+use link::uart::UartParity;
+use settings::setting::BAUD_RATES;
+use utils::string_to_kv::string_to_kv;
+
+/// * `uart_switch=<baud>[:<parity>]` changes the uart format until the next boot, parity stays
+///   as it is when left out. Answered with `uart=switching:<baud>:<parity>` at the old format,
+///   the device changes over right after it.
+/// * `uart_confirm=1` sent at the new format within `UART_CONFIRM_MS`, answered with
+///   `uart=ok:<baud>:<parity>`. Without it the device goes back and sends `uart=fallback:...`.
+///
+/// Unsupported values are answered with `uart=bad_value`. Use `setting_set=baud:<rate>` and
+/// `setting_set=parity:<parity>` to keep a format over reboots.
+pub struct Pi2PicoUart {
+    /// One of `BAUD_RATES`, parity when given
+    pub switch: Option<(u32, Option<UartParity>)>,
+    pub confirm: bool,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoUart {
+    type Error = Pi2PicoUartError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut pi2_pico_uart = Pi2PicoUart {
+            switch: None,
+            confirm: false,
+        };
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("uart_switch", switch) => {
+                            let (baud, parity) = match switch.split_once(':') {
+                                Some((baud, parity)) => {
+                                    (baud, Some(UartParity::try_from(parity).map_err(|_| Pi2PicoUartError::BadValue)?))
+                                }
+                                None => (switch, None),
+                            };
+                            let baud = baud.parse::<u32>().ok()
+                                .filter(|baud| BAUD_RATES.contains(baud))
+                                .ok_or(Pi2PicoUartError::BadValue)?;
+                            pi2_pico_uart.switch = Some((baud, parity));
+                        }
+                        ("uart_confirm", _) => pi2_pico_uart.confirm = true,
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoUartError::ParseError);
+            }
+        }
+        if pi2_pico_uart.switch.is_none() && !pi2_pico_uart.confirm {
+            return Err(Pi2PicoUartError::StringMismatch);
+        }
+        Ok(pi2_pico_uart)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoUartError {
+    StringMismatch,
+    ParseError,
+    /// Reported back to the Pi
+    BadValue,
+}
@@ -0,0 +1,173 @@
+use core::convert::TryFrom;
+use heapless::String;
+use qr::code::{EccLevel, QrCode, QrError, MAX_DATA_BYTES};
+use utils::base64;
+use utils::string_to_kv::string_to_kv;
+use utils::truncate::truncated_chars;
+use widgets::dialog::Dialog;
+use widgets::gauge::Gauge;
+use widgets::layer::Widget;
+use widgets::progress::ProgressBar;
+use widgets::spinner::Spinner;
+use widgets::toast::DEFAULT_TOAST_MS;
+use widgets::{LABEL_CAPACITY, LABEL_CHARS};
+
+/// Widgets over the data lines, labels go last and may contain `:`
+/// * `progress=<percent>[:<label>]`
+/// * `gauge=<value>:<min>:<max>[:<label>]`
+/// * `dialog=<question>`, answered with `dialog=yes|no`
+/// * `spinner=<value>:<min>:<max>[:<step>[:<label>]]`, answered with `spin=<value>`
+/// * `widget_close=1` gives data lines back
+/// * `toast=<text>[&toast_ms=<ms>]` notification over the screen
+/// * `battery=<percent>|off` icon in the status line
+/// * `qr=<text>` or `qr_b64=<base64>` for data with `&`, `[&qr_ecc=l|m|q|h]` QR code over the whole screen,
+///   answered with `qr=ok|<error code>`
+/// * `qr_close=1` hides it
+pub struct Pi2PicoWidget {
+    pub widget: Option<Widget>,
+    pub close: bool,
+    /// text and how long to show it
+    pub toast: Option<(String<LABEL_CAPACITY>, u32)>,
+    /// `Some(None)` hides the icon
+    pub battery: Option<Option<u8>>,
+    /// Encoded while parsing, errors go back to the Pi
+    pub qr: Option<Result<QrCode, QrError>>,
+    pub qr_close: bool,
+}
+
+impl TryFrom<&String<2048>> for Pi2PicoWidget {
+    type Error = Pi2PicoWidgetError;
+
+    fn try_from(value: &String<2048>) -> Result<Self, Self::Error> {
+        let mut pi2_pico_widget = Pi2PicoWidget {
+            widget: None,
+            close: false,
+            toast: None,
+            battery: None,
+            qr: None,
+            qr_close: false,
+        };
+        let mut toast_ms = DEFAULT_TOAST_MS;
+        let mut qr_data: Option<Result<&[u8], QrError>> = None;
+        let mut qr_buffer = [0u8; MAX_DATA_BYTES];
+        let mut qr_ecc = EccLevel::Medium;
+        match string_to_kv::<10>(value) {
+            Ok(kv) => {
+                for x in kv {
+                    match x {
+                        ("progress", progress) => {
+                            let mut progress_iter = progress.splitn(2, ':');
+                            let percent = next_number::<u8, _>(&mut progress_iter)?;
+                            let label = truncated_chars(progress_iter.next().unwrap_or(""), LABEL_CHARS);
+                            pi2_pico_widget.widget = Some(Widget::Progress(ProgressBar::new(percent, label)));
+                        }
+                        ("gauge", gauge) => {
+                            let mut gauge_iter = gauge.splitn(4, ':');
+                            let value = next_number::<i32, _>(&mut gauge_iter)?;
+                            let min = next_number::<i32, _>(&mut gauge_iter)?;
+                            let max = next_number::<i32, _>(&mut gauge_iter)?;
+                            let label = truncated_chars(gauge_iter.next().unwrap_or(""), LABEL_CHARS);
+                            pi2_pico_widget.widget = Some(Widget::Gauge(Gauge::new(value, min, max, label)));
+                        }
+                        ("dialog", question) => {
+                            let question = truncated_chars(question, LABEL_CHARS);
+                            pi2_pico_widget.widget = Some(Widget::Dialog(Dialog::new(question)));
+                        }
+                        ("spinner", spinner) => {
+                            let mut spinner_iter = spinner.splitn(5, ':');
+                            let value = next_number::<i32, _>(&mut spinner_iter)?;
+                            let min = next_number::<i32, _>(&mut spinner_iter)?;
+                            let max = next_number::<i32, _>(&mut spinner_iter)?;
+                            let step = match spinner_iter.next() {
+                                Some(step) => step.parse::<i32>().map_err(|_| Pi2PicoWidgetError::BadValue)?,
+                                None => 1,
+                            };
+                            let label = truncated_chars(spinner_iter.next().unwrap_or(""), LABEL_CHARS);
+                            pi2_pico_widget.widget = Some(Widget::Spinner(Spinner::new(value, min, max, step, label)));
+                        }
+                        ("widget_close", _) => pi2_pico_widget.close = true,
+                        ("toast", text) => pi2_pico_widget.toast = Some((truncated_chars(text, LABEL_CHARS), 0)),
+                        ("toast_ms", ms) => {
+                            toast_ms = ms.parse::<u32>().map_err(|_| Pi2PicoWidgetError::BadValue)?;
+                        }
+                        ("battery", "off") => pi2_pico_widget.battery = Some(None),
+                        ("battery", percent) => {
+                            let percent = percent.parse::<u8>().map_err(|_| Pi2PicoWidgetError::BadValue)?;
+                            pi2_pico_widget.battery = Some(Some(percent.min(100)));
+                        }
+                        ("qr", text) => qr_data = Some(Ok(text.as_bytes())),
+                        ("qr_b64", data) => {
+                            qr_data = Some(match base64::decode(data, &mut qr_buffer) {
+                                Ok(len) => Ok(&qr_buffer[..len]),
+                                Err(base64::Base64Error::TooLong) => Err(QrError::TooLong),
+                                Err(_) => Err(QrError::BadData),
+                            });
+                        }
+                        ("qr_ecc", ecc) => qr_ecc = EccLevel::try_from(ecc).map_err(|_| Pi2PicoWidgetError::BadValue)?,
+                        ("qr_close", _) => pi2_pico_widget.qr_close = true,
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Error: {:?}", err);
+                return Err(Pi2PicoWidgetError::ParseError);
+            }
+        }
+        if let Some(toast) = &mut pi2_pico_widget.toast {
+            toast.1 = toast_ms;
+        }
+        pi2_pico_widget.qr = qr_data.map(|data| QrCode::encode(data?, qr_ecc));
+        if pi2_pico_widget.widget.is_none()
+            && !pi2_pico_widget.close
+            && pi2_pico_widget.toast.is_none()
+            && pi2_pico_widget.battery.is_none()
+            && pi2_pico_widget.qr.is_none()
+            && !pi2_pico_widget.qr_close {
+            return Err(Pi2PicoWidgetError::StringMismatch);
+        }
+        Ok(pi2_pico_widget)
+    }
+}
+
+fn next_number<'a, N: core::str::FromStr, I: Iterator<Item = &'a str>>(iter: &mut I) -> Result<N, Pi2PicoWidgetError> {
+    iter.next().unwrap_or("").parse::<N>().map_err(|_| Pi2PicoWidgetError::BadValue)
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pi2PicoWidgetError {
+    StringMismatch,
+    ParseError,
+    BadValue,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_fixtures;
+
+    fn parse(line: &str) -> Result<Pi2PicoWidget, Pi2PicoWidgetError> {
+        test_fixtures::parse(line)
+    }
+
+    #[test]
+    fn cyrillic_labels_are_kept_whole() {
+        let label = "Обновление прошивки!";
+        assert_eq!(label.chars().count(), LABEL_CHARS);
+        let progress = parse(&["progress=40:", label].concat()).ok().unwrap();
+        assert_eq!(progress.widget, Some(Widget::Progress(ProgressBar::new(40, String::from(label)))));
+        let dialog = parse(&["dialog=", label].concat()).ok().unwrap();
+        assert_eq!(dialog.widget, Some(Widget::Dialog(Dialog::new(String::from(label)))));
+        let toast = parse(&["toast=", label, "&toast_ms=500"].concat()).ok().unwrap();
+        assert_eq!(toast.toast, Some((String::from(label), 500)));
+    }
+
+    #[test]
+    fn labels_are_cut_to_what_fits_across_the_screen() {
+        let gauge = parse("gauge=1:0:9:Temperature of the CPU core").ok().unwrap();
+        assert_eq!(gauge.widget, Some(Widget::Gauge(Gauge::new(1, 0, 9, String::from("Temperature of the C")))));
+        let spinner = parse("spinner=1:0:9:1:Яркость подсветки экрана").ok().unwrap();
+        let label = String::from("Яркость подсветки эк");
+        assert_eq!(spinner.widget, Some(Widget::Spinner(Spinner::new(1, 0, 9, 1, label))));
+    }
+}
@@ -0,0 +1,406 @@
+use core::convert::TryFrom;
+use heapless::String;
+use crash::record::CrashRecord;
+use diagnostics::counters::StatsReport;
+use device::reset::ResetReason;
+use images::error::ImageError;
+use input::keyboard_codes::KeyboardCodes;
+use layout::error::LayoutError;
+use link::monitor::LinkState;
+use link::uart::{UartEvent, UartFormat};
+use qr::code::QrError;
+use settings::setting::{SettingKey, SettingsError};
+use update::error::UpdateError;
+use update::event::FirmwareEvent;
+use utils::string_to_kv::string_to_kv;
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Pico2PiMessage {
+    pub wh: Option<[i32; 2]>,
+    //todo: add multi-key press
+    pub keyboard_codes: Option<KeyboardCodes>,
+    pub keypress_ms: u64,
+    /// Answer to `ping=<token>`
+    pub pong: Option<String<16>>,
+    /// Item chosen with `Ok` in the on-device list
+    pub list_selected: Option<usize>,
+    /// List scrolled to another page, 0 based
+    pub list_page: Option<usize>,
+    /// Dialog answered, true for yes
+    pub dialog_answer: Option<bool>,
+    /// Spinner value confirmed with `Ok`
+    pub spinner_value: Option<i32>,
+    /// Answer to `layout` and `layout_set`
+    pub layout_result: Option<Result<(), LayoutError>>,
+    /// Answer to image commands, with the image id
+    pub image_result: Option<(u8, Result<(), ImageError>)>,
+    /// Answer to `qr` and `qr_b64`
+    pub qr_result: Option<Result<(), QrError>>,
+    /// Device heartbeat sequence number, sent every `HEARTBEAT_INTERVAL_MS`
+    pub heartbeat: Option<u32>,
+    /// Link state as the device sees it, sent with heartbeats
+    pub link_state: Option<LinkState>,
+    /// Why the device restarted, sent once on first contact after boot
+    pub reset_reason: Option<ResetReason>,
+    /// Panic, sent by the panic handler and again on first contact after the reboot
+    pub crash: Option<CrashRecord>,
+    /// Current value, answer to `setting_set` and `setting_get`
+    pub setting: Option<(SettingKey, String<24>)>,
+    /// Answer to `setting_reset`
+    pub settings_reset: bool,
+    /// Setting command failed or the value was not saved
+    pub setting_error: Option<SettingsError>,
+    /// Answer to firmware commands, also trial and rollback on first contact after an update
+    pub firmware: Option<FirmwareEvent>,
+    /// Firmware command failed
+    pub firmware_error: Option<UpdateError>,
+    /// Answer to `uart_switch` and `uart_confirm`, also the fallback when there was no confirm
+    pub uart: Option<UartEvent>,
+    /// Answer to `stats`, one frame per group
+    pub stats: Option<StatsReport>,
+}
+
+impl Pico2PiMessage {
+    /// Frame sent over uart: `len=<n>&wh=128,128&kc=<code>&keypressms=<ms>&pong=<token>&sel=<index>&page=<page>&dialog=<yes|no>&spin=<value>&layout=<ok|error>&img=<id>:<ok|error>&qr=<ok|error>&hb=<seq>&link=<state>&reset=<reason>&crash=<core>:<file>:<line>:<message>&setting=<name>:<value>&setting_reset=ok&setting_error=<code>[:<name>]&fw=<event>[:<offset>]&fw_error=<code>[:<bytes>]&uart=<event>[:<baud>:<parity>]&stats=<group>:<value>...\r\n`,
+    /// where `len` is the length of everything after the number, without `\r\n`
+    pub fn to_frame(&self) -> String<100> {
+        let mut message: String<80> = String::new();
+        if let Some([w, h]) = self.wh {
+            message.push_str("&wh=").unwrap();
+            message.push_str(String::<11>::from(w).as_str()).unwrap();
+            message.push(',').unwrap();
+            message.push_str(String::<11>::from(h).as_str()).unwrap();
+        }
+        if let Some(keycode) = self.keyboard_codes {
+            message.push_str("&kc=").unwrap();
+            message.push(keycode.as_char()).unwrap();
+            message.push_str("&keypressms=").unwrap();
+            message.push_str(String::<20>::from(self.keypress_ms).as_str()).unwrap();
+        }
+        if let Some(token) = &self.pong {
+            message.push_str("&pong=").unwrap();
+            message.push_str(token.as_str()).unwrap();
+        }
+        if let Some(index) = self.list_selected {
+            message.push_str("&sel=").unwrap();
+            message.push_str(String::<10>::from(index as u32).as_str()).unwrap();
+        }
+        if let Some(page) = self.list_page {
+            message.push_str("&page=").unwrap();
+            message.push_str(String::<10>::from(page as u32).as_str()).unwrap();
+        }
+        if let Some(answer) = self.dialog_answer {
+            message.push_str(if answer { "&dialog=yes" } else { "&dialog=no" }).unwrap();
+        }
+        if let Some(value) = self.spinner_value {
+            message.push_str("&spin=").unwrap();
+            message.push_str(String::<11>::from(value).as_str()).unwrap();
+        }
+        if let Some(layout_result) = self.layout_result {
+            message.push_str("&layout=").unwrap();
+            match layout_result {
+                Ok(()) => message.push_str("ok").unwrap(),
+                Err(err) => {
+                    message.push_str(err.code()).unwrap();
+                    if let Some(node) = err.node() {
+                        message.push(':').unwrap();
+                        message.push_str(String::<3>::from(node).as_str()).unwrap();
+                    }
+                }
+            }
+        }
+        if let Some((id, image_result)) = self.image_result {
+            message.push_str("&img=").unwrap();
+            message.push_str(String::<3>::from(id).as_str()).unwrap();
+            message.push(':').unwrap();
+            match image_result {
+                Ok(()) => message.push_str("ok").unwrap(),
+                Err(err) => {
+                    message.push_str(err.code()).unwrap();
+                    if let Some(bytes) = err.bytes() {
+                        message.push(':').unwrap();
+                        message.push_str(String::<10>::from(bytes).as_str()).unwrap();
+                    }
+                }
+            }
+        }
+        if let Some(qr_result) = self.qr_result {
+            message.push_str("&qr=").unwrap();
+            message.push_str(match qr_result {
+                Ok(()) => "ok",
+                Err(err) => err.code(),
+            }).unwrap();
+        }
+        if let Some(seq) = self.heartbeat {
+            message.push_str("&hb=").unwrap();
+            message.push_str(String::<10>::from(seq).as_str()).unwrap();
+        }
+        if let Some(link_state) = self.link_state {
+            message.push_str("&link=").unwrap();
+            message.push_str(link_state.as_str()).unwrap();
+        }
+        if let Some(reset_reason) = self.reset_reason {
+            message.push_str("&reset=").unwrap();
+            message.push_str(reset_reason.code()).unwrap();
+            if let Some(core) = reset_reason.stalled_core() {
+                message.push(':').unwrap();
+                message.push_str(String::<3>::from(core).as_str()).unwrap();
+            }
+        }
+        if let Some(crash) = &self.crash {
+            message.push_str("&crash=").unwrap();
+            message.push_str(crash.to_text().as_str()).unwrap();
+        }
+        if let Some((key, value)) = &self.setting {
+            message.push_str("&setting=").unwrap();
+            message.push_str(key.as_str()).unwrap();
+            message.push(':').unwrap();
+            message.push_str(value.as_str()).unwrap();
+        }
+        if self.settings_reset {
+            message.push_str("&setting_reset=ok").unwrap();
+        }
+        if let Some(err) = self.setting_error {
+            message.push_str("&setting_error=").unwrap();
+            message.push_str(err.code()).unwrap();
+            if let Some(key) = err.key() {
+                message.push(':').unwrap();
+                message.push_str(key.as_str()).unwrap();
+            }
+        }
+        if let Some(event) = self.firmware {
+            message.push_str("&fw=").unwrap();
+            message.push_str(event.code()).unwrap();
+            if let Some(offset) = event.offset() {
+                message.push(':').unwrap();
+                message.push_str(String::<10>::from(offset).as_str()).unwrap();
+            }
+        }
+        if let Some(err) = self.firmware_error {
+            message.push_str("&fw_error=").unwrap();
+            message.push_str(err.code()).unwrap();
+            if let Some(bytes) = err.bytes() {
+                message.push(':').unwrap();
+                message.push_str(String::<10>::from(bytes).as_str()).unwrap();
+            }
+        }
+        if let Some(event) = self.uart {
+            message.push_str("&uart=").unwrap();
+            message.push_str(event.code()).unwrap();
+            if let Some(format) = event.format() {
+                message.push(':').unwrap();
+                message.push_str(format.to_text().as_str()).unwrap();
+            }
+        }
+        if let Some(report) = &self.stats {
+            message.push_str("&stats=").unwrap();
+            message.push_str(report.to_text().as_str()).unwrap();
+        }
+
+        let message_len = message.len() as u8;
+        let message_len_string: String<4> = String::from(message_len);
+        let mut full_message: String<100> = String::new();
+        full_message.push_str("len=").unwrap();
+        full_message.push_str(message_len_string.as_str()).unwrap();
+        full_message.push_str(message.as_str()).unwrap();
+        full_message.push_str("\r\n").unwrap();
+        full_message
+    }
+}
+
+/// Parse frame produced by `to_frame`, trailing `\r\n` is optional. Used on the Pi side.
+impl<'a> TryFrom<&'a str> for Pico2PiMessage {
+    type Error = Pico2PiMessageError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let frame = value.trim_end_matches("\r\n");
+        let rest = frame.strip_prefix("len=").ok_or(Pico2PiMessageError::NotAFrame)?;
+        let len_end = rest.find('&').unwrap_or(rest.len());
+        let message_len = rest[..len_end].parse::<usize>().map_err(|_| Pico2PiMessageError::NotAFrame)?;
+        let message = &rest[len_end..];
+        if message.len() != message_len {
+            return Err(Pico2PiMessageError::LengthMismatch);
+        }
+
+        let mut pico2_pi_message = Pico2PiMessage::default();
+        let kv = string_to_kv::<10>(message.trim_start_matches('&'))
+            .map_err(|_| Pico2PiMessageError::BadField)?;
+        for x in kv {
+            match x {
+                ("wh", wh) => {
+                    let mut wh_iter = wh.split(',').map(|x| x.parse::<i32>());
+                    match (wh_iter.next(), wh_iter.next()) {
+                        (Some(Ok(w)), Some(Ok(h))) => pico2_pi_message.wh = Some([w, h]),
+                        _ => return Err(Pico2PiMessageError::BadField),
+                    }
+                }
+                ("kc", kc) => {
+                    let keycode = kc.chars().next().and_then(KeyboardCodes::from_char)
+                        .ok_or(Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.keyboard_codes = Some(keycode);
+                }
+                ("keypressms", keypress_ms) => {
+                    pico2_pi_message.keypress_ms = keypress_ms.parse::<u64>()
+                        .map_err(|_| Pico2PiMessageError::BadField)?;
+                }
+                ("pong", token) => {
+                    pico2_pi_message.pong = Some(token.parse::<String<16>>()
+                        .map_err(|_| Pico2PiMessageError::BadField)?);
+                }
+                ("sel", index) => {
+                    pico2_pi_message.list_selected = Some(index.parse::<usize>()
+                        .map_err(|_| Pico2PiMessageError::BadField)?);
+                }
+                ("page", page) => {
+                    pico2_pi_message.list_page = Some(page.parse::<usize>()
+                        .map_err(|_| Pico2PiMessageError::BadField)?);
+                }
+                ("dialog", "yes") => pico2_pi_message.dialog_answer = Some(true),
+                ("dialog", "no") => pico2_pi_message.dialog_answer = Some(false),
+                ("dialog", _) => return Err(Pico2PiMessageError::BadField),
+                ("spin", value) => {
+                    pico2_pi_message.spinner_value = Some(value.parse::<i32>()
+                        .map_err(|_| Pico2PiMessageError::BadField)?);
+                }
+                ("layout", "ok") => pico2_pi_message.layout_result = Some(Ok(())),
+                ("layout", layout_error) => {
+                    let (code, node) = match layout_error.split_once(':') {
+                        Some((code, node)) => (code, Some(node.parse::<u8>().map_err(|_| Pico2PiMessageError::BadField)?)),
+                        None => (layout_error, None),
+                    };
+                    let err = LayoutError::from_code(code, node).ok_or(Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.layout_result = Some(Err(err));
+                }
+                ("img", image_result) => {
+                    let (id, result) = image_result.split_once(':').ok_or(Pico2PiMessageError::BadField)?;
+                    let id = id.parse::<u8>().map_err(|_| Pico2PiMessageError::BadField)?;
+                    let result = if result == "ok" {
+                        Ok(())
+                    } else {
+                        let (code, bytes) = match result.split_once(':') {
+                            Some((code, bytes)) => (code, Some(bytes.parse::<u32>().map_err(|_| Pico2PiMessageError::BadField)?)),
+                            None => (result, None),
+                        };
+                        Err(ImageError::from_code(code, bytes).ok_or(Pico2PiMessageError::BadField)?)
+                    };
+                    pico2_pi_message.image_result = Some((id, result));
+                }
+                ("qr", "ok") => pico2_pi_message.qr_result = Some(Ok(())),
+                ("qr", code) => {
+                    pico2_pi_message.qr_result = Some(Err(QrError::from_code(code).ok_or(Pico2PiMessageError::BadField)?));
+                }
+                ("hb", seq) => {
+                    pico2_pi_message.heartbeat = Some(seq.parse::<u32>()
+                        .map_err(|_| Pico2PiMessageError::BadField)?);
+                }
+                ("link", link_state) => {
+                    pico2_pi_message.link_state = Some(LinkState::try_from(link_state)
+                        .map_err(|_| Pico2PiMessageError::BadField)?);
+                }
+                ("reset", reset_reason) => {
+                    let (code, core) = match reset_reason.split_once(':') {
+                        Some((code, core)) => (code, Some(core.parse::<u8>().map_err(|_| Pico2PiMessageError::BadField)?)),
+                        None => (reset_reason, None),
+                    };
+                    let reset_reason = ResetReason::from_code(code, core).ok_or(Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.reset_reason = Some(reset_reason);
+                }
+                ("crash", crash) => {
+                    pico2_pi_message.crash = Some(CrashRecord::parse(crash).ok_or(Pico2PiMessageError::BadField)?);
+                }
+                ("setting", setting) => {
+                    let (name, value) = setting.split_once(':').ok_or(Pico2PiMessageError::BadField)?;
+                    let key = SettingKey::try_from(name).map_err(|_| Pico2PiMessageError::BadField)?;
+                    let value = value.parse::<String<24>>().map_err(|_| Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.setting = Some((key, value));
+                }
+                ("setting_reset", _) => pico2_pi_message.settings_reset = true,
+                ("setting_error", setting_error) => {
+                    let (code, key) = match setting_error.split_once(':') {
+                        Some((code, name)) => (code, Some(SettingKey::try_from(name).map_err(|_| Pico2PiMessageError::BadField)?)),
+                        None => (setting_error, None),
+                    };
+                    let err = SettingsError::from_code(code, key).ok_or(Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.setting_error = Some(err);
+                }
+                ("fw", event) => {
+                    let (code, offset) = match event.split_once(':') {
+                        Some((code, offset)) => (code, Some(offset.parse::<u32>().map_err(|_| Pico2PiMessageError::BadField)?)),
+                        None => (event, None),
+                    };
+                    let event = FirmwareEvent::from_code(code, offset).ok_or(Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.firmware = Some(event);
+                }
+                ("fw_error", update_error) => {
+                    let (code, bytes) = match update_error.split_once(':') {
+                        Some((code, bytes)) => (code, Some(bytes.parse::<u32>().map_err(|_| Pico2PiMessageError::BadField)?)),
+                        None => (update_error, None),
+                    };
+                    let err = UpdateError::from_code(code, bytes).ok_or(Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.firmware_error = Some(err);
+                }
+                ("uart", event) => {
+                    let (code, format) = match event.split_once(':') {
+                        Some((code, format)) => (code, Some(UartFormat::parse(format).ok_or(Pico2PiMessageError::BadField)?)),
+                        None => (event, None),
+                    };
+                    let event = UartEvent::from_code(code, format).ok_or(Pico2PiMessageError::BadField)?;
+                    pico2_pi_message.uart = Some(event);
+                }
+                ("stats", report) => {
+                    pico2_pi_message.stats = Some(StatsReport::parse(report).ok_or(Pico2PiMessageError::BadField)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(pico2_pi_message)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pico2PiMessageError {
+    NotAFrame,
+    LengthMismatch,
+    BadField,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_frame_has_length_and_parses_back() {
+        let message = Pico2PiMessage {
+            wh: Some([128, 128]),
+            keyboard_codes: Some(KeyboardCodes::Ok),
+            keypress_ms: 1300,
+            ..Default::default()
+        };
+        let frame = message.to_frame();
+        assert_eq!(frame.as_str(), "len=32&wh=128,128&kc=o&keypressms=1300\r\n");
+        assert_eq!(Pico2PiMessage::try_from(frame.as_str()), Ok(message));
+    }
+
+    #[test]
+    fn replies_parse_back() {
+        let messages = [
+            Pico2PiMessage { pong: Some(String::from("abc")), ..Default::default() },
+            Pico2PiMessage { list_selected: Some(3), list_page: Some(1), ..Default::default() },
+            Pico2PiMessage { dialog_answer: Some(false), ..Default::default() },
+            Pico2PiMessage { spinner_value: Some(-5), ..Default::default() },
+            Pico2PiMessage { heartbeat: Some(7), link_state: Some(LinkState::Degraded), ..Default::default() },
+            Pico2PiMessage { reset_reason: Some(ResetReason::Watchdog(Some(1))), ..Default::default() },
+        ];
+        for message in messages {
+            assert_eq!(Pico2PiMessage::try_from(message.to_frame().as_str()), Ok(message));
+        }
+    }
+
+    #[test]
+    fn rejects_broken_frames() {
+        assert_eq!(Pico2PiMessage::try_from("pong=abc\r\n"), Err(Pico2PiMessageError::NotAFrame));
+        assert_eq!(Pico2PiMessage::try_from("len=12&pong=abc\r\n"), Err(Pico2PiMessageError::LengthMismatch));
+        assert_eq!(Pico2PiMessage::try_from("len=7&wh=1,x\r\n"), Err(Pico2PiMessageError::BadField));
+    }
+}
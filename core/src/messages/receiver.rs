@@ -0,0 +1,177 @@
+use heapless::String;
+
+/// Collects bytes from uart into `\r\n` terminated lines.
+/// Multi-byte UTF-8 characters are kept aside until their last byte arrives.
+pub struct LineReceiver {
+    text_buffer: String<2048>,
+    line_ready: bool,
+    /// Bytes of an unfinished character
+    pending: [u8; 4],
+    pending_len: usize,
+}
+
+impl LineReceiver {
+    pub fn new() -> Self {
+        LineReceiver {
+            text_buffer: String::new(),
+            line_ready: false,
+            pending: [0; 4],
+            pending_len: 0,
+        }
+    }
+
+    /// Push received byte. Returns complete line without `\r\n` when terminator is received,
+    /// the line is dropped on the next call.
+    pub fn push_byte(&mut self, byte: u8) -> Result<Option<&String<2048>>, ReceiverError> {
+        if self.line_ready {
+            self.text_buffer.clear();
+            self.line_ready = false;
+        }
+        let is_continuation = byte & 0xc0 == 0x80;
+        if self.pending_len > 0 && !is_continuation {
+            // character cut short, its bytes are dropped and this byte starts over
+            error!("Incomplete UTF-8 character dropped");
+            self.pending_len = 0;
+        }
+        if self.pending_len == 0 && is_continuation {
+            return Err(ReceiverError::Utf8);
+        }
+        self.pending[self.pending_len] = byte;
+        self.pending_len += 1;
+        // 0xc0 and 0xc1 only start overlong encodings, past 0xf4 is beyond U+10FFFF
+        let char_len = match self.pending[0] {
+            0x00..=0x7f => 1,
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => {
+                self.pending_len = 0;
+                return Err(ReceiverError::Utf8);
+            }
+        };
+        if self.pending_len < char_len {
+            return Ok(None);
+        }
+        let char_len = self.pending_len;
+        self.pending_len = 0;
+        let uart_buffer_str = core::str::from_utf8(&self.pending[..char_len]).map_err(|_| ReceiverError::Utf8)?;
+        if self.text_buffer.push_str(uart_buffer_str).is_err() {
+            self.text_buffer.clear();
+            return Err(ReceiverError::Overflow);
+        }
+        if self.text_buffer.ends_with("\r\n") {
+            let line_len = self.text_buffer.len() - 2;
+            self.text_buffer.truncate(line_len);
+            self.line_ready = true;
+            return Ok(Some(&self.text_buffer));
+        }
+        Ok(None)
+    }
+}
+
+impl Default for LineReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReceiverError {
+    Utf8,
+    Overflow,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heapless::Vec;
+
+    /// Lines completed by `bytes`, errors as they come
+    fn push_all(receiver: &mut LineReceiver, bytes: &[u8]) -> Vec<Result<String<2048>, ReceiverError>, 8> {
+        let mut results = Vec::new();
+        for &byte in bytes {
+            match receiver.push_byte(byte) {
+                Ok(Some(line)) => results.push(Ok(line.clone())).unwrap(),
+                Ok(None) => {}
+                Err(err) => results.push(Err(err)).unwrap(),
+            }
+        }
+        results
+    }
+
+    fn line(text: &str) -> Result<String<2048>, ReceiverError> {
+        Ok(String::from(text))
+    }
+
+    #[test]
+    fn character_split_across_reads_is_joined() {
+        let mut receiver = LineReceiver::new();
+        let text = "ping=Жук€😀\r\n".as_bytes();
+        assert!(push_all(&mut receiver, &text[..6]).is_empty());
+        assert!(push_all(&mut receiver, &text[6..9]).is_empty());
+        assert_eq!(push_all(&mut receiver, &text[9..]).as_slice(), &[line("ping=Жук€😀")]);
+    }
+
+    #[test]
+    fn character_cut_by_a_new_lead_byte_is_dropped() {
+        let mut receiver = LineReceiver::new();
+        // Ж cut after its first byte, then a whole Ж
+        assert_eq!(push_all(&mut receiver, b"a\xd0\xd0\x96b\r\n").as_slice(), &[line("aЖb")]);
+        // € cut after two bytes by ASCII
+        assert_eq!(push_all(&mut receiver, b"a\xe2\x82b\r\n").as_slice(), &[line("ab")]);
+    }
+
+    #[test]
+    fn character_cut_by_the_line_end_is_dropped() {
+        let mut receiver = LineReceiver::new();
+        assert_eq!(push_all(&mut receiver, b"ab\xd0\r\nc\r\n").as_slice(), &[line("ab"), line("c")]);
+    }
+
+    #[test]
+    fn stray_continuation_bytes_are_errors() {
+        let mut receiver = LineReceiver::new();
+        assert_eq!(push_all(&mut receiver, b"a\x96\x80b\r\n").as_slice(), &[
+            Err(ReceiverError::Utf8),
+            Err(ReceiverError::Utf8),
+            line("ab"),
+        ]);
+    }
+
+    #[test]
+    fn invalid_lead_bytes_are_errors() {
+        let mut receiver = LineReceiver::new();
+        for lead in [0xc0, 0xc1, 0xf5, 0xf8, 0xff] {
+            assert_eq!(receiver.push_byte(lead), Err(ReceiverError::Utf8));
+        }
+        // the continuation byte of an overlong `/` is stray after its rejected lead
+        assert_eq!(push_all(&mut receiver, b"\xc0\xafx\r\n").as_slice(), &[
+            Err(ReceiverError::Utf8),
+            Err(ReceiverError::Utf8),
+            line("x"),
+        ]);
+    }
+
+    #[test]
+    fn surrogates_are_errors() {
+        let mut receiver = LineReceiver::new();
+        assert_eq!(push_all(&mut receiver, b"\xed\xa0\x80x\r\n").as_slice(), &[Err(ReceiverError::Utf8), line("x")]);
+    }
+
+    #[test]
+    fn overflow_in_the_middle_of_a_character_starts_a_new_line() {
+        let mut receiver = LineReceiver::new();
+        for _ in 0..2047 {
+            assert_eq!(receiver.push_byte(b'a'), Ok(None));
+        }
+        assert_eq!(receiver.push_byte(0xd0), Ok(None));
+        assert_eq!(receiver.push_byte(0x96), Err(ReceiverError::Overflow));
+        assert_eq!(push_all(&mut receiver, b"ok\r\n").as_slice(), &[line("ok")]);
+    }
+
+    #[test]
+    fn line_is_dropped_on_the_next_byte() {
+        let mut receiver = LineReceiver::new();
+        assert_eq!(push_all(&mut receiver, b"one\r\ntwo\r\n\r\n").as_slice(), &[line("one"), line("two"), line("")]);
+    }
+}
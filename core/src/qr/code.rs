@@ -0,0 +1,768 @@
+use core::cmp::max;
+use core::convert::TryFrom;
+use qr::reed_solomon::{divisor, remainder, MAX_DEGREE};
+
+/// Largest version, its 57 modules still get 2 pixels each on a 128 pixel display
+pub const MAX_VERSION: u8 = 10;
+/// Side of the largest code in modules
+pub const MAX_SIZE: usize = 17 + 4 * MAX_VERSION as usize;
+/// Longest data, version 10 with low error correction
+pub const MAX_DATA_BYTES: usize = 271;
+/// Data and error correction codewords of the largest version
+const MAX_CODEWORDS: usize = 346;
+const MODULE_BYTES: usize = (MAX_SIZE * MAX_SIZE).div_ceil(8);
+
+// index 0 is unused, tables go up to MAX_VERSION
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 11]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28],
+];
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 11]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8],
+];
+/// Most error correction blocks of a version up to MAX_VERSION
+const MAX_BLOCKS: usize = 8;
+
+const PENALTY_N1: i32 = 3;
+const PENALTY_N2: i32 = 3;
+const PENALTY_N3: i32 = 40;
+const PENALTY_N4: i32 = 10;
+
+/// Share of codewords which can be restored: about 7, 15, 25 and 30 percent
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EccLevel {
+    Low,
+    Medium,
+    Quartile,
+    High,
+}
+
+impl EccLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EccLevel::Low => "l",
+            EccLevel::Medium => "m",
+            EccLevel::Quartile => "q",
+            EccLevel::High => "h",
+        }
+    }
+
+    fn ordinal(self) -> usize {
+        self as usize
+    }
+
+    /// Two bits of the format information
+    fn format_bits(self) -> u32 {
+        match self {
+            EccLevel::Low => 1,
+            EccLevel::Medium => 0,
+            EccLevel::Quartile => 3,
+            EccLevel::High => 2,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for EccLevel {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "l" => Ok(EccLevel::Low),
+            "m" => Ok(EccLevel::Medium),
+            "q" => Ok(EccLevel::Quartile),
+            "h" => Ok(EccLevel::High),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum QrError {
+    /// Does not fit into `MAX_VERSION` at the requested error correction
+    TooLong,
+    Empty,
+    /// `qr_b64` value is not valid base64
+    BadData,
+}
+
+impl QrError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            QrError::TooLong => "too_long",
+            QrError::Empty => "empty",
+            QrError::BadData => "bad_data",
+        }
+    }
+
+    /// Reverse of `code`, used on the Pi side
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "too_long" => Some(QrError::TooLong),
+            "empty" => Some(QrError::Empty),
+            "bad_data" => Some(QrError::BadData),
+            _ => None,
+        }
+    }
+}
+
+/// QR code of bytes in byte mode, built without allocation. Follows the reference encoder
+/// by Project Nayuki step by step, so the same data gives the same modules.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct QrCode {
+    version: u8,
+    size: usize,
+    ecc: EccLevel,
+    mask: u8,
+    /// Dark modules, row by row
+    modules: [u8; MODULE_BYTES],
+}
+
+impl QrCode {
+    /// Smallest version which fits `data` at `ecc`, error correction is raised while the version stays the same
+    pub fn encode(data: &[u8], ecc: EccLevel) -> Result<Self, QrError> {
+        if data.is_empty() {
+            return Err(QrError::Empty);
+        }
+        let mut version = 1;
+        let used_bits = loop {
+            let used_bits = 4 + char_count_bits(version) + data.len() * 8;
+            if used_bits <= num_data_codewords(version, ecc) * 8 {
+                break used_bits;
+            }
+            if version == MAX_VERSION {
+                return Err(QrError::TooLong);
+            }
+            version += 1;
+        };
+        let mut ecc = ecc;
+        for higher in [EccLevel::Medium, EccLevel::Quartile, EccLevel::High] {
+            if higher > ecc && used_bits <= num_data_codewords(version, higher) * 8 {
+                ecc = higher;
+            }
+        }
+
+        // mode, count, data, up to 4 terminator bits, zeros to a byte, then pad bytes
+        let data_len = num_data_codewords(version, ecc);
+        let mut codewords = [0u8; MAX_CODEWORDS];
+        let mut bits = BitWriter { buffer: &mut codewords[..data_len], len: 0 };
+        bits.append(0b0100, 4);
+        bits.append(data.len() as u32, char_count_bits(version));
+        for byte in data {
+            bits.append(*byte as u32, 8);
+        }
+        let data_end = (bits.len + 4).min(data_len * 8).div_ceil(8);
+        for (index, byte) in codewords[data_end..data_len].iter_mut().enumerate() {
+            *byte = if index % 2 == 0 { 0xec } else { 0x11 };
+        }
+
+        let mut qr = Builder {
+            qr: QrCode {
+                version,
+                size: 17 + 4 * version as usize,
+                ecc,
+                mask: 0,
+                modules: [0; MODULE_BYTES],
+            },
+            function: [0; MODULE_BYTES],
+        };
+        qr.draw_function_patterns();
+        let all_codewords = qr.add_ecc_and_interleave(&codewords[..data_len]);
+        qr.draw_codewords(&all_codewords[..num_raw_data_modules(version) / 8]);
+
+        // mask with the lowest penalty, each mask is applied twice to undo it
+        let mut best = (i32::MAX, 0);
+        for mask in 0..8 {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(mask);
+            let penalty = qr.qr.penalty_score();
+            if penalty < best.0 {
+                best = (penalty, mask);
+            }
+            qr.apply_mask(mask);
+        }
+        qr.qr.mask = best.1;
+        qr.apply_mask(best.1);
+        qr.draw_format_bits(best.1);
+        Ok(qr.qr)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Side in modules, without quiet zone
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn ecc(&self) -> EccLevel {
+        self.ecc
+    }
+
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// True for dark, modules outside the code are light
+    pub fn module(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && get_bit(&self.modules, y * self.size + x)
+    }
+
+    /// Long runs, 2x2 blocks, finder-like patterns and dark/light imbalance, lower is better
+    fn penalty_score(&self) -> i32 {
+        let size = self.size;
+        let mut result = 0;
+        for transposed in [false, true] {
+            for line in 0..size {
+                let module = |i: usize| if transposed { self.module(line, i) } else { self.module(i, line) };
+                let mut run_color = false;
+                let mut run_len = 0;
+                let mut history = FinderPenalty::new(size as i32);
+                for i in 0..size {
+                    if module(i) == run_color {
+                        run_len += 1;
+                        if run_len == 5 {
+                            result += PENALTY_N1;
+                        } else if run_len > 5 {
+                            result += 1;
+                        }
+                    } else {
+                        history.add(run_len);
+                        if !run_color {
+                            result += history.count_patterns() * PENALTY_N3;
+                        }
+                        run_color = module(i);
+                        run_len = 1;
+                    }
+                }
+                result += history.terminate_and_count(run_color, run_len) * PENALTY_N3;
+            }
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.module(x, y);
+                if color == self.module(x + 1, y) && color == self.module(x, y + 1) && color == self.module(x + 1, y + 1) {
+                    result += PENALTY_N2;
+                }
+            }
+        }
+
+        // smallest k with (45 - 5k)% <= dark share <= (55 + 5k)%
+        let dark = (0..size * size).filter(|index| get_bit(&self.modules, *index)).count() as i32;
+        let total = (size * size) as i32;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        result + k * PENALTY_N4
+    }
+}
+
+/// Code being built with the modules data does not go into: finder, timing
+/// and alignment patterns, format and version bits
+struct Builder {
+    qr: QrCode,
+    function: [u8; MODULE_BYTES],
+}
+
+impl Builder {
+    fn set_function_module(&mut self, x: usize, y: usize, dark: bool) {
+        let index = y * self.qr.size + x;
+        set_bit(&mut self.qr.modules, index, dark);
+        set_bit(&mut self.function, index, true);
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.qr.size;
+        for i in 0..size {
+            self.set_function_module(6, i, i % 2 == 0);
+            self.set_function_module(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder_pattern(3, 3);
+        self.draw_finder_pattern(size as i32 - 4, 3);
+        self.draw_finder_pattern(3, size as i32 - 4);
+
+        let (positions, count) = self.alignment_pattern_positions();
+        for i in 0..count {
+            for j in 0..count {
+                // corners with finder patterns
+                if (i == 0 && (j == 0 || j == count - 1)) || (i == count - 1 && j == 0) {
+                    continue;
+                }
+                self.draw_alignment_pattern(positions[i], positions[j]);
+            }
+        }
+
+        // reserved, real format bits come with the mask
+        self.draw_format_bits(0);
+        self.draw_version();
+    }
+
+    fn draw_format_bits(&mut self, mask: u8) {
+        let data = self.qr.ecc.format_bits() << 3 | mask as u32;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = (data << 10 | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+
+        // around the top left finder
+        for i in 0..6 {
+            self.set_function_module(8, i, bit(i));
+        }
+        self.set_function_module(8, 7, bit(6));
+        self.set_function_module(8, 8, bit(7));
+        self.set_function_module(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function_module(14 - i, 8, bit(i));
+        }
+
+        // copy next to the other two finders
+        let size = self.qr.size;
+        for i in 0..8 {
+            self.set_function_module(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function_module(8, size - 15 + i, bit(i));
+        }
+        // always dark
+        self.set_function_module(8, size - 8, true);
+    }
+
+    /// Versions 7 and up carry their number twice
+    fn draw_version(&mut self) {
+        if self.qr.version < 7 {
+            return;
+        }
+        let version = self.qr.version as u32;
+        let mut rem = version;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1f25);
+        }
+        let bits = version << 12 | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.qr.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function_module(a, b, dark);
+            self.set_function_module(b, a, dark);
+        }
+    }
+
+    /// 9x9 including the separator, clipped at the edges
+    fn draw_finder_pattern(&mut self, x: i32, y: i32) {
+        for dy in -4..=4 {
+            for dx in -4..=4 {
+                let (xx, yy) = (x + dx, y + dy);
+                if (0..self.qr.size as i32).contains(&xx) && (0..self.qr.size as i32).contains(&yy) {
+                    let distance = max(dx.abs(), dy.abs());
+                    self.set_function_module(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment_pattern(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = max(dx.abs(), dy.abs()) != 1;
+                self.set_function_module((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    /// Centers of alignment patterns, the same list for rows and columns
+    fn alignment_pattern_positions(&self) -> ([usize; 3], usize) {
+        let mut positions = [0; 3];
+        if self.qr.version == 1 {
+            return (positions, 0);
+        }
+        let version = self.qr.version as usize;
+        let count = version / 7 + 2;
+        let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+        positions[0] = 6;
+        for i in 1..count {
+            positions[count - i] = self.qr.size - 7 - (i - 1) * step;
+        }
+        (positions, count)
+    }
+
+    /// Split data into blocks, add error correction to each and interleave them
+    fn add_ecc_and_interleave(&self, data: &[u8]) -> [u8; MAX_CODEWORDS] {
+        let (version, ecc) = (self.qr.version as usize, self.qr.ecc.ordinal());
+        let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[ecc][version] as usize;
+        let block_ecc_len = ECC_CODEWORDS_PER_BLOCK[ecc][version] as usize;
+        let raw_codewords = num_raw_data_modules(self.qr.version) / 8;
+        let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+        let short_data_len = raw_codewords / num_blocks - block_ecc_len;
+
+        let divisor = divisor(block_ecc_len);
+        let mut block_starts = [0usize; MAX_BLOCKS + 1];
+        let mut block_eccs = [[0u8; MAX_DEGREE]; MAX_BLOCKS];
+        for block in 0..num_blocks {
+            let data_len = short_data_len + if block < num_short_blocks { 0 } else { 1 };
+            block_starts[block + 1] = block_starts[block] + data_len;
+            block_eccs[block] = remainder(&data[block_starts[block]..block_starts[block + 1]], &divisor, block_ecc_len);
+        }
+
+        let mut result = [0u8; MAX_CODEWORDS];
+        let mut len = 0;
+        // short blocks have no byte at `short_data_len`
+        for i in 0..=short_data_len {
+            for block in 0..num_blocks {
+                let start = block_starts[block];
+                if start + i < block_starts[block + 1] {
+                    result[len] = data[start + i];
+                    len += 1;
+                }
+            }
+        }
+        for i in 0..block_ecc_len {
+            for block_ecc in &block_eccs[..num_blocks] {
+                result[len] = block_ecc[i];
+                len += 1;
+            }
+        }
+        result
+    }
+
+    /// Zigzag from the bottom right corner in two module wide columns, skipping function modules
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.qr.size as i32;
+        let mut bit = 0;
+        let mut right = size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vertical } else { vertical } as usize;
+                    let index = y * self.qr.size + x;
+                    if !get_bit(&self.function, index) && bit < data.len() * 8 {
+                        set_bit(&mut self.qr.modules, index, (data[bit >> 3] >> (7 - (bit & 7))) & 1 != 0);
+                        bit += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    /// XOR of data modules with mask pattern, applying it again undoes it
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.qr.size {
+            for x in 0..self.qr.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = y * self.qr.size + x;
+                if invert && !get_bit(&self.function, index) {
+                    let dark = get_bit(&self.qr.modules, index);
+                    set_bit(&mut self.qr.modules, index, !dark);
+                }
+            }
+        }
+    }
+}
+
+/// Run lengths of a row or column, newest first, to find 1:1:3:1:1 patterns with light space around
+struct FinderPenalty {
+    size: i32,
+    history: [i32; 7],
+}
+
+impl FinderPenalty {
+    fn new(size: i32) -> Self {
+        FinderPenalty { size, history: [0; 7] }
+    }
+
+    fn add(&mut self, mut run_len: i32) {
+        // light border before the first run
+        if self.history[0] == 0 {
+            run_len += self.size;
+        }
+        self.history.copy_within(0..6, 1);
+        self.history[0] = run_len;
+    }
+
+    /// Right after a light run was added, 0, 1 or 2
+    fn count_patterns(&self) -> i32 {
+        let h = &self.history;
+        let n = h[1];
+        let core = n > 0 && h[2] == n && h[3] == n * 3 && h[4] == n && h[5] == n;
+        (core && h[0] >= n * 4 && h[6] >= n) as i32 + (core && h[6] >= n * 4 && h[0] >= n) as i32
+    }
+
+    /// End of the line with light border after it
+    fn terminate_and_count(mut self, run_color: bool, mut run_len: i32) -> i32 {
+        if run_color {
+            self.add(run_len);
+            run_len = 0;
+        }
+        self.add(run_len + self.size);
+        self.count_patterns()
+    }
+}
+
+struct BitWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> BitWriter<'a> {
+    /// Lowest `count` bits of `value`, most significant first
+    fn append(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            set_bit(self.buffer, self.len, (value >> i) & 1 != 0);
+            self.len += 1;
+        }
+    }
+}
+
+fn char_count_bits(version: u8) -> usize {
+    if version <= 9 { 8 } else { 16 }
+}
+
+/// Modules left for data and error correction after function patterns
+fn num_raw_data_modules(version: u8) -> usize {
+    let version = version as usize;
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let num_align = version / 7 + 2;
+        result -= (25 * num_align - 10) * num_align - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn num_data_codewords(version: u8, ecc: EccLevel) -> usize {
+    let (v, e) = (version as usize, ecc.ordinal());
+    num_raw_data_modules(version) / 8 - ECC_CODEWORDS_PER_BLOCK[e][v] as usize * NUM_ERROR_CORRECTION_BLOCKS[e][v] as usize
+}
+
+/// Bit `index` of a buffer, most significant bit of each byte first
+fn get_bit(buffer: &[u8], index: usize) -> bool {
+    buffer[index >> 3] & (0x80 >> (index & 7)) != 0
+}
+
+fn set_bit(buffer: &mut [u8], index: usize, value: bool) {
+    if value {
+        buffer[index >> 3] |= 0x80 >> (index & 7);
+    } else {
+        buffer[index >> 3] &= !(0x80 >> (index & 7));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics_core::pixelcolor::Rgb565;
+    use embedded_graphics_core::prelude::RgbColor;
+    use utils::crc32::crc32;
+    use utils::test_display::TestDisplay;
+    use widgets::qr::draw_qr;
+
+    /// CRC-32 of the modules packed row by row, most significant bit first
+    fn modules_crc(code: &QrCode) -> u32 {
+        let mut bits = [0u8; MODULE_BYTES];
+        for y in 0..code.size() {
+            for x in 0..code.size() {
+                set_bit(&mut bits, y * code.size() + x, code.module(x, y));
+            }
+        }
+        crc32(&bits[..(code.size() * code.size()).div_ceil(8)])
+    }
+
+    fn assert_rows(code: &QrCode, rows: &[&str]) {
+        assert_eq!(code.size(), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            let actual: heapless::String<64> = (0..code.size()).map(|x| if code.module(x, y) { '#' } else { '.' }).collect();
+            assert_eq!(actual.as_str(), *row, "row {}", y);
+        }
+    }
+
+    // Expected values are from the Project Nayuki reference encoder (qrcodegen 1.8.0, byte mode,
+    // automatic mask, boosted error correction)
+
+    #[test]
+    fn hello_low() {
+        let code = QrCode::encode(b"HELLO", EccLevel::Low).unwrap();
+        assert_eq!((code.version(), code.ecc(), code.mask()), (1, EccLevel::High, 4));
+        assert_rows(&code, &[
+            "#######..#..#.#######",
+            "#.....#.###...#.....#",
+            "#.###.#..#.##.#.###.#",
+            "#.###.#..###..#.###.#",
+            "#.###.#..##.#.#.###.#",
+            "#.....#.###...#.....#",
+            "#######.#.#.#.#######",
+            "........###.#........",
+            "....####.#..#.##...#.",
+            "#.###..###...###.####",
+            "##..####.##.#..##..#.",
+            ".###.#..#.###.#.#....",
+            "####..######......##.",
+            "........###......#.##",
+            "#######.#...###..#.#.",
+            "#.....#.####.###...#.",
+            "#.###.#.#..#...##.#.#",
+            "#.###.#..#.#..#..#.##",
+            "#.###.#..#.#.#.###...",
+            "#.....#....#.##......",
+            "#######..#.##..##.#.#",
+        ]);
+        assert_eq!(modules_crc(&code), 0x8aa8_100e);
+    }
+
+    #[test]
+    fn url_medium() {
+        let code = QrCode::encode(b"https://pico.local/", EccLevel::Medium).unwrap();
+        assert_eq!((code.version(), code.ecc(), code.mask()), (2, EccLevel::Quartile, 6));
+        assert_rows(&code, &[
+            "#######..#....#...#######",
+            "#.....#.######..#.#.....#",
+            "#.###.#...####.##.#.###.#",
+            "#.###.#.###..##.#.#.###.#",
+            "#.###.#.##..###.#.#.###.#",
+            "#.....#...##.##...#.....#",
+            "#######.#.#.#.#.#.#######",
+            "........###.##.#.........",
+            ".#.####.###.##.####.##.#.",
+            "#.##.......#.###...#####.",
+            ".##..##.#.#.##.###..##..#",
+            "##..#...##.#.#.#..#..####",
+            "..#...###.##.#..#.#.....#",
+            "##..##..#.##.####...#..#.",
+            "##.######...#####.#.#####",
+            "#.......###..#...#.#.##.#",
+            "#.#...#.##.#..#.#####.##.",
+            "........#.#..#..#...#.##.",
+            "#######...##....#.#.#...#",
+            "#.....#.#...##.##...#..#.",
+            "#.###.#.#.#....######...#",
+            "#.###.#.#.#......##....##",
+            "#.###.#....##.#.##..#####",
+            "#.....#.#...##.....##.###",
+            "#######..##.##.##....#..#",
+        ]);
+    }
+
+    #[test]
+    fn binary_high() {
+        let code = QrCode::encode(&[0, 255, 10, 13, 128], EccLevel::High).unwrap();
+        assert_eq!((code.version(), code.ecc(), code.mask()), (1, EccLevel::High, 1));
+        assert_eq!(modules_crc(&code), 0x00bb_ac63);
+    }
+
+    #[test]
+    fn larger_versions() {
+        let code = QrCode::encode(b"WIFI:S:pico;T:WPA;P:secret;;", EccLevel::Quartile).unwrap();
+        assert_eq!((code.version(), code.ecc(), code.mask()), (3, EccLevel::Quartile, 3));
+        assert_eq!(modules_crc(&code), 0x9416_e4d7);
+
+        // with version information and several error correction blocks
+        let mut data = [0u8; 150];
+        for (index, byte) in data.iter_mut().enumerate() {
+            *byte = (index * 7 % 251) as u8;
+        }
+        let code = QrCode::encode(&data, EccLevel::Medium).unwrap();
+        assert_eq!((code.version(), code.ecc(), code.mask()), (8, EccLevel::Medium, 2));
+        assert_eq!(modules_crc(&code), 0x7ce8_93f8);
+
+        let mut data = [0u8; MAX_DATA_BYTES];
+        for (index, byte) in data.iter_mut().enumerate() {
+            *byte = b'a' + (index % 26) as u8;
+        }
+        let code = QrCode::encode(&data, EccLevel::Low).unwrap();
+        assert_eq!((code.version(), code.ecc(), code.mask()), (MAX_VERSION, EccLevel::Low, 2));
+        assert_eq!(modules_crc(&code), 0x96db_a7ae);
+    }
+
+    #[test]
+    fn data_limits() {
+        assert_eq!(QrCode::encode(b"", EccLevel::Low), Err(QrError::Empty));
+        assert_eq!(QrCode::encode(&[b'x'; MAX_DATA_BYTES + 1], EccLevel::Low), Err(QrError::TooLong));
+        assert!(QrCode::encode(&[b'x'; 200], EccLevel::High).is_err());
+    }
+
+    /// Error correction and mask from the format information of rendered modules, both copies
+    /// have to match a valid BCH code word
+    fn decode_format(module: &dyn Fn(usize, usize) -> bool, size: usize) -> (EccLevel, u8) {
+        let mut first = 0u32;
+        let mut second = 0u32;
+        for i in 0..15 {
+            let (x, y) = match i {
+                0..=5 => (8, i),
+                6 => (8, 7),
+                7 => (8, 8),
+                8 => (7, 8),
+                _ => (14 - i, 8),
+            };
+            first |= (module(x, y) as u32) << i;
+            let (x, y) = if i < 8 { (size - 1 - i, 8) } else { (8, size - 15 + i) };
+            second |= (module(x, y) as u32) << i;
+        }
+        assert_eq!(first, second);
+        for ecc in [EccLevel::Low, EccLevel::Medium, EccLevel::Quartile, EccLevel::High] {
+            for mask in 0..8 {
+                let data = ecc.format_bits() << 3 | mask as u32;
+                // BCH(15,5) with generator x^10 + x^8 + x^5 + x^4 + x^2 + x + 1
+                let mut rem = data << 10;
+                for bit in (10..15).rev() {
+                    if rem & (1 << bit) != 0 {
+                        rem ^= 0x537 << (bit - 10);
+                    }
+                }
+                if (data << 10 | rem) ^ 0x5412 == first {
+                    return (ecc, mask);
+                }
+            }
+        }
+        panic!("no valid format information: {:015b}", first);
+    }
+
+    #[test]
+    fn rendered_code_reads_back() {
+        for (data, ecc) in [(&b"HELLO"[..], EccLevel::Low), (&b"https://pico.local/"[..], EccLevel::Medium), (&[7u8; 150][..], EccLevel::Medium)] {
+            let code = QrCode::encode(data, ecc).unwrap();
+            let mut display: TestDisplay<128, 128> = TestDisplay::new();
+            draw_qr(&mut display, &code).unwrap();
+
+            // origin is the dark top left corner of the finder, the code is centered
+            let size = code.size();
+            let origin = (0..128).find(|xy| display.pixel(*xy, *xy) == Rgb565::BLACK).unwrap();
+            let pitch = (128 - 2 * origin) / size;
+            assert!(pitch >= 2, "version {} needs 2 pixels per module", code.version());
+            // quiet zone of 2 modules at least
+            assert!(origin >= 2 * pitch);
+            assert!((0..128).all(|y| display.pixel(origin - 1, y) == Rgb565::WHITE));
+
+            let module = |x: usize, y: usize| display.pixel(origin + x * pitch + pitch / 2, origin + y * pitch + pitch / 2) == Rgb565::BLACK;
+            for y in 0..size {
+                for x in 0..size {
+                    assert_eq!(module(x, y), code.module(x, y), "module {},{}", x, y);
+                }
+            }
+            assert_eq!(decode_format(&module, size), (code.ecc(), code.mask()));
+        }
+    }
+}
@@ -0,0 +1,44 @@
+/// Most error correction codewords of a block, up to version 10
+pub const MAX_DEGREE: usize = 30;
+
+/// Generator polynomial of `degree`, highest coefficient left out as it is always 1
+pub fn divisor(degree: usize) -> [u8; MAX_DEGREE] {
+    let mut result = [0u8; MAX_DEGREE];
+    result[degree - 1] = 1;
+    // product of (x - r^i) for i in 0..degree, r = 0x02 is the generator of GF(2^8/0x11D)
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = multiply(root, 0x02);
+    }
+    result
+}
+
+/// Error correction codewords of `data`, the first `degree` bytes of the result
+pub fn remainder(data: &[u8], divisor: &[u8; MAX_DEGREE], degree: usize) -> [u8; MAX_DEGREE] {
+    let mut result = [0u8; MAX_DEGREE];
+    for byte in data {
+        let factor = byte ^ result[0];
+        result.copy_within(1..degree, 0);
+        result[degree - 1] = 0;
+        for (x, y) in result[..degree].iter_mut().zip(divisor.iter()) {
+            *x ^= multiply(*y, factor);
+        }
+    }
+    result
+}
+
+/// Product in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn multiply(x: u8, y: u8) -> u8 {
+    let mut z = 0u16;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= ((y as u16 >> i) & 1) * x as u16;
+    }
+    z as u8
+}
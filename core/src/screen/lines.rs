@@ -0,0 +1,51 @@
+use heapless::String;
+use messages::pi_2_pico_message::Pi2PicoMessage;
+use utils::truncate::truncated;
+
+pub const LINES_COUNT: usize = 10;
+pub const FIRST_DATA_LINE: usize = 1;
+pub const DATA_LINES_COUNT: usize = 8;
+/// Bytes of text in one line, Cyrillic takes two per character
+pub const LINE_CAPACITY: usize = 100;
+/// Bytes of the ip in the status line and of a title, 15 Cyrillic characters
+pub const TITLE_CAPACITY: usize = 30;
+
+/// Text of every screen line and whether the line is under cursor.
+/// core0 owns it, core1 gets pointer through the sio fifo and draws it.
+pub type ScreenLines = [Option<(String<LINE_CAPACITY>, bool)>; LINES_COUNT];
+
+/// Put screen update from the Pi to lines:
+/// first line - ip and battery, lines 2-9 - data lines, last line - title and paginator
+pub fn apply_pi_2_pico_message(lines: &mut ScreenLines, message: &Pi2PicoMessage) {
+    if let Some((ip, battery)) = &message.ip_and_battery {
+        let mut line: String<LINE_CAPACITY> = truncated(ip.as_str());
+        _ = line.push(' ');
+        _ = line.push_str(battery.as_str());
+        _ = line.push('%');
+        lines[0] = Some((line, false));
+    }
+
+    if let Some((title, paginator)) = &message.title_and_paginator {
+        let mut line: String<LINE_CAPACITY> = truncated(title.as_str());
+        _ = line.push(' ');
+        _ = line.push_str(paginator.as_str());
+        lines[LINES_COUNT - 1] = Some((line, false));
+    }
+
+    if let Some(data_lines) = &message.data_lines {
+        for index in 0..DATA_LINES_COUNT {
+            lines[FIRST_DATA_LINE + index] = match data_lines.get(index) {
+                Some(Some(data_line)) => Some((truncated(data_line.as_str()), false)),
+                _ => None,
+            };
+        }
+    }
+
+    if let Some(cursor_index) = message.cursor_index {
+        for index in 0..DATA_LINES_COUNT {
+            if let Some(line) = &mut lines[FIRST_DATA_LINE + index] {
+                line.1 = index as isize == cursor_index;
+            }
+        }
+    }
+}
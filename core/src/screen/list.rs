@@ -0,0 +1,171 @@
+use heapless::{String, Vec};
+use input::keyboard_codes::KeyboardCodes;
+use screen::lines::{ScreenLines, DATA_LINES_COUNT, FIRST_DATA_LINE, LINES_COUNT, LINE_CAPACITY, TITLE_CAPACITY};
+
+/// Items the device keeps for one list, 4 screen pages
+pub const LIST_CAPACITY: usize = 32;
+/// Bytes of one item, 20 Cyrillic characters
+pub const ITEM_CAPACITY: usize = 40;
+
+/// What the Pi should know after a key press on the list
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ListEvent {
+    /// `Ok` pressed, index of the item under cursor
+    Selected(usize),
+    /// Cursor moved to another page, 0 based
+    PageChanged(usize),
+}
+
+/// Menu held on the device: cursor moves and page scrolling happen locally without the Pi.
+/// Up/Down move the cursor, Left/Right jump a page, Ok selects.
+/// Uses data lines for items and the last line for title and paginator, same as `Pi2PicoMessage`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListWidget {
+    title: String<TITLE_CAPACITY>,
+    items: Vec<String<ITEM_CAPACITY>, LIST_CAPACITY>,
+    cursor: usize,
+}
+
+impl ListWidget {
+    pub fn new(title: String<TITLE_CAPACITY>, items: Vec<String<ITEM_CAPACITY>, LIST_CAPACITY>, cursor: usize) -> Self {
+        let cursor = cursor.min(items.len().saturating_sub(1));
+        ListWidget { title, items, cursor }
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn page(&self) -> usize {
+        self.cursor / DATA_LINES_COUNT
+    }
+
+    pub fn pages(&self) -> usize {
+        self.items.len().div_ceil(DATA_LINES_COUNT).max(1)
+    }
+
+    /// Move cursor, `None` when nothing the Pi cares about happened
+    pub fn on_key(&mut self, keycode: KeyboardCodes) -> Option<ListEvent> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let last = self.items.len() - 1;
+        let page = self.page();
+        match keycode {
+            KeyboardCodes::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyboardCodes::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyboardCodes::Left => self.cursor = self.cursor.saturating_sub(DATA_LINES_COUNT),
+            KeyboardCodes::Right => self.cursor = (self.cursor + DATA_LINES_COUNT).min(last),
+            KeyboardCodes::Ok => return Some(ListEvent::Selected(self.cursor)),
+        }
+        if self.page() != page {
+            return Some(ListEvent::PageChanged(self.page()));
+        }
+        None
+    }
+
+    /// Put visible page into data lines and title with `page/pages` into the last line
+    pub fn apply(&self, lines: &mut ScreenLines) {
+        let first_item = self.page() * DATA_LINES_COUNT;
+        for index in 0..DATA_LINES_COUNT {
+            let item_index = first_item + index;
+            lines[FIRST_DATA_LINE + index] = self.items.get(item_index)
+                .map(|item| (String::from(item.as_str()), item_index == self.cursor));
+        }
+
+        let mut line: String<LINE_CAPACITY> = String::from(self.title.as_str());
+        _ = line.push(' ');
+        _ = line.push_str(String::<10>::from((self.page() + 1) as u32).as_str());
+        _ = line.push('/');
+        _ = line.push_str(String::<10>::from(self.pages() as u32).as_str());
+        lines[LINES_COUNT - 1] = Some((line, false));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(count: usize, cursor: usize) -> ListWidget {
+        let items = (0..count).map(|index| String::from(index as u32)).collect();
+        ListWidget::new(String::from("Menu"), items, cursor)
+    }
+
+    fn texts(lines: &ScreenLines) -> [Option<(&str, bool)>; LINES_COUNT] {
+        let mut texts = [None; LINES_COUNT];
+        for (text, line) in texts.iter_mut().zip(lines) {
+            *text = line.as_ref().map(|(line, is_cursor)| (line.as_str(), *is_cursor));
+        }
+        texts
+    }
+
+    #[test]
+    fn cursor_stops_at_the_ends() {
+        let mut list = list(3, 0);
+        assert_eq!(list.on_key(KeyboardCodes::Up), None);
+        assert_eq!(list.cursor(), 0);
+        assert_eq!(list.on_key(KeyboardCodes::Down), None);
+        assert_eq!(list.on_key(KeyboardCodes::Down), None);
+        assert_eq!(list.on_key(KeyboardCodes::Down), None);
+        assert_eq!(list.cursor(), 2);
+        assert_eq!(list.on_key(KeyboardCodes::Right), None);
+        assert_eq!(list.cursor(), 2);
+        assert_eq!(list.on_key(KeyboardCodes::Left), None);
+        assert_eq!(list.cursor(), 0);
+    }
+
+    #[test]
+    fn moving_across_a_page_border_reports_the_page() {
+        let mut list = list(20, 7);
+        assert_eq!(list.on_key(KeyboardCodes::Down), Some(ListEvent::PageChanged(1)));
+        assert_eq!(list.on_key(KeyboardCodes::Up), Some(ListEvent::PageChanged(0)));
+        assert_eq!(list.on_key(KeyboardCodes::Right), Some(ListEvent::PageChanged(1)));
+        assert_eq!(list.cursor(), 15);
+        // the last page is shorter, the cursor stops on the last item
+        assert_eq!(list.on_key(KeyboardCodes::Right), Some(ListEvent::PageChanged(2)));
+        assert_eq!(list.cursor(), 19);
+        assert_eq!(list.on_key(KeyboardCodes::Right), None);
+        assert_eq!((list.page(), list.pages()), (2, 3));
+        assert_eq!(list.on_key(KeyboardCodes::Left), Some(ListEvent::PageChanged(1)));
+        assert_eq!(list.cursor(), 11);
+    }
+
+    #[test]
+    fn ok_selects_the_item_under_the_cursor() {
+        let mut list = list(20, 12);
+        assert_eq!(list.on_key(KeyboardCodes::Ok), Some(ListEvent::Selected(12)));
+        assert_eq!(list.cursor(), 12);
+    }
+
+    #[test]
+    fn empty_list_ignores_keys() {
+        let mut list = list(0, 5);
+        assert_eq!(list.cursor(), 0);
+        assert_eq!(list.pages(), 1);
+        assert_eq!(list.on_key(KeyboardCodes::Ok), None);
+    }
+
+    #[test]
+    fn page_goes_to_the_data_lines_with_the_paginator() {
+        let mut lines: ScreenLines = Default::default();
+        list(20, 9).apply(&mut lines);
+        let texts = texts(&lines);
+        assert_eq!(texts[FIRST_DATA_LINE], Some(("8", false)));
+        assert_eq!(texts[FIRST_DATA_LINE + 1], Some(("9", true)));
+        assert_eq!(texts[LINES_COUNT - 1], Some(("Menu 2/3", false)));
+    }
+
+    #[test]
+    fn shorter_list_clears_the_rest_and_clamps_the_cursor() {
+        let mut lines: ScreenLines = Default::default();
+        list(20, 0).apply(&mut lines);
+        let shorter = list(3, 15);
+        assert_eq!(shorter.cursor(), 2);
+        shorter.apply(&mut lines);
+        let texts = texts(&lines);
+        assert_eq!(texts[FIRST_DATA_LINE..FIRST_DATA_LINE + 4], [Some(("0", false)), Some(("1", false)), Some(("2", true)), None]);
+        assert!(texts[FIRST_DATA_LINE + 3..LINES_COUNT - 1].iter().all(Option::is_none));
+        assert_eq!(texts[LINES_COUNT - 1], Some(("Menu 1/1", false)));
+    }
+}
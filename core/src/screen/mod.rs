@@ -0,0 +1,7 @@
+pub mod lines;
+pub mod render;
+pub mod list;
+pub mod style;
+pub mod overflow;
+pub mod text;
+pub mod snapshot;
@@ -0,0 +1,236 @@
+use heapless::String;
+use screen::lines::{ScreenLines, DATA_LINES_COUNT, FIRST_DATA_LINE, LINES_COUNT, LINE_CAPACITY};
+use screen::style::{LineStyle, LineStyles, Overflow};
+
+/// Width of the text part of a line, right of the line number
+pub const TEXT_AREA_WIDTH: u32 = 113;
+/// Marquee moves one pixel per step
+pub const MARQUEE_STEP_MS: u64 = 50;
+/// Space between the end of the text and its next round
+pub const MARQUEE_GAP: u32 = 24;
+
+/// What a screen line shows: its own text or a part of a wrapped line above it
+#[derive(Debug, Clone, Copy)]
+pub struct VisibleLine<'a> {
+    pub text: &'a str,
+    pub is_cursor: bool,
+    pub style: &'a LineStyle,
+}
+
+impl<'a> VisibleLine<'a> {
+    /// Whole characters fitting into the text area
+    pub fn max_chars(&self) -> usize {
+        let font = self.style.mono_font();
+        (TEXT_AREA_WIDTH / (font.character_size.width + font.character_spacing)) as usize
+    }
+
+    pub fn overflows(&self) -> bool {
+        self.text.chars().count() > self.max_chars()
+    }
+
+    /// Needs a redraw on every marquee step
+    pub fn is_marquee(&self) -> bool {
+        self.style.overflow == Overflow::Marquee && self.overflows()
+    }
+
+    /// Text cut to the line with `...` at the end
+    pub fn ellipsized(&self) -> String<LINE_CAPACITY> {
+        let mut text: String<LINE_CAPACITY> = String::new();
+        for c in self.text.chars().take(self.max_chars().saturating_sub(3)) {
+            _ = text.push(c);
+        }
+        _ = text.push_str("...");
+        text
+    }
+
+    /// Pixels the text is moved to the left at `now_ms`, it comes back after `MARQUEE_GAP`
+    pub fn marquee_offset(&self, now_ms: u64) -> u32 {
+        let round = self.text_width() + MARQUEE_GAP;
+        ((now_ms / MARQUEE_STEP_MS) % round as u64) as u32
+    }
+
+    pub fn text_width(&self) -> u32 {
+        let font = self.style.mono_font();
+        self.text.chars().count() as u32 * (font.character_size.width + font.character_spacing)
+    }
+}
+
+/// Lines as they go on screen. Lines with `Overflow::Wrap` continue in the following lines
+/// while those are empty, data lines don't wrap into the title and status and title don't wrap at all.
+/// Continuation lines keep style and cursor of the wrapped line.
+pub fn visible_lines<'a>(lines: &'a ScreenLines, styles: &'a LineStyles) -> [Option<VisibleLine<'a>>; LINES_COUNT] {
+    let mut visible = [None; LINES_COUNT];
+    let mut index = 0;
+    while index < LINES_COUNT {
+        let (text, is_cursor) = match &lines[index] {
+            Some((text, is_cursor)) => (text.as_str(), *is_cursor),
+            None => {
+                index += 1;
+                continue;
+            }
+        };
+        let style = &styles[index];
+        let line = VisibleLine { text, is_cursor, style };
+        if style.overflow != Overflow::Wrap {
+            visible[index] = Some(line);
+            index += 1;
+            continue;
+        }
+
+        let last_line = if (FIRST_DATA_LINE..FIRST_DATA_LINE + DATA_LINES_COUNT).contains(&index) {
+            FIRST_DATA_LINE + DATA_LINES_COUNT - 1
+        } else {
+            index
+        };
+        let mut rest = text;
+        loop {
+            let has_next_line = index < last_line && lines[index + 1].is_none();
+            // no room left, the last line is clipped
+            let (segment, next) = if has_next_line { wrap_segment(rest, line.max_chars()) } else { (rest, "") };
+            visible[index] = Some(VisibleLine { text: segment, ..line });
+            index += 1;
+            rest = next;
+            if rest.is_empty() {
+                break;
+            }
+        }
+    }
+    visible
+}
+
+/// Split off what fits into `max_chars`, breaking after the last word that fits.
+/// Words longer than the line are broken anywhere.
+fn wrap_segment(text: &str, max_chars: usize) -> (&str, &str) {
+    let end = match text.char_indices().nth(max_chars) {
+        Some((end, _)) => end,
+        None => return (text, ""),
+    };
+    let split = if text[end..].starts_with(' ') {
+        Some(end)
+    } else {
+        text[..end].rfind(' ').filter(|split| *split > 0)
+    };
+    match split {
+        Some(split) => (text[..split].trim_end(), text[split..].trim_start()),
+        None => (&text[..end], &text[end..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 18 characters of the normal font fit next to the line number
+    const CHARS: usize = 18;
+
+    fn screen(texts: &[(usize, &str)]) -> ScreenLines {
+        let mut lines: ScreenLines = Default::default();
+        for &(index, text) in texts {
+            lines[index] = Some((String::from(text), index == 1));
+        }
+        lines
+    }
+
+    fn styles(overflow: Overflow) -> LineStyles {
+        [LineStyle { overflow, ..LineStyle::default() }; LINES_COUNT]
+    }
+
+    fn texts<'a>(visible: &[Option<VisibleLine<'a>>]) -> [Option<&'a str>; LINES_COUNT] {
+        let mut texts = [None; LINES_COUNT];
+        for (text, line) in texts.iter_mut().zip(visible) {
+            *text = line.map(|line| line.text);
+        }
+        texts
+    }
+
+    #[test]
+    fn wrap_breaks_after_the_last_word_that_fits() {
+        assert_eq!(wrap_segment("one two three four five", CHARS), ("one two three four", "five"));
+        // a space right at the end of the line
+        assert_eq!(wrap_segment("one two three fourteen five", 13), ("one two three", "fourteen five"));
+        assert_eq!(wrap_segment("short", CHARS), ("short", ""));
+    }
+
+    #[test]
+    fn words_longer_than_a_line_are_broken_anywhere() {
+        assert_eq!(wrap_segment("abcdefghijklmnopqrstuvwxyz", CHARS), ("abcdefghijklmnopqr", "stuvwxyz"));
+        assert_eq!(wrap_segment("ab abcdefghijklmnopqrstuvwxyz", CHARS), ("ab", "abcdefghijklmnopqrstuvwxyz"));
+        // breaks count characters, not bytes
+        assert_eq!(wrap_segment("ЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖ", CHARS), ("ЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖ", "ЖЖ"));
+    }
+
+    #[test]
+    fn wrapped_line_continues_in_empty_lines_with_its_cursor() {
+        let lines = screen(&[(1, "one two three four five six seven eight nine ten eleven")]);
+        let styles = styles(Overflow::Wrap);
+        let visible = visible_lines(&lines, &styles);
+        assert_eq!(VisibleLine { text: "", is_cursor: false, style: &styles[1] }.max_chars(), CHARS);
+        assert_eq!(texts(&visible)[1..5], [
+            Some("one two three four"),
+            Some("five six seven"),
+            Some("eight nine ten"),
+            Some("eleven"),
+        ]);
+        assert!(visible[1..5].iter().all(|line| line.unwrap().is_cursor));
+        assert!(visible[5].is_none());
+    }
+
+    #[test]
+    fn wrapping_stops_at_a_line_with_text() {
+        let lines = screen(&[(2, "one two three four five six seven eight"), (4, "next")]);
+        let styles = styles(Overflow::Wrap);
+        let visible = visible_lines(&lines, &styles);
+        // the rest goes into the line before `next` and is clipped when drawn
+        assert_eq!(texts(&visible)[2..5], [Some("one two three four"), Some("five six seven eight"), Some("next")]);
+    }
+
+    #[test]
+    fn wrapping_stops_at_the_last_data_line() {
+        let last = FIRST_DATA_LINE + DATA_LINES_COUNT - 1;
+        let lines = screen(&[(last - 1, "one two three four five six seven eight nine ten eleven")]);
+        let styles = styles(Overflow::Wrap);
+        let visible = visible_lines(&lines, &styles);
+        assert_eq!(texts(&visible)[last - 1..], [
+            Some("one two three four"),
+            Some("five six seven eight nine ten eleven"),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn status_and_title_do_not_wrap() {
+        let long = "one two three four five six";
+        let lines = screen(&[(0, long), (LINES_COUNT - 1, long)]);
+        let styles = styles(Overflow::Wrap);
+        let visible = visible_lines(&lines, &styles);
+        assert_eq!(texts(&visible), [Some(long), None, None, None, None, None, None, None, None, Some(long)]);
+    }
+
+    #[test]
+    fn ellipsis_only_past_the_width() {
+        let style = LineStyle { overflow: Overflow::Ellipsis, ..LineStyle::default() };
+        let exact = VisibleLine { text: "abcdefghijklmnopqr", is_cursor: false, style: &style };
+        assert!(!exact.overflows());
+        let longer = VisibleLine { text: "abcdefghijklmnopqrs", ..exact };
+        assert!(longer.overflows());
+        assert_eq!(longer.ellipsized().as_str(), "abcdefghijklmno...");
+        let cyrillic = VisibleLine { text: "ЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖЖ", ..exact };
+        assert_eq!(cyrillic.ellipsized().chars().count(), CHARS);
+    }
+
+    #[test]
+    fn marquee_offset_wraps_around_after_the_gap() {
+        let style = LineStyle { overflow: Overflow::Marquee, ..LineStyle::default() };
+        let line = VisibleLine { text: "abcdefghijklmnopqrstuvwxyz", is_cursor: false, style: &style };
+        assert!(line.is_marquee());
+        let round = (line.text_width() + MARQUEE_GAP) as u64;
+        assert_eq!(line.marquee_offset(0), 0);
+        assert_eq!(line.marquee_offset(MARQUEE_STEP_MS - 1), 0);
+        assert_eq!(line.marquee_offset(MARQUEE_STEP_MS), 1);
+        assert_eq!(line.marquee_offset((round - 1) * MARQUEE_STEP_MS), round as u32 - 1);
+        assert_eq!(line.marquee_offset(round * MARQUEE_STEP_MS), 0);
+        assert!(line.marquee_offset(u64::MAX) < round as u32);
+        let short = VisibleLine { text: "short", ..line };
+        assert!(!short.is_marquee());
+    }
+}
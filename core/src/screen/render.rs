@@ -0,0 +1,323 @@
+use embedded_graphics::draw_target::DrawTargetExt;
+use embedded_graphics::mono_font::ascii::FONT_6X12;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::{Line, PrimitiveStyle};
+use embedded_graphics::text::{Baseline, Text};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::Vec;
+use screen::lines::{ScreenLines, DATA_LINES_COUNT, FIRST_DATA_LINE};
+use screen::overflow::{visible_lines, VisibleLine, MARQUEE_GAP, MARQUEE_STEP_MS, TEXT_AREA_WIDTH};
+use screen::style::{LineStyle, LineStyles, Overflow};
+use screen::text::draw_text;
+use utils::itoa::itoa;
+use diagnostics::counters::Diagnostics;
+use diagnostics::screen::draw_diagnostics;
+use images::cache::{ImageCache, PLACED_IMAGES};
+use layout::node::Align;
+use layout::render::draw_layout;
+use layout::tree::Layout;
+use link::monitor::LinkMonitor;
+use link::screen::draw_link_lost;
+use widgets::layer::Widgets;
+
+/// Draw screen lines. Line numbers and separators are static, so they are drawn on `first_draw` only.
+/// Works on any `DrawTarget`: the ST7735 on the device or a framebuffer in the simulator.
+/// `now_ms` positions marquee lines.
+pub fn draw_lines<D>(
+    display: &mut D,
+    lines: &ScreenLines,
+    styles: &LineStyles,
+    now_ms: u64,
+    first_draw: bool,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    for (index, line) in visible_lines(lines, styles).iter().enumerate() {
+        draw_line(display, index, line, &styles[index], now_ms, first_draw)?;
+    }
+    Ok(())
+}
+
+/// Everything on screen, borrowed from `DeviceState::screen`
+#[derive(Clone, Copy)]
+pub struct ScreenContent<'a> {
+    pub lines: &'a ScreenLines,
+    pub styles: &'a LineStyles,
+    pub widgets: &'a Widgets,
+    pub layout: Option<&'a Layout>,
+    pub images: &'a ImageCache,
+    pub link: &'a LinkMonitor,
+    /// Debug screen toggled on the device, drawn instead of everything else
+    pub debug: Option<&'a Diagnostics>,
+}
+
+/// Draw lines, or the layout when the Pi sent one, then placed images and widgets on top.
+/// Data lines are skipped while a widget covers them, a QR code hides everything but toasts.
+/// While the link to the Pi is lost only the offline screen is drawn, the debug screen goes over that too.
+/// `full_redraw` clears the display first, `ScreenRenderer` decides when it is needed.
+pub fn draw_screen<D>(
+    display: &mut D,
+    screen: &ScreenContent,
+    now_ms: u64,
+    full_redraw: bool,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    if let Some(diagnostics) = screen.debug {
+        return draw_diagnostics(display, diagnostics, full_redraw);
+    }
+    if let Some(silent_ms) = screen.link.lost_for_ms(now_ms) {
+        return draw_link_lost(display, silent_ms, full_redraw);
+    }
+    // a QR code covers everything and fills the screen itself
+    if screen.widgets.covers_screen() {
+        return screen.widgets.draw(display);
+    }
+    if full_redraw {
+        display.clear(Rgb565::BLACK)?;
+    }
+    match screen.layout {
+        Some(layout) => draw_layout(display, layout)?,
+        None => {
+            for (index, line) in visible_lines(screen.lines, screen.styles).iter().enumerate() {
+                if is_covered(index, screen.widgets) {
+                    continue;
+                }
+                draw_line(display, index, line, &screen.styles[index], now_ms, full_redraw)?;
+            }
+        }
+    }
+    screen.images.draw(display)?;
+    screen.widgets.draw(display)
+}
+
+/// Images are not copied, their revision and placements tell what changed
+struct DrawnScreen {
+    lines: ScreenLines,
+    styles: LineStyles,
+    widgets: Widgets,
+    layout: Option<Layout>,
+    images_revision: u32,
+    placed_images: Vec<(u8, Point), PLACED_IMAGES>,
+    /// Seconds since last contact shown on the offline screen
+    link_lost_s: Option<u64>,
+    debug: Option<Diagnostics>,
+}
+
+impl DrawnScreen {
+    fn new(screen: &ScreenContent, link_lost_s: Option<u64>) -> Self {
+        DrawnScreen {
+            lines: screen.lines.clone(),
+            styles: *screen.styles,
+            widgets: screen.widgets.clone(),
+            layout: screen.layout.cloned(),
+            images_revision: screen.images.revision(),
+            placed_images: Vec::from_slice(screen.images.placed()).unwrap_or_default(),
+            link_lost_s,
+            debug: screen.debug.copied(),
+        }
+    }
+
+    fn is_same(&self, screen: &ScreenContent, link_lost_s: Option<u64>) -> bool {
+        self.link_lost_s == link_lost_s && self.debug.as_ref() == screen.debug && &self.lines == screen.lines && &self.styles == screen.styles && &self.widgets == screen.widgets
+            && self.layout.as_ref() == screen.layout && self.images_revision == screen.images.revision()
+    }
+
+    /// Something disappears or moves, drawing over it is not enough
+    fn needs_full_redraw(&self, screen: &ScreenContent, link_lost_s: Option<u64>) -> bool {
+        // same for the debug screen, its values are drawn over themselves
+        if self.debug.is_some() || screen.debug.is_some() {
+            return self.debug.is_none() || screen.debug.is_none();
+        }
+        // the offline screen comes and goes as a whole, while it stays only the elapsed time changes
+        if self.link_lost_s.is_some() || link_lost_s.is_some() {
+            return self.link_lost_s.is_none() || link_lost_s.is_none();
+        }
+        // nodes don't clean up after themselves, layout changes are drawn from scratch
+        screen.widgets.needs_full_redraw(&self.widgets) || self.layout.as_ref() != screen.layout
+            || self.placed_images.iter().any(|placed| !screen.images.placed().contains(placed))
+    }
+}
+
+/// Keeps a copy of what is on the display, so the screen is drawn only when something changed
+/// and cleared only when parts of it disappear. Marquee lines are redrawn on their own every step.
+#[derive(Default)]
+pub struct ScreenRenderer {
+    drawn: Option<DrawnScreen>,
+    marquee_step: u64,
+}
+
+impl ScreenRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true when the display was updated
+    pub fn render<D>(
+        &mut self,
+        display: &mut D,
+        screen: &ScreenContent,
+        now_ms: u64,
+    ) -> Result<bool, D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        let marquee_step = now_ms / MARQUEE_STEP_MS;
+        let link_lost_s = screen.link.lost_for_ms(now_ms).map(|silent_ms| silent_ms / 1_000);
+        let full_redraw = match &self.drawn {
+            Some(drawn) => {
+                if drawn.is_same(screen, link_lost_s) {
+                    if screen.layout.is_some() || screen.widgets.covers_screen() || link_lost_s.is_some()
+                        || screen.debug.is_some() || marquee_step == self.marquee_step {
+                        return Ok(false);
+                    }
+                    self.marquee_step = marquee_step;
+                    return self.draw_marquee_lines(display, screen, now_ms);
+                }
+                drawn.needs_full_redraw(screen, link_lost_s)
+            }
+            None => true,
+        };
+        draw_screen(display, screen, now_ms, full_redraw)?;
+        self.drawn = Some(DrawnScreen::new(screen, link_lost_s));
+        self.marquee_step = marquee_step;
+        Ok(true)
+    }
+
+    /// Only lines which move, images and widgets over them are drawn again
+    fn draw_marquee_lines<D>(
+        &self,
+        display: &mut D,
+        screen: &ScreenContent,
+        now_ms: u64,
+    ) -> Result<bool, D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        let mut redrawn = false;
+        for (index, line) in visible_lines(screen.lines, screen.styles).iter().enumerate() {
+            if is_covered(index, screen.widgets) || !line.is_some_and(|line| line.is_marquee()) {
+                continue;
+            }
+            draw_line(display, index, line, &screen.styles[index], now_ms, false)?;
+            screen.images.draw_over(display, Some(&line_area(index)))?;
+            redrawn = true;
+        }
+        if redrawn && (screen.widgets.toast.is_some() || screen.widgets.battery.is_some()) {
+            screen.widgets.draw(display)?;
+        }
+        Ok(redrawn)
+    }
+}
+
+fn is_covered(index: usize, widgets: &Widgets) -> bool {
+    let is_data_line = (FIRST_DATA_LINE..FIRST_DATA_LINE + DATA_LINES_COUNT).contains(&index);
+    is_data_line && widgets.covers_data_lines()
+}
+
+/// Text part of line `index`, cleared on every draw
+fn line_area(index: usize) -> Rectangle {
+    let offset_y = 12 * (index as i32 + 1) + 1;
+    Rectangle::new(Point::new(15, offset_y - 8), Size::new(TEXT_AREA_WIDTH, 10))
+}
+
+fn draw_line<D>(
+    display: &mut D,
+    index: usize,
+    line: &Option<VisibleLine>,
+    own_style: &LineStyle,
+    now_ms: u64,
+    first_draw: bool,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let index = index as i32;
+    let offset_y = (12 * (index + 1)) + 1;
+    // I did not find a way to make something like - `to_str(num:i32) -> str`
+    let num_line_buffer = itoa(index + 1);
+    let num_line_str = core::str::from_utf8(&num_line_buffer).unwrap();
+
+    // draw line and line number when first draw
+    if first_draw {
+        Text::new(
+            num_line_str,
+            Point::new(0, offset_y),
+            MonoTextStyle::new(&FONT_6X12, Rgb565::WHITE),
+        ).draw(display)?;
+
+        Line::new(
+            Point::new(2, offset_y + 2),
+            Point::new(125, offset_y + 2),
+        )
+            .into_styled(PrimitiveStyle::with_stroke(Rgb565::BLUE, 1))
+            .draw(display)?;
+    }
+
+    // Clean up area for text, line under cursor gets highlighted background.
+    // Continuation of a wrapped line is drawn in the style of the wrapped line.
+    let style = line.as_ref().map_or(own_style, |line| line.style);
+    let is_cursor = line.as_ref().is_some_and(|line| line.is_cursor);
+    let (color, background) = style.colors(is_cursor);
+    let text_area = line_area(index as usize);
+    text_area.into_styled(PrimitiveStyle::with_fill(background)).draw(display)?;
+
+    if let Some(line) = line {
+        // text never goes over the line number or into separators
+        let mut clipped = display.clipped(&Rectangle::new(
+            Point::new(text_area.top_left.x, offset_y - 9),
+            Size::new(TEXT_AREA_WIDTH, 11),
+        ));
+        if line.is_marquee() {
+            let x = text_area.top_left.x - line.marquee_offset(now_ms) as i32;
+            let next_round = (line.text_width() + MARQUEE_GAP) as i32;
+            draw_styled_text(&mut clipped, line.text, Point::new(x, offset_y), Align::Left, style, color)?;
+            draw_styled_text(&mut clipped, line.text, Point::new(x + next_round, offset_y), Align::Left, style, color)?;
+        } else {
+            // overflowing text starts at the left edge, whatever the alignment
+            let align = if line.overflows() { Align::Left } else { style.align };
+            let x = match align {
+                Align::Left => text_area.top_left.x,
+                Align::Center => text_area.center().x,
+                Align::Right => text_area.top_left.x + text_area.size.width as i32 - 1,
+            };
+            let ellipsized;
+            let text = if style.overflow == Overflow::Ellipsis && line.overflows() {
+                ellipsized = line.ellipsized();
+                ellipsized.as_str()
+            } else {
+                line.text
+            };
+            draw_styled_text(&mut clipped, text, Point::new(x, offset_y), align, style, color)?;
+        }
+    }
+    Ok(())
+}
+
+/// Bold is the same text once more one pixel to the right
+fn draw_styled_text<D>(
+    display: &mut D,
+    text: &str,
+    position: Point,
+    align: Align,
+    style: &LineStyle,
+    color: Rgb565,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let font_set = style.font.font_set();
+    draw_text(display, text, position, font_set, color, align.alignment(), Baseline::Alphabetic)?;
+    if style.bold {
+        draw_text(display, text, position + Point::new(1, 0), font_set, color, align.alignment(), Baseline::Alphabetic)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use diagnostics::counters::Diagnostics;
+use images::cache::ImageCache;
+use layout::tree::Layout;
+use link::monitor::LinkMonitor;
+use screen::lines::ScreenLines;
+use screen::render::ScreenContent;
+use screen::style::LineStyles;
+use widgets::layer::Widgets;
+
+/// Copy of what `DeviceState::screen` borrows, so core1 draws from memory core0 leaves alone
+/// while it keeps handling the Pi. Image data is copied only when the cache revision changed.
+pub struct ScreenSnapshot {
+    lines: ScreenLines,
+    styles: LineStyles,
+    widgets: Widgets,
+    layout: Option<Layout>,
+    images: ImageCache,
+    link: LinkMonitor,
+    debug: Option<Diagnostics>,
+}
+
+impl ScreenSnapshot {
+    pub fn new(screen: &ScreenContent) -> Self {
+        ScreenSnapshot {
+            lines: screen.lines.clone(),
+            styles: *screen.styles,
+            widgets: screen.widgets.clone(),
+            layout: screen.layout.cloned(),
+            images: screen.images.clone(),
+            link: screen.link.clone(),
+            debug: screen.debug.copied(),
+        }
+    }
+
+    /// Take over `screen` without allocating new buffers
+    pub fn update(&mut self, screen: &ScreenContent) {
+        self.lines.clone_from(screen.lines);
+        self.styles = *screen.styles;
+        self.widgets.clone_from(screen.widgets);
+        match (&mut self.layout, screen.layout) {
+            (Some(layout), Some(new_layout)) => layout.clone_from(new_layout),
+            (layout, new_layout) => *layout = new_layout.cloned(),
+        }
+        if self.images.revision() != screen.images.revision() {
+            self.images.clone_from(screen.images);
+        }
+        self.link.clone_from(screen.link);
+        self.debug = screen.debug.copied();
+    }
+
+    pub fn content(&self) -> ScreenContent<'_> {
+        ScreenContent {
+            lines: &self.lines,
+            styles: &self.styles,
+            widgets: &self.widgets,
+            layout: self.layout.as_ref(),
+            images: &self.images,
+            link: &self.link,
+            debug: self.debug.as_ref(),
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use core::convert::TryFrom;
+use core::fmt::Write;
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use heapless::String;
+use layout::node::{Align, Font};
+use screen::lines::LINES_COUNT;
+use utils::color::{hex_color, parse_color};
+
+/// What happens to text wider than the line
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Overflow {
+    Clip,
+    /// Cut with `...` at the end
+    Ellipsis,
+    /// Continue in the following empty lines, up to the last data line
+    Wrap,
+    /// Scroll horizontally, animated by the renderer
+    Marquee,
+}
+
+impl Overflow {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Overflow::Clip => "clip",
+            Overflow::Ellipsis => "ellipsis",
+            Overflow::Wrap => "wrap",
+            Overflow::Marquee => "marquee",
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Overflow {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "clip" => Ok(Overflow::Clip),
+            "ellipsis" => Ok(Overflow::Ellipsis),
+            "wrap" => Ok(Overflow::Wrap),
+            "marquee" => Ok(Overflow::Marquee),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Colours, font and alignment of one screen line, kept by the device until the Pi sends another one.
+/// Colours left as `None` are the defaults: red text on black, white on blue under cursor.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LineStyle {
+    pub fg: Option<Rgb565>,
+    pub bg: Option<Rgb565>,
+    /// Small or normal, large does not fit into a line
+    pub font: Font,
+    pub align: Align,
+    pub bold: bool,
+    /// Swap foreground and background
+    pub inverse: bool,
+    pub overflow: Overflow,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle {
+            fg: None,
+            bg: None,
+            font: Font::Normal,
+            align: Align::Left,
+            bold: false,
+            inverse: false,
+            overflow: Overflow::Clip,
+        }
+    }
+}
+
+/// Style of every line in `ScreenLines`, by the same index
+pub type LineStyles = [LineStyle; LINES_COUNT];
+
+impl LineStyle {
+    /// `<property>=<value>|...` after the line index of `line_style=`:
+    /// `fg=<rrggbb>`, `bg=<rrggbb>`, `font=small|normal`, `align=left|center|right`, `bold=0|1`, `inverse=0|1`,
+    /// `overflow=clip|ellipsis|wrap|marquee`.
+    /// No properties is the default style.
+    pub fn parse(value: &str) -> Result<LineStyle, LineStyleError> {
+        let mut style = LineStyle::default();
+        for property in value.split('|').filter(|property| !property.is_empty()) {
+            let (key, value) = property.split_once('=').ok_or(LineStyleError::BadProperty)?;
+            match key {
+                "fg" => style.fg = Some(parse_color(value).ok_or(LineStyleError::BadProperty)?),
+                "bg" => style.bg = Some(parse_color(value).ok_or(LineStyleError::BadProperty)?),
+                "font" => {
+                    style.font = match Font::try_from(value) {
+                        Ok(Font::Large) | Err(_) => return Err(LineStyleError::BadProperty),
+                        Ok(font) => font,
+                    };
+                }
+                "align" => style.align = Align::try_from(value).map_err(|_| LineStyleError::BadProperty)?,
+                "bold" => style.bold = parse_flag(value)?,
+                "inverse" => style.inverse = parse_flag(value)?,
+                "overflow" => style.overflow = Overflow::try_from(value).map_err(|_| LineStyleError::BadProperty)?,
+                _ => return Err(LineStyleError::BadProperty),
+            }
+        }
+        Ok(style)
+    }
+
+    /// Opposite of `parse`, only properties which differ from the default
+    pub fn encode(&self) -> String<80> {
+        let mut value: String<80> = String::new();
+        if let Some(fg) = self.fg {
+            _ = write!(value, "|fg={}", hex_color(fg));
+        }
+        if let Some(bg) = self.bg {
+            _ = write!(value, "|bg={}", hex_color(bg));
+        }
+        if self.font != Font::Normal {
+            _ = write!(value, "|font={}", self.font.as_str());
+        }
+        if self.align != Align::Left {
+            _ = write!(value, "|align={}", self.align.as_str());
+        }
+        if self.bold {
+            _ = value.push_str("|bold=1");
+        }
+        if self.inverse {
+            _ = value.push_str("|inverse=1");
+        }
+        if self.overflow != Overflow::Clip {
+            _ = write!(value, "|overflow={}", self.overflow.as_str());
+        }
+        value
+    }
+
+    /// Text and background colours, `is_cursor` for the highlighted data line
+    pub fn colors(&self, is_cursor: bool) -> (Rgb565, Rgb565) {
+        let (fg, bg) = if is_cursor {
+            (self.fg.unwrap_or(Rgb565::WHITE), Rgb565::BLUE)
+        } else {
+            (self.fg.unwrap_or(Rgb565::RED), self.bg.unwrap_or(Rgb565::BLACK))
+        };
+        if self.inverse { (bg, fg) } else { (fg, bg) }
+    }
+
+    pub fn mono_font(&self) -> &'static MonoFont<'static> {
+        self.font.mono_font()
+    }
+}
+
+fn parse_flag(value: &str) -> Result<bool, LineStyleError> {
+    match value {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(LineStyleError::BadProperty),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LineStyleError {
+    BadLine,
+    BadProperty,
+}
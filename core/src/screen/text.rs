@@ -0,0 +1,146 @@
+use embedded_graphics::mono_font::{iso_8859_1, iso_8859_5, MonoFont, MonoTextStyle};
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics::text::renderer::TextRenderer;
+use embedded_graphics::text::{Alignment, Baseline, Text};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Same size mono fonts for every supported script, a character is drawn with the one that has it.
+/// `MonoFont` knows one 8-bit charset only.
+pub struct FontSet {
+    /// ASCII and Latin-1 supplement
+    pub latin1: MonoFont<'static>,
+    pub cyrillic: MonoFont<'static>,
+}
+
+pub static FONT_SET_6X10: FontSet = FontSet {
+    latin1: iso_8859_1::FONT_6X10,
+    cyrillic: iso_8859_5::FONT_6X10,
+};
+
+pub static FONT_SET_6X12: FontSet = FontSet {
+    latin1: iso_8859_1::FONT_6X12,
+    cyrillic: iso_8859_5::FONT_6X12,
+};
+
+pub static FONT_SET_10X20: FontSet = FontSet {
+    latin1: iso_8859_1::FONT_10X20,
+    cyrillic: iso_8859_5::FONT_10X20,
+};
+
+impl FontSet {
+    /// Cell of one character, the same in every font of the set
+    pub fn character_width(&self) -> u32 {
+        self.latin1.character_size.width + self.latin1.character_spacing
+    }
+
+    pub fn text_width(&self, text: &str) -> u32 {
+        text.chars().count() as u32 * self.character_width()
+    }
+
+    fn font_for(&self, charset: Charset) -> Option<&MonoFont<'static>> {
+        match charset {
+            Charset::Latin1 => Some(&self.latin1),
+            Charset::Cyrillic => Some(&self.cyrillic),
+            Charset::Unknown => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Charset {
+    Latin1,
+    Cyrillic,
+    /// Drawn as a box
+    Unknown,
+}
+
+fn charset(c: char) -> Charset {
+    match c {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => Charset::Latin1,
+        // ISO 8859-5 skips U+040D, U+0450 and U+045D
+        '\u{401}'..='\u{40c}' | '\u{40e}'..='\u{44f}' | '\u{451}'..='\u{45c}' | '\u{45e}'..='\u{45f}' | '№' => {
+            Charset::Cyrillic
+        }
+        _ => Charset::Unknown,
+    }
+}
+
+/// Draw UTF-8 text like `Text::with_text_style` does, switching fonts between runs of Latin-1
+/// and Cyrillic characters. Characters none of the fonts has are drawn as an empty box.
+pub fn draw_text<D>(
+    display: &mut D,
+    text: &str,
+    position: Point,
+    font_set: &FontSet,
+    color: Rgb565,
+    alignment: Alignment,
+    baseline: Baseline,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let width = font_set.text_width(text) as i32;
+    let mut x = match alignment {
+        Alignment::Left => position.x,
+        Alignment::Center => position.x - width / 2,
+        Alignment::Right => position.x - width + 1,
+    };
+
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        let run_charset = charset(first);
+        let run_end = rest.char_indices()
+            .find(|(_, c)| charset(*c) != run_charset)
+            .map_or(rest.len(), |(index, _)| index);
+        let (run, next) = rest.split_at(run_end);
+        let run_position = Point::new(x, position.y);
+        match font_set.font_for(run_charset) {
+            Some(font) => {
+                Text::with_baseline(run, run_position, MonoTextStyle::new(font, color), baseline).draw(display)?;
+            }
+            None => {
+                let character_style = MonoTextStyle::new(&font_set.latin1, color);
+                let cell = character_style.measure_string(" ", run_position, baseline).bounding_box;
+                for index in 0..run.chars().count() as i32 {
+                    let top_left = cell.top_left + Point::new(index * font_set.character_width() as i32 + 1, 1);
+                    Rectangle::new(top_left, cell.size - Size::new(2, 2))
+                        .into_styled(PrimitiveStyle::with_stroke(color, 1))
+                        .draw(display)?;
+                }
+            }
+        }
+        x += font_set.text_width(run) as i32;
+        rest = next;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cyrillic_is_exactly_the_iso_8859_5_set() {
+        for c in ['Ё', 'Ќ', 'Ў', 'А', 'я', 'ё', 'ќ', 'ў', 'џ', '№'] {
+            assert_eq!(charset(c), Charset::Cyrillic, "{}", c);
+        }
+        for c in ['\u{400}', '\u{40d}', '\u{450}', '\u{45d}', '\u{460}', 'Ґ'] {
+            assert_eq!(charset(c), Charset::Unknown, "{}", c);
+        }
+    }
+
+    #[test]
+    fn latin1_covers_ascii_and_the_supplement() {
+        for c in [' ', 'A', '~', '\u{a0}', 'é', 'ÿ'] {
+            assert_eq!(charset(c), Charset::Latin1);
+        }
+        for c in ['\t', '\u{7f}', '\u{9f}', '€'] {
+            assert_eq!(charset(c), Charset::Unknown);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+/// NOR flash area the settings store or the firmware update lives in. Offsets are relative to the start of the area,
+/// erased bytes read as `0xff` and writing can only clear bits.
+pub trait Flash {
+    type Error;
+    /// Erase unit
+    const SECTOR_SIZE: u32;
+
+    fn sectors(&self) -> u32;
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error>;
+    fn erase(&mut self, sector: u32) -> Result<(), Self::Error>;
+    /// Any length at any offset, the store writes erased bytes only
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+pub const MEM_FLASH_SECTOR_SIZE: u32 = 4096;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MemFlashError {
+    OutOfBounds,
+    /// Power was cut with `cut_power_after`, nothing works until `restore_power`
+    PowerLost,
+}
+
+/// Flash emulator for the simulator and host checks, `SIZE` is a multiple of 4 KB sectors.
+/// Behaves like NOR flash: erase sets bytes to `0xff`, write ANDs data into them.
+/// Power loss stops an erase or write in the middle, leaving it half done.
+pub struct MemFlash<const SIZE: usize> {
+    data: [u8; SIZE],
+    /// Bytes which can still be erased or written before the power goes
+    power_budget: Option<usize>,
+    erase_counts: [u32; 64],
+}
+
+impl<const SIZE: usize> MemFlash<SIZE> {
+    /// Erased flash, like a new chip
+    pub fn new() -> Self {
+        MemFlash { data: [0xff; SIZE], power_budget: None, erase_counts: [0; 64] }
+    }
+
+    /// Flash holding an image saved with `as_bytes`
+    pub fn from_bytes(data: [u8; SIZE]) -> Self {
+        MemFlash { data, power_budget: None, erase_counts: [0; 64] }
+    }
+
+    /// Let `bytes` more bytes be erased or written, then fail every operation
+    pub fn cut_power_after(&mut self, bytes: usize) {
+        self.power_budget = Some(bytes);
+    }
+
+    pub fn restore_power(&mut self) {
+        self.power_budget = None;
+    }
+
+    /// How many times each of the first 64 sectors was erased, to check wear levelling
+    pub fn erase_counts(&self) -> &[u32] {
+        let sectors = SIZE / MEM_FLASH_SECTOR_SIZE as usize;
+        &self.erase_counts[..sectors.min(self.erase_counts.len())]
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Bytes done before the power goes, error when it went during the operation
+    fn spend(&mut self, bytes: usize) -> Result<(), usize> {
+        match &mut self.power_budget {
+            Some(budget) if *budget < bytes => {
+                let done = *budget;
+                *budget = 0;
+                Err(done)
+            }
+            Some(budget) => {
+                *budget -= bytes;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<const SIZE: usize> Default for MemFlash<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SIZE: usize> Flash for MemFlash<SIZE> {
+    type Error = MemFlashError;
+    const SECTOR_SIZE: u32 = MEM_FLASH_SECTOR_SIZE;
+
+    fn sectors(&self) -> u32 {
+        (SIZE / MEM_FLASH_SECTOR_SIZE as usize) as u32
+    }
+
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let start = offset as usize;
+        let source = self.data.get(start..start + buffer.len()).ok_or(MemFlashError::OutOfBounds)?;
+        buffer.copy_from_slice(source);
+        Ok(())
+    }
+
+    fn erase(&mut self, sector: u32) -> Result<(), Self::Error> {
+        if sector >= self.sectors() {
+            return Err(MemFlashError::OutOfBounds);
+        }
+        let start = (sector * Self::SECTOR_SIZE) as usize;
+        let sector_bytes = &mut self.data[start..start + Self::SECTOR_SIZE as usize];
+        let done = self.power_budget.map_or(sector_bytes.len(), |budget| budget.min(sector_bytes.len()));
+        sector_bytes[..done].fill(0xff);
+        // only the first 64 sectors are counted, enough for the settings ring
+        if let Some(count) = self.erase_counts.get_mut(sector as usize) {
+            *count += 1;
+        }
+        self.spend(Self::SECTOR_SIZE as usize).map_err(|_| MemFlashError::PowerLost)
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let start = offset as usize;
+        if start + data.len() > SIZE {
+            return Err(MemFlashError::OutOfBounds);
+        }
+        let result = self.spend(data.len());
+        let done = match result {
+            Ok(()) => data.len(),
+            Err(done) => done,
+        };
+        for (target, byte) in self.data[start..start + done].iter_mut().zip(data) {
+            *target &= *byte;
+        }
+        result.map_err(|_| MemFlashError::PowerLost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestFlash = MemFlash<{ 2 * MEM_FLASH_SECTOR_SIZE as usize }>;
+
+    fn bytes(flash: &mut TestFlash, offset: u32) -> [u8; 4] {
+        let mut buffer = [0u8; 4];
+        flash.read(offset, &mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn writes_only_clear_bits() {
+        let mut flash = TestFlash::new();
+        assert_eq!(flash.sectors(), 2);
+        assert_eq!(bytes(&mut flash, 0), [0xff; 4]);
+        flash.write(1, &[0x0f, 0xf0]).unwrap();
+        flash.write(1, &[0xf3, 0x3f]).unwrap();
+        assert_eq!(bytes(&mut flash, 0), [0xff, 0x03, 0x30, 0xff]);
+        flash.erase(0).unwrap();
+        assert_eq!(bytes(&mut flash, 0), [0xff; 4]);
+    }
+
+    #[test]
+    fn erase_counts_per_sector() {
+        let mut flash = TestFlash::new();
+        flash.erase(1).unwrap();
+        flash.erase(1).unwrap();
+        flash.erase(0).unwrap();
+        assert_eq!(flash.erase_counts(), &[1, 2]);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut flash = TestFlash::new();
+        let end = 2 * MEM_FLASH_SECTOR_SIZE;
+        assert_eq!(flash.erase(2), Err(MemFlashError::OutOfBounds));
+        assert_eq!(flash.write(end - 1, &[0, 0]), Err(MemFlashError::OutOfBounds));
+        assert_eq!(flash.read(end - 1, &mut [0, 0]), Err(MemFlashError::OutOfBounds));
+        assert_eq!(flash.write(end - 2, &[0, 0]), Ok(()));
+    }
+
+    #[test]
+    fn power_loss_leaves_write_half_done() {
+        let mut flash = TestFlash::new();
+        flash.cut_power_after(3);
+        flash.write(0, &[1, 2]).unwrap();
+        assert_eq!(flash.write(2, &[3, 4]), Err(MemFlashError::PowerLost));
+        assert_eq!(bytes(&mut flash, 0), [1, 2, 3, 0xff]);
+        assert_eq!(flash.write(4, &[5]), Err(MemFlashError::PowerLost));
+        flash.restore_power();
+        flash.write(3, &[4]).unwrap();
+        assert_eq!(bytes(&mut flash, 0), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn power_loss_leaves_erase_half_done() {
+        let mut flash = TestFlash::new();
+        flash.write(0, &[0; 4]).unwrap();
+        flash.cut_power_after(2);
+        assert_eq!(flash.erase(0), Err(MemFlashError::PowerLost));
+        assert_eq!(bytes(&mut flash, 0), [0xff, 0xff, 0, 0]);
+        assert_eq!(flash.erase(0), Err(MemFlashError::PowerLost));
+        assert_eq!(bytes(&mut flash, 0), [0xff, 0xff, 0, 0]);
+    }
+
+    #[test]
+    fn image_round_trip() {
+        let mut flash = TestFlash::new();
+        flash.write(10, &[7, 8]).unwrap();
+        let mut image = [0u8; 2 * MEM_FLASH_SECTOR_SIZE as usize];
+        image.copy_from_slice(flash.as_bytes());
+        let mut copy = TestFlash::from_bytes(image);
+        assert_eq!(bytes(&mut copy, 9), [0xff, 7, 8, 0xff]);
+    }
+}
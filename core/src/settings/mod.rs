@@ -0,0 +1,3 @@
+pub mod flash;
+pub mod store;
+pub mod setting;
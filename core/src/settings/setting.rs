@@ -0,0 +1,301 @@
+use core::convert::TryFrom;
+use core::fmt;
+use heapless::String;
+use input::keyboard_codes::KeyboardCodes;
+use link::uart::{UartParity, UartPins};
+use link::usb::UsbMode;
+use settings::flash::Flash;
+use settings::store::{SettingsStore, StoreError, MAX_VALUE_BYTES};
+
+/// Rates `baud` accepts
+pub const BAUD_RATES: [u32; 8] = [9_600, 19_200, 38_400, 57_600, 115_200, 230_400, 460_800, 921_600];
+
+/// Names the Pi uses in `setting_set` and `setting_get`. The number is the store key and never
+/// changes, values are stored as their protocol text so a firmware which can't parse an old
+/// value falls back to the default.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SettingKey {
+    /// `landscape`, `portrait`, `landscape_swapped` or `portrait_swapped`, applied on boot
+    Orientation = 0,
+    /// Panel offset `<x>,<y>`, applied on boot
+    Offset = 1,
+    /// Backlight percent on boot, applied at once
+    Brightness = 2,
+    /// Uart rate, applied on boot
+    Baud = 3,
+    /// Code reported for the physical down, up, left, right and ok keys, `dulro` by default
+    Keymap = 4,
+    /// Uart parity, `none`, `odd` or `even`, applied on boot
+    Parity = 5,
+    /// Uart and pins to the Pi, `uart0_gp16`, `uart0_gp0` or `uart1_gp8`, applied on boot
+    UartPins = 6,
+    /// `serial`, `keyboard` or `keyboard_serial` for firmware built with USB, applied on boot
+    UsbMode = 7,
+}
+
+impl SettingKey {
+    pub const ALL: [SettingKey; 8] = [
+        SettingKey::Orientation,
+        SettingKey::Offset,
+        SettingKey::Brightness,
+        SettingKey::Baud,
+        SettingKey::Keymap,
+        SettingKey::Parity,
+        SettingKey::UartPins,
+        SettingKey::UsbMode,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettingKey::Orientation => "orientation",
+            SettingKey::Offset => "offset",
+            SettingKey::Brightness => "brightness",
+            SettingKey::Baud => "baud",
+            SettingKey::Keymap => "keymap",
+            SettingKey::Parity => "parity",
+            SettingKey::UartPins => "uart_pins",
+            SettingKey::UsbMode => "usb_mode",
+        }
+    }
+
+    pub fn store_key(self) -> u8 {
+        self as u8
+    }
+}
+
+impl<'a> TryFrom<&'a str> for SettingKey {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        SettingKey::ALL.iter().copied().find(|key| key.as_str() == value).ok_or(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DisplayOrientation {
+    Landscape,
+    Portrait,
+    LandscapeSwapped,
+    PortraitSwapped,
+}
+
+impl DisplayOrientation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisplayOrientation::Landscape => "landscape",
+            DisplayOrientation::Portrait => "portrait",
+            DisplayOrientation::LandscapeSwapped => "landscape_swapped",
+            DisplayOrientation::PortraitSwapped => "portrait_swapped",
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for DisplayOrientation {
+    type Error = ();
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "landscape" => Ok(DisplayOrientation::Landscape),
+            "portrait" => Ok(DisplayOrientation::Portrait),
+            "landscape_swapped" => Ok(DisplayOrientation::LandscapeSwapped),
+            "portrait_swapped" => Ok(DisplayOrientation::PortraitSwapped),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Why a setting command failed, reported as `setting_error=<code>[:<name>]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SettingsError {
+    /// No setting with this name
+    Unknown,
+    /// Value can't be parsed or is out of range, nothing changed
+    BadValue(SettingKey),
+    /// Applied but not saved to flash, lost on reboot
+    Storage(SettingKey),
+}
+
+impl SettingsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SettingsError::Unknown => "unknown",
+            SettingsError::BadValue(_) => "bad_value",
+            SettingsError::Storage(_) => "storage",
+        }
+    }
+
+    pub fn key(&self) -> Option<SettingKey> {
+        match *self {
+            SettingsError::BadValue(key) | SettingsError::Storage(key) => Some(key),
+            SettingsError::Unknown => None,
+        }
+    }
+
+    /// Reverse of `code` and `key`, used on the Pi side
+    pub fn from_code(code: &str, key: Option<SettingKey>) -> Option<Self> {
+        match (code, key) {
+            ("unknown", _) => Some(SettingsError::Unknown),
+            ("bad_value", Some(key)) => Some(SettingsError::BadValue(key)),
+            ("storage", Some(key)) => Some(SettingsError::Storage(key)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.key() {
+            Some(key) => write!(f, "{} {}", self.code(), key.as_str()),
+            None => f.write_str(self.code()),
+        }
+    }
+}
+
+/// Device settings kept in flash
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Settings {
+    pub orientation: DisplayOrientation,
+    pub offset: (u8, u8),
+    pub brightness: u8,
+    pub baud: u32,
+    /// Indexed by physical key: down, up, left, right, ok
+    pub keymap: [KeyboardCodes; 5],
+    pub parity: UartParity,
+    pub uart_pins: UartPins,
+    pub usb_mode: UsbMode,
+}
+
+impl Default for Settings {
+    /// What the board was built with
+    fn default() -> Self {
+        Settings {
+            orientation: DisplayOrientation::LandscapeSwapped,
+            offset: (1, 2),
+            brightness: 100,
+            baud: 115_200,
+            keymap: [KeyboardCodes::Down, KeyboardCodes::Up, KeyboardCodes::Left, KeyboardCodes::Right, KeyboardCodes::Ok],
+            parity: UartParity::Odd,
+            uart_pins: UartPins::Uart0Gp16,
+            usb_mode: UsbMode::Serial,
+        }
+    }
+}
+
+impl Settings {
+    /// Stored values over the defaults, values which don't parse are skipped
+    pub fn load<F: Flash>(store: &mut SettingsStore<F>) -> Result<Self, StoreError<F::Error>> {
+        let mut settings = Settings::default();
+        let mut buffer = [0u8; MAX_VALUE_BYTES];
+        for key in SettingKey::ALL {
+            let len = match store.read(key.store_key(), &mut buffer)? {
+                Some(len) => len,
+                None => continue,
+            };
+            let applied = core::str::from_utf8(&buffer[..len]).ok()
+                .map(|value| settings.set(key, value).is_ok());
+            if applied != Some(true) {
+                error!("Stored setting {} is invalid, using default", key.as_str());
+            }
+        }
+        Ok(settings)
+    }
+
+    pub fn save<F: Flash>(&self, store: &mut SettingsStore<F>, key: SettingKey) -> Result<(), StoreError<F::Error>> {
+        store.write(key.store_key(), self.value(key).as_bytes())
+    }
+
+    /// Parse `value` in protocol text, the setting is unchanged when it is invalid
+    pub fn set(&mut self, key: SettingKey, value: &str) -> Result<(), SettingsError> {
+        let bad_value = SettingsError::BadValue(key);
+        match key {
+            SettingKey::Orientation => {
+                self.orientation = DisplayOrientation::try_from(value).map_err(|_| bad_value)?;
+            }
+            SettingKey::Offset => {
+                let (x, y) = value.split_once(',').ok_or(bad_value)?;
+                let x = x.parse::<u8>().map_err(|_| bad_value)?;
+                let y = y.parse::<u8>().map_err(|_| bad_value)?;
+                self.offset = (x, y);
+            }
+            SettingKey::Brightness => {
+                let brightness = value.parse::<u8>().map_err(|_| bad_value)?;
+                if brightness > 100 {
+                    return Err(bad_value);
+                }
+                self.brightness = brightness;
+            }
+            SettingKey::Baud => {
+                let baud = value.parse::<u32>().map_err(|_| bad_value)?;
+                if !BAUD_RATES.contains(&baud) {
+                    return Err(bad_value);
+                }
+                self.baud = baud;
+            }
+            SettingKey::Keymap => {
+                let mut keymap = [KeyboardCodes::Ok; 5];
+                let mut chars = value.chars();
+                for index in 0..keymap.len() {
+                    let code = chars.next().and_then(KeyboardCodes::from_char).ok_or(bad_value)?;
+                    // every code stays reachable
+                    if keymap[..index].contains(&code) {
+                        return Err(bad_value);
+                    }
+                    keymap[index] = code;
+                }
+                if chars.next().is_some() {
+                    return Err(bad_value);
+                }
+                self.keymap = keymap;
+            }
+            SettingKey::Parity => {
+                self.parity = UartParity::try_from(value).map_err(|_| bad_value)?;
+            }
+            SettingKey::UartPins => {
+                self.uart_pins = UartPins::try_from(value).map_err(|_| bad_value)?;
+            }
+            SettingKey::UsbMode => {
+                self.usb_mode = UsbMode::try_from(value).map_err(|_| bad_value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Value in protocol text, also what is stored
+    pub fn value(&self, key: SettingKey) -> String<24> {
+        let mut value: String<24> = String::new();
+        match key {
+            SettingKey::Orientation => value.push_str(self.orientation.as_str()).unwrap(),
+            SettingKey::Offset => {
+                value.push_str(String::<3>::from(self.offset.0).as_str()).unwrap();
+                value.push(',').unwrap();
+                value.push_str(String::<3>::from(self.offset.1).as_str()).unwrap();
+            }
+            SettingKey::Brightness => value.push_str(String::<3>::from(self.brightness).as_str()).unwrap(),
+            SettingKey::Baud => value.push_str(String::<10>::from(self.baud).as_str()).unwrap(),
+            SettingKey::Keymap => {
+                for code in self.keymap {
+                    value.push(code.as_char()).unwrap();
+                }
+            }
+            SettingKey::Parity => value.push_str(self.parity.as_str()).unwrap(),
+            SettingKey::UartPins => value.push_str(self.uart_pins.as_str()).unwrap(),
+            SettingKey::UsbMode => value.push_str(self.usb_mode.as_str()).unwrap(),
+        }
+        value
+    }
+
+    /// Code to report for a physical key
+    pub fn map_key(&self, physical: KeyboardCodes) -> KeyboardCodes {
+        let index = match physical {
+            KeyboardCodes::Down => 0,
+            KeyboardCodes::Up => 1,
+            KeyboardCodes::Left => 2,
+            KeyboardCodes::Right => 3,
+            KeyboardCodes::Ok => 4,
+        };
+        self.keymap[index]
+    }
+}
@@ -0,0 +1,461 @@
+use core::fmt;
+use settings::flash::Flash;
+use utils::crc32::crc32;
+
+/// Flash reserved at the end of the firmware flash, 4 sectors of 4 KB
+pub const SETTINGS_AREA_BYTES: usize = 16 * 1024;
+/// Keys are `0..MAX_KEYS`, the typed layer on top names them
+pub const MAX_KEYS: usize = 32;
+/// Longest value of one record
+pub const MAX_VALUE_BYTES: usize = 32;
+/// Bumped when the record layout changes, sectors of another version are formatted on mount
+pub const FORMAT_VERSION: u16 = 1;
+
+const SECTOR_MAGIC: u32 = 0x5345_5453;
+/// magic u32, format version u16, reserved u16, sequence u32, crc32 of the first 12 bytes,
+/// all little endian
+const SECTOR_HEADER_BYTES: u32 = 16;
+/// key u8, value length u8, crc32 of key, length and value
+const RECORD_HEADER_BYTES: u32 = 6;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StoreError<E> {
+    Flash(E),
+    /// Key not below `MAX_KEYS`
+    BadKey,
+    /// Value longer than `MAX_VALUE_BYTES`
+    TooLong,
+}
+
+impl<E: fmt::Debug> fmt::Display for StoreError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Flash(error) => write!(f, "flash error {:?}", error),
+            StoreError::BadKey => f.write_str("bad key"),
+            StoreError::TooLong => f.write_str("value too long"),
+        }
+    }
+}
+
+/// Key/value store over all sectors of a `Flash`, at least two.
+///
+/// Writes append records to the active sector. When it is full the latest record of every key is
+/// copied to the next sector and its header is written last, so the new sector only becomes
+/// active once complete and the sectors wear evenly in a ring. A write cut by power loss leaves
+/// a record with a bad checksum which is skipped on mount, the previous value stays.
+pub struct SettingsStore<F: Flash> {
+    flash: F,
+    active: u32,
+    sequence: u32,
+    /// Offset of the first byte never written in the active sector
+    free: u32,
+    /// Offset of the latest valid record of each key in the active sector, 0 when none
+    latest: [u32; MAX_KEYS],
+}
+
+impl<F: Flash> SettingsStore<F> {
+    /// Find the newest complete sector, format the flash when there is none or it has
+    /// another format version
+    pub fn mount(flash: F) -> Result<Self, StoreError<F::Error>> {
+        let mut store = SettingsStore {
+            flash,
+            active: 0,
+            sequence: 0,
+            free: SECTOR_HEADER_BYTES,
+            latest: [0; MAX_KEYS],
+        };
+        let mut newest: Option<(u32, u32, u16)> = None;
+        for sector in 0..store.flash.sectors() {
+            if let Some((sequence, version)) = store.read_sector_header(sector)? {
+                if !matches!(newest, Some((_, newest_sequence, _)) if newest_sequence >= sequence) {
+                    newest = Some((sector, sequence, version));
+                }
+            }
+        }
+        match newest {
+            Some((sector, sequence, FORMAT_VERSION)) => {
+                store.active = sector;
+                store.sequence = sequence;
+                store.scan()?;
+            }
+            Some((_, _, version)) => {
+                println!("settings format {} found, formatting", version);
+                store.format()?;
+            }
+            None => store.format()?,
+        }
+        Ok(store)
+    }
+
+    /// Erase all sectors and start an empty log, every key reads as missing
+    pub fn format(&mut self) -> Result<(), StoreError<F::Error>> {
+        for sector in 0..self.flash.sectors() {
+            self.flash.erase(sector).map_err(StoreError::Flash)?;
+        }
+        self.active = 0;
+        self.sequence = 1;
+        self.free = SECTOR_HEADER_BYTES;
+        self.latest = [0; MAX_KEYS];
+        self.write_sector_header(0, 1)
+    }
+
+    /// Copy the value of `key` into `buffer`, its length or `None` when never written
+    pub fn read(&mut self, key: u8, buffer: &mut [u8; MAX_VALUE_BYTES]) -> Result<Option<usize>, StoreError<F::Error>> {
+        let offset = *self.latest.get(key as usize).ok_or(StoreError::BadKey)?;
+        if offset == 0 {
+            return Ok(None);
+        }
+        Ok(self.read_record(offset, buffer)?.map(|(_, len)| len))
+    }
+
+    /// Store `value` for `key`, nothing is written when the value is already stored
+    pub fn write(&mut self, key: u8, value: &[u8]) -> Result<(), StoreError<F::Error>> {
+        if key as usize >= MAX_KEYS {
+            return Err(StoreError::BadKey);
+        }
+        if value.len() > MAX_VALUE_BYTES {
+            return Err(StoreError::TooLong);
+        }
+        let mut current = [0u8; MAX_VALUE_BYTES];
+        if let Some(len) = self.read(key, &mut current)? {
+            if &current[..len] == value {
+                return Ok(());
+            }
+        }
+        if self.free + RECORD_HEADER_BYTES + value.len() as u32 > F::SECTOR_SIZE {
+            self.compact()?;
+        }
+        let offset = self.free;
+        // a failed write leaves the bytes dirty, never write there again
+        self.free += RECORD_HEADER_BYTES + value.len() as u32;
+        self.append_record(self.active, offset, key, value)?;
+        self.latest[key as usize] = offset;
+        Ok(())
+    }
+
+    /// Sector holding the current values and how many sectors were started since the format
+    pub fn position(&self) -> (u32, u32) {
+        (self.active, self.sequence)
+    }
+
+    pub fn flash(&self) -> &F {
+        &self.flash
+    }
+
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Latest record of every key and the free offset of the active sector
+    fn scan(&mut self) -> Result<(), StoreError<F::Error>> {
+        self.latest = [0; MAX_KEYS];
+        let mut value = [0u8; MAX_VALUE_BYTES];
+        let mut offset = SECTOR_HEADER_BYTES;
+        while offset + RECORD_HEADER_BYTES <= F::SECTOR_SIZE {
+            let mut header = [0u8; RECORD_HEADER_BYTES as usize];
+            self.flash.read(self.sector_offset(self.active) + offset, &mut header).map_err(StoreError::Flash)?;
+            let (key, len) = (header[0], header[1] as u32);
+            if key == 0xff || len as usize > MAX_VALUE_BYTES || offset + RECORD_HEADER_BYTES + len > F::SECTOR_SIZE {
+                break;
+            }
+            // torn records fail the checksum and are stepped over
+            if self.read_record(offset, &mut value)?.is_some() && (key as usize) < MAX_KEYS {
+                self.latest[key as usize] = offset;
+            }
+            offset += RECORD_HEADER_BYTES + len;
+        }
+        // a write torn inside the record header leaves garbage the scan can't step over, records
+        // after it would be lost on the next mount. The next write starts a new sector instead.
+        self.free = if self.written_end(self.active)? > offset { F::SECTOR_SIZE } else { offset };
+        Ok(())
+    }
+
+    /// Start the next sector in the ring with the latest value of every key
+    fn compact(&mut self) -> Result<(), StoreError<F::Error>> {
+        let target = (self.active + 1) % self.flash.sectors();
+        self.flash.erase(target).map_err(StoreError::Flash)?;
+        let mut latest = [0u32; MAX_KEYS];
+        let mut offset = SECTOR_HEADER_BYTES;
+        let mut value = [0u8; MAX_VALUE_BYTES];
+        let records = self.latest;
+        for (key, &record) in records.iter().enumerate() {
+            if record == 0 {
+                continue;
+            }
+            if let Some((_, len)) = self.read_record(record, &mut value)? {
+                self.append_record(target, offset, key as u8, &value[..len])?;
+                latest[key] = offset;
+                offset += RECORD_HEADER_BYTES + len as u32;
+            }
+        }
+        // the old sector stays active until this header is complete
+        self.write_sector_header(target, self.sequence + 1)?;
+        self.active = target;
+        self.sequence += 1;
+        self.free = offset;
+        self.latest = latest;
+        println!("settings compacted into sector {}", target);
+        Ok(())
+    }
+
+    fn sector_offset(&self, sector: u32) -> u32 {
+        sector * F::SECTOR_SIZE
+    }
+
+    /// Sequence and format version of a complete sector header
+    fn read_sector_header(&mut self, sector: u32) -> Result<Option<(u32, u16)>, StoreError<F::Error>> {
+        let mut header = [0u8; SECTOR_HEADER_BYTES as usize];
+        self.flash.read(self.sector_offset(sector), &mut header).map_err(StoreError::Flash)?;
+        let word = |offset: usize| u32::from_le_bytes([header[offset], header[offset + 1], header[offset + 2], header[offset + 3]]);
+        if word(0) != SECTOR_MAGIC || word(12) != crc32(&header[..12]) {
+            return Ok(None);
+        }
+        Ok(Some((word(8), u16::from_le_bytes([header[4], header[5]]))))
+    }
+
+    fn write_sector_header(&mut self, sector: u32, sequence: u32) -> Result<(), StoreError<F::Error>> {
+        let mut header = [0u8; SECTOR_HEADER_BYTES as usize];
+        header[0..4].copy_from_slice(&SECTOR_MAGIC.to_le_bytes());
+        header[4..6].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header[8..12].copy_from_slice(&sequence.to_le_bytes());
+        let checksum = crc32(&header[..12]);
+        header[12..16].copy_from_slice(&checksum.to_le_bytes());
+        self.flash.write(self.sector_offset(sector), &header).map_err(StoreError::Flash)
+    }
+
+    /// Key and value length of the record at `offset` of the active sector, `None` when torn
+    fn read_record(&mut self, offset: u32, value: &mut [u8; MAX_VALUE_BYTES]) -> Result<Option<(u8, usize)>, StoreError<F::Error>> {
+        let start = self.sector_offset(self.active) + offset;
+        let mut header = [0u8; RECORD_HEADER_BYTES as usize];
+        self.flash.read(start, &mut header).map_err(StoreError::Flash)?;
+        let len = header[1] as usize;
+        if len > MAX_VALUE_BYTES {
+            return Ok(None);
+        }
+        self.flash.read(start + RECORD_HEADER_BYTES, &mut value[..len]).map_err(StoreError::Flash)?;
+        let checksum = u32::from_le_bytes([header[2], header[3], header[4], header[5]]);
+        if checksum != record_checksum(header[0], &value[..len]) {
+            return Ok(None);
+        }
+        Ok(Some((header[0], len)))
+    }
+
+    fn append_record(&mut self, sector: u32, offset: u32, key: u8, value: &[u8]) -> Result<(), StoreError<F::Error>> {
+        let mut record = [0u8; RECORD_HEADER_BYTES as usize + MAX_VALUE_BYTES];
+        record[0] = key;
+        record[1] = value.len() as u8;
+        record[2..6].copy_from_slice(&record_checksum(key, value).to_le_bytes());
+        record[6..6 + value.len()].copy_from_slice(value);
+        let len = RECORD_HEADER_BYTES as usize + value.len();
+        self.flash.write(self.sector_offset(sector) + offset, &record[..len]).map_err(StoreError::Flash)
+    }
+
+    /// Offset after the last byte of the sector which is not erased
+    fn written_end(&mut self, sector: u32) -> Result<u32, StoreError<F::Error>> {
+        let mut chunk = [0u8; 64];
+        let mut end = F::SECTOR_SIZE;
+        while end > SECTOR_HEADER_BYTES {
+            let start = end.saturating_sub(chunk.len() as u32).max(SECTOR_HEADER_BYTES);
+            let chunk = &mut chunk[..(end - start) as usize];
+            self.flash.read(self.sector_offset(sector) + start, chunk).map_err(StoreError::Flash)?;
+            if let Some(last) = chunk.iter().rposition(|byte| *byte != 0xff) {
+                return Ok(start + last as u32 + 1);
+            }
+            end = start;
+        }
+        Ok(SECTOR_HEADER_BYTES)
+    }
+}
+
+fn record_checksum(key: u8, value: &[u8]) -> u32 {
+    let mut data = [0u8; 2 + MAX_VALUE_BYTES];
+    data[0] = key;
+    data[1] = value.len() as u8;
+    data[2..2 + value.len()].copy_from_slice(value);
+    crc32(&data[..2 + value.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use settings::flash::{MemFlash, MemFlashError, MEM_FLASH_SECTOR_SIZE};
+
+    type TestFlash = MemFlash<SETTINGS_AREA_BYTES>;
+
+    fn read(store: &mut SettingsStore<TestFlash>, key: u8) -> Option<([u8; MAX_VALUE_BYTES], usize)> {
+        let mut value = [0u8; MAX_VALUE_BYTES];
+        store.read(key, &mut value).unwrap().map(|len| (value, len))
+    }
+
+    fn assert_value(store: &mut SettingsStore<TestFlash>, key: u8, expected: &[u8]) {
+        let (value, len) = read(store, key).expect("value missing");
+        assert_eq!(&value[..len], expected);
+    }
+
+    fn copy(flash: &TestFlash) -> TestFlash {
+        let mut bytes = [0u8; SETTINGS_AREA_BYTES];
+        bytes.copy_from_slice(flash.as_bytes());
+        MemFlash::from_bytes(bytes)
+    }
+
+    fn remount(store: SettingsStore<TestFlash>) -> SettingsStore<TestFlash> {
+        let mut flash = store.into_inner();
+        flash.restore_power();
+        SettingsStore::mount(flash).unwrap()
+    }
+
+    /// Store where the next write of a 32 byte value to key 1 starts a new sector
+    fn full_store() -> SettingsStore<TestFlash> {
+        let mut store = SettingsStore::mount(TestFlash::new()).unwrap();
+        store.write(2, b"keep").unwrap();
+        let mut round = 0u8;
+        loop {
+            let value = [round; MAX_VALUE_BYTES];
+            if store.free + 2 * (RECORD_HEADER_BYTES + MAX_VALUE_BYTES as u32) > TestFlash::SECTOR_SIZE {
+                store.write(1, &value).unwrap();
+                return store;
+            }
+            store.write(1, &value).unwrap();
+            round = round.wrapping_add(1);
+        }
+    }
+
+    #[test]
+    fn values_survive_a_remount() {
+        let mut store = SettingsStore::mount(TestFlash::new()).unwrap();
+        assert_eq!(read(&mut store, 3), None);
+        store.write(3, b"portrait").unwrap();
+        store.write(4, b"").unwrap();
+        store.write(3, b"landscape").unwrap();
+        let mut store = remount(store);
+        assert_value(&mut store, 3, b"landscape");
+        assert_value(&mut store, 4, b"");
+        assert_eq!(read(&mut store, 5), None);
+    }
+
+    #[test]
+    fn bad_keys_and_values() {
+        let mut store = SettingsStore::mount(TestFlash::new()).unwrap();
+        assert_eq!(store.write(MAX_KEYS as u8, b"x"), Err(StoreError::BadKey));
+        assert_eq!(store.write(0, &[0; MAX_VALUE_BYTES + 1]), Err(StoreError::TooLong));
+        let mut value = [0u8; MAX_VALUE_BYTES];
+        assert_eq!(store.read(0xff, &mut value), Err(StoreError::BadKey));
+    }
+
+    #[test]
+    fn same_value_is_not_written_again() {
+        let mut store = SettingsStore::mount(TestFlash::new()).unwrap();
+        store.write(1, b"60").unwrap();
+        let free = store.free;
+        store.write(1, b"60").unwrap();
+        assert_eq!(store.free, free);
+    }
+
+    #[test]
+    fn sectors_wear_evenly() {
+        let mut store = SettingsStore::mount(TestFlash::new()).unwrap();
+        for round in 0..2_000u32 {
+            store.write(1, &round.to_le_bytes()).unwrap();
+            store.write(2, b"keep").unwrap();
+        }
+        let (_, sequence) = store.position();
+        assert!(sequence >= 5);
+        let counts = store.flash().erase_counts();
+        let (min, max) = (counts.iter().min().unwrap(), counts.iter().max().unwrap());
+        assert!(max - min <= 1, "{:?}", counts);
+        let mut store = remount(store);
+        assert_value(&mut store, 1, &1_999u32.to_le_bytes());
+        assert_value(&mut store, 2, b"keep");
+    }
+
+    #[test]
+    fn other_format_version_is_formatted() {
+        let mut store = SettingsStore::mount(TestFlash::new()).unwrap();
+        store.write(1, b"old").unwrap();
+        let mut flash = store.into_inner();
+        // version field of sector 0 with a matching header checksum
+        let mut header = [0u8; SECTOR_HEADER_BYTES as usize];
+        flash.read(0, &mut header).unwrap();
+        header[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let checksum = crc32(&header[..12]);
+        header[12..16].copy_from_slice(&checksum.to_le_bytes());
+        flash.erase(0).unwrap();
+        flash.write(0, &header).unwrap();
+        let mut store = SettingsStore::mount(flash).unwrap();
+        assert_eq!(read(&mut store, 1), None);
+    }
+
+    #[test]
+    fn power_loss_during_a_write_keeps_old_or_new_value() {
+        let mut base = SettingsStore::mount(TestFlash::new()).unwrap();
+        base.write(1, b"old value").unwrap();
+        base.write(2, b"keep").unwrap();
+        let base = base.into_inner();
+
+        for budget in 0.. {
+            let mut store = SettingsStore::mount(copy(&base)).unwrap();
+            store.flash.cut_power_after(budget);
+            let result = store.write(1, b"new value");
+            let done = result.is_ok();
+            if !done {
+                assert_eq!(result, Err(StoreError::Flash(MemFlashError::PowerLost)));
+            }
+
+            let mut store = remount(store);
+            let (value, len) = read(&mut store, 1).expect("value lost");
+            assert!(&value[..len] == b"old value" || &value[..len] == b"new value", "budget {}", budget);
+            if done {
+                assert_eq!(&value[..len], b"new value");
+            }
+            assert_value(&mut store, 2, b"keep");
+            // the torn record is not written over
+            store.write(1, b"after").unwrap();
+            let mut store = remount(store);
+            assert_value(&mut store, 1, b"after");
+            assert_value(&mut store, 2, b"keep");
+            if done {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn power_loss_during_compaction_keeps_old_or_new_value() {
+        let base = full_store();
+        let (old_value, old_len) = {
+            let mut store = SettingsStore::mount(copy(base.flash())).unwrap();
+            read(&mut store, 1).unwrap()
+        };
+        let base = base.into_inner();
+        let new_value = [0xa5u8; MAX_VALUE_BYTES];
+
+        // through the erase in steps, then every byte of the copied records and the new sector header
+        let mut budget = 0;
+        loop {
+            let mut store = SettingsStore::mount(copy(&base)).unwrap();
+            let (active, sequence) = store.position();
+            store.flash.cut_power_after(budget);
+            let done = store.write(1, &new_value).is_ok();
+            if done {
+                assert_eq!(store.position(), ((active + 1) % 4, sequence + 1));
+            }
+
+            let mut store = remount(store);
+            let (value, len) = read(&mut store, 1).expect("value lost");
+            assert!(value[..len] == old_value[..old_len] || value[..len] == new_value[..], "budget {}", budget);
+            if done {
+                assert_eq!(&value[..len], &new_value[..]);
+            }
+            assert_value(&mut store, 2, b"keep");
+            store.write(3, b"after").unwrap();
+            let mut store = remount(store);
+            assert_value(&mut store, 3, b"after");
+            assert_value(&mut store, 2, b"keep");
+            if done {
+                break;
+            }
+            budget += if budget < MEM_FLASH_SECTOR_SIZE as usize { 256 } else { 1 };
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use core::convert::TryInto;
+use settings::flash::Flash;
+use update::layout::UpdateLayout;
+use utils::crc32::crc32;
+
+const STATE_MAGIC: u32 = 0x4657_5550;
+/// magic, image length, image crc32, length of the image it replaces, crc32 of the first 16 bytes,
+/// all little endian u32
+const HEADER_BYTES: usize = 20;
+/// Marks are written once after the header, a mark cut by power loss reads as not set and is
+/// written again, programming can only clear more bits
+const MARK_WORD: u32 = 0x4d41_524b;
+/// A byte per copy step, 0 when done. Room for 3 steps of 256 sectors each.
+const SWAP_PROGRESS: u32 = 0x100;
+const REVERT_PROGRESS: u32 = 0x800;
+const PROGRESS_BYTES: u32 = 0x700;
+
+/// Steps of an update, each a word of the state sector
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Mark {
+    /// Bootloader put the staged image into `active`
+    Swapped = 0,
+    /// Bootloader started the new image on trial
+    Started = 1,
+    /// New image confirmed itself
+    Confirmed = 2,
+    /// Bootloader began putting the previous image back
+    RevertStarted = 3,
+    RevertDone = 4,
+    /// Pi was told about the rollback
+    Reported = 5,
+}
+
+impl Mark {
+    fn offset(self) -> u32 {
+        HEADER_BYTES as u32 + 4 * self as u32
+    }
+}
+
+/// What the state sector asks for
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FirmwareStatus {
+    /// Nothing to do, also after a confirmed update
+    Idle,
+    /// Staged image is to be swapped in, the swap may have begun
+    SwapPending,
+    /// Swapped, not started yet
+    Swapped,
+    /// New image started and not confirmed. The firmware confirms it, the bootloader finding
+    /// this on boot rolls back.
+    Testing,
+    /// Previous image is being put back
+    Reverting,
+    /// Previous image is back, the Pi was not told yet
+    RolledBack,
+}
+
+/// The state sector: a header written by the firmware to request an update and marks the
+/// bootloader and the firmware add as the update goes on. Nothing is erased until the next
+/// request, so every step can be redone after power loss.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BootState {
+    /// No valid header, a new board or a request cut by power loss
+    pub empty: bool,
+    pub len: u32,
+    pub checksum: u32,
+    /// Length of the image which was running when the update was requested
+    pub previous_len: u32,
+    marks: u8,
+}
+
+impl BootState {
+    pub fn read<F: Flash>(flash: &mut F, layout: &UpdateLayout) -> Result<Self, F::Error> {
+        let mut header = [0u8; HEADER_BYTES];
+        flash.read(layout.state, &mut header)?;
+        let word = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+        let mut state = BootState {
+            empty: word(0) != STATE_MAGIC || word(16) != crc32(&header[..16]),
+            len: word(4),
+            checksum: word(8),
+            previous_len: word(12),
+            marks: 0,
+        };
+        if state.empty {
+            return Ok(state);
+        }
+        for mark in [Mark::Swapped, Mark::Started, Mark::Confirmed, Mark::RevertStarted, Mark::RevertDone, Mark::Reported] {
+            let mut value = [0u8; 4];
+            flash.read(layout.state + mark.offset(), &mut value)?;
+            if u32::from_le_bytes(value) == MARK_WORD {
+                state.marks |= 1 << mark as u8;
+            }
+        }
+        Ok(state)
+    }
+
+    /// Ask the bootloader to swap in the staged image of `len` bytes on the next boot.
+    /// A request cut by power loss leaves an empty state, the running firmware stays.
+    pub fn request<F: Flash>(flash: &mut F, layout: &UpdateLayout, len: u32, checksum: u32, previous_len: u32) -> Result<Self, F::Error> {
+        flash.erase(layout.state / F::SECTOR_SIZE)?;
+        let mut header = [0u8; HEADER_BYTES];
+        header[0..4].copy_from_slice(&STATE_MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&len.to_le_bytes());
+        header[8..12].copy_from_slice(&checksum.to_le_bytes());
+        header[12..16].copy_from_slice(&previous_len.to_le_bytes());
+        let header_checksum = crc32(&header[..16]);
+        header[16..20].copy_from_slice(&header_checksum.to_le_bytes());
+        flash.write(layout.state, &header)?;
+        Ok(BootState { empty: false, len, checksum, previous_len, marks: 0 })
+    }
+
+    pub fn is_marked(&self, mark: Mark) -> bool {
+        self.marks & (1 << mark as u8) != 0
+    }
+
+    pub fn mark<F: Flash>(&mut self, flash: &mut F, layout: &UpdateLayout, mark: Mark) -> Result<(), F::Error> {
+        if self.is_marked(mark) {
+            return Ok(());
+        }
+        flash.write(layout.state + mark.offset(), &MARK_WORD.to_le_bytes())?;
+        self.marks |= 1 << mark as u8;
+        Ok(())
+    }
+
+    pub fn status(&self) -> FirmwareStatus {
+        if self.empty {
+            FirmwareStatus::Idle
+        } else if self.is_marked(Mark::RevertDone) {
+            if self.is_marked(Mark::Reported) { FirmwareStatus::Idle } else { FirmwareStatus::RolledBack }
+        } else if self.is_marked(Mark::RevertStarted) {
+            FirmwareStatus::Reverting
+        } else if self.is_marked(Mark::Confirmed) {
+            FirmwareStatus::Idle
+        } else if self.is_marked(Mark::Started) {
+            FirmwareStatus::Testing
+        } else if self.is_marked(Mark::Swapped) {
+            FirmwareStatus::Swapped
+        } else {
+            FirmwareStatus::SwapPending
+        }
+    }
+
+    /// Sectors the swap and the revert exchange, enough for the larger of both images
+    pub fn swap_sectors(&self, layout: &UpdateLayout, sector_size: u32) -> u32 {
+        let len = if self.previous_len == 0 { 0 } else { self.len.max(self.previous_len) };
+        layout.sectors(len, sector_size).min(PROGRESS_BYTES / 3)
+    }
+
+    /// Copy steps of the swap (or the revert) which are done
+    pub fn progress<F: Flash>(&self, flash: &mut F, layout: &UpdateLayout, revert: bool) -> Result<u32, F::Error> {
+        let start = layout.state + if revert { REVERT_PROGRESS } else { SWAP_PROGRESS };
+        let mut chunk = [0u8; 64];
+        let mut done = 0;
+        while done < PROGRESS_BYTES {
+            flash.read(start + done, &mut chunk)?;
+            match chunk.iter().position(|byte| *byte != 0) {
+                Some(index) => return Ok(done + index as u32),
+                None => done += chunk.len() as u32,
+            }
+        }
+        Ok(done)
+    }
+
+    pub fn step_done<F: Flash>(&self, flash: &mut F, layout: &UpdateLayout, revert: bool, step: u32) -> Result<(), F::Error> {
+        let start = layout.state + if revert { REVERT_PROGRESS } else { SWAP_PROGRESS };
+        flash.write(start + step, &[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use settings::flash::MemFlash;
+    use update::layout::TEST_LAYOUT;
+
+    type TestFlash = MemFlash<0x8000>;
+
+    fn read(flash: &mut TestFlash) -> BootState {
+        BootState::read(flash, &TEST_LAYOUT).unwrap()
+    }
+
+    #[test]
+    fn erased_state_is_idle() {
+        let mut flash = TestFlash::new();
+        let state = read(&mut flash);
+        assert!(state.empty);
+        assert_eq!(state.status(), FirmwareStatus::Idle);
+    }
+
+    #[test]
+    fn request_is_read_back() {
+        let mut flash = TestFlash::new();
+        BootState::request(&mut flash, &TEST_LAYOUT, 9000, 0x1234_5678, 5000).unwrap();
+        let state = read(&mut flash);
+        assert!(!state.empty);
+        assert_eq!((state.len, state.checksum, state.previous_len), (9000, 0x1234_5678, 5000));
+        assert_eq!(state.status(), FirmwareStatus::SwapPending);
+        assert_eq!(state.swap_sectors(&TEST_LAYOUT, 4096), 3);
+    }
+
+    #[test]
+    fn marks_move_the_status_on() {
+        let mut flash = TestFlash::new();
+        let mut state = BootState::request(&mut flash, &TEST_LAYOUT, 100, 0, 100).unwrap();
+        let steps = [
+            (Mark::Swapped, FirmwareStatus::Swapped),
+            (Mark::Started, FirmwareStatus::Testing),
+            (Mark::RevertStarted, FirmwareStatus::Reverting),
+            (Mark::RevertDone, FirmwareStatus::RolledBack),
+            (Mark::Reported, FirmwareStatus::Idle),
+        ];
+        for (mark, status) in steps {
+            state.mark(&mut flash, &TEST_LAYOUT, mark).unwrap();
+            assert_eq!(state.status(), status);
+            assert_eq!(read(&mut flash), state);
+        }
+    }
+
+    #[test]
+    fn confirmed_update_is_idle() {
+        let mut flash = TestFlash::new();
+        let mut state = BootState::request(&mut flash, &TEST_LAYOUT, 100, 0, 100).unwrap();
+        for mark in [Mark::Swapped, Mark::Started, Mark::Confirmed] {
+            state.mark(&mut flash, &TEST_LAYOUT, mark).unwrap();
+        }
+        assert_eq!(read(&mut flash).status(), FirmwareStatus::Idle);
+    }
+
+    #[test]
+    fn torn_mark_is_written_again() {
+        let mut flash = TestFlash::new();
+        BootState::request(&mut flash, &TEST_LAYOUT, 100, 0, 100).unwrap();
+        let mut state = read(&mut flash);
+        flash.cut_power_after(2);
+        assert!(state.mark(&mut flash, &TEST_LAYOUT, Mark::Swapped).is_err());
+        flash.restore_power();
+        let mut state = read(&mut flash);
+        assert!(!state.is_marked(Mark::Swapped));
+        state.mark(&mut flash, &TEST_LAYOUT, Mark::Swapped).unwrap();
+        assert!(read(&mut flash).is_marked(Mark::Swapped));
+    }
+
+    #[test]
+    fn torn_request_keeps_the_running_firmware() {
+        for budget in [0, 100, 4096, 4096 + 10] {
+            let mut flash = TestFlash::new();
+            flash.cut_power_after(budget);
+            assert!(BootState::request(&mut flash, &TEST_LAYOUT, 100, 0, 100).is_err());
+            flash.restore_power();
+            assert_eq!(read(&mut flash).status(), FirmwareStatus::Idle);
+        }
+    }
+
+    #[test]
+    fn progress_counts_done_steps() {
+        let mut flash = TestFlash::new();
+        let state = BootState::request(&mut flash, &TEST_LAYOUT, 100, 0, 100).unwrap();
+        assert_eq!(state.progress(&mut flash, &TEST_LAYOUT, false).unwrap(), 0);
+        for step in 0..70 {
+            state.step_done(&mut flash, &TEST_LAYOUT, false, step).unwrap();
+        }
+        assert_eq!(state.progress(&mut flash, &TEST_LAYOUT, false).unwrap(), 70);
+        assert_eq!(state.progress(&mut flash, &TEST_LAYOUT, true).unwrap(), 0);
+    }
+
+    #[test]
+    fn unknown_previous_length_swaps_the_whole_partition() {
+        let mut flash = TestFlash::new();
+        let state = BootState::request(&mut flash, &TEST_LAYOUT, 100, 0, 0).unwrap();
+        assert_eq!(state.swap_sectors(&TEST_LAYOUT, 4096), 3);
+        let state = BootState::request(&mut flash, &TEST_LAYOUT, 100, 0, 50).unwrap();
+        assert_eq!(state.swap_sectors(&TEST_LAYOUT, 4096), 1);
+    }
+}
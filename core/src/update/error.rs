@@ -0,0 +1,83 @@
+use core::fmt;
+
+/// Why a firmware command failed, reported back to the Pi as `fw_error=<code>[:<bytes>]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UpdateError {
+    /// `fw_begin` is not `<len>:<crc32 hex>` or the image is empty
+    BadHeader,
+    /// Bigger than the update partition
+    TooLarge,
+    /// The running firmware is on trial, its predecessor in the update partition is kept
+    /// until it is confirmed
+    Busy,
+    /// No upload started, or `fw_apply` before `fw_end` checked it
+    NotStarted,
+    /// Chunk does not continue the received data, carries the offset expected instead
+    Offset(u32),
+    /// Bad base64, chunk longer than `CHUNK_BYTES` or more data than announced
+    BadData,
+    /// `fw_end` before all data came, carries the number of bytes received
+    Incomplete(u32),
+    /// CRC-32 of a chunk or of the whole image differs
+    Checksum,
+    /// Staged data reads back different from what was written
+    Verify,
+    /// Vector table does not point into the active partition, not built for the bootloader
+    BadImage,
+    /// Flash erase or write failed
+    Storage,
+}
+
+impl UpdateError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            UpdateError::BadHeader => "bad_header",
+            UpdateError::TooLarge => "too_large",
+            UpdateError::Busy => "busy",
+            UpdateError::NotStarted => "not_started",
+            UpdateError::Offset(_) => "offset",
+            UpdateError::BadData => "bad_data",
+            UpdateError::Incomplete(_) => "incomplete",
+            UpdateError::Checksum => "checksum",
+            UpdateError::Verify => "verify",
+            UpdateError::BadImage => "bad_image",
+            UpdateError::Storage => "storage",
+        }
+    }
+
+    /// Byte count of `Offset` and `Incomplete`
+    pub fn bytes(&self) -> Option<u32> {
+        match *self {
+            UpdateError::Offset(bytes) | UpdateError::Incomplete(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Reverse of `code` and `bytes`, used on the Pi side
+    pub fn from_code(code: &str, bytes: Option<u32>) -> Option<Self> {
+        match (code, bytes) {
+            ("bad_header", _) => Some(UpdateError::BadHeader),
+            ("too_large", _) => Some(UpdateError::TooLarge),
+            ("busy", _) => Some(UpdateError::Busy),
+            ("not_started", _) => Some(UpdateError::NotStarted),
+            ("offset", Some(bytes)) => Some(UpdateError::Offset(bytes)),
+            ("bad_data", _) => Some(UpdateError::BadData),
+            ("incomplete", Some(bytes)) => Some(UpdateError::Incomplete(bytes)),
+            ("checksum", _) => Some(UpdateError::Checksum),
+            ("verify", _) => Some(UpdateError::Verify),
+            ("bad_image", _) => Some(UpdateError::BadImage),
+            ("storage", _) => Some(UpdateError::Storage),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bytes() {
+            Some(bytes) => write!(f, "{} at byte {}", self.code(), bytes),
+            None => f.write_str(self.code()),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use core::fmt;
+
+/// Progress of an update, reported to the Pi as `fw=<code>[:<offset>]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FirmwareEvent {
+    /// Answer to `fw_status`: nothing staged, the running firmware stays
+    Idle,
+    /// Upload started, send the first chunk
+    Ready,
+    /// Chunk stored, carries the offset of the next one. Also the answer to `fw_status` during
+    /// an upload, the Pi can resume from there.
+    Next(u32),
+    /// Whole image staged and checked, `fw_apply` installs it
+    Verified,
+    /// Rebooting, the bootloader installs the staged image
+    Applying,
+    /// New firmware runs on trial, sent on first contact. A reset before it is confirmed
+    /// puts the previous one back.
+    Testing,
+    /// New firmware ran long enough talking to the Pi, it stays
+    Confirmed,
+    /// New firmware reset before it was confirmed, the previous one runs again. Sent once on
+    /// first contact.
+    RolledBack,
+}
+
+impl FirmwareEvent {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FirmwareEvent::Idle => "idle",
+            FirmwareEvent::Ready => "ready",
+            FirmwareEvent::Next(_) => "next",
+            FirmwareEvent::Verified => "verified",
+            FirmwareEvent::Applying => "applying",
+            FirmwareEvent::Testing => "testing",
+            FirmwareEvent::Confirmed => "confirmed",
+            FirmwareEvent::RolledBack => "rolled_back",
+        }
+    }
+
+    pub fn offset(&self) -> Option<u32> {
+        match *self {
+            FirmwareEvent::Next(offset) => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Reverse of `code` and `offset`, used on the Pi side
+    pub fn from_code(code: &str, offset: Option<u32>) -> Option<Self> {
+        match (code, offset) {
+            ("idle", None) => Some(FirmwareEvent::Idle),
+            ("ready", None) => Some(FirmwareEvent::Ready),
+            ("next", Some(offset)) => Some(FirmwareEvent::Next(offset)),
+            ("verified", None) => Some(FirmwareEvent::Verified),
+            ("applying", None) => Some(FirmwareEvent::Applying),
+            ("testing", None) => Some(FirmwareEvent::Testing),
+            ("confirmed", None) => Some(FirmwareEvent::Confirmed),
+            ("rolled_back", None) => Some(FirmwareEvent::RolledBack),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FirmwareEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset() {
+            Some(offset) => write!(f, "{} {}", self.code(), offset),
+            None => f.write_str(self.code()),
+        }
+    }
+}
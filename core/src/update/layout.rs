@@ -0,0 +1,67 @@
+use core::convert::TryInto;
+
+/// Flash is mapped here for reading and execution
+pub const XIP_BASE: u32 = 0x1000_0000;
+/// RAM an image's initial stack pointer may point into, striped banks and both scratch banks
+const RAM_START: u32 = 0x2000_0000;
+const RAM_END: u32 = 0x2004_2000;
+
+/// Where the update areas are, offsets from the start of flash. The bootloader and boot2 sit
+/// before `state`, the settings area after the update partition.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct UpdateLayout {
+    /// One sector, what the bootloader has to do and how far it got
+    pub state: u32,
+    /// One sector, holds a sector of the running image while the swap moves it
+    pub scratch: u32,
+    /// Running image, linked to start here
+    pub active: u32,
+    /// Staged image, the previous one after an update
+    pub update: u32,
+    /// Size of `active` and `update` each
+    pub partition_bytes: u32,
+}
+
+/// Pico with 2 MB flash: 32 KB bootloader, state and scratch sectors, two partitions of 996 KB
+/// and the 16 KB settings area at the end
+pub const PICO_LAYOUT: UpdateLayout = UpdateLayout {
+    state: 0x8000,
+    scratch: 0x9000,
+    active: 0xa000,
+    update: 0x10_3000,
+    partition_bytes: 0xf_9000,
+};
+
+/// Layout over a `MemFlash<0x8000>` for tests, partitions of 3 sectors
+#[cfg(test)]
+pub const TEST_LAYOUT: UpdateLayout = UpdateLayout {
+    state: 0x0000,
+    scratch: 0x1000,
+    active: 0x2000,
+    update: 0x5000,
+    partition_bytes: 0x3000,
+};
+
+impl UpdateLayout {
+    /// Sectors of `sector_size` bytes an image of `len` bytes covers, the whole partition when
+    /// `len` is unknown (0)
+    pub fn sectors(&self, len: u32, sector_size: u32) -> u32 {
+        let len = if len == 0 { self.partition_bytes } else { len.min(self.partition_bytes) };
+        len.div_ceil(sector_size)
+    }
+
+    /// Whether the first 8 bytes of an image, the initial stack pointer and the reset vector,
+    /// fit an image linked to run from `active`
+    pub fn check_vector_table(&self, table: &[u8; 8]) -> bool {
+        let stack_pointer = u32::from_le_bytes(table[0..4].try_into().unwrap());
+        let reset_vector = u32::from_le_bytes(table[4..8].try_into().unwrap());
+        let active_start = XIP_BASE + self.active;
+        let reset_address = reset_vector & !1;
+        (RAM_START..=RAM_END).contains(&stack_pointer)
+            && stack_pointer % 4 == 0
+            // thumb code
+            && reset_vector & 1 == 1
+            && reset_address >= active_start + 8
+            && reset_address < active_start + self.partition_bytes
+    }
+}
@@ -0,0 +1,6 @@
+pub mod layout;
+pub mod error;
+pub mod event;
+pub mod boot_state;
+pub mod receiver;
+pub mod swap;
@@ -0,0 +1,398 @@
+use heapless::Vec;
+use settings::flash::Flash;
+use update::boot_state::{BootState, FirmwareStatus, Mark};
+use update::error::UpdateError;
+use update::event::FirmwareEvent;
+use update::layout::UpdateLayout;
+use utils::crc32::{crc32, crc32_update};
+
+/// Longest chunk of `fw_data`, 1368 characters of base64
+pub const CHUNK_BYTES: usize = 1024;
+
+/// Firmware commands, the device runs them between loops with the flash at hand
+#[derive(Debug, PartialEq, Eq, Clone)]
+// one is kept at a time, there is no heap to box the chunk
+#[allow(clippy::large_enum_variant)]
+pub enum FirmwareCommand {
+    Begin { len: u32, checksum: u32 },
+    Data { offset: u32, checksum: u32, data: Vec<u8, CHUNK_BYTES> },
+    End,
+    Apply,
+    Status,
+    /// Device itself: the image on trial worked long enough
+    Confirm,
+    /// Device itself: the Pi was told about the rollback
+    RollbackReported,
+}
+
+struct Upload {
+    len: u32,
+    checksum: u32,
+    received: u32,
+    /// CRC-32 of the received bytes
+    crc: u32,
+    verified: bool,
+}
+
+/// Stages an image sent in chunks in the update partition and asks the bootloader to install it.
+/// The Pi sends a chunk once the previous one is answered, every chunk is read back after it
+/// is written.
+pub struct UpdateReceiver {
+    layout: UpdateLayout,
+    upload: Option<Upload>,
+}
+
+impl UpdateReceiver {
+    pub fn new(layout: UpdateLayout) -> Self {
+        UpdateReceiver { layout, upload: None }
+    }
+
+    /// Run `command`, `running_len` is the length of the running image. `None` when there is
+    /// nothing to answer.
+    pub fn handle<F: Flash>(&mut self, flash: &mut F, command: FirmwareCommand, running_len: u32) -> Result<Option<FirmwareEvent>, UpdateError> {
+        match command {
+            FirmwareCommand::Begin { len, checksum } => self.begin(flash, len, checksum).map(Some),
+            FirmwareCommand::Data { offset, checksum, data } => self.write(flash, offset, &data, checksum).map(Some),
+            FirmwareCommand::End => self.finish(flash).map(Some),
+            FirmwareCommand::Apply => self.apply(flash, running_len).map(Some),
+            FirmwareCommand::Status => self.status(flash).map(Some),
+            FirmwareCommand::Confirm => self.mark(flash, Mark::Confirmed).map(|_| Some(FirmwareEvent::Confirmed)),
+            FirmwareCommand::RollbackReported => self.mark(flash, Mark::Reported).map(|_| None),
+        }
+    }
+
+    /// Start a new upload of `len` bytes, drops an unfinished one
+    pub fn begin<F: Flash>(&mut self, flash: &mut F, len: u32, checksum: u32) -> Result<FirmwareEvent, UpdateError> {
+        if len == 0 {
+            return Err(UpdateError::BadHeader);
+        }
+        if len > self.layout.partition_bytes {
+            return Err(UpdateError::TooLarge);
+        }
+        match self.boot_state(flash)?.status() {
+            FirmwareStatus::Testing => return Err(UpdateError::Busy),
+            // applied without rebooting, the bootloader must not install a half written image
+            FirmwareStatus::SwapPending => {
+                flash.erase(self.layout.state / F::SECTOR_SIZE).map_err(|_| UpdateError::Storage)?;
+            }
+            _ => {}
+        }
+        self.upload = Some(Upload { len, checksum, received: 0, crc: 0, verified: false });
+        Ok(FirmwareEvent::Ready)
+    }
+
+    /// Stage the chunk at `offset`. The last chunk sent again, because its answer got lost,
+    /// is answered again.
+    pub fn write<F: Flash>(&mut self, flash: &mut F, offset: u32, data: &[u8], checksum: u32) -> Result<FirmwareEvent, UpdateError> {
+        let layout = self.layout;
+        let upload = self.upload.as_mut().ok_or(UpdateError::NotStarted)?;
+        if data.is_empty() || data.len() > CHUNK_BYTES {
+            return Err(UpdateError::BadData);
+        }
+        if crc32(data) != checksum {
+            return Err(UpdateError::Checksum);
+        }
+        let end = offset.saturating_add(data.len() as u32);
+        if offset != upload.received {
+            if end == upload.received && staged_matches(flash, layout.update + offset, data)? {
+                return Ok(FirmwareEvent::Next(end));
+            }
+            return Err(UpdateError::Offset(upload.received));
+        }
+        if end > upload.len {
+            return Err(UpdateError::BadData);
+        }
+        // sectors are erased when the data first reaches them
+        let mut sector_start = offset.div_ceil(F::SECTOR_SIZE) * F::SECTOR_SIZE;
+        while sector_start < end {
+            flash.erase((layout.update + sector_start) / F::SECTOR_SIZE).map_err(|_| UpdateError::Storage)?;
+            sector_start += F::SECTOR_SIZE;
+        }
+        flash.write(layout.update + offset, data).map_err(|_| UpdateError::Storage)?;
+        if !staged_matches(flash, layout.update + offset, data)? {
+            return Err(UpdateError::Verify);
+        }
+        upload.crc = crc32_update(upload.crc, data);
+        upload.received = end;
+        Ok(FirmwareEvent::Next(end))
+    }
+
+    /// Check the staged image as a whole
+    pub fn finish<F: Flash>(&mut self, flash: &mut F) -> Result<FirmwareEvent, UpdateError> {
+        let layout = self.layout;
+        let upload = self.upload.as_mut().ok_or(UpdateError::NotStarted)?;
+        if upload.received != upload.len {
+            return Err(UpdateError::Incomplete(upload.received));
+        }
+        if upload.crc != upload.checksum {
+            return Err(UpdateError::Checksum);
+        }
+        let mut vector_table = [0u8; 8];
+        flash.read(layout.update, &mut vector_table).map_err(|_| UpdateError::Storage)?;
+        if upload.len < 8 || !layout.check_vector_table(&vector_table) {
+            return Err(UpdateError::BadImage);
+        }
+        upload.verified = true;
+        Ok(FirmwareEvent::Verified)
+    }
+
+    /// Ask the bootloader to install the verified image, the device reboots after answering
+    pub fn apply<F: Flash>(&mut self, flash: &mut F, running_len: u32) -> Result<FirmwareEvent, UpdateError> {
+        let (len, checksum) = match &self.upload {
+            Some(upload) if upload.verified => (upload.len, upload.checksum),
+            _ => return Err(UpdateError::NotStarted),
+        };
+        BootState::request(flash, &self.layout, len, checksum, running_len).map_err(|_| UpdateError::Storage)?;
+        self.upload = None;
+        Ok(FirmwareEvent::Applying)
+    }
+
+    /// Trial or rollback of the last update, else how far the upload is
+    pub fn status<F: Flash>(&self, flash: &mut F) -> Result<FirmwareEvent, UpdateError> {
+        Ok(match self.boot_state(flash)?.status() {
+            FirmwareStatus::Testing => FirmwareEvent::Testing,
+            FirmwareStatus::RolledBack => FirmwareEvent::RolledBack,
+            _ => match &self.upload {
+                Some(upload) if upload.verified => FirmwareEvent::Verified,
+                Some(upload) => FirmwareEvent::Next(upload.received),
+                None => FirmwareEvent::Idle,
+            },
+        })
+    }
+
+    fn boot_state<F: Flash>(&self, flash: &mut F) -> Result<BootState, UpdateError> {
+        BootState::read(flash, &self.layout).map_err(|_| UpdateError::Storage)
+    }
+
+    fn mark<F: Flash>(&mut self, flash: &mut F, mark: Mark) -> Result<(), UpdateError> {
+        let mut state = self.boot_state(flash)?;
+        state.mark(flash, &self.layout, mark).map_err(|_| UpdateError::Storage)
+    }
+}
+
+/// Whether flash at `offset` holds `data`
+fn staged_matches<F: Flash>(flash: &mut F, offset: u32, data: &[u8]) -> Result<bool, UpdateError> {
+    let mut buffer = [0u8; 256];
+    for (index, chunk) in data.chunks(buffer.len()).enumerate() {
+        let staged = &mut buffer[..chunk.len()];
+        flash.read(offset + (index * 256) as u32, staged).map_err(|_| UpdateError::Storage)?;
+        if staged != chunk {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use settings::flash::MemFlash;
+    use update::layout::{TEST_LAYOUT, XIP_BASE};
+    use update::swap::{prepare_boot, BootAction};
+
+    type TestFlash = MemFlash<0x8000>;
+
+    const LEN: usize = 5000;
+    const RUNNING_LEN: u32 = 3000;
+
+    /// Image with a vector table pointing into `active`
+    fn image() -> [u8; LEN] {
+        let mut image = [0u8; LEN];
+        for (index, byte) in image.iter_mut().enumerate() {
+            *byte = (index as u8).wrapping_mul(7);
+        }
+        image[0..4].copy_from_slice(&0x2004_0000u32.to_le_bytes());
+        image[4..8].copy_from_slice(&(XIP_BASE + TEST_LAYOUT.active + 0x101).to_le_bytes());
+        image
+    }
+
+    fn chunk(data: &[u8]) -> (&[u8], u32) {
+        (data, crc32(data))
+    }
+
+    fn send(receiver: &mut UpdateReceiver, flash: &mut TestFlash, image: &[u8], offset: usize, len: usize) -> Result<FirmwareEvent, UpdateError> {
+        let (data, checksum) = chunk(&image[offset..offset + len]);
+        receiver.write(flash, offset as u32, data, checksum)
+    }
+
+    /// Whole image staged and checked
+    fn upload(receiver: &mut UpdateReceiver, flash: &mut TestFlash, image: &[u8]) {
+        assert_eq!(receiver.begin(flash, image.len() as u32, crc32(image)), Ok(FirmwareEvent::Ready));
+        for offset in (0..image.len()).step_by(CHUNK_BYTES) {
+            let len = CHUNK_BYTES.min(image.len() - offset);
+            assert_eq!(send(receiver, flash, image, offset, len), Ok(FirmwareEvent::Next((offset + len) as u32)));
+        }
+        assert_eq!(receiver.finish(flash), Ok(FirmwareEvent::Verified));
+    }
+
+    fn staged(flash: &TestFlash, len: usize) -> &[u8] {
+        &flash.as_bytes()[TEST_LAYOUT.update as usize..TEST_LAYOUT.update as usize + len]
+    }
+
+    #[test]
+    fn image_is_staged_and_applied() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let image = image();
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::Idle));
+        upload(&mut receiver, &mut flash, &image);
+        assert!(staged(&flash, LEN) == image);
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::Verified));
+        assert_eq!(receiver.apply(&mut flash, RUNNING_LEN), Ok(FirmwareEvent::Applying));
+        let state = BootState::read(&mut flash, &TEST_LAYOUT).unwrap();
+        assert_eq!((state.len, state.checksum, state.previous_len), (LEN as u32, crc32(&image), RUNNING_LEN));
+        assert_eq!(state.status(), FirmwareStatus::SwapPending);
+    }
+
+    #[test]
+    fn commands_need_an_upload() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        assert_eq!(send(&mut receiver, &mut flash, &image(), 0, 10), Err(UpdateError::NotStarted));
+        assert_eq!(receiver.finish(&mut flash), Err(UpdateError::NotStarted));
+        assert_eq!(receiver.apply(&mut flash, RUNNING_LEN), Err(UpdateError::NotStarted));
+        assert_eq!(receiver.begin(&mut flash, 0, 0), Err(UpdateError::BadHeader));
+        assert_eq!(receiver.begin(&mut flash, TEST_LAYOUT.partition_bytes + 1, 0), Err(UpdateError::TooLarge));
+    }
+
+    #[test]
+    fn out_of_order_chunks_are_refused() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let image = image();
+        receiver.begin(&mut flash, LEN as u32, crc32(&image)).unwrap();
+        assert_eq!(send(&mut receiver, &mut flash, &image, 1024, 1024), Err(UpdateError::Offset(0)));
+        send(&mut receiver, &mut flash, &image, 0, 1024).unwrap();
+        assert_eq!(send(&mut receiver, &mut flash, &image, 2048, 1024), Err(UpdateError::Offset(1024)));
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::Next(1024)));
+
+        // past the announced length
+        receiver.begin(&mut flash, 1500, crc32(&image[..1500])).unwrap();
+        send(&mut receiver, &mut flash, &image, 0, 1024).unwrap();
+        assert_eq!(send(&mut receiver, &mut flash, &image, 1024, 1024), Err(UpdateError::BadData));
+        assert_eq!(send(&mut receiver, &mut flash, &image, 1024, 476), Ok(FirmwareEvent::Next(1500)));
+    }
+
+    #[test]
+    fn duplicate_chunks_are_answered_again() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let image = image();
+        receiver.begin(&mut flash, LEN as u32, crc32(&image)).unwrap();
+        send(&mut receiver, &mut flash, &image, 0, 1024).unwrap();
+        send(&mut receiver, &mut flash, &image, 1024, 1024).unwrap();
+        // answer of the last chunk got lost
+        assert_eq!(send(&mut receiver, &mut flash, &image, 1024, 1024), Ok(FirmwareEvent::Next(2048)));
+        // an older chunk is not the last one, the Pi is told where to go on
+        assert_eq!(send(&mut receiver, &mut flash, &image, 0, 1024), Err(UpdateError::Offset(2048)));
+        // same offset, other data
+        let (other, checksum) = chunk(&[0xaa; 1024]);
+        assert_eq!(receiver.write(&mut flash, 1024, other, checksum), Err(UpdateError::Offset(2048)));
+        for offset in [2048, 3072, 4096] {
+            send(&mut receiver, &mut flash, &image, offset, CHUNK_BYTES.min(LEN - offset)).unwrap();
+        }
+        assert_eq!(receiver.finish(&mut flash), Ok(FirmwareEvent::Verified));
+        assert!(staged(&flash, LEN) == image);
+    }
+
+    #[test]
+    fn bad_chunk_checksum_is_refused() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let image = image();
+        receiver.begin(&mut flash, LEN as u32, crc32(&image)).unwrap();
+        let (data, checksum) = chunk(&image[..1024]);
+        assert_eq!(receiver.write(&mut flash, 0, data, checksum ^ 1), Err(UpdateError::Checksum));
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::Next(0)));
+        assert_eq!(receiver.write(&mut flash, 0, &[], crc32(&[])), Err(UpdateError::BadData));
+        assert_eq!(receiver.write(&mut flash, 0, data, checksum), Ok(FirmwareEvent::Next(1024)));
+    }
+
+    #[test]
+    fn bad_image_checksum_is_refused() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let image = image();
+        receiver.begin(&mut flash, LEN as u32, crc32(&image) ^ 1).unwrap();
+        for offset in (0..LEN).step_by(CHUNK_BYTES) {
+            send(&mut receiver, &mut flash, &image, offset, CHUNK_BYTES.min(LEN - offset)).unwrap();
+        }
+        assert_eq!(receiver.finish(&mut flash), Err(UpdateError::Checksum));
+        assert_eq!(receiver.apply(&mut flash, RUNNING_LEN), Err(UpdateError::NotStarted));
+    }
+
+    #[test]
+    fn incomplete_and_foreign_images_are_refused() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let mut image = image();
+        receiver.begin(&mut flash, LEN as u32, crc32(&image)).unwrap();
+        send(&mut receiver, &mut flash, &image, 0, 1024).unwrap();
+        assert_eq!(receiver.finish(&mut flash), Err(UpdateError::Incomplete(1024)));
+
+        // linked to run from the start of flash
+        image[4..8].copy_from_slice(&(XIP_BASE + 0x101).to_le_bytes());
+        receiver.begin(&mut flash, LEN as u32, crc32(&image)).unwrap();
+        for offset in (0..LEN).step_by(CHUNK_BYTES) {
+            send(&mut receiver, &mut flash, &image, offset, CHUNK_BYTES.min(LEN - offset)).unwrap();
+        }
+        assert_eq!(receiver.finish(&mut flash), Err(UpdateError::BadImage));
+    }
+
+    #[test]
+    fn new_upload_is_refused_during_trial() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let image = image();
+        upload(&mut receiver, &mut flash, &image);
+        receiver.apply(&mut flash, RUNNING_LEN).unwrap();
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Trial));
+
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::Testing));
+        assert_eq!(receiver.begin(&mut flash, LEN as u32, 0), Err(UpdateError::Busy));
+        assert_eq!(receiver.handle(&mut flash, FirmwareCommand::Confirm, LEN as u32), Ok(Some(FirmwareEvent::Confirmed)));
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::Idle));
+        assert_eq!(receiver.begin(&mut flash, LEN as u32, 0), Ok(FirmwareEvent::Ready));
+    }
+
+    #[test]
+    fn rollback_is_reported_once() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        upload(&mut receiver, &mut flash, &image());
+        receiver.apply(&mut flash, RUNNING_LEN).unwrap();
+        prepare_boot(&mut flash, &TEST_LAYOUT).unwrap();
+        // reset before confirming
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Run));
+
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::RolledBack));
+        assert_eq!(receiver.handle(&mut flash, FirmwareCommand::RollbackReported, RUNNING_LEN), Ok(None));
+        assert_eq!(receiver.status(&mut flash), Ok(FirmwareEvent::Idle));
+    }
+
+    #[test]
+    fn upload_after_apply_without_reboot_cancels_the_swap() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        upload(&mut receiver, &mut flash, &image());
+        receiver.apply(&mut flash, RUNNING_LEN).unwrap();
+        receiver.begin(&mut flash, LEN as u32, 0).unwrap();
+        assert_eq!(BootState::read(&mut flash, &TEST_LAYOUT).unwrap().status(), FirmwareStatus::Idle);
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Run));
+    }
+
+    #[test]
+    fn flash_failure_is_a_storage_error() {
+        let mut flash = TestFlash::new();
+        let mut receiver = UpdateReceiver::new(TEST_LAYOUT);
+        let image = image();
+        receiver.begin(&mut flash, LEN as u32, crc32(&image)).unwrap();
+        flash.cut_power_after(4096 + 10);
+        assert_eq!(send(&mut receiver, &mut flash, &image, 0, 1024), Err(UpdateError::Storage));
+        flash.restore_power();
+        // chunk was not counted, sent again it is staged
+        assert_eq!(send(&mut receiver, &mut flash, &image, 0, 1024), Ok(FirmwareEvent::Next(1024)));
+    }
+}
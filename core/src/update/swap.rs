@@ -0,0 +1,210 @@
+use settings::flash::Flash;
+use update::boot_state::{BootState, FirmwareStatus, Mark};
+use update::layout::UpdateLayout;
+
+/// What the bootloader starts after `prepare_boot`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BootAction {
+    /// Image in `active` as usual
+    Run,
+    /// New image in `active` on trial, the watchdog has to reset the board if it hangs before
+    /// it confirms itself
+    Trial,
+}
+
+/// Bootloader, every boot: install a requested update, or put the previous image back when the
+/// new one reset before confirming itself. A swap cut by power loss goes on where it stopped.
+pub fn prepare_boot<F: Flash>(flash: &mut F, layout: &UpdateLayout) -> Result<BootAction, F::Error> {
+    let mut state = BootState::read(flash, layout)?;
+    match state.status() {
+        FirmwareStatus::Idle | FirmwareStatus::RolledBack => Ok(BootAction::Run),
+        FirmwareStatus::SwapPending | FirmwareStatus::Swapped => {
+            exchange(flash, layout, &state, false)?;
+            state.mark(flash, layout, Mark::Swapped)?;
+            state.mark(flash, layout, Mark::Started)?;
+            Ok(BootAction::Trial)
+        }
+        FirmwareStatus::Testing | FirmwareStatus::Reverting => {
+            state.mark(flash, layout, Mark::RevertStarted)?;
+            // exchanging again puts both images back
+            exchange(flash, layout, &state, true)?;
+            state.mark(flash, layout, Mark::RevertDone)?;
+            Ok(BootAction::Run)
+        }
+    }
+}
+
+/// Exchange `active` and `update` sector by sector through `scratch`. Each step only overwrites
+/// data a finished step saved elsewhere, so redoing the step power loss cut is safe.
+fn exchange<F: Flash>(flash: &mut F, layout: &UpdateLayout, state: &BootState, revert: bool) -> Result<(), F::Error> {
+    let sectors = state.swap_sectors(layout, F::SECTOR_SIZE);
+    for step in state.progress(flash, layout, revert)?..sectors * 3 {
+        let offset = step / 3 * F::SECTOR_SIZE;
+        match step % 3 {
+            0 => copy_sector(flash, layout.active + offset, layout.scratch)?,
+            1 => copy_sector(flash, layout.update + offset, layout.active + offset)?,
+            _ => copy_sector(flash, layout.scratch, layout.update + offset)?,
+        }
+        state.step_done(flash, layout, revert, step)?;
+    }
+    Ok(())
+}
+
+fn copy_sector<F: Flash>(flash: &mut F, from: u32, to: u32) -> Result<(), F::Error> {
+    flash.erase(to / F::SECTOR_SIZE)?;
+    let mut page = [0u8; 256];
+    for offset in (0..F::SECTOR_SIZE).step_by(page.len()) {
+        flash.read(from + offset, &mut page)?;
+        // erased pages stay erased
+        if page.iter().any(|byte| *byte != 0xff) {
+            flash.write(to + offset, &page)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use settings::flash::MemFlash;
+    use update::layout::TEST_LAYOUT;
+    use utils::crc32::crc32;
+
+    type TestFlash = MemFlash<0x8000>;
+
+    const OLD_LEN: usize = 5000;
+    const NEW_LEN: usize = 9000;
+
+    fn image<const N: usize>(seed: u8) -> [u8; N] {
+        let mut image = [0u8; N];
+        for (index, byte) in image.iter_mut().enumerate() {
+            *byte = (index as u8).wrapping_mul(31).wrapping_add(seed);
+        }
+        image
+    }
+
+    /// Old image running, new one staged and requested
+    fn staged() -> TestFlash {
+        let mut flash = TestFlash::new();
+        flash.write(TEST_LAYOUT.active, &image::<OLD_LEN>(1)).unwrap();
+        flash.write(TEST_LAYOUT.update, &image::<NEW_LEN>(2)).unwrap();
+        let new = image::<NEW_LEN>(2);
+        BootState::request(&mut flash, &TEST_LAYOUT, NEW_LEN as u32, crc32(&new), OLD_LEN as u32).unwrap();
+        flash
+    }
+
+    fn copy(flash: &TestFlash) -> TestFlash {
+        let mut bytes = [0u8; 0x8000];
+        bytes.copy_from_slice(flash.as_bytes());
+        MemFlash::from_bytes(bytes)
+    }
+
+    fn status(flash: &mut TestFlash) -> FirmwareStatus {
+        BootState::read(flash, &TEST_LAYOUT).unwrap().status()
+    }
+
+    fn assert_new_running(flash: &TestFlash) {
+        let bytes = flash.as_bytes();
+        let (active, update) = (TEST_LAYOUT.active as usize, TEST_LAYOUT.update as usize);
+        assert!(bytes[active..active + NEW_LEN] == image::<NEW_LEN>(2));
+        assert!(bytes[update..update + OLD_LEN] == image::<OLD_LEN>(1));
+    }
+
+    fn assert_old_running(flash: &TestFlash) {
+        let bytes = flash.as_bytes();
+        let (active, update) = (TEST_LAYOUT.active as usize, TEST_LAYOUT.update as usize);
+        assert!(bytes[active..active + OLD_LEN] == image::<OLD_LEN>(1));
+        assert!(bytes[update..update + NEW_LEN] == image::<NEW_LEN>(2));
+    }
+
+    #[test]
+    fn nothing_requested_runs_as_is() {
+        let mut flash = TestFlash::new();
+        flash.write(TEST_LAYOUT.active, &image::<OLD_LEN>(1)).unwrap();
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Run));
+        assert_eq!(flash.erase_counts(), &[0; 8]);
+    }
+
+    #[test]
+    fn requested_image_is_swapped_in_on_trial() {
+        let mut flash = staged();
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Trial));
+        assert_new_running(&flash);
+        assert_eq!(status(&mut flash), FirmwareStatus::Testing);
+    }
+
+    #[test]
+    fn confirmed_image_stays() {
+        let mut flash = staged();
+        prepare_boot(&mut flash, &TEST_LAYOUT).unwrap();
+        let mut state = BootState::read(&mut flash, &TEST_LAYOUT).unwrap();
+        state.mark(&mut flash, &TEST_LAYOUT, Mark::Confirmed).unwrap();
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Run));
+        assert_new_running(&flash);
+        assert_eq!(status(&mut flash), FirmwareStatus::Idle);
+    }
+
+    #[test]
+    fn trial_without_confirm_is_reverted() {
+        let mut flash = staged();
+        prepare_boot(&mut flash, &TEST_LAYOUT).unwrap();
+        // reset on trial, e.g. by the watchdog
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Run));
+        assert_old_running(&flash);
+        assert_eq!(status(&mut flash), FirmwareStatus::RolledBack);
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Run));
+        assert_old_running(&flash);
+    }
+
+    #[test]
+    fn interrupted_swap_resumes_through_scratch() {
+        let base = staged();
+        let mut budget = 0;
+        loop {
+            let mut flash = copy(&base);
+            flash.cut_power_after(budget);
+            if prepare_boot(&mut flash, &TEST_LAYOUT) == Ok(BootAction::Trial) {
+                break;
+            }
+            flash.restore_power();
+            assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Trial), "budget {}", budget);
+            assert_new_running(&flash);
+            assert_eq!(status(&mut flash), FirmwareStatus::Testing);
+            budget += 97;
+        }
+        assert!(budget > 9 * 4096);
+    }
+
+    #[test]
+    fn interrupted_revert_resumes_through_scratch() {
+        let mut base = staged();
+        prepare_boot(&mut base, &TEST_LAYOUT).unwrap();
+        let mut budget = 0;
+        loop {
+            let mut flash = copy(&base);
+            flash.cut_power_after(budget);
+            if prepare_boot(&mut flash, &TEST_LAYOUT) == Ok(BootAction::Run) {
+                break;
+            }
+            flash.restore_power();
+            assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Run), "budget {}", budget);
+            assert_old_running(&flash);
+            assert_eq!(status(&mut flash), FirmwareStatus::RolledBack);
+            budget += 97;
+        }
+        assert!(budget > 9 * 4096);
+    }
+
+    #[test]
+    fn power_lost_twice_during_swap() {
+        let mut flash = staged();
+        flash.cut_power_after(3 * 4096 + 700);
+        assert!(prepare_boot(&mut flash, &TEST_LAYOUT).is_err());
+        flash.cut_power_after(2 * 4096 + 300);
+        assert!(prepare_boot(&mut flash, &TEST_LAYOUT).is_err());
+        flash.restore_power();
+        assert_eq!(prepare_boot(&mut flash, &TEST_LAYOUT), Ok(BootAction::Trial));
+        assert_new_running(&flash);
+    }
+}
@@ -0,0 +1,70 @@
+use heapless::String;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with `=` padding, binary data travels in text lines this way.
+/// Fails when `out` is too small.
+pub fn encode<const N: usize>(data: &[u8], out: &mut String<N>) -> Result<(), Base64Error> {
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            let c = if index <= chunk.len() {
+                ALPHABET[(group >> (18 - index * 6)) as usize & 0x3f] as char
+            } else {
+                '='
+            };
+            out.push(c).map_err(|_| Base64Error::TooLong)?;
+        }
+    }
+    Ok(())
+}
+
+/// Decode into `out`, returns number of bytes written. Fails on bad characters,
+/// length which is not a multiple of 4 and when `out` is too small.
+pub fn decode(input: &str, out: &mut [u8]) -> Result<usize, Base64Error> {
+    let input = input.as_bytes();
+    if !input.len().is_multiple_of(4) {
+        return Err(Base64Error::BadLength);
+    }
+    let mut written = 0;
+    for chunk in input.chunks(4) {
+        let mut group = 0u32;
+        let mut padding = 0;
+        for (index, c) in chunk.iter().enumerate() {
+            let value = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                // padding only at the end of the last group
+                b'=' if index >= 2 => {
+                    padding += 1;
+                    0
+                }
+                _ => return Err(Base64Error::BadCharacter),
+            };
+            if padding > 0 && *c != b'=' {
+                return Err(Base64Error::BadCharacter);
+            }
+            group = group << 6 | value as u32;
+        }
+        let bytes = [(group >> 16) as u8, (group >> 8) as u8, group as u8];
+        let count = 3 - padding;
+        if written + count > out.len() {
+            return Err(Base64Error::TooLong);
+        }
+        out[written..written + count].copy_from_slice(&bytes[..count]);
+        written += count;
+    }
+    Ok(written)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Base64Error {
+    BadLength,
+    BadCharacter,
+    TooLong,
+}
@@ -0,0 +1,25 @@
+use core::fmt::Write;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::IntoStorage;
+use heapless::String;
+
+/// `rrggbb` hex, as used by layout nodes and line styles
+pub fn parse_color(value: &str) -> Option<Rgb565> {
+    if value.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(value, 16).ok()?;
+    let (r, g, b) = ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8);
+    Some(Rgb565::new(r >> 3, g >> 2, b >> 3))
+}
+
+/// `rrggbb` that `parse_color` turns back into the same colour
+pub fn hex_color(color: Rgb565) -> String<6> {
+    let raw = color.into_storage();
+    let r = ((raw >> 11) & 0x1f) << 3;
+    let g = ((raw >> 5) & 0x3f) << 2;
+    let b = (raw & 0x1f) << 3;
+    let mut value: String<6> = String::new();
+    _ = write!(value, "{:02x}{:02x}{:02x}", r, g, b);
+    value
+}
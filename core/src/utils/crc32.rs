@@ -0,0 +1,17 @@
+/// CRC-32 (IEEE 802.3, the one of zip and png), bitwise to keep flash usage low
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// CRC-32 of data which comes in pieces: `crc32_update(crc32(a), b)` is the CRC-32 of `a` then `b`,
+/// start with 0
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
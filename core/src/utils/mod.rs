@@ -0,0 +1,10 @@
+pub mod base64;
+pub mod color;
+pub mod crc32;
+pub mod itoa;
+pub mod string_to_kv;
+pub mod truncate;
+#[cfg(test)]
+pub mod test_display;
+#[cfg(test)]
+pub mod test_fixtures;
@@ -0,0 +1,62 @@
+use core::ops::Not;
+use heapless::Vec;
+
+pub fn string_to_kv<const OLEN: usize>(data: &str) -> Result<Vec<(&str, &str), OLEN>, StringToKVError>
+{
+    if data.contains("=").not() {
+        return Err(StringToKVError::NotAnKVString);
+    }
+
+    let mut kv = Vec::new();
+    let parts = data.split('&');
+    for part in parts {
+        // split on first '=' only, values like rtttl melodies contain '=' too
+        let key_value = match part.find('=') {
+            Some(index) => (&part[..index], &part[index + 1..]),
+            None => (part, ""),
+        };
+        kv.push(key_value).map_err(|_| StringToKVError::TooManyPairs)?;
+    }
+    Ok(kv)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StringToKVError {
+    NotAnKVString,
+    TooManyPairs,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_pairs_in_order() {
+        let kv = string_to_kv::<4>("a=1&b=two&c=").unwrap();
+        assert_eq!(kv.as_slice(), &[("a", "1"), ("b", "two"), ("c", "")]);
+    }
+
+    #[test]
+    fn splits_on_first_equals_only() {
+        let kv = string_to_kv::<2>("melody=tune:d=8,o=5,b=120:c").unwrap();
+        assert_eq!(kv.as_slice(), &[("melody", "tune:d=8,o=5,b=120:c")]);
+    }
+
+    #[test]
+    fn part_without_equals_has_empty_value() {
+        let kv = string_to_kv::<2>("a=1&flag").unwrap();
+        assert_eq!(kv.as_slice(), &[("a", "1"), ("flag", "")]);
+    }
+
+    #[test]
+    fn rejects_text_without_pairs() {
+        assert_eq!(string_to_kv::<2>("stats"), Err(StringToKVError::NotAnKVString));
+        assert_eq!(string_to_kv::<2>(""), Err(StringToKVError::NotAnKVString));
+    }
+
+    #[test]
+    fn rejects_more_pairs_than_capacity() {
+        assert_eq!(string_to_kv::<2>("a=1&b=2&c=3"), Err(StringToKVError::TooManyPairs));
+    }
+}
@@ -0,0 +1,73 @@
+use core::convert::Infallible;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::Pixel;
+
+/// In-memory framebuffer for rendering tests, pixels outside are dropped like on the display
+pub struct TestDisplay<const W: usize, const H: usize> {
+    pixels: [[Rgb565; W]; H],
+}
+
+impl<const W: usize, const H: usize> TestDisplay<W, H> {
+    pub fn new() -> Self {
+        TestDisplay { pixels: [[Rgb565::BLACK; W]; H] }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb565 {
+        self.pixels[y][x]
+    }
+
+    pub fn count(&self, color: Rgb565) -> usize {
+        self.pixels.iter().flatten().filter(|pixel| **pixel == color).count()
+    }
+
+    /// Compare with rows of `.` black, `#` white, `G` green, `Y` yellow, `R` red and `?` anything else,
+    /// starting at row `top`
+    pub fn assert_rows(&self, top: usize, expected: &[&str]) {
+        for (offset, expected_row) in expected.iter().enumerate() {
+            for (x, expected_char) in expected_row.chars().enumerate() {
+                let pixel = self.pixels[top + offset][x];
+                let actual_char = match pixel {
+                    Rgb565::BLACK => '.',
+                    Rgb565::WHITE => '#',
+                    Rgb565::GREEN => 'G',
+                    Rgb565::YELLOW => 'Y',
+                    Rgb565::RED => 'R',
+                    _ => '?',
+                };
+                assert_eq!(actual_char, expected_char, "pixel {},{} in row {:?}", x, top + offset, expected_row);
+            }
+        }
+    }
+}
+
+impl<const W: usize, const H: usize> Default for TestDisplay<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for TestDisplay<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> DrawTarget for TestDisplay<W, H> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item=Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if (0..W as i32).contains(&point.x) && (0..H as i32).contains(&point.y) {
+                self.pixels[point.y as usize][point.x as usize] = color;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use core::convert::TryFrom;
+use device::reset::ResetReason;
+use device::state::DeviceState;
+use embedded_graphics_core::geometry::Size;
+use heapless::String;
+use settings::setting::Settings;
+use update::boot_state::FirmwareStatus;
+
+/// Fresh device after a power on, 128x128 display, no crash and default settings
+pub fn device() -> DeviceState {
+    DeviceState::new(1_000, Size::new(128, 128), ResetReason::PowerOn, None, Settings::default(), FirmwareStatus::Idle)
+}
+
+/// Parse a received line with one of the `Pi2Pico*` parsers
+pub fn parse<T, E>(line: &str) -> Result<T, E>
+where
+    T: for<'a> TryFrom<&'a String<2048>, Error = E>,
+{
+    T::try_from(&String::<2048>::from(line))
+}
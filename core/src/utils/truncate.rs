@@ -0,0 +1,18 @@
+use heapless::String;
+
+/// Copy as much of `value` as fits into `String<N>`, cutting on char boundary.
+/// `String::from(&str)` panics when value is longer than capacity.
+pub fn truncated<const N: usize>(value: &str) -> String<N> {
+    let mut result = String::new();
+    for c in value.chars() {
+        if result.push(c).is_err() {
+            break;
+        }
+    }
+    result
+}
+
+/// `truncated` stopping after `max_chars` characters too, for text that is drawn in one piece
+pub fn truncated_chars<const N: usize>(value: &str, max_chars: usize) -> String<N> {
+    truncated(value.char_indices().nth(max_chars).map_or(value, |(index, _)| &value[..index]))
+}
@@ -0,0 +1,72 @@
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+
+pub const BATTERY_ICON_SIZE: Size = Size::new(16, 8);
+
+/// Battery outline with charge level, green above 50%, yellow above 20%, red below
+pub fn draw_battery<D>(display: &mut D, top_left: Point, percent: u8) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let body = Rectangle::new(top_left, Size::new(BATTERY_ICON_SIZE.width - 2, BATTERY_ICON_SIZE.height));
+    body.into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK)).draw(display)?;
+    body.into_styled(PrimitiveStyle::with_stroke(Rgb565::WHITE, 1)).draw(display)?;
+    Rectangle::new(top_left + Point::new(body.size.width as i32, 2), Size::new(2, 4))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::WHITE))
+        .draw(display)?;
+
+    let percent = percent.min(100) as u32;
+    let color = match percent {
+        51..=100 => Rgb565::GREEN,
+        21..=50 => Rgb565::YELLOW,
+        _ => Rgb565::RED,
+    };
+    let inner_width = body.size.width - 4;
+    let level_width = (inner_width * percent).div_ceil(100);
+    Rectangle::new(top_left + Point::new(2, 2), Size::new(level_width, 4))
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(display)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_display::TestDisplay;
+
+    fn draw(percent: u8) -> TestDisplay<16, 8> {
+        let mut display = TestDisplay::new();
+        draw_battery(&mut display, Point::zero(), percent).unwrap();
+        display
+    }
+
+    #[test]
+    fn half_full_is_yellow() {
+        draw(50).assert_rows(0, &[
+            "##############..",
+            "#............#..",
+            "#.YYYYY......###",
+            "#.YYYYY......###",
+            "#.YYYYY......###",
+            "#.YYYYY......###",
+            "#............#..",
+            "##############..",
+        ]);
+    }
+
+    #[test]
+    fn level_colors() {
+        draw(100).assert_rows(2, &["#.GGGGGGGGGG.###"]);
+        draw(51).assert_rows(2, &["#.GGGGGG.....###"]);
+        draw(20).assert_rows(2, &["#.RR.........###"]);
+        draw(1).assert_rows(2, &["#.R..........###"]);
+        draw(0).assert_rows(2, &["#............###"]);
+        draw(255).assert_rows(2, &["#.GGGGGGGGGG.###"]);
+    }
+}
@@ -0,0 +1,126 @@
+use embedded_graphics::mono_font::ascii::FONT_6X12;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics::text::{Alignment, Baseline, Text};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use screen::text::{draw_text, FONT_SET_6X12};
+use widgets::LABEL_CAPACITY;
+use input::keyboard_codes::KeyboardCodes;
+
+/// Yes/no question. Left/Right pick the answer, Ok confirms. "No" is preselected.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Dialog {
+    question: String<LABEL_CAPACITY>,
+    yes_selected: bool,
+}
+
+impl Dialog {
+    pub fn new(question: String<LABEL_CAPACITY>) -> Self {
+        Dialog { question, yes_selected: false }
+    }
+
+    /// Answer when `Ok` is pressed
+    pub fn on_key(&mut self, keycode: KeyboardCodes) -> Option<bool> {
+        match keycode {
+            KeyboardCodes::Left => self.yes_selected = false,
+            KeyboardCodes::Right => self.yes_selected = true,
+            KeyboardCodes::Up | KeyboardCodes::Down => self.yes_selected = !self.yes_selected,
+            KeyboardCodes::Ok => return Some(self.yes_selected),
+        }
+        None
+    }
+
+    pub fn draw<D>(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        let center = area.center();
+        draw_text(
+            display,
+            self.question.as_str(),
+            Point::new(center.x, center.y - 12),
+            &FONT_SET_6X12,
+            Rgb565::WHITE,
+            Alignment::Center,
+            Baseline::Alphabetic,
+        )?;
+
+        draw_button(display, "No", Point::new(center.x - 50, center.y + 8), !self.yes_selected)?;
+        draw_button(display, "Yes", Point::new(center.x + 6, center.y + 8), self.yes_selected)?;
+        Ok(())
+    }
+}
+
+fn draw_button<D>(display: &mut D, text: &str, top_left: Point, selected: bool) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let button = Rectangle::new(top_left, Size::new(44, 16));
+    let (style, color) = if selected {
+        (PrimitiveStyle::with_fill(Rgb565::BLUE), Rgb565::WHITE)
+    } else {
+        (PrimitiveStyle::with_stroke(Rgb565::BLUE, 1), Rgb565::RED)
+    };
+    button.into_styled(style).draw(display)?;
+    Text::with_alignment(
+        text,
+        button.center() + Point::new(0, 3),
+        MonoTextStyle::new(&FONT_6X12, color),
+        Alignment::Center,
+    ).draw(display)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_display::TestDisplay;
+    use widgets::layer::WIDGET_AREA;
+
+    /// Blue fill of the button at `x`
+    fn filled(display: &TestDisplay<128, 128>, x: i32) -> bool {
+        let center = WIDGET_AREA.center();
+        display.pixel((center.x + x + 2) as usize, (center.y + 10) as usize) == Rgb565::BLUE
+    }
+
+    fn draw(dialog: &Dialog) -> TestDisplay<128, 128> {
+        let mut display = TestDisplay::new();
+        dialog.draw(&mut display, WIDGET_AREA).unwrap();
+        display
+    }
+
+    #[test]
+    fn keys_pick_the_answer() {
+        let mut dialog = Dialog::new(String::from("Sure?"));
+        assert_eq!(dialog.on_key(KeyboardCodes::Ok), Some(false));
+        assert_eq!(dialog.on_key(KeyboardCodes::Right), None);
+        assert_eq!(dialog.on_key(KeyboardCodes::Ok), Some(true));
+        dialog.on_key(KeyboardCodes::Down);
+        assert_eq!(dialog.on_key(KeyboardCodes::Ok), Some(false));
+        dialog.on_key(KeyboardCodes::Up);
+        dialog.on_key(KeyboardCodes::Left);
+        assert_eq!(dialog.on_key(KeyboardCodes::Ok), Some(false));
+    }
+
+    #[test]
+    fn selected_button_is_filled() {
+        let mut dialog = Dialog::new(String::from("Sure?"));
+        let display = draw(&dialog);
+        assert!(filled(&display, -50));
+        assert!(!filled(&display, 6));
+        // unselected answer in red
+        assert!(display.count(Rgb565::RED) > 0);
+
+        dialog.on_key(KeyboardCodes::Right);
+        let display = draw(&dialog);
+        assert!(!filled(&display, -50));
+        assert!(filled(&display, 6));
+    }
+}
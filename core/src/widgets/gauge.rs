@@ -0,0 +1,149 @@
+use embedded_graphics::mono_font::ascii::FONT_10X20;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::{AngleUnit, Primitive};
+use embedded_graphics::primitives::{Arc, PrimitiveStyle};
+use embedded_graphics::text::{Alignment, Baseline, Text};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::Point;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use screen::text::{draw_text, FONT_SET_6X12};
+use widgets::LABEL_CAPACITY;
+
+const DIAMETER: u32 = 64;
+const SWEEP_DEGREES: f32 = 270.0;
+
+/// Value between `min` and `max` on a 270 degrees arc with the number in the middle,
+/// e.g. cpu temperature or load
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Gauge {
+    value: i32,
+    min: i32,
+    max: i32,
+    label: String<LABEL_CAPACITY>,
+}
+
+impl Gauge {
+    /// `min` and `max` are swapped when given in wrong order, value is kept as is and clamped on drawing
+    pub fn new(value: i32, min: i32, max: i32, label: String<LABEL_CAPACITY>) -> Self {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        Gauge { value, min, max, label }
+    }
+
+    /// Filled part of the arc, 0.0 - 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.max == self.min {
+            return 1.0;
+        }
+        // i64, the span of i32 extremes does not fit into i32
+        let value = self.value.clamp(self.min, self.max) as i64;
+        (value - self.min as i64) as f32 / (self.max as i64 - self.min as i64) as f32
+    }
+
+    pub fn draw<D>(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        let center = area.center() - Point::new(0, 6);
+        // starts at bottom left, grows clockwise to bottom right
+        Arc::with_center(center, DIAMETER, 135.0.deg(), SWEEP_DEGREES.deg())
+            .into_styled(PrimitiveStyle::with_stroke(Rgb565::new(8, 16, 8), 6))
+            .draw(display)?;
+        let sweep = SWEEP_DEGREES * self.fraction();
+        if sweep > 0.0 {
+            Arc::with_center(center, DIAMETER, 135.0.deg(), sweep.deg())
+                .into_styled(PrimitiveStyle::with_stroke(Rgb565::GREEN, 6))
+                .draw(display)?;
+        }
+
+        let value_text: String<11> = String::from(self.value);
+        Text::with_alignment(
+            value_text.as_str(),
+            center + Point::new(0, 7),
+            MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE),
+            Alignment::Center,
+        ).draw(display)?;
+        draw_text(
+            display,
+            self.label.as_str(),
+            Point::new(center.x, area.top_left.y + area.size.height as i32 - 4),
+            &FONT_SET_6X12,
+            Rgb565::WHITE,
+            Alignment::Center,
+            Baseline::Alphabetic,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_display::TestDisplay;
+    use widgets::layer::WIDGET_AREA;
+
+    fn gauge(value: i32, min: i32, max: i32) -> Gauge {
+        Gauge::new(value, min, max, String::from("Temp"))
+    }
+
+    fn draw(gauge: &Gauge) -> TestDisplay<128, 128> {
+        let mut display = TestDisplay::new();
+        gauge.draw(&mut display, WIDGET_AREA).unwrap();
+        display
+    }
+
+    #[test]
+    fn fraction_of_range() {
+        assert_eq!(gauge(5, 0, 10).fraction(), 0.5);
+        assert_eq!(gauge(-5, 0, 10).fraction(), 0.0);
+        assert_eq!(gauge(50, 0, 10).fraction(), 1.0);
+        assert_eq!(gauge(5, 10, 0).fraction(), 0.5);
+        assert_eq!(gauge(3, 3, 3).fraction(), 1.0);
+    }
+
+    #[test]
+    fn fraction_of_extreme_range() {
+        assert_eq!(gauge(0, i32::MIN, i32::MAX).fraction(), 0.5);
+        assert_eq!(gauge(i32::MAX, i32::MIN, i32::MAX).fraction(), 1.0);
+        assert_eq!(gauge(i32::MIN, i32::MIN, i32::MAX).fraction(), 0.0);
+        assert_eq!(gauge(0, -2, i32::MAX).fraction(), 2.0 / (i32::MAX as f32 + 2.0));
+    }
+
+    #[test]
+    fn empty_gauge_has_no_green() {
+        let display = draw(&gauge(0, 0, 10));
+        assert_eq!(display.count(Rgb565::GREEN), 0);
+        // value and label
+        assert!(display.count(Rgb565::WHITE) > 0);
+    }
+
+    #[test]
+    fn arc_grows_with_value() {
+        let half = draw(&gauge(5, 0, 10)).count(Rgb565::GREEN);
+        let full = draw(&gauge(10, 0, 10)).count(Rgb565::GREEN);
+        assert!(half > 0);
+        assert!(full > half);
+        // bottom left start of the arc is green from the first value on, bottom right only when full
+        let center = WIDGET_AREA.center() - Point::new(0, 6);
+        let (left, right) = ((center.x - 22) as usize, (center.x + 22) as usize);
+        let bottom = (center.y + 22) as usize;
+        assert_eq!(draw(&gauge(5, 0, 10)).pixel(left, bottom), Rgb565::GREEN);
+        assert_ne!(draw(&gauge(5, 0, 10)).pixel(right, bottom), Rgb565::GREEN);
+        assert_eq!(draw(&gauge(10, 0, 10)).pixel(right, bottom), Rgb565::GREEN);
+    }
+
+    #[test]
+    fn extreme_values_are_drawn() {
+        let display = draw(&gauge(i32::MIN, i32::MIN, i32::MAX));
+        assert_eq!(display.count(Rgb565::GREEN), 0);
+        // the long number covers part of the arc, its ends are still green
+        let display = draw(&gauge(i32::MAX, -2147483648, 2147483647));
+        let center = WIDGET_AREA.center() - Point::new(0, 6);
+        let bottom = (center.y + 22) as usize;
+        assert_eq!(display.pixel((center.x - 22) as usize, bottom), Rgb565::GREEN);
+        assert_eq!(display.pixel((center.x + 22) as usize, bottom), Rgb565::GREEN);
+    }
+}
@@ -0,0 +1,139 @@
+use core::mem::discriminant;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use input::keyboard_codes::KeyboardCodes;
+use qr::code::QrCode;
+use widgets::battery::draw_battery;
+use widgets::dialog::Dialog;
+use widgets::gauge::Gauge;
+use widgets::progress::ProgressBar;
+use widgets::qr::draw_qr;
+use widgets::spinner::Spinner;
+use widgets::toast::Toast;
+
+/// Data lines part of the screen, between status and title lines
+pub const WIDGET_AREA: Rectangle = Rectangle::new(Point::new(0, 16), Size::new(128, 96));
+/// Right end of the status line
+pub const BATTERY_POSITION: Point = Point::new(110, 5);
+
+/// Widget taking the data lines area, one at a time
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Widget {
+    Progress(ProgressBar),
+    Gauge(Gauge),
+    Dialog(Dialog),
+    Spinner(Spinner),
+}
+
+/// Answer of an interactive widget, the widget is closed after it
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WidgetEvent {
+    DialogAnswer(bool),
+    SpinnerValue(i32),
+}
+
+/// Everything drawn over screen lines. core1 draws it after the lines.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Widgets {
+    pub active: Option<Widget>,
+    pub toast: Option<Toast>,
+    /// Battery icon in the status line, percent
+    pub battery: Option<u8>,
+    /// Over the whole screen, only toasts are drawn on top
+    pub qr: Option<QrCode>,
+}
+
+impl Widgets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Dialog and spinner handle keys on the device, nothing is sent to the Pi until they are answered
+    pub fn takes_keys(&self) -> bool {
+        matches!(self.active, Some(Widget::Dialog(_)) | Some(Widget::Spinner(_)))
+    }
+
+    pub fn on_key(&mut self, keycode: KeyboardCodes) -> Option<WidgetEvent> {
+        let event = match &mut self.active {
+            Some(Widget::Dialog(dialog)) => dialog.on_key(keycode).map(WidgetEvent::DialogAnswer),
+            Some(Widget::Spinner(spinner)) => spinner.on_key(keycode).map(WidgetEvent::SpinnerValue),
+            _ => None,
+        };
+        if event.is_some() {
+            self.active = None;
+        }
+        event
+    }
+
+    /// Hide expired toast, run every loop
+    pub fn update(&mut self, now_ms: u64) {
+        if self.toast.as_ref().is_some_and(|toast| toast.is_expired(now_ms)) {
+            self.toast = None;
+        }
+    }
+
+    pub fn covers_data_lines(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Nothing under widgets is visible
+    pub fn covers_screen(&self) -> bool {
+        self.qr.is_some()
+    }
+
+    /// Screen under widgets has to be drawn from scratch when a widget or toast appears, disappears
+    /// or a widget of another kind takes its place. Value updates are drawn in place.
+    pub fn needs_full_redraw(&self, drawn: &Widgets) -> bool {
+        let widget_changed = match (&self.active, &drawn.active) {
+            (Some(active), Some(drawn_active)) => discriminant(active) != discriminant(drawn_active),
+            (None, None) => false,
+            _ => true,
+        };
+        widget_changed
+            || self.toast.is_some() != drawn.toast.is_some()
+            || self.battery.is_some() != drawn.battery.is_some()
+            || self.qr.is_some() != drawn.qr.is_some()
+    }
+
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        if let Some(code) = &self.qr {
+            draw_qr(display, code)?;
+        } else {
+            self.draw_over_lines(display)?;
+        }
+        if let Some(toast) = &self.toast {
+            toast.draw(display, WIDGET_AREA)?;
+        }
+        Ok(())
+    }
+
+    /// Active widget and battery, hidden by a QR code as the battery would cover a corner of a large one
+    fn draw_over_lines<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        if let Some(widget) = &self.active {
+            WIDGET_AREA.into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK)).draw(display)?;
+            match widget {
+                Widget::Progress(progress) => progress.draw(display, WIDGET_AREA)?,
+                Widget::Gauge(gauge) => gauge.draw(display, WIDGET_AREA)?,
+                Widget::Dialog(dialog) => dialog.draw(display, WIDGET_AREA)?,
+                Widget::Spinner(spinner) => spinner.draw(display, WIDGET_AREA)?,
+            }
+        }
+        if let Some(battery) = self.battery {
+            draw_battery(display, BATTERY_POSITION, battery)?;
+        }
+        Ok(())
+    }
+}
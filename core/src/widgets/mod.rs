@@ -0,0 +1,13 @@
+pub mod layer;
+pub mod progress;
+pub mod gauge;
+pub mod dialog;
+pub mod spinner;
+pub mod toast;
+pub mod battery;
+pub mod qr;
+
+/// Characters of a label, question or toast text, as many as fit across the screen
+pub const LABEL_CHARS: usize = 20;
+/// Bytes of `LABEL_CHARS` Cyrillic characters, two each
+pub const LABEL_CAPACITY: usize = 2 * LABEL_CHARS;
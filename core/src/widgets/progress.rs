@@ -0,0 +1,114 @@
+use embedded_graphics::mono_font::ascii::FONT_6X12;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics::text::{Alignment, Baseline, Text};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use screen::text::{draw_text, FONT_SET_6X12};
+use widgets::LABEL_CAPACITY;
+
+/// Horizontal bar with label and percent, for long running jobs on the Pi
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProgressBar {
+    percent: u8,
+    label: String<LABEL_CAPACITY>,
+}
+
+impl ProgressBar {
+    pub fn new(percent: u8, label: String<LABEL_CAPACITY>) -> Self {
+        ProgressBar { percent: percent.min(100), label }
+    }
+
+    pub fn percent(&self) -> u8 {
+        self.percent
+    }
+
+    pub fn draw<D>(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        let center_x = area.center().x;
+        let bar_top = area.center().y - 7;
+        draw_text(
+            display,
+            self.label.as_str(),
+            Point::new(center_x, bar_top - 10),
+            &FONT_SET_6X12,
+            Rgb565::WHITE,
+            Alignment::Center,
+            Baseline::Alphabetic,
+        )?;
+
+        let bar = Rectangle::new(Point::new(area.top_left.x + 10, bar_top), Size::new(area.size.width - 20, 14));
+        bar.into_styled(PrimitiveStyle::with_stroke(Rgb565::WHITE, 1)).draw(display)?;
+        let inner_width = bar.size.width - 4;
+        let filled_width = inner_width * self.percent as u32 / 100;
+        Rectangle::new(bar.top_left + Point::new(2, 2), Size::new(filled_width, 10))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN)).draw(display)?;
+
+        let mut percent_text: String<4> = String::from(self.percent as u32);
+        _ = percent_text.push('%');
+        Text::with_alignment(
+            percent_text.as_str(),
+            Point::new(center_x, bar_top + 30),
+            MonoTextStyle::new(&FONT_6X12, Rgb565::WHITE),
+            Alignment::Center,
+        ).draw(display)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_display::TestDisplay;
+
+    fn bar_top(percent: u8) -> (TestDisplay<40, 60>, usize) {
+        let mut display = TestDisplay::new();
+        let area = Rectangle::new(Point::zero(), Size::new(40, 60));
+        ProgressBar::new(percent, String::from("Copy")).draw(&mut display, area).unwrap();
+        (display, (area.center().y - 7) as usize)
+    }
+
+    #[test]
+    fn half_bar() {
+        let (display, top) = bar_top(50);
+        display.assert_rows(top, &[
+            "..........####################..........",
+            "..........#..................#..........",
+            "..........#.GGGGGGGG.........#..........",
+        ]);
+        display.assert_rows(top + 11, &[
+            "..........#.GGGGGGGG.........#..........",
+            "..........#..................#..........",
+            "..........####################..........",
+        ]);
+    }
+
+    #[test]
+    fn empty_and_full_bar() {
+        let (display, top) = bar_top(0);
+        display.assert_rows(top + 2, &["..........#..................#.........."]);
+        assert_eq!(display.count(Rgb565::GREEN), 0);
+        let (display, top) = bar_top(150);
+        display.assert_rows(top + 2, &["..........#.GGGGGGGGGGGGGGGG.#.........."]);
+        assert_eq!(display.count(Rgb565::GREEN), 16 * 10);
+    }
+
+    #[test]
+    fn label_and_percent_are_drawn() {
+        let (display, top) = bar_top(50);
+        let white_above = (0..top).flat_map(|y| (0..40).map(move |x| (x, y)))
+            .filter(|(x, y)| display.pixel(*x, *y) == Rgb565::WHITE).count();
+        let white_below = (top + 14..60).flat_map(|y| (0..40).map(move |x| (x, y)))
+            .filter(|(x, y)| display.pixel(*x, *y) == Rgb565::WHITE).count();
+        assert!(white_above > 0);
+        assert!(white_below > 0);
+    }
+}
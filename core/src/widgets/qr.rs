@@ -0,0 +1,30 @@
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use qr::code::QrCode;
+
+/// Light modules around the code, readers need at least this much
+const QUIET_ZONE_MODULES: u32 = 2;
+
+/// Code over the whole display, black on white, modules scaled to the largest whole number of pixels
+/// which leaves room for the quiet zone
+pub fn draw_qr<D>(display: &mut D, code: &QrCode) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color=Rgb565>,
+{
+    let screen = display.bounding_box();
+    display.fill_solid(&screen, Rgb565::WHITE)?;
+
+    let size = code.size() as u32;
+    let side = screen.size.width.min(screen.size.height);
+    let scale = (side / (size + 2 * QUIET_ZONE_MODULES)).max(1);
+    let code_side = size * scale;
+    let top_left = screen.center() - Point::new(code_side as i32 / 2, code_side as i32 / 2);
+    let pixels = (0..code_side * code_side).map(|index| {
+        let (x, y) = (index % code_side / scale, index / code_side / scale);
+        if code.module(x as usize, y as usize) { Rgb565::BLACK } else { Rgb565::WHITE }
+    });
+    display.fill_contiguous(&Rectangle::new(top_left, Size::new(code_side, code_side)), pixels)
+}
@@ -0,0 +1,154 @@
+use embedded_graphics::mono_font::ascii::FONT_10X20;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::{PrimitiveStyle, Triangle};
+use embedded_graphics::text::{Alignment, Baseline, Text};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::Point;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use screen::text::{draw_text, FONT_SET_6X12};
+use widgets::LABEL_CAPACITY;
+use input::keyboard_codes::KeyboardCodes;
+
+/// Number picker. Up/Down change value by `step`, Left/Right by ten steps, Ok confirms.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Spinner {
+    value: i32,
+    min: i32,
+    max: i32,
+    step: i32,
+    label: String<LABEL_CAPACITY>,
+}
+
+impl Spinner {
+    pub fn new(value: i32, min: i32, max: i32, step: i32, label: String<LABEL_CAPACITY>) -> Self {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        Spinner {
+            value: value.clamp(min, max),
+            min,
+            max,
+            step: step.max(1),
+            label,
+        }
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Chosen value when `Ok` is pressed
+    pub fn on_key(&mut self, keycode: KeyboardCodes) -> Option<i32> {
+        let delta = match keycode {
+            KeyboardCodes::Up => self.step,
+            KeyboardCodes::Down => -self.step,
+            KeyboardCodes::Right => self.step.saturating_mul(10),
+            KeyboardCodes::Left => -self.step.saturating_mul(10),
+            KeyboardCodes::Ok => return Some(self.value),
+        };
+        self.value = self.value.saturating_add(delta).clamp(self.min, self.max);
+        None
+    }
+
+    pub fn draw<D>(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        let center = area.center();
+        draw_text(
+            display,
+            self.label.as_str(),
+            Point::new(center.x, area.top_left.y + 14),
+            &FONT_SET_6X12,
+            Rgb565::WHITE,
+            Alignment::Center,
+            Baseline::Alphabetic,
+        )?;
+
+        let up_color = if self.value < self.max { Rgb565::BLUE } else { Rgb565::new(8, 16, 8) };
+        let down_color = if self.value > self.min { Rgb565::BLUE } else { Rgb565::new(8, 16, 8) };
+        Triangle::new(center + Point::new(-8, -18), center + Point::new(8, -18), center + Point::new(0, -26))
+            .into_styled(PrimitiveStyle::with_fill(up_color))
+            .draw(display)?;
+        Triangle::new(center + Point::new(-8, 18), center + Point::new(8, 18), center + Point::new(0, 26))
+            .into_styled(PrimitiveStyle::with_fill(down_color))
+            .draw(display)?;
+
+        let value_text: String<11> = String::from(self.value);
+        Text::with_alignment(
+            value_text.as_str(),
+            center + Point::new(0, 6),
+            MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE),
+            Alignment::Center,
+        ).draw(display)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics_core::geometry::Size;
+    use utils::test_display::TestDisplay;
+
+    fn spinner(value: i32, min: i32, max: i32, step: i32) -> Spinner {
+        Spinner::new(value, min, max, step, String::from("Volume"))
+    }
+
+    #[test]
+    fn keys_step_the_value_within_bounds() {
+        let mut spinner = spinner(50, 0, 100, 5);
+        assert_eq!(spinner.on_key(KeyboardCodes::Up), None);
+        assert_eq!(spinner.value(), 55);
+        spinner.on_key(KeyboardCodes::Down);
+        spinner.on_key(KeyboardCodes::Down);
+        assert_eq!(spinner.value(), 45);
+        spinner.on_key(KeyboardCodes::Right);
+        assert_eq!(spinner.value(), 95);
+        spinner.on_key(KeyboardCodes::Right);
+        assert_eq!(spinner.value(), 100);
+        for _ in 0..3 {
+            spinner.on_key(KeyboardCodes::Left);
+        }
+        assert_eq!(spinner.value(), 0);
+        assert_eq!(spinner.on_key(KeyboardCodes::Ok), Some(0));
+    }
+
+    #[test]
+    fn construction_fixes_bad_arguments() {
+        // swapped bounds, value outside them, no step
+        let mut spinner = spinner(500, 10, -10, 0);
+        assert_eq!(spinner.value(), 10);
+        spinner.on_key(KeyboardCodes::Down);
+        assert_eq!(spinner.value(), 9);
+    }
+
+    #[test]
+    fn extreme_steps_saturate() {
+        let mut spinner = spinner(0, i32::MIN, i32::MAX, i32::MAX);
+        spinner.on_key(KeyboardCodes::Right);
+        assert_eq!(spinner.value(), i32::MAX);
+        spinner.on_key(KeyboardCodes::Left);
+        assert_eq!(spinner.value(), 0);
+        spinner.on_key(KeyboardCodes::Left);
+        spinner.on_key(KeyboardCodes::Left);
+        assert_eq!(spinner.value(), i32::MIN);
+    }
+
+    #[test]
+    fn arrow_at_a_bound_is_greyed_out() {
+        let draw = |value| {
+            let mut display = TestDisplay::<60, 80>::new();
+            spinner(value, 0, 10, 1).draw(&mut display, Rectangle::new(Point::zero(), Size::new(60, 80))).unwrap();
+            // tips of the up and down arrows
+            (display.pixel(30, 16), display.pixel(30, 63))
+        };
+        let grey = Rgb565::new(8, 16, 8);
+        assert_eq!(draw(5), (Rgb565::BLUE, Rgb565::BLUE));
+        assert_eq!(draw(10), (grey, Rgb565::BLUE));
+        assert_eq!(draw(0), (Rgb565::BLUE, grey));
+    }
+}
@@ -0,0 +1,98 @@
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::{PrimitiveStyleBuilder, RoundedRectangle};
+use embedded_graphics::text::{Alignment, Baseline};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+use screen::text::{draw_text, FONT_SET_6X12};
+use widgets::LABEL_CAPACITY;
+
+pub const DEFAULT_TOAST_MS: u32 = 2000;
+
+/// Short notification over everything else, disappears by itself
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Toast {
+    text: String<LABEL_CAPACITY>,
+    hide_at_ms: u64,
+}
+
+impl Toast {
+    pub fn new(text: String<LABEL_CAPACITY>, duration_ms: u32, now_ms: u64) -> Self {
+        Toast { text, hide_at_ms: now_ms.saturating_add(duration_ms as u64) }
+    }
+
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        now_ms >= self.hide_at_ms
+    }
+
+    /// Box in the lower part of `area`
+    pub fn draw<D>(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color=Rgb565>,
+    {
+        let bottom = area.top_left.y + area.size.height as i32;
+        let toast = Rectangle::new(Point::new(area.top_left.x + 2, bottom - 24), Size::new(area.size.width - 4, 20));
+        RoundedRectangle::with_equal_corners(toast, Size::new(4, 4))
+            .into_styled(PrimitiveStyleBuilder::new()
+                .fill_color(Rgb565::new(4, 8, 4))
+                .stroke_color(Rgb565::WHITE)
+                .stroke_width(1)
+                .build())
+            .draw(display)?;
+        draw_text(
+            display,
+            self.text.as_str(),
+            toast.center() + Point::new(0, 3),
+            &FONT_SET_6X12,
+            Rgb565::WHITE,
+            Alignment::Center,
+            Baseline::Alphabetic,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::test_display::TestDisplay;
+
+    #[test]
+    fn hides_after_its_duration() {
+        let toast = Toast::new(String::from("Saved"), 500, 1_000);
+        assert!(!toast.is_expired(1_000));
+        assert!(!toast.is_expired(1_499));
+        assert!(toast.is_expired(1_500));
+        assert!(Toast::new(String::from("Now"), 0, 1_000).is_expired(1_000));
+        assert!(!Toast::new(String::from("Late"), 500, u64::MAX - 10).is_expired(u64::MAX - 1));
+    }
+
+    #[test]
+    fn box_sits_at_the_bottom_of_the_area() {
+        let mut display = TestDisplay::<40, 40>::new();
+        Toast::new(String::from("ok"), 500, 0).draw(&mut display, Rectangle::new(Point::zero(), Size::new(40, 40))).unwrap();
+        // 20 px high rounded box with the text in the middle, ending 4 px above the bottom
+        display.assert_rows(15, &[
+            "........................................",
+            "....################################....",
+            "...#????????????????????????????????#...",
+            "..#??????????????????????????????????#..",
+        ]);
+        display.assert_rows(24, &[
+            "..#???????????###??#???#?????????????#..",
+            "..#??????????#???#?#??#??????????????#..",
+            "..#??????????#???#?###???????????????#..",
+            "..#??????????#???#?#??#??????????????#..",
+            "..#???????????###??#???#?????????????#..",
+        ]);
+        display.assert_rows(34, &[
+            "...#????????????????????????????????#...",
+            "....################################....",
+            "........................................",
+        ]);
+    }
+}
@@ -0,0 +1,18 @@
+MEMORY {
+    /* boot2, the bootloader and its boot state sector come first, see bootloader/memory.x */
+    /* active partition, pico_ui_core::update::layout::PICO_LAYOUT; the update partition follows it */
+    FLASH : ORIGIN = 0x1000A000, LENGTH = 996K
+    /* settings store, last 4 sectors of flash, see flash::driver */
+    SETTINGS : ORIGIN = 0x101FC000, LENGTH = 16K
+    RAM   : ORIGIN = 0x20000000, LENGTH = 256K - 256
+    /* kept over resets: crash record written by the panic handler */
+    CRASH : ORIGIN = 0x2003FF00, LENGTH = 256
+}
+
+SECTIONS {
+    /* ### Crash record, not zeroed or initialized on boot */
+    .crash_record (NOLOAD) : ALIGN(4)
+    {
+        KEEP(*(.crash_record));
+    } > CRASH
+} INSERT AFTER .bss;
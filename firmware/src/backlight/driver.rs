@@ -0,0 +1,31 @@
+use embedded_hal::pwm::SetDutyCycle;
+use rp2040_hal::gpio::bank0::Gpio12;
+use rp2040_hal::gpio::{FunctionNull, Pin, PullDown};
+use rp2040_hal::pwm::{FreeRunning, Pwm6, Slice};
+
+/// LCD backlight on gpio12 (channel A of PWM slice 6)
+pub struct BacklightPwm {
+    pwm: Slice<Pwm6, FreeRunning>,
+    last_level: Option<u8>,
+}
+
+impl BacklightPwm {
+    pub fn new(mut pwm: Slice<Pwm6, FreeRunning>, pin: Pin<Gpio12, FunctionNull, PullDown>) -> Self {
+        pwm.channel_a.output_to(pin);
+        _ = pwm.channel_a.set_duty_cycle(0);
+        pwm.enable();
+        BacklightPwm { pwm, last_level: None }
+    }
+
+    /// Apply brightness in percent
+    pub fn apply(&mut self, level: u8) {
+        if self.last_level == Some(level) {
+            return;
+        }
+        self.last_level = Some(level);
+        let level = level.min(100) as u32;
+        // perceived brightness is closer to linear with squared duty
+        let duty = level * level * self.pwm.channel_a.max_duty_cycle() as u32 / (100 * 100);
+        _ = self.pwm.channel_a.set_duty_cycle(duty as u16);
+    }
+}
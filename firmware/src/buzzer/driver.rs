@@ -0,0 +1,40 @@
+use embedded_hal::pwm::SetDutyCycle;
+use rp2040_hal::gpio::bank0::Gpio26;
+use rp2040_hal::gpio::{FunctionNull, Pin, PullDown};
+use rp2040_hal::pwm::{FreeRunning, Pwm5, Slice};
+use pico_ui_core::buzzer::player::BuzzerOutput;
+use pico_ui_core::buzzer::tone::tone_divider;
+
+/// Passive buzzer on gpio26 (channel A of PWM slice 5)
+pub struct BuzzerPwm {
+    pwm: Slice<Pwm5, FreeRunning>,
+    sys_hz: u32,
+}
+
+impl BuzzerPwm {
+    pub fn new(mut pwm: Slice<Pwm5, FreeRunning>, pin: Pin<Gpio26, FunctionNull, PullDown>, sys_hz: u32) -> Self {
+        pwm.channel_a.output_to(pin);
+        _ = pwm.channel_a.set_duty_cycle(0);
+        pwm.enable();
+        BuzzerPwm { pwm, sys_hz }
+    }
+
+    pub fn apply(&mut self, output: BuzzerOutput) {
+        match output {
+            BuzzerOutput::Silent => {
+                _ = self.pwm.channel_a.set_duty_cycle(0);
+            }
+            BuzzerOutput::Tone(freq_hz) => match tone_divider(freq_hz as u32, self.sys_hz) {
+                Some(divider) => {
+                    self.pwm.set_div_int(divider.div_int);
+                    self.pwm.set_div_frac(0);
+                    self.pwm.set_top(divider.top);
+                    _ = self.pwm.channel_a.set_duty_cycle(divider.top / 2);
+                }
+                None => {
+                    _ = self.pwm.channel_a.set_duty_cycle(0);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use core::convert::Infallible;
+use core::sync::atomic::{AtomicU16, Ordering};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::raw::{RawData, RawU16};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_graphics_core::Pixel;
+use rp2040_hal::pac;
+use lcd::instruction::Instruction;
+
+/// Data/command pin of the display
+const DC_PIN: u32 = 13;
+/// What `main` gives `ST7735::set_offset`, from the settings
+static OFFSET_X: AtomicU16 = AtomicU16::new(1);
+static OFFSET_Y: AtomicU16 = AtomicU16::new(2);
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 128;
+
+/// The ST7735 written through SPI0 registers. The panic handler can not reach the driver core1 owns,
+/// the controller keeps the setup `main` gave it, so only windows and pixels are sent.
+pub struct PanicDisplay {
+    spi: &'static pac::spi0::RegisterBlock,
+    sio: &'static pac::sio::RegisterBlock,
+}
+
+impl PanicDisplay {
+    /// Call with the offset the display was set up with
+    pub fn set_offset(x: u16, y: u16) {
+        OFFSET_X.store(x, Ordering::Relaxed);
+        OFFSET_Y.store(y, Ordering::Relaxed);
+    }
+
+    /// `None` before `main` enabled SPI0, a peripheral still in reset hangs the bus
+    pub fn take() -> Option<Self> {
+        let resets = unsafe { &*pac::RESETS::ptr() };
+        let spi = unsafe { &*pac::SPI0::ptr() };
+        if resets.reset_done().read().spi0().bit_is_clear() || spi.sspcr1().read().sse().bit_is_clear() {
+            return None;
+        }
+        Some(PanicDisplay { spi, sio: unsafe { &*pac::SIO::ptr() } })
+    }
+
+    fn command(&mut self, command: Instruction, params: &[u8]) {
+        self.wait_idle();
+        self.sio.gpio_out_clr().write(|w| unsafe { w.bits(1 << DC_PIN) });
+        self.write(&[command as u8]);
+        self.wait_idle();
+        self.sio.gpio_out_set().write(|w| unsafe { w.bits(1 << DC_PIN) });
+        self.write(params);
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        for byte in data {
+            while self.spi.sspsr().read().tnf().bit_is_clear() {}
+            self.spi.sspdr().write(|w| unsafe { w.data().bits(*byte as u16) });
+            // nothing is read back, keep the receive fifo from overflowing
+            while self.spi.sspsr().read().rne().bit_is_set() {
+                _ = self.spi.sspdr().read();
+            }
+        }
+    }
+
+    fn wait_idle(&self) {
+        while self.spi.sspsr().read().bsy().bit_is_set() {}
+    }
+
+    /// Start writing pixels into `area`, which has to be on screen
+    fn start_pixels(&mut self, area: &Rectangle) {
+        let (sx, sy) = (area.top_left.x as u16 + OFFSET_X.load(Ordering::Relaxed), area.top_left.y as u16 + OFFSET_Y.load(Ordering::Relaxed));
+        let (ex, ey) = (sx + area.size.width as u16 - 1, sy + area.size.height as u16 - 1);
+        let [sx_hi, sx_lo] = sx.to_be_bytes();
+        let [ex_hi, ex_lo] = ex.to_be_bytes();
+        self.command(Instruction::CASET, &[sx_hi, sx_lo, ex_hi, ex_lo]);
+        let [sy_hi, sy_lo] = sy.to_be_bytes();
+        let [ey_hi, ey_lo] = ey.to_be_bytes();
+        self.command(Instruction::RASET, &[sy_hi, sy_lo, ey_hi, ey_lo]);
+        self.command(Instruction::RAMWR, &[]);
+    }
+}
+
+impl DrawTarget for PanicDisplay {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item=Pixel<Self::Color>>,
+    {
+        let screen = self.bounding_box();
+        for Pixel(point, color) in pixels {
+            if screen.contains(point) {
+                self.start_pixels(&Rectangle::new(point, Size::new(1, 1)));
+                self.write(&RawU16::from(color).into_inner().to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if area.size == Size::zero() {
+            return Ok(());
+        }
+        self.start_pixels(&area);
+        let bytes = RawU16::from(color).into_inner().to_be_bytes();
+        for _ in 0..area.size.width * area.size.height {
+            self.write(&bytes);
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for PanicDisplay {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
@@ -0,0 +1,81 @@
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicBool, Ordering};
+use cortex_m::asm;
+use rp2040_hal::pac;
+use pico_ui_core::crash::record::CrashRecord;
+use pico_ui_core::crash::screen::{draw_crash, CRASH_SCREEN_MS};
+use pico_ui_core::crash::store::{self, CRASH_STORE_BYTES};
+use pico_ui_core::messages::pico_2_pi_message::Pico2PiMessage;
+use crash::display::PanicDisplay;
+
+/// In the `CRASH` region of memory.x, startup code neither zeroes nor initializes it
+#[link_section = ".crash_record"]
+static mut CRASH_STORE: MaybeUninit<[u8; CRASH_STORE_BYTES]> = MaybeUninit::uninit();
+/// Set on the first panic, a panic while handling it goes straight to the reset
+static PANICKING: AtomicBool = AtomicBool::new(false);
+/// At the 125 MHz `init_clocks_and_plls` sets, longer if the panic came before
+const CYCLES_PER_MS: u32 = 125_000;
+/// Watchdog resets everything but the oscillators, like `Watchdog::start` sets it up
+const WATCHDOG_RESETS: u32 = 0x0001_fffc;
+
+/// Panic saved before the last reset, reported to the Pi on first contact. Call once at boot.
+pub fn take_crash_record() -> Option<CrashRecord> {
+    store::take(unsafe { (*addr_of_mut!(CRASH_STORE)).assume_init_mut() })
+}
+
+/// Stops the other core, saves the record, sends the crash frame, shows the red screen
+/// and reboots after `CRASH_SCREEN_MS`
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let psm = unsafe { &*pac::PSM::ptr() };
+    let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+
+    if !PANICKING.load(Ordering::Relaxed) {
+        PANICKING.store(true, Ordering::Relaxed);
+        // jobs::core0 runs on proc1, records use the jobs numbering
+        let cpu = unsafe { &*pac::SIO::ptr() }.cpuid().read().bits();
+        let record = CrashRecord::from_panic(info, if cpu == 1 { 0 } else { 1 });
+        // the other core would go on drawing or talking to the Pi
+        psm.frce_off().modify(|_, w| if cpu == 1 { w.proc0().set_bit() } else { w.proc1().set_bit() });
+        // reboot when the screen was shown, not when the watchdog runs out
+        watchdog.ctrl().modify(|_, w| w.enable().clear_bit());
+
+        store::save(unsafe { (*addr_of_mut!(CRASH_STORE)).assume_init_mut() }, &record);
+        send_crash_frame(&record);
+        if let Some(mut display) = PanicDisplay::take() {
+            _ = draw_crash(&mut display, &record);
+        }
+        for _ in 0..CRASH_SCREEN_MS {
+            asm::delay(CYCLES_PER_MS);
+        }
+    }
+
+    psm.wdsel().write(|w| unsafe { w.bits(WATCHDOG_RESETS) });
+    // forced off stays over a watchdog reset, the other core has to be released first
+    psm.frce_off().modify(|_, w| w.proc0().clear_bit().proc1().clear_bit());
+    watchdog.ctrl().write(|w| w.trigger().set_bit());
+    loop {
+        asm::nop();
+    }
+}
+
+/// Straight into the UART0 fifo, the uart belongs to jobs::core0 which may be the one that panicked
+fn send_crash_frame(record: &CrashRecord) {
+    let resets = unsafe { &*pac::RESETS::ptr() };
+    let uart = unsafe { &*pac::UART0::ptr() };
+    if resets.reset_done().read().uart0().bit_is_clear() || uart.uartcr().read().uarten().bit_is_clear() {
+        return;
+    }
+    let message = Pico2PiMessage {
+        crash: Some(record.clone()),
+        ..Default::default()
+    };
+    for byte in message.to_frame().as_bytes() {
+        while uart.uartfr().read().txff().bit_is_set() {}
+        uart.uartdr().write(|w| unsafe { w.data().bits(*byte) });
+    }
+    while uart.uartfr().read().busy().bit_is_set() {}
+}
@@ -0,0 +1,2 @@
+pub mod display;
+pub mod handler;
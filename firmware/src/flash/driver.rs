@@ -0,0 +1,133 @@
+use core::ptr;
+use rp2040_hal::rom_data;
+use pico_ui_core::settings::flash::Flash;
+use pico_ui_core::settings::store::SETTINGS_AREA_BYTES;
+use pico_ui_core::update::layout::XIP_BASE;
+use flash::lockout::with_core1_parked;
+
+/// Whole flash of the Pico
+const FLASH_BYTES: u32 = 2048 * 1024;
+/// `SETTINGS` region of memory.x, the last sectors of flash
+pub const SETTINGS_FLASH_OFFSET: u32 = FLASH_BYTES - SETTINGS_AREA_BYTES as u32;
+const SECTOR_BYTES: u32 = 4096;
+const PAGE_BYTES: usize = 256;
+/// Erases a sector at a time when the range is not 64 KB aligned, like the pico-sdk calls it
+const BLOCK_BYTES: u32 = 1 << 16;
+const BLOCK_ERASE_COMMAND: u8 = 0xd8;
+
+/// Part of the on-board flash through the boot ROM routines, offsets are from its start
+pub struct RpFlash {
+    /// From the start of flash
+    start: u32,
+    bytes: u32,
+}
+
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum RpFlashError {
+    OutOfBounds,
+}
+
+/// Looked up while flash is still readable, called from RAM
+struct RomFunctions {
+    connect_internal_flash: unsafe extern "C" fn(),
+    flash_exit_xip: unsafe extern "C" fn(),
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: unsafe extern "C" fn(),
+}
+
+enum Operation {
+    Erase(u32),
+    /// A page, the pointer is to RAM
+    Program(u32, *const u8),
+}
+
+impl RpFlash {
+    /// `SETTINGS` region, for the settings store
+    pub const fn settings() -> Self {
+        RpFlash { start: SETTINGS_FLASH_OFFSET, bytes: SETTINGS_AREA_BYTES as u32 }
+    }
+
+    /// Everything before the settings, offsets are those of `PICO_LAYOUT`. Firmware updates
+    /// only write the update partition and the boot state sector.
+    pub const fn firmware() -> Self {
+        RpFlash { start: 0, bytes: SETTINGS_FLASH_OFFSET }
+    }
+
+    fn check_bounds(&self, offset: u32, len: usize) -> Result<(), RpFlashError> {
+        if offset as usize + len > self.bytes as usize {
+            return Err(RpFlashError::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    fn run(operation: Operation) {
+        let rom = RomFunctions {
+            connect_internal_flash: rom_data::connect_internal_flash::ptr(),
+            flash_exit_xip: rom_data::flash_exit_xip::ptr(),
+            flash_range_erase: rom_data::flash_range_erase::ptr(),
+            flash_range_program: rom_data::flash_range_program::ptr(),
+            flash_flush_cache: rom_data::flash_flush_cache::ptr(),
+        };
+        // boot2 of the bootloader sets up fast reads again afterwards, the ROM's own xip setup is far slower
+        let mut boot2 = [0u32; 64];
+        unsafe { ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), boot2.len()) };
+        with_core1_parked(|| {
+            cortex_m::interrupt::free(|_| unsafe { flash_operation(&rom, &operation, boot2.as_ptr()) });
+        });
+    }
+}
+
+impl Flash for RpFlash {
+    type Error = RpFlashError;
+    const SECTOR_SIZE: u32 = SECTOR_BYTES;
+
+    fn sectors(&self) -> u32 {
+        self.bytes / SECTOR_BYTES
+    }
+
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_bounds(offset, buffer.len())?;
+        let source = (XIP_BASE + self.start + offset) as *const u8;
+        unsafe { ptr::copy_nonoverlapping(source, buffer.as_mut_ptr(), buffer.len()) };
+        Ok(())
+    }
+
+    fn erase(&mut self, sector: u32) -> Result<(), Self::Error> {
+        self.check_bounds(sector * SECTOR_BYTES, SECTOR_BYTES as usize)?;
+        Self::run(Operation::Erase(self.start + sector * SECTOR_BYTES));
+        Ok(())
+    }
+
+    /// The ROM programs whole pages, bytes around `data` are programmed as 0xff which keeps them
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        self.check_bounds(offset, data.len())?;
+        let mut written = 0;
+        while written < data.len() {
+            let address = offset as usize + written;
+            let page_start = address - address % PAGE_BYTES;
+            let in_page = (PAGE_BYTES - address % PAGE_BYTES).min(data.len() - written);
+            let mut page = [0xffu8; PAGE_BYTES];
+            page[address - page_start..address - page_start + in_page].copy_from_slice(&data[written..written + in_page]);
+            Self::run(Operation::Program(self.start + page_start as u32, page.as_ptr()));
+            written += in_page;
+        }
+        Ok(())
+    }
+}
+
+/// Nothing here may run from flash, XIP is off until boot2 ran again
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_operation(rom: &RomFunctions, operation: &Operation, boot2: *const u32) {
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    match *operation {
+        Operation::Erase(address) => (rom.flash_range_erase)(address, SECTOR_BYTES as usize, BLOCK_BYTES, BLOCK_ERASE_COMMAND),
+        Operation::Program(address, page) => (rom.flash_range_program)(address, page, PAGE_BYTES),
+    }
+    (rom.flash_flush_cache)();
+    // thumb bit set
+    let enter_xip: extern "C" fn() = core::mem::transmute(boot2 as usize + 1);
+    enter_xip();
+}
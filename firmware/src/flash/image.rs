@@ -0,0 +1,17 @@
+use pico_ui_core::update::layout::{PICO_LAYOUT, XIP_BASE};
+
+extern "C" {
+    // cortex-m-rt link.x: `.data` is copied to RAM from right after the code
+    static __sidata: u32;
+    static __sdata: u32;
+    static __edata: u32;
+}
+
+/// Bytes of this image in the active partition, code and the initial values of `.data`.
+/// A rollback only has to copy back that much of it.
+pub fn running_image_len() -> u32 {
+    unsafe {
+        let data_len = &__edata as *const u32 as u32 - &__sdata as *const u32 as u32;
+        &__sidata as *const u32 as u32 + data_len - (XIP_BASE + PICO_LAYOUT.active)
+    }
+}
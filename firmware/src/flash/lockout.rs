@@ -0,0 +1,60 @@
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use cortex_m::asm;
+use rp2040_hal::pac;
+
+/// Sent through the fifo instead of a screen snapshot pointer, jobs::core1 parks when it reads it
+pub const PARK_REQUEST: u32 = 0;
+
+/// Set from spawning jobs::core0 on, before that only one core runs and nothing needs parking
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Held by jobs::core0 while it erases or programs flash
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+/// jobs::core1 spins in RAM
+static PARKED: AtomicBool = AtomicBool::new(false);
+
+/// Call before spawning jobs::core0
+pub fn enable() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// jobs::core0: run `operation` while jobs::core1 spins in RAM. Flash can't be read while it is
+/// written, code running from it would fault.
+pub fn with_core1_parked<R>(operation: impl FnOnce() -> R) -> R {
+    if !ENABLED.load(Ordering::Acquire) {
+        return operation();
+    }
+    REQUESTED.store(true, Ordering::Release);
+    // core1 may wait for a frame in read_blocking, frames queued before are drawn first
+    let sio = unsafe { &*pac::SIO::ptr() };
+    while sio.fifo_st().read().rdy().bit_is_clear() {}
+    sio.fifo_wr().write(|w| unsafe { w.bits(PARK_REQUEST) });
+    asm::sev();
+    while !PARKED.load(Ordering::Acquire) {}
+
+    let result = operation();
+
+    REQUESTED.store(false, Ordering::Release);
+    // back in flash only after the operation is over
+    while PARKED.load(Ordering::Acquire) {}
+    result
+}
+
+/// jobs::core1 after reading `PARK_REQUEST`
+pub fn park() {
+    unsafe {
+        park_in_ram(
+            &REQUESTED as *const AtomicBool as *const bool,
+            &PARKED as *const AtomicBool as *mut bool,
+        );
+    }
+}
+
+/// Volatile accesses only, any call could land in flash
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn park_in_ram(requested: *const bool, parked: *mut bool) {
+    ptr::write_volatile(parked, true);
+    while ptr::read_volatile(requested) {}
+    ptr::write_volatile(parked, false);
+}
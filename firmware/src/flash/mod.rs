@@ -0,0 +1,3 @@
+pub mod driver;
+pub mod image;
+pub mod lockout;
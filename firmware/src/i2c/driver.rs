@@ -0,0 +1,90 @@
+use embedded_hal::digital::OutputPin;
+use rp2040_hal::gpio::bank0::{Gpio10, Gpio11, Gpio15};
+use rp2040_hal::gpio::{FunctionI2C, FunctionSioOutput, Pin, PullDown, PullUp};
+use rp2040_hal::i2c::peripheral::{Event, I2CPeripheralEventIterator};
+use rp2040_hal::pac::I2C1;
+use pico_ui_core::link::i2c::I2cRegisters;
+use pico_ui_core::link::transport::{Transport, TransportError};
+use timer::now_us;
+
+/// The Pi stretches the clock between bytes while a transaction waits for this loop, a
+/// transaction is served to its end once it started
+const TRANSACTION_TIMEOUT_US: u32 = 20_000;
+/// `flush` gives the Pi this long to read what is waiting, the firmware reboots after it
+const FLUSH_TIMEOUT_US: u32 = 500_000;
+
+pub type I2cTarget = I2CPeripheralEventIterator<I2C1, (Pin<Gpio10, FunctionI2C, PullUp>, Pin<Gpio11, FunctionI2C, PullUp>)>;
+
+/// I2C1 as a target on gpio10 (SDA) and gpio11 (SCL) with the `I2cRegisters` map, gpio15 goes low
+/// while frames wait to be read
+pub struct I2cTransport {
+    i2c: I2cTarget,
+    irq: Pin<Gpio15, FunctionSioOutput, PullDown>,
+    registers: I2cRegisters,
+}
+
+impl I2cTransport {
+    pub fn new(i2c: I2cTarget, mut irq: Pin<Gpio15, FunctionSioOutput, PullDown>) -> Self {
+        irq.set_high().unwrap();
+        I2cTransport { i2c, irq, registers: I2cRegisters::new() }
+    }
+
+    /// Serve what the Pi started. Bytes are read one at a time, more in the tx fifo would be lost
+    /// when the Pi stops reading early.
+    fn poll(&mut self) {
+        let started = now_us();
+        let mut in_transaction = false;
+        loop {
+            match self.i2c.next() {
+                Some(Event::Start) => {
+                    self.registers.on_start();
+                    in_transaction = true;
+                }
+                Some(Event::Restart) => in_transaction = true,
+                Some(Event::TransferWrite) => {
+                    let mut buffer = [0u8; 16];
+                    let count = self.i2c.read(&mut buffer);
+                    for &byte in &buffer[..count] {
+                        self.registers.on_write(byte);
+                    }
+                }
+                Some(Event::TransferRead) => {
+                    let byte = self.registers.on_read();
+                    self.i2c.write(&[byte]);
+                }
+                Some(Event::Stop) => in_transaction = false,
+                None if !in_transaction || now_us().wrapping_sub(started) > TRANSACTION_TIMEOUT_US => break,
+                None => {}
+            }
+        }
+        if self.registers.events_pending() {
+            self.irq.set_low().unwrap();
+        } else {
+            self.irq.set_high().unwrap();
+        }
+    }
+}
+
+impl Transport for I2cTransport {
+    fn read_byte(&mut self) -> Option<Result<u8, TransportError>> {
+        self.poll();
+        self.registers.read_byte()
+    }
+
+    fn is_writable(&self) -> bool {
+        self.registers.is_writable()
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) {
+        self.registers.write_all(bytes);
+        // the Pi hears about it without waiting for the next read
+        self.irq.set_low().unwrap();
+    }
+
+    fn flush(&mut self) {
+        let started = now_us();
+        while self.registers.events_pending() && now_us().wrapping_sub(started) <= FLUSH_TIMEOUT_US {
+            self.poll();
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use core::convert::{Infallible, TryFrom};
+use core::fmt::Write;
+use core::ops::Deref;
+use core::slice;
+use core::str::Utf8Error;
+use core::sync::atomic::{AtomicU32, Ordering};
+use cortex_m::asm::delay;
+use cortex_m::delay::Delay;
+
+use cortex_m::prelude::_embedded_hal_serial_Write;
+use defmt::{debug, error, Format, Formatter, info, println};
+use embedded_graphics_core::geometry::Size;
+use embedded_graphics_core::pixelcolor::raw::ToBytes;
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_hal::digital::{InputPin, OutputPin};
+use heapless::{String, Vec};
+
+use rp2040_hal::{Clock, pac, Sio, Timer};
+use rp2040_hal::gpio::{Error, FunctionSio, Pin, PullUp, SioInput, ValidFunction};
+use rp2040_hal::spi::{SpiDevice, ValidSpiPinout};
+use rp2040_hal::uart::{DataBits, ReadErrorType, StopBits, UartConfig, UartDevice, UartPeripheral, ValidUartPinout};
+use ::{rp2040_hal as hal, XTAL_FREQ_HZ};
+use backlight::driver::BacklightPwm;
+use flash::driver::RpFlash;
+use flash::image::running_image_len;
+use flash::lockout::{self, PARK_REQUEST};
+use buzzer::driver::BuzzerPwm;
+use pico_ui_core::crash::record::CrashRecord;
+use pico_ui_core::device::reset::ResetReason;
+use pico_ui_core::device::state::DeviceState;
+use pico_ui_core::input::keyboard_codes::KeyboardCodes;
+use lcd::lcd::ST7735;
+use leds::driver::LedPwm;
+use watchdog::driver::{WatchdogDriver, CHECK_INS};
+#[cfg(feature = "usb")]
+use usb::auto::AutoTransport;
+#[cfg(feature = "usb")]
+use usb::driver::UsbPort;
+use pico_ui_core::link::transport::{exchange, Transport};
+use pico_ui_core::messages::receiver::LineReceiver;
+use pico_ui_core::screen::render::ScreenRenderer;
+use pico_ui_core::screen::snapshot::ScreenSnapshot;
+use pico_ui_core::settings::setting::Settings;
+use pico_ui_core::settings::store::SettingsStore;
+use pico_ui_core::update::boot_state::FirmwareStatus;
+use pico_ui_core::update::layout::PICO_LAYOUT;
+use pico_ui_core::update::receiver::UpdateReceiver;
+
+/// Time of the last render which changed the display, set by core1 and taken by core0. 0 when
+/// nothing was drawn since.
+static REDRAW_US: AtomicU32 = AtomicU32::new(0);
+/// Sent back by jobs::core1 once it is done with the snapshot
+const FRAME_DONE: u32 = 1;
+
+//todo read about ! mark as return type
+/// Core responsible for handling keyboard input, uart, I2C or USB IO
+pub fn core0<
+    ID: ValidFunction<FunctionSio<SioInput>>,
+    IU: ValidFunction<FunctionSio<SioInput>>,
+    IL: ValidFunction<FunctionSio<SioInput>>,
+    IR: ValidFunction<FunctionSio<SioInput>>,
+    IOK: ValidFunction<FunctionSio<SioInput>>,
+    L: Transport,
+>(
+    link: L,
+    down_button_pin: &mut Pin<ID, FunctionSio<SioInput>, PullUp>,
+    up_button_pin: &mut Pin<IU, FunctionSio<SioInput>, PullUp>,
+    left_button_pin: &mut Pin<IL, FunctionSio<SioInput>, PullUp>,
+    right_button_pin: &mut Pin<IR, FunctionSio<SioInput>, PullUp>,
+    ok_button_pin: &mut Pin<IOK, FunctionSio<SioInput>, PullUp>,
+    leds: &mut LedPwm,
+    buzzer: &mut BuzzerPwm,
+    backlight: &mut BacklightPwm,
+    mut watchdog: hal::Watchdog,
+    display_size: Size,
+    reset_reason: ResetReason,
+    crash: Option<CrashRecord>,
+    settings: Settings,
+    mut settings_store: SettingsStore<RpFlash>,
+    firmware_status: FirmwareStatus,
+) -> !
+{
+    println!("Hello, world! from core0");
+    let mut pac = unsafe { pac::Peripherals::steal() };
+    let core = unsafe { pac::CorePeripherals::steal() };
+    let mut sio = hal::Sio::new(pac.SIO);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+        .ok()
+        .unwrap();
+    let timer = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    let mut receiver = LineReceiver::new();
+    #[cfg(feature = "usb")]
+    let mut transport = AutoTransport::new(
+        link,
+        UsbPort::new(pac.USBCTRL_REGS, pac.USBCTRL_DPRAM, clocks.usb_clock, &mut pac.RESETS, settings.usb_mode),
+    );
+    #[cfg(not(feature = "usb"))]
+    let mut transport = link;
+    let mut device_state = DeviceState::new(timer.get_counter().ticks(), display_size, reset_reason, crash, settings, firmware_status);
+    let mut updater = UpdateReceiver::new(PICO_LAYOUT);
+    let mut firmware_flash = RpFlash::firmware();
+    let image_len = running_image_len();
+    // belongs to core1 from handing it over until `FRAME_DONE` comes back
+    let snapshot: &'static mut ScreenSnapshot = cortex_m::singleton!(
+        : ScreenSnapshot = ScreenSnapshot::new(&device_state.screen())
+    ).unwrap();
+    let mut frame_in_flight = false;
+    let mut watchdog = WatchdogDriver::start(watchdog, timer.get_counter().ticks() / 1_000);
+    loop {
+        let general_timer = timer.get_counter().ticks();
+        let now_ms = general_timer / 1_000;
+        CHECK_INS[0].check_in();
+        watchdog.feed(now_ms);
+        // left and right together toggle the debug screen, the Pi sees no key meanwhile
+        let debug_chord = left_button_pin.is_low().unwrap() && right_button_pin.is_low().unwrap();
+        device_state.on_debug_chord(debug_chord, general_timer);
+        let keydown_code_option = if debug_chord {
+            None
+        } else if down_button_pin.is_low().unwrap() {
+            Some(KeyboardCodes::Down)
+        } else if up_button_pin.is_low().unwrap() {
+            Some(KeyboardCodes::Up)
+        } else if left_button_pin.is_low().unwrap() {
+            Some(KeyboardCodes::Left)
+        } else if right_button_pin.is_low().unwrap() {
+            Some(KeyboardCodes::Right)
+        } else if ok_button_pin.is_low().unwrap() {
+            Some(KeyboardCodes::Ok)
+        } else {
+            None
+        };
+        device_state.on_keys(keydown_code_option, general_timer);
+        #[cfg(feature = "usb")]
+        transport.usb().set_keyboard_key(device_state.keyboard_key());
+        device_state.update(general_timer);
+
+        exchange(&mut transport, &mut receiver, &mut device_state, general_timer);
+
+        // erase stalls this loop for tens of ms, well within the watchdog period
+        while let Some(key) = device_state.take_unsaved_setting() {
+            let saved = device_state.settings.save(&mut settings_store, key);
+            if let Err(err) = &saved {
+                error!("Saving setting {:?} failed: {:?}", key, err);
+            }
+            device_state.on_setting_saved(key, saved.is_ok());
+        }
+
+        // a chunk erases at most one sector, like a setting
+        while let Some(command) = device_state.take_firmware_command() {
+            let result = updater.handle(&mut firmware_flash, command, image_len);
+            device_state.on_firmware_result(result);
+        }
+        // `fw=applying` went out, the bootloader installs the update on the way up
+        if device_state.wants_reboot() {
+            transport.flush();
+            println!("Rebooting to apply the firmware update");
+            watchdog.reboot();
+        }
+
+        leds.apply(device_state.led_controller.levels(now_ms));
+        backlight.apply(device_state.backlight_policy.level(now_ms));
+        if let Some(output) = device_state.buzzer_player.update(now_ms) {
+            buzzer.apply(output);
+        }
+
+        let redraw_us = REDRAW_US.swap(0, Ordering::Relaxed);
+        if redraw_us != 0 {
+            device_state.diagnostics.record_redraw(redraw_us);
+        }
+        device_state.diagnostics.record_loop((timer.get_counter().ticks() - general_timer) as u32);
+
+        if frame_in_flight && sio.fifo.read() == Some(FRAME_DONE) {
+            frame_in_flight = false;
+        }
+        // core1 draws from a copy, the state keeps changing while it does
+        if !frame_in_flight {
+            snapshot.update(&device_state.screen());
+            sio.fifo.write(snapshot as *const ScreenSnapshot as u32);
+            frame_in_flight = true;
+        }
+    }
+}
+
+/// Responsible for drawing on screen
+pub fn core1<DC, RST, D, PP>(display: &mut ST7735<DC, RST, D, PP>) -> !
+where
+    DC: OutputPin,
+    RST: OutputPin,
+    D: SpiDevice,
+    PP: ValidSpiPinout<D>,
+{
+    println!("Hello, world! from core1");
+    let mut _sio = unsafe { pac::Peripherals::steal() }.SIO;
+    let mut sio = Sio::new(_sio);
+    // only reads the timer, core0 owns it
+    let timer = unsafe { &*pac::TIMER::ptr() };
+    let mut renderer = ScreenRenderer::new();
+    loop {
+        let message = sio.fifo.read_blocking();
+        // core0 writes settings to flash, nothing may run from it meanwhile
+        if message == PARK_REQUEST {
+            lockout::park();
+            continue;
+        }
+        let snapshot = unsafe { &*(message as *const ScreenSnapshot) };
+
+        let started = timer.timerawl().read().bits();
+        let redrawn = renderer.render(
+            display,
+            &snapshot.content(),
+            // raw low word wraps every ~71 minutes, marquee jumps once then
+            started as u64 / 1_000,
+        ).unwrap();
+        if redrawn {
+            let redraw_us = timer.timerawl().read().bits().wrapping_sub(started);
+            REDRAW_US.store(redraw_us.max(1), Ordering::Relaxed);
+        }
+        sio.fifo.write(FRAME_DONE);
+        CHECK_INS[1].check_in();
+    }
+}
+
+fn concat_str_simple(a: &str, b: &str) -> [u8; 32] {
+    let mut buffer = [0u8; 32]; // Buffer size needs to be sufficient
+    let bytes_a = a.as_bytes();
+    let bytes_b = b.as_bytes();
+
+    buffer[..bytes_a.len()].copy_from_slice(bytes_a);
+    buffer[bytes_a.len()..bytes_a.len() + bytes_b.len()].copy_from_slice(bytes_b);
+
+    buffer
+}
@@ -15,6 +15,7 @@ use rp2040_hal::spi::{Enabled, State};
 use rp2040_hal::Spi;
 use rp2040_hal::spi::{SpiDevice, ValidSpiPinout};
 use crate::lcd::instruction::Instruction;
+use pico_ui_core::settings::setting::DisplayOrientation;
 
 /// ST7735 driver to connect to TFT displays.
 ///
@@ -61,6 +62,17 @@ pub enum Orientation {
     LandscapeSwapped = 0xA0,
 }
 
+impl From<DisplayOrientation> for Orientation {
+    fn from(orientation: DisplayOrientation) -> Self {
+        match orientation {
+            DisplayOrientation::Portrait => Orientation::Portrait,
+            DisplayOrientation::Landscape => Orientation::Landscape,
+            DisplayOrientation::PortraitSwapped => Orientation::PortraitSwapped,
+            DisplayOrientation::LandscapeSwapped => Orientation::LandscapeSwapped,
+        }
+    }
+}
+
 impl</*SPI, */DC, RST, D, PP> ST7735< DC, RST, D, PP>
     where
     // SPI: spi::SpiDevice,
@@ -0,0 +1,62 @@
+use core::convert::Infallible;
+use embedded_hal::pwm::SetDutyCycle;
+use rp2040_hal::gpio::bank0::{Gpio2, Gpio3, Gpio4, Gpio5};
+use rp2040_hal::gpio::{FunctionNull, Pin, PullDown};
+use rp2040_hal::pwm::{FreeRunning, Pwm1, Pwm2, Slice};
+use pico_ui_core::leds::pattern::LedId;
+
+/// PWM outputs of the status LEDs.
+/// gpio2/gpio3 are channels A/B of slice 1, gpio4/gpio5 are channels A/B of slice 2.
+pub struct LedPwm {
+    pwm1: Slice<Pwm1, FreeRunning>,
+    pwm2: Slice<Pwm2, FreeRunning>,
+    last_levels: [Option<u8>; 4],
+}
+
+impl LedPwm {
+    pub fn new(
+        mut pwm1: Slice<Pwm1, FreeRunning>,
+        mut pwm2: Slice<Pwm2, FreeRunning>,
+        green: Pin<Gpio2, FunctionNull, PullDown>,
+        blue1: Pin<Gpio3, FunctionNull, PullDown>,
+        blue2: Pin<Gpio4, FunctionNull, PullDown>,
+        red: Pin<Gpio5, FunctionNull, PullDown>,
+    ) -> Self {
+        pwm1.set_ph_correct();
+        pwm1.enable();
+        pwm2.set_ph_correct();
+        pwm2.enable();
+
+        pwm1.channel_a.output_to(green);
+        pwm1.channel_b.output_to(blue1);
+        pwm2.channel_a.output_to(blue2);
+        pwm2.channel_b.output_to(red);
+
+        LedPwm {
+            pwm1,
+            pwm2,
+            last_levels: [None; 4],
+        }
+    }
+
+    /// Apply brightness levels from `LedController::levels`. Unchanged LEDs are not touched.
+    pub fn apply(&mut self, levels: [u8; 4]) {
+        for led in LedId::ALL.iter() {
+            let level = levels[led.index()];
+            if self.last_levels[led.index()] == Some(level) {
+                continue;
+            }
+            self.last_levels[led.index()] = Some(level);
+
+            let channel: &mut dyn SetDutyCycle<Error=Infallible> = match led {
+                LedId::Green => &mut self.pwm1.channel_a,
+                LedId::Blue1 => &mut self.pwm1.channel_b,
+                LedId::Blue2 => &mut self.pwm2.channel_a,
+                LedId::Red => &mut self.pwm2.channel_b,
+            };
+            // square the level so pulse looks linear to the eye
+            let duty = (level as u32 * level as u32 * channel.max_duty_cycle() as u32) / (255 * 255);
+            _ = channel.set_duty_cycle(duty as u16);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+#![no_std]
+#![no_main]
+
+pub mod lcd;
+mod jobs;
+mod leds;
+mod buzzer;
+mod backlight;
+mod watchdog;
+mod crash;
+mod flash;
+mod uart;
+mod timer;
+#[cfg(feature = "i2c")]
+mod i2c;
+#[cfg(feature = "usb")]
+mod usb;
+
+extern crate embedded_hal;
+extern crate rp2040_hal;
+extern crate embedded_graphics;
+extern crate embedded_graphics_core;
+extern crate cortex_m;
+extern crate defmt;
+extern crate defmt_rtt;
+extern crate heapless;
+extern crate pico_ui_core;
+#[cfg(feature = "usb")]
+extern crate usb_device;
+#[cfg(feature = "usb")]
+extern crate usbd_hid;
+#[cfg(feature = "usb")]
+extern crate usbd_serial;
+// extern crate alloc;
+// extern crate panic_probe;
+
+use core::any::{Any, TypeId};
+use core::convert::TryInto;
+use core::fmt::Debug;
+use cortex_m::asm::delay;
+use cortex_m::prelude::{_embedded_hal_serial_Read, _embedded_hal_serial_Write};
+// use core::fmt::Debug;
+// use cortex_m::prelude::_embedded_hal_serial_Read;
+use defmt::*;
+use defmt::export::panic;
+use embedded_graphics::mono_font::ascii::FONT_6X12;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::Primitive;
+use embedded_graphics::primitives::{Line, PrimitiveStyle};
+use embedded_graphics::text::Text;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::Drawable;
+use embedded_graphics_core::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics_core::pixelcolor::{Rgb565, WebColors};
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_hal::digital::{InputPin, OutputPin};
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+use hal::pac;
+use rp2040_hal::clocks::Clock;
+use rp2040_hal::fugit::RateExtU32;
+use rp2040_hal::multicore::{Multicore, Stack};
+use rp2040_hal::uart::{DataBits, Error, Parity, StopBits, UartConfig};
+use jobs::core0;
+use lcd::lcd::{Orientation, ST7735};
+use leds::driver::LedPwm;
+use buzzer::driver::BuzzerPwm;
+use backlight::driver::BacklightPwm;
+use watchdog::driver::reset_reason;
+use crash::display::PanicDisplay;
+use crash::handler::take_crash_record;
+use flash::driver::RpFlash;
+use flash::lockout;
+use uart::driver::{uart_config, UartPort};
+use uart::transport::UartTransport;
+#[cfg(feature = "i2c")]
+use i2c::driver::I2cTransport;
+#[cfg(feature = "i2c")]
+use pico_ui_core::link::i2c::I2C_ADDRESS;
+use pico_ui_core::link::uart::{UartFormat, UartPins};
+use pico_ui_core::settings::setting::Settings;
+use pico_ui_core::settings::store::SettingsStore;
+use pico_ui_core::update::boot_state::{BootState, FirmwareStatus};
+use pico_ui_core::update::layout::PICO_LAYOUT;
+use pico_ui_core::utils::itoa::itoa;
+
+// use panic_probe as _;
+// use defmt::info;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+/// core0 job runs here, DeviceState with its layout, widget and image buffers lives on this stack
+static mut CORE1_STACK: Stack<8192> = Stack::new();
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[rp2040_hal::entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables and the spinlock are initialised.
+///
+/// The function configures the RP2040 peripherals, then toggles a GPIO pin in
+/// an infinite loop. If there is an LED connected to that pin, it will blink.
+#[rp2040_hal::entry]
+// #[hal::entry]
+fn main() -> ! {
+    defmt::info!("Hello, world!");
+    // info!("Hello, world!");
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+
+    // before anything touches the watchdog, reported to the Pi on first contact
+    let reset_reason = reset_reason(&pac.WATCHDOG, &pac.VREG_AND_CHIP_RESET);
+    let crash = take_crash_record();
+    // only this core runs, the store can format the flash without parking the other one
+    let mut settings_store = SettingsStore::mount(RpFlash::settings()).unwrap();
+    let mut settings = Settings::load(&mut settings_store).unwrap_or_default();
+    // left by the bootloader: on trial after an update, or rolled back from one
+    let firmware_status = BootState::read(&mut RpFlash::firmware(), &PICO_LAYOUT)
+        .map(|state| state.status())
+        .unwrap_or(FirmwareStatus::Idle);
+
+    // Set up the watchdog driver - needed by the clock setup code, started and fed by core0
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+        .ok()
+        .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    // The single-cycle I/O block controls our GPIO pins
+    let mut sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    // panic();
+    // hal::panic!("some panic message");
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // These are implicitly used by the spi driver if they are in the correct mode
+    let _spi_sclk = pins.gpio6.into_function::<hal::gpio::FunctionSpi>();
+    let _spi_mosi = pins.gpio7.into_function::<hal::gpio::FunctionSpi>();
+    // let _spi_miso = pins.gpio4.into_function::<hal::gpio::FunctionSpi>();
+
+    let spi_pin_layout = (_spi_mosi, _spi_sclk);
+
+    let spi = hal::Spi::<_, _, _, 8>::new(pac.SPI0, spi_pin_layout);
+
+    let dc = pins.gpio13.into_push_pull_output();
+    let rst = pins.gpio14.into_push_pull_output();
+
+    // Exchange the uninitialised SPI driver for an initialised one
+    let spi = spi.init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        16_000_000u32.Hz(),
+        &embedded_hal::spi::MODE_0,
+    );
+
+
+    let mut disp = ST7735::new(
+        spi,
+        dc,
+        Some(rst),
+        true, false, 128, 128);
+
+    disp.init(&mut delay).unwrap();
+    disp.set_orientation(&Orientation::from(settings.orientation)).unwrap();
+
+    let (offset_x, offset_y) = settings.offset;
+    disp.set_offset(offset_x as u16, offset_y as u16);
+    PanicDisplay::set_offset(offset_x as u16, offset_y as u16);
+    disp.set_address_window(0, 0, 127, 127).unwrap();
+    disp.clear(Rgb565::BLACK).unwrap();
+    let display_size = disp.size();
+
+    led_pin.set_high().unwrap();
+
+    let pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    // green, blue1, blue2, red. Patterns are driven from core0 loop
+    let mut leds = LedPwm::new(
+        pwm_slices.pwm1,
+        pwm_slices.pwm2,
+        pins.gpio2,
+        pins.gpio3,
+        pins.gpio4,
+        pins.gpio5,
+    );
+
+    // brightness, fades and auto-dim are handled in core0 loop
+    let mut backlight = BacklightPwm::new(pwm_slices.pwm6, pins.gpio12);
+
+    // tones are played from core0 loop without blocking
+    let mut buzzer = BuzzerPwm::new(
+        pwm_slices.pwm5,
+        pins.gpio26,
+        clocks.system_clock.freq().to_Hz(),
+    );
+
+    // Ok held at power on starts the uart as built, a wrong format or pin setting can't lock the Pi out
+    let mut ok_button_pin = pins.gpio20.into_pull_up_input();
+    delay.delay_us(100);
+    // only for this boot, the stored settings stay as they are
+    if ok_button_pin.is_low().unwrap() {
+        let defaults = Settings::default();
+        settings.baud = defaults.baud;
+        settings.parity = defaults.parity;
+        settings.uart_pins = defaults.uart_pins;
+    }
+    // the protocol runs over the uart, or the I2C target on carrier boards which only route I2C
+    #[cfg(not(feature = "i2c"))]
+    let link = {
+        // format and pins from the settings, `uart_switch` changes the format until the next boot
+        let uart_config = uart_config(UartFormat { baud: settings.baud, parity: settings.parity });
+        let peripheral_freq = clocks.peripheral_clock.freq();
+        let uart = match settings.uart_pins {
+            UartPins::Uart0Gp16 => UartPort::Uart0Gp16(
+                hal::uart::UartPeripheral::new(pac.UART0, (pins.gpio16.into_function(), pins.gpio17.into_function()), &mut pac.RESETS)
+                    .enable(uart_config, peripheral_freq)
+                    .unwrap(),
+            ),
+            UartPins::Uart0Gp0 => UartPort::Uart0Gp0(
+                hal::uart::UartPeripheral::new(pac.UART0, (pins.gpio0.into_function(), pins.gpio1.into_function()), &mut pac.RESETS)
+                    .enable(uart_config, peripheral_freq)
+                    .unwrap(),
+            ),
+            UartPins::Uart1Gp8 => UartPort::Uart1Gp8(
+                hal::uart::UartPeripheral::new(pac.UART1, (pins.gpio8.into_function(), pins.gpio9.into_function()), &mut pac.RESETS)
+                    .enable(uart_config, peripheral_freq)
+                    .unwrap(),
+            ),
+        };
+        UartTransport::new(uart, peripheral_freq)
+    };
+    #[cfg(feature = "i2c")]
+    let link = I2cTransport::new(
+        hal::I2C::new_peripheral_event_iterator(
+            pac.I2C1,
+            pins.gpio10.reconfigure(),
+            pins.gpio11.reconfigure(),
+            &mut pac.RESETS,
+            I2C_ADDRESS as u16,
+        ),
+        pins.gpio15.into_push_pull_output(),
+    );
+
+    let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+    let cores = mc.cores();
+    let core0 = &mut cores[1];
+
+
+    let mut down_button_pin = pins.gpio21.into_pull_up_input();
+    let mut up_button_pin = pins.gpio19.into_pull_up_input();
+    let mut left_button_pin = pins.gpio18.into_pull_up_input();
+    let mut right_button_pin = pins.gpio22.into_pull_up_input();
+
+    // from here on flash writes park jobs::core1 in RAM
+    lockout::enable();
+    let _test = core0.spawn(unsafe { &mut CORE1_STACK.mem }, move || {
+        jobs::core0(
+            link,
+            &mut down_button_pin,
+            &mut up_button_pin,
+            &mut left_button_pin,
+            &mut right_button_pin,
+            &mut ok_button_pin,
+            &mut leds,
+            &mut buzzer,
+            &mut backlight,
+            watchdog,
+            display_size,
+            reset_reason,
+            crash,
+            settings,
+            settings_store,
+            firmware_status,
+        );
+    });
+
+    jobs::core1(&mut disp);
+}
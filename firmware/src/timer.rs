@@ -0,0 +1,7 @@
+use rp2040_hal::pac;
+
+/// Low word of the timer core0 owns, for waits inside a driver. Wraps every ~71 minutes, only
+/// compare it over short spans with `wrapping_sub`.
+pub fn now_us() -> u32 {
+    unsafe { &*pac::TIMER::ptr() }.timerawl().read().bits()
+}
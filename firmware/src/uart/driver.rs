@@ -0,0 +1,98 @@
+use core::fmt::Write;
+use rp2040_hal::fugit::HertzU32;
+use rp2040_hal::gpio::bank0::{Gpio0, Gpio1, Gpio16, Gpio17, Gpio8, Gpio9};
+use rp2040_hal::gpio::{FunctionUart, Pin, PullDown};
+use rp2040_hal::pac::{self, UART0, UART1};
+use rp2040_hal::uart::{DataBits, Enabled, Parity, ReadErrorType, StopBits, UartConfig, UartPeripheral};
+use pico_ui_core::link::uart::{UartFormat, UartParity};
+
+type UartPin<I> = Pin<I, FunctionUart, PullDown>;
+
+/// Uart to the Pi on the pins from the `uart_pins` setting, the format can change while it runs
+pub enum UartPort {
+    Uart0Gp16(UartPeripheral<Enabled, UART0, (UartPin<Gpio16>, UartPin<Gpio17>)>),
+    Uart0Gp0(UartPeripheral<Enabled, UART0, (UartPin<Gpio0>, UartPin<Gpio1>)>),
+    Uart1Gp8(UartPeripheral<Enabled, UART1, (UartPin<Gpio8>, UartPin<Gpio9>)>),
+}
+
+/// Same code for every variant, they only differ in types
+macro_rules! with_uart {
+    ($port:expr, $uart:ident => $body:expr) => {
+        match $port {
+            UartPort::Uart0Gp16($uart) => $body,
+            UartPort::Uart0Gp0($uart) => $body,
+            UartPort::Uart1Gp8($uart) => $body,
+        }
+    };
+}
+
+/// What `UartPeripheral::enable` takes for `format`, always 8 data bits and 1 stop bit
+pub fn uart_config(format: UartFormat) -> UartConfig {
+    UartConfig::new(HertzU32::from_raw(format.baud), DataBits::Eight, hal_parity(format.parity), StopBits::One)
+}
+
+fn hal_parity(parity: UartParity) -> Option<Parity> {
+    match parity {
+        UartParity::None => None,
+        UartParity::Odd => Some(Parity::Odd),
+        UartParity::Even => Some(Parity::Even),
+    }
+}
+
+impl UartPort {
+    pub fn uart_is_readable(&self) -> bool {
+        with_uart!(self, uart => uart.uart_is_readable())
+    }
+
+    pub fn uart_is_writable(&self) -> bool {
+        with_uart!(self, uart => uart.uart_is_writable())
+    }
+
+    /// Still shifting out, the format must not change before it is done
+    pub fn uart_is_busy(&self) -> bool {
+        with_uart!(self, uart => uart.uart_is_busy())
+    }
+
+    pub fn read_full_blocking(&mut self, buffer: &mut [u8]) -> Result<(), ReadErrorType> {
+        with_uart!(self, uart => uart.read_full_blocking(buffer))
+    }
+
+    pub fn write_char(&mut self, c: char) -> core::fmt::Result {
+        with_uart!(self, uart => uart.write_char(c))
+    }
+
+    /// Change rate and parity in place after everything sent so far went out. `UartPeripheral`
+    /// can only be reconfigured by taking it apart, so the registers are written like its
+    /// `enable` does.
+    pub fn set_format(&mut self, format: UartFormat, frequency: HertzU32) {
+        while self.uart_is_busy() {}
+        let device = match self {
+            UartPort::Uart0Gp16(_) | UartPort::Uart0Gp0(_) => unsafe { &*UART0::ptr() },
+            UartPort::Uart1Gp8(_) => unsafe { &*UART1::ptr() },
+        };
+        write_format(device, format, frequency);
+    }
+}
+
+fn write_format(device: &pac::uart0::RegisterBlock, format: UartFormat, frequency: HertzU32) {
+    // `BAUD_RATES` are all within the divider range at the peripheral clock
+    let divider = 8 * frequency.to_Hz() / format.baud;
+    let (divider_int, divider_frac) = (divider >> 7, ((divider & 0x7f) + 1) / 2);
+
+    // line control is only written while the uart is off
+    device.uartcr().modify(|_, w| w.uarten().clear_bit());
+    device.uartibrd().write(|w| unsafe { w.baud_divint().bits(divider_int as u16) });
+    device.uartfbrd().write(|w| unsafe { w.baud_divfrac().bits(divider_frac as u8) });
+    // also latches the divisors
+    device.uartlcr_h().write(|w| {
+        w.fen().set_bit();
+        unsafe { w.wlen().bits(0b11) };
+        w.stp2().clear_bit();
+        match format.parity {
+            UartParity::None => w.pen().clear_bit(),
+            UartParity::Odd => w.pen().set_bit().eps().clear_bit(),
+            UartParity::Even => w.pen().set_bit().eps().set_bit(),
+        }
+    });
+    device.uartcr().modify(|_, w| w.uarten().set_bit());
+}
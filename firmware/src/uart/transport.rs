@@ -0,0 +1,51 @@
+use rp2040_hal::fugit::HertzU32;
+use rp2040_hal::uart::ReadErrorType;
+use pico_ui_core::link::transport::{Transport, TransportError};
+use pico_ui_core::link::uart::UartFormat;
+use uart::driver::UartPort;
+
+/// The protocol over `UartPort`, with the peripheral clock `set_format` needs
+pub struct UartTransport {
+    port: UartPort,
+    frequency: HertzU32,
+}
+
+impl UartTransport {
+    pub fn new(port: UartPort, frequency: HertzU32) -> Self {
+        UartTransport { port, frequency }
+    }
+}
+
+impl Transport for UartTransport {
+    fn read_byte(&mut self) -> Option<Result<u8, TransportError>> {
+        if !self.port.uart_is_readable() {
+            return None;
+        }
+        let mut buffer = [0u8; 1];
+        Some(self.port.read_full_blocking(&mut buffer).map(|_| buffer[0]).map_err(|err| match err {
+            ReadErrorType::Break => TransportError::Break,
+            ReadErrorType::Overrun => TransportError::Overrun,
+            ReadErrorType::Parity => TransportError::Parity,
+            ReadErrorType::Framing => TransportError::Framing,
+        }))
+    }
+
+    fn is_writable(&self) -> bool {
+        self.port.uart_is_writable()
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            // only write char and write blocking works as expected for now
+            self.port.write_char(byte as char).unwrap();
+        }
+    }
+
+    fn flush(&mut self) {
+        while self.port.uart_is_busy() {}
+    }
+
+    fn set_format(&mut self, format: UartFormat) {
+        self.port.set_format(format, self.frequency);
+    }
+}
@@ -0,0 +1,87 @@
+use defmt::info;
+use pico_ui_core::link::transport::{Transport, TransportError};
+use pico_ui_core::link::uart::UartFormat;
+use usb::driver::UsbPort;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Active {
+    Wired,
+    Usb,
+}
+
+/// The uart (or I2C) and USB both listen until the first good byte comes in on one of them, that
+/// one carries the protocol until the next boot. Frames from before go out on both.
+pub struct AutoTransport<T: Transport> {
+    wired: T,
+    usb: UsbPort,
+    active: Option<Active>,
+}
+
+impl<T: Transport> AutoTransport<T> {
+    pub fn new(wired: T, usb: UsbPort) -> Self {
+        AutoTransport { wired, usb, active: None }
+    }
+
+    /// Also the keyboard, whichever transport was picked
+    pub fn usb(&mut self) -> &mut UsbPort {
+        &mut self.usb
+    }
+}
+
+impl<T: Transport> Transport for AutoTransport<T> {
+    fn read_byte(&mut self) -> Option<Result<u8, TransportError>> {
+        // also keeps the USB device enumerated while the other one is in use
+        let usb = self.usb.read_byte();
+        match self.active {
+            Some(Active::Usb) => usb,
+            Some(Active::Wired) => self.wired.read_byte(),
+            None => {
+                if let Some(Ok(byte)) = usb {
+                    info!("Pi talks over USB");
+                    self.active = Some(Active::Usb);
+                    return Some(Ok(byte));
+                }
+                // an unconnected uart rx pin reads as a break, errors don't pick it
+                match self.wired.read_byte() {
+                    Some(Ok(byte)) => {
+                        info!("Pi talks over the uart or I2C");
+                        self.active = Some(Active::Wired);
+                        Some(Ok(byte))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn is_writable(&self) -> bool {
+        match self.active {
+            Some(Active::Usb) => self.usb.is_writable(),
+            Some(Active::Wired) | None => self.wired.is_writable(),
+        }
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) {
+        if self.active != Some(Active::Usb) {
+            self.wired.write_all(bytes);
+        }
+        if self.active != Some(Active::Wired) {
+            self.usb.write_all(bytes);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.active != Some(Active::Usb) {
+            self.wired.flush();
+        }
+        if self.active != Some(Active::Wired) {
+            self.usb.flush();
+        }
+    }
+
+    fn set_format(&mut self, format: UartFormat) {
+        if self.active != Some(Active::Usb) {
+            self.wired.set_format(format);
+        }
+    }
+}
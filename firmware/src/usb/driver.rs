@@ -0,0 +1,144 @@
+use defmt::error;
+use rp2040_hal::clocks::UsbClock;
+use rp2040_hal::pac::{RESETS, USBCTRL_DPRAM, USBCTRL_REGS};
+use rp2040_hal::usb::UsbBus;
+use usb_device::class_prelude::{UsbBusAllocator, UsbClass};
+use usb_device::prelude::{StringDescriptors, UsbDevice, UsbDeviceBuilder, UsbDeviceState, UsbVidPid};
+use usb_device::UsbError;
+use usbd_hid::descriptor::{KeyboardReport, SerializedDescriptor};
+use usbd_hid::hid_class::HIDClass;
+use usbd_serial::{SerialPort, USB_CLASS_CDC};
+use pico_ui_core::input::keyboard_codes::KeyboardCodes;
+use pico_ui_core::link::transport::{Transport, TransportError};
+use pico_ui_core::link::usb::{PacketBuffer, UsbMode};
+use timer::now_us;
+
+/// Raspberry Pi vendor id with the Pico SDK CDC product id, Linux binds `cdc_acm` to it as `/dev/ttyACM<n>`
+const USB_VID_PID: UsbVidPid = UsbVidPid(0x2e8a, 0x000a);
+/// A host that keeps the port open without reading loses the rest of the frame after this
+const WRITE_TIMEOUT_US: u32 = 50_000;
+/// Host asks for keyboard reports this often
+const KEYBOARD_POLL_MS: u8 = 10;
+
+/// USB device peripheral: CDC-ACM serial port, keyboard, or both as a composite device, from
+/// `UsbMode`. There is no interrupt handler, every call polls the device (reads once their packet
+/// is drained), so the core0 loop keeps it enumerated.
+pub struct UsbPort {
+    device: UsbDevice<'static, UsbBus>,
+    serial: Option<SerialPort<'static, UsbBus>>,
+    keyboard: Option<HIDClass<'static, UsbBus>>,
+    /// Key in the last report the host took
+    keyboard_key: Option<KeyboardCodes>,
+    rx: PacketBuffer,
+}
+
+impl UsbPort {
+    pub fn new(regs: USBCTRL_REGS, dpram: USBCTRL_DPRAM, clock: UsbClock, resets: &mut RESETS, mode: UsbMode) -> Self {
+        // the device and the class keep borrowing the bus for as long as the firmware runs
+        let bus: &'static UsbBusAllocator<UsbBus> = cortex_m::singleton!(
+            : UsbBusAllocator<UsbBus> = UsbBusAllocator::new(UsbBus::new(regs, dpram, clock, true, resets))
+        ).unwrap();
+        // interfaces are numbered in the order the classes are made
+        let serial = mode.has_serial().then(|| SerialPort::new(bus));
+        let keyboard = mode.has_keyboard().then(|| HIDClass::new(bus, KeyboardReport::desc(), KEYBOARD_POLL_MS));
+        let builder = UsbDeviceBuilder::new(bus, USB_VID_PID)
+            .strings(&[StringDescriptors::default()
+                .manufacturer("pico-ui")
+                .product("Pico UI")
+                .serial_number("pico-ui")])
+            .unwrap();
+        let device = match mode {
+            UsbMode::Serial => builder.device_class(USB_CLASS_CDC),
+            // the class is given by the interface
+            UsbMode::Keyboard => builder,
+            UsbMode::KeyboardSerial => builder.composite_with_iads(),
+        }.build();
+        UsbPort { device, serial, keyboard, keyboard_key: None, rx: PacketBuffer::new() }
+    }
+
+    /// Enumerated and a program on the host has the serial port open
+    pub fn is_open(&self) -> bool {
+        let dtr = self.serial.as_ref().map_or(false, |serial| serial.dtr());
+        self.device.state() == UsbDeviceState::Configured && dtr
+    }
+
+    /// Report `key` held down, or all keys up. Only changes are sent, the host repeats a held key
+    /// by itself. A report the host did not take yet is tried again on the next call.
+    pub fn set_keyboard_key(&mut self, key: Option<KeyboardCodes>) {
+        if self.keyboard.is_none() || key == self.keyboard_key {
+            return;
+        }
+        self.poll();
+        let mut keycodes = [0u8; 6];
+        if let Some(key) = key {
+            keycodes[0] = key.hid_usage();
+        }
+        let report = KeyboardReport { modifier: 0, reserved: 0, leds: 0, keycodes };
+        if let Some(Ok(_)) = self.keyboard.as_mut().map(|keyboard| keyboard.push_input(&report)) {
+            self.keyboard_key = key;
+        }
+    }
+
+    fn poll(&mut self) {
+        poll(&mut self.device, &mut self.serial, &mut self.keyboard);
+    }
+}
+
+/// Fields one by one, so the receive buffer can stay borrowed meanwhile
+fn poll(
+    device: &mut UsbDevice<'static, UsbBus>,
+    serial: &mut Option<SerialPort<'static, UsbBus>>,
+    keyboard: &mut Option<HIDClass<'static, UsbBus>>,
+) {
+    match (serial, keyboard) {
+        (Some(serial), Some(keyboard)) => device.poll(&mut [serial as &mut dyn UsbClass<UsbBus>, keyboard]),
+        (Some(serial), None) => device.poll(&mut [serial]),
+        (None, Some(keyboard)) => device.poll(&mut [keyboard]),
+        (None, None) => device.poll(&mut []),
+    };
+}
+
+impl Transport for UsbPort {
+    fn read_byte(&mut self) -> Option<Result<u8, TransportError>> {
+        let UsbPort { device, serial, keyboard, rx, .. } = self;
+        rx.read_byte(|buffer| {
+            poll(device, serial, keyboard);
+            serial.as_mut().map_or(0, |serial| serial.read(buffer).unwrap_or(0))
+        })
+    }
+
+    fn is_writable(&self) -> bool {
+        true
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) {
+        // nobody listens, the class would only buffer it until the port is opened
+        if !self.is_open() {
+            return;
+        }
+        let started = now_us();
+        let mut sent = 0;
+        while sent < bytes.len() {
+            self.poll();
+            match self.serial.as_mut().map(|serial| serial.write(&bytes[sent..])) {
+                Some(Ok(count)) => sent += count,
+                Some(Err(UsbError::WouldBlock)) => {}
+                _ => return,
+            }
+            if now_us().wrapping_sub(started) > WRITE_TIMEOUT_US {
+                error!("USB host not reading, {} bytes dropped", bytes.len() - sent);
+                return;
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let started = now_us();
+        while self.is_open() && now_us().wrapping_sub(started) <= WRITE_TIMEOUT_US {
+            self.poll();
+            if let Some(Ok(_)) = self.serial.as_mut().map(|serial| serial.flush()) {
+                return;
+            }
+        }
+    }
+}
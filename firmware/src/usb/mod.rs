@@ -0,0 +1,2 @@
+pub mod auto;
+pub mod driver;
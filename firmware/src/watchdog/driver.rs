@@ -0,0 +1,80 @@
+use defmt::error;
+use rp2040_hal::fugit::ExtU32;
+use rp2040_hal::pac;
+use rp2040_hal::watchdog::{ScratchRegister, Watchdog};
+use pico_ui_core::device::reset::ResetReason;
+use pico_ui_core::device::supervisor::{CheckIn, Supervisor, SUPERVISED_CORES};
+
+/// Longer than the check-in timeout, a hung core is noticed before the reset
+const WATCHDOG_PERIOD_MS: u32 = 1500;
+/// Upper half of `SCRATCH0` when it holds the stalled core, scratch registers survive watchdog resets
+const STALLED_CORE_MAGIC: u32 = 0x5354_0000;
+
+/// Both loops check in here every pass: index 0 is `jobs::core0`, 1 is `jobs::core1`
+pub static CHECK_INS: [CheckIn; SUPERVISED_CORES] = [CheckIn::new(), CheckIn::new()];
+
+/// Hardware watchdog, fed from the core0 loop only while `Supervisor` sees both cores alive
+pub struct WatchdogDriver {
+    watchdog: Watchdog,
+    supervisor: Supervisor,
+    stall_recorded: bool,
+}
+
+impl WatchdogDriver {
+    /// Start counting down, `feed` has to be called every core0 loop from now on
+    pub fn start(mut watchdog: Watchdog, now_ms: u64) -> Self {
+        // stopping on a breakpoint should not reset the board
+        watchdog.pause_on_debug(true);
+        watchdog.start((WATCHDOG_PERIOD_MS * 1_000).micros());
+        WatchdogDriver { watchdog, supervisor: Supervisor::new(now_ms), stall_recorded: false }
+    }
+
+    pub fn feed(&mut self, now_ms: u64) {
+        if self.supervisor.update(&CHECK_INS, now_ms) {
+            self.watchdog.feed();
+            return;
+        }
+        if let (Some(core), false) = (self.supervisor.stalled_core(), self.stall_recorded) {
+            error!("core{} stopped checking in, waiting for the watchdog reset", core);
+            self.watchdog.write_scratch(ScratchRegister::Scratch0, STALLED_CORE_MAGIC | core as u32);
+            self.stall_recorded = true;
+        }
+    }
+
+    /// Reset the board now, `reset_reason` reports it as `Software` after the restart
+    pub fn reboot(&mut self) -> ! {
+        let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+        watchdog.ctrl().modify(|_, w| w.trigger().set_bit());
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+}
+
+/// Why the chip started, read before the watchdog is handed to `hal::Watchdog`.
+/// The stalled core record is cleared, so a later reset is not blamed on it.
+pub fn reset_reason(watchdog: &pac::WATCHDOG, chip_reset: &pac::VREG_AND_CHIP_RESET) -> ResetReason {
+    let scratch = watchdog.scratch0().read().bits();
+    watchdog.scratch0().write(|w| unsafe { w.bits(0) });
+
+    // watchdog resets leave the chip reset flags of the previous power on in place, check it first
+    let reason = watchdog.reason().read();
+    if reason.timer().bit_is_set() {
+        let stalled_core = match scratch & 0xffff_0000 {
+            STALLED_CORE_MAGIC => Some((scratch & 0xff) as u8),
+            _ => None,
+        };
+        return ResetReason::Watchdog(stalled_core);
+    }
+    if reason.force().bit_is_set() {
+        return ResetReason::Software;
+    }
+    let chip_reset = chip_reset.chip_reset().read();
+    if chip_reset.had_run().bit_is_set() {
+        ResetReason::ResetPin
+    } else if chip_reset.had_psm_restart().bit_is_set() {
+        ResetReason::Debugger
+    } else {
+        ResetReason::PowerOn
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use pico_ui_core::device::state::DEBUG_CHORD_MS;
+use pico_ui_core::input::keyboard_codes::KeyboardCodes;
+
+/// Default time a key is held for `key <code>`
+pub const TAP_MS: u64 = 100;
+
+/// Simulator control commands, one per line:
+///
+/// * `key <u|d|l|r|o> [hold_ms]` - press a button, optionally holding it
+/// * `chord [hold_ms]` - hold left and right together, held for `DEBUG_CHORD_MS` it toggles the debug screen
+/// * `dump <path.png|path.ppm>` - save current frame
+/// * `panic [message]` - crash like the firmware does: crash screen, crash frame, reboot
+/// * `reset` - press the reset button, a firmware update on trial is rolled back
+/// * `quit`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    Key(KeyboardCodes, u64),
+    Chord(u64),
+    Dump(PathBuf),
+    Panic(String),
+    Reset,
+    Quit,
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut parts = line.split_whitespace();
+    match parts.next() {
+        Some("key") => {
+            let code = parts.next()
+                .and_then(|x| x.chars().next())
+                .and_then(KeyboardCodes::from_char)
+                .ok_or_else(|| "expected key code: u, d, l, r or o".to_string())?;
+            let hold_ms = match parts.next() {
+                Some(hold_ms) => hold_ms.parse::<u64>().map_err(|_| format!("bad hold time: {}", hold_ms))?,
+                None => TAP_MS,
+            };
+            Ok(Command::Key(code, hold_ms))
+        }
+        Some("chord") => {
+            let hold_ms = match parts.next() {
+                Some(hold_ms) => hold_ms.parse::<u64>().map_err(|_| format!("bad hold time: {}", hold_ms))?,
+                // a little over, the loop does not run exactly on time
+                None => DEBUG_CHORD_MS + 100,
+            };
+            Ok(Command::Chord(hold_ms))
+        }
+        Some("dump") => {
+            let path = parts.next().ok_or_else(|| "expected file path".to_string())?;
+            Ok(Command::Dump(PathBuf::from(path)))
+        }
+        Some("panic") => {
+            let message = parts.collect::<Vec<_>>().join(" ");
+            Ok(Command::Panic(if message.is_empty() { "simulated panic".to_string() } else { message }))
+        }
+        Some("reset") => Ok(Command::Reset),
+        Some("quit") => Ok(Command::Quit),
+        Some(other) => Err(format!("unknown command: {}", other)),
+        None => Err("empty command".to_string()),
+    }
+}
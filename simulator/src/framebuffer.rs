@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::{Rgb565, Rgb888};
+use embedded_graphics_core::prelude::RgbColor;
+use embedded_graphics_core::Pixel;
+
+pub const WIDTH: u32 = 128;
+pub const HEIGHT: u32 = 128;
+
+/// In-memory replacement of the ST7735, same size and color format
+pub struct Framebuffer {
+    pixels: Vec<Rgb565>,
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Framebuffer { pixels: vec![Rgb565::BLACK; (WIDTH * HEIGHT) as usize] }
+    }
+
+    /// Pixels as 8 bit RGB triplets, row by row
+    pub fn to_rgb888(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.pixels.len() * 3);
+        for pixel in &self.pixels {
+            let color = Rgb888::from(*pixel);
+            data.extend_from_slice(&[color.r(), color.g(), color.b()]);
+        }
+        data
+    }
+
+    /// Write frame as binary PPM (P6)
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", WIDTH, HEIGHT)?;
+        writer.write_all(&self.to_rgb888())
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, WIDTH, HEIGHT);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+        png_writer.write_image_data(&self.to_rgb888()).map_err(io::Error::other)
+    }
+
+    /// Write PNG or PPM, chosen by file extension
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("ppm") => self.write_ppm(writer),
+            _ => self.write_png(writer),
+        }
+    }
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = Rgb565;
+    type Error = ();
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item=Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels.into_iter() {
+            // Only draw pixels that would be on screen, same as the ST7735 driver
+            if coord.x >= 0 && coord.y >= 0 && coord.x < WIDTH as i32 && coord.y < HEIGHT as i32 {
+                self.pixels[(coord.y as u32 * WIDTH + coord.x as u32) as usize] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
@@ -0,0 +1,371 @@
+//! Desktop simulator of the device.
+//!
+//! Runs the firmware screen model, rendering and protocol state machines on the host against
+//! an in-memory 128x128 framebuffer, so Pi applications can be developed without hardware.
+//!
+//! ```bash
+//! # uart on stdin/stdout, lines starting with `#sim ` are simulator commands
+//! cargo run -- --frames /tmp/frames
+//! # uart on a pseudo terminal, stdin is for simulator commands
+//! cargo run -- --pty
+//! ```
+
+extern crate embedded_graphics;
+extern crate embedded_graphics_core;
+extern crate heapless;
+extern crate nix;
+extern crate pico_ui_core;
+extern crate png;
+
+mod commands;
+mod framebuffer;
+mod transport;
+
+use std::convert::TryInto;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use embedded_graphics_core::geometry::OriginDimensions;
+
+use pico_ui_core::crash::record::CrashRecord;
+use pico_ui_core::crash::screen::{draw_crash, CRASH_SCREEN_MS};
+use pico_ui_core::device::reset::ResetReason;
+use pico_ui_core::device::state::DeviceState;
+use pico_ui_core::input::keyboard_codes::KeyboardCodes;
+use pico_ui_core::link::uart::UartChange;
+use pico_ui_core::messages::pico_2_pi_message::Pico2PiMessage;
+use pico_ui_core::messages::receiver::LineReceiver;
+use pico_ui_core::screen::render::ScreenRenderer;
+use pico_ui_core::settings::flash::{Flash, MemFlash};
+use pico_ui_core::settings::setting::Settings;
+use pico_ui_core::settings::store::{SettingsStore, SETTINGS_AREA_BYTES};
+use pico_ui_core::update::boot_state::{BootState, FirmwareStatus};
+use pico_ui_core::update::layout::PICO_LAYOUT;
+use pico_ui_core::update::receiver::UpdateReceiver;
+use pico_ui_core::update::swap::{prepare_boot, BootAction};
+
+use commands::Command;
+use framebuffer::Framebuffer;
+use transport::Event;
+
+/// Time to finish pending key events after piped stdin is over
+const INPUT_CLOSED_GRACE_US: u64 = 500_000;
+/// Flash up to the end of the update partition, the bootloader and settings areas are not used
+const FIRMWARE_FLASH_BYTES: usize = (PICO_LAYOUT.update + PICO_LAYOUT.partition_bytes) as usize;
+
+struct Options {
+    pty: bool,
+    frames_dir: Option<PathBuf>,
+    verbose: bool,
+    /// Reported to the Pi on first contact, lets Pi applications test their reboot handling
+    reset_reason: ResetReason,
+    /// Settings flash image, loaded on start and saved on every change
+    flash_file: Option<PathBuf>,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options { pty: false, frames_dir: None, verbose: false, reset_reason: ResetReason::PowerOn, flash_file: None };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pty" => options.pty = true,
+            "--frames" => options.frames_dir = args.next().map(PathBuf::from),
+            "--verbose" | "-v" => options.verbose = true,
+            "--flash" => options.flash_file = args.next().map(PathBuf::from),
+            "--reset-reason" => {
+                let value = args.next().unwrap_or_default();
+                let (code, core) = match value.split_once(':') {
+                    Some((code, core)) => (code, core.parse::<u8>().ok()),
+                    None => (value.as_str(), None),
+                };
+                options.reset_reason = match ResetReason::from_code(code, core) {
+                    Some(reset_reason) => reset_reason,
+                    None => {
+                        eprintln!("unknown reset reason: {}", value);
+                        process::exit(2);
+                    }
+                };
+            }
+            "--help" | "-h" => {
+                println!("usage: pico-ui-simulator [--pty] [--frames <dir>] [--verbose] [--reset-reason <reason>] [--flash <file>]");
+                println!();
+                println!("  --pty            uart on a pseudo terminal, stdin for simulator commands");
+                println!("  --frames <dir>   save every redrawn frame as png");
+                println!("  --verbose        log led, backlight and buzzer changes to stderr");
+                println!("  --reset-reason   power_on (default), reset_pin, debugger, software or watchdog[:<core>]");
+                println!("  --flash <file>   keep settings in this file, in memory only by default");
+                println!();
+                println!("commands: key <u|d|l|r|o> [hold_ms], dump <file.png|file.ppm>, panic [message], reset, quit");
+                println!("in stdio mode prefix commands with `{}`", transport::COMMAND_PREFIX.trim());
+                process::exit(0);
+            }
+            other => {
+                eprintln!("unknown argument: {}", other);
+                process::exit(2);
+            }
+        }
+    }
+    options
+}
+
+/// Settings area of the emulated flash, erased when there is no file yet
+fn load_flash(path: Option<&PathBuf>) -> MemFlash<SETTINGS_AREA_BYTES> {
+    let image = match path.map(fs::read) {
+        Some(Ok(image)) => image,
+        Some(Err(_)) | None => return MemFlash::new(),
+    };
+    match image.try_into() {
+        Ok(image) => MemFlash::from_bytes(image),
+        Err(_) => {
+            eprintln!("[sim] flash file must be {} bytes, starting erased", SETTINGS_AREA_BYTES);
+            MemFlash::new()
+        }
+    }
+}
+
+/// Bootloader part of a reboot: install an applied update or roll back one which did not confirm.
+/// The simulated firmware stays the same, only the update state changes.
+fn run_bootloader<F: Flash>(flash: &mut F) -> FirmwareStatus
+where
+    F::Error: std::fmt::Debug,
+{
+    match prepare_boot(flash, &PICO_LAYOUT) {
+        Ok(BootAction::Trial) => eprintln!("[sim] bootloader: update installed, running it on trial"),
+        Ok(BootAction::Run) => {}
+        Err(err) => eprintln!("[sim] bootloader failed: {:?}", err),
+    }
+    let status = BootState::read(flash, &PICO_LAYOUT).map(|state| state.status()).unwrap_or(FirmwareStatus::Idle);
+    if status == FirmwareStatus::RolledBack {
+        eprintln!("[sim] bootloader: update was not confirmed, rolled back");
+    }
+    status
+}
+
+fn main() {
+    let options = parse_options();
+    let (events_tx, events) = channel();
+
+    // keep pty slave open for the whole run
+    let (mut uart, _pty_slave) = if options.pty {
+        match transport::pty(events_tx) {
+            Ok((uart, path, slave)) => {
+                eprintln!("[sim] uart is {}", path.display());
+                (uart, Some(slave))
+            }
+            Err(err) => {
+                eprintln!("[sim] can not open pty: {}", err);
+                process::exit(1);
+            }
+        }
+    } else {
+        (transport::stdio(events_tx), None)
+    };
+
+    let started = Instant::now();
+    let now_us = || started.elapsed().as_micros() as u64 + 1;
+
+    let mut framebuffer = Framebuffer::new();
+    let mut receiver = LineReceiver::new();
+    let mut settings_store = match SettingsStore::mount(load_flash(options.flash_file.as_ref())) {
+        Ok(store) => store,
+        Err(err) => {
+            eprintln!("[sim] can not mount settings: {}", err);
+            process::exit(1);
+        }
+    };
+    let settings = Settings::load(&mut settings_store).unwrap_or_default();
+    // boxed, 2 MB is too much for the stack. Kept in memory only, every run starts without update.
+    let mut firmware_flash = Box::new(MemFlash::<FIRMWARE_FLASH_BYTES>::new());
+    let mut updater = UpdateReceiver::new(PICO_LAYOUT);
+    let mut device_state = DeviceState::new(now_us(), framebuffer.size(), options.reset_reason, None, settings, FirmwareStatus::Idle);
+    let mut pressed: Option<(KeyboardCodes, u64)> = None;
+    // left and right held together until then
+    let mut chord_until: Option<u64> = None;
+    let mut renderer = ScreenRenderer::new();
+    let mut frame_number = 0u32;
+    let mut last_backlight = None;
+    let mut last_leds = None;
+    let mut input_closed_at: Option<u64> = None;
+    // crash screen is up, the device reboots at the time with the record like the firmware does
+    let mut crashed: Option<(u64, CrashRecord)> = None;
+    let mut reboot: Option<(ResetReason, Option<CrashRecord>)> = None;
+
+    loop {
+        let loop_started = Instant::now();
+        let now = now_us();
+        let now_ms = now / 1_000;
+        let mut quit = false;
+
+        loop {
+            match events.try_recv() {
+                // nobody listens while the crash screen is up
+                Ok(Event::Uart(_)) if crashed.is_some() => {}
+                Ok(Event::Uart(byte)) => match receiver.push_byte(byte) {
+                    Ok(Some(line)) => device_state.handle_line(line, now),
+                    Ok(None) => {}
+                    Err(err) => {
+                        eprintln!("[sim] uart receive error: {:?}", err);
+                        device_state.diagnostics.on_receive_error(err);
+                    }
+                },
+                Ok(Event::Command(Command::Key(code, hold_ms))) => {
+                    pressed = Some((code, now + hold_ms * 1_000));
+                }
+                Ok(Event::Command(Command::Chord(hold_ms))) => {
+                    chord_until = Some(now + hold_ms * 1_000);
+                }
+                Ok(Event::Command(Command::Dump(path))) => {
+                    match framebuffer.save(&path) {
+                        Ok(_) => eprintln!("[sim] frame saved to {}", path.display()),
+                        Err(err) => eprintln!("[sim] can not save {}: {}", path.display(), err),
+                    }
+                }
+                Ok(Event::Command(Command::Panic(message))) => {
+                    let record = CrashRecord::new(0, file!(), line!(), format_args!("{}", message));
+                    eprintln!("[sim] {}", record);
+                    let frame = Pico2PiMessage { crash: Some(record.clone()), ..Default::default() }.to_frame();
+                    if uart.write_all(frame.as_bytes()).and_then(|_| uart.flush()).is_err() {
+                        eprintln!("[sim] uart write failed");
+                    }
+                    _ = draw_crash(&mut framebuffer, &record);
+                    crashed = Some((now + CRASH_SCREEN_MS as u64 * 1_000, record));
+                }
+                Ok(Event::Command(Command::Reset)) if crashed.is_none() => reboot = Some((ResetReason::ResetPin, None)),
+                Ok(Event::Command(Command::Reset)) => {}
+                Ok(Event::Command(Command::Quit)) => quit = true,
+                Ok(Event::InputClosed) => input_closed_at = Some(now),
+                Err(_) => break,
+            }
+        }
+        // piped stdin is over, let pending key events go out and stop
+        if let Some(closed_at) = input_closed_at {
+            if !options.pty && pressed.is_none() && chord_until.is_none() && now - closed_at > INPUT_CLOSED_GRACE_US {
+                quit = true;
+            }
+        }
+
+        if let Some((reboot_at, _)) = &crashed {
+            if now < *reboot_at {
+                if quit {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+            let (_, record) = crashed.take().unwrap();
+            eprintln!("[sim] rebooting after panic");
+            reboot = Some((ResetReason::Software, Some(record)));
+        }
+        if let Some((reset_reason, crash)) = reboot.take() {
+            let firmware = run_bootloader(&mut *firmware_flash);
+            let settings = Settings::load(&mut settings_store).unwrap_or_default();
+            device_state = DeviceState::new(now, framebuffer.size(), reset_reason, crash, settings, firmware);
+            updater = UpdateReceiver::new(PICO_LAYOUT);
+            receiver = LineReceiver::new();
+            renderer = ScreenRenderer::new();
+            pressed = None;
+            chord_until = None;
+        }
+
+        if let Some((_, release_at)) = pressed {
+            if now >= release_at {
+                pressed = None;
+            }
+        }
+        if chord_until.is_some_and(|release_at| now >= release_at) {
+            chord_until = None;
+        }
+        let mut settings_saved = false;
+        while let Some(key) = device_state.take_unsaved_setting() {
+            let saved = device_state.settings.save(&mut settings_store, key);
+            if let Err(err) = &saved {
+                eprintln!("[sim] setting {} not saved: {}", key.as_str(), err);
+            }
+            device_state.on_setting_saved(key, saved.is_ok());
+            settings_saved |= saved.is_ok();
+        }
+        if let (true, Some(path)) = (settings_saved, &options.flash_file) {
+            if let Err(err) = fs::write(path, settings_store.flash().as_bytes()) {
+                eprintln!("[sim] can not save {}: {}", path.display(), err);
+            }
+        }
+
+        while let Some(command) = device_state.take_firmware_command() {
+            // there is no real image running, the swap exchanges the whole partition
+            let result = updater.handle(&mut *firmware_flash, command, 0);
+            device_state.on_firmware_result(result);
+        }
+
+        // like the firmware, the chord is not a key press for the Pi
+        let chord = chord_until.is_some();
+        device_state.on_debug_chord(chord, now);
+        device_state.on_keys(pressed.map(|(code, _)| code).filter(|_| !chord), now);
+        device_state.update(now);
+
+        if let Some(frame) = device_state.poll_outgoing(now) {
+            if uart.write_all(frame.as_bytes()).and_then(|_| uart.flush()).is_err() {
+                eprintln!("[sim] uart write failed");
+            }
+        }
+        // a pty has no line rate, the change is only logged
+        match device_state.take_uart_change(now) {
+            Some(UartChange::Try(format)) => eprintln!("[sim] uart now {} {}", format.baud, format.parity.as_str()),
+            Some(UartChange::Fallback(format)) => {
+                eprintln!("[sim] uart not confirmed, back to {} {}", format.baud, format.parity.as_str());
+            }
+            None => {}
+        }
+        device_state.diagnostics.record_loop(loop_started.elapsed().as_micros() as u32);
+        if device_state.wants_reboot() {
+            eprintln!("[sim] rebooting to apply the firmware update");
+            reboot = Some((ResetReason::Software, None));
+        }
+
+        let leds = device_state.led_controller.levels(now_ms);
+        let backlight = device_state.backlight_policy.level(now_ms);
+        if let Some(output) = device_state.buzzer_player.update(now_ms) {
+            if options.verbose {
+                eprintln!("[sim] buzzer {:?}", output);
+            }
+        }
+        if options.verbose {
+            if last_leds != Some(leds) && leds.iter().all(|x| *x == 0 || *x == 255) {
+                eprintln!("[sim] leds green/blue1/blue2/red {:?}", leds);
+            }
+            if last_backlight != Some(backlight) && (backlight == 0 || backlight == device_state.backlight_policy.brightness()) {
+                eprintln!("[sim] backlight {}%", backlight);
+            }
+        }
+        last_leds = Some(leds);
+        last_backlight = Some(backlight);
+
+        let redraw_started = Instant::now();
+        let redrawn = renderer
+            .render(
+                &mut framebuffer,
+                &device_state.screen(),
+                now_ms,
+            )
+            .unwrap_or(false);
+        if redrawn {
+            device_state.diagnostics.record_redraw(redraw_started.elapsed().as_micros() as u32);
+            if let Some(frames_dir) = &options.frames_dir {
+                frame_number += 1;
+                let path = frames_dir.join(format!("frame_{:05}.png", frame_number));
+                if let Err(err) = framebuffer.save(&path) {
+                    eprintln!("[sim] can not save {}: {}", path.display(), err);
+                }
+            }
+        }
+
+        if quit {
+            break;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
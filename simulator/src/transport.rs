@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use nix::pty::openpty;
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+use nix::unistd::ttyname;
+
+use commands::{parse_command, Command};
+
+/// In stdio mode lines starting with this prefix are simulator commands, not uart data
+pub const COMMAND_PREFIX: &str = "#sim ";
+
+pub enum Event {
+    Uart(u8),
+    Command(Command),
+    InputClosed,
+}
+
+/// "UART" of the simulated device: Pi writes to stdin or to the pty slave, device answers here
+pub type UartWriter = Box<dyn Write + Send>;
+
+/// UART over stdin/stdout. Each stdin line is one frame, `\r\n` is added like the Pi does.
+pub fn stdio(events: Sender<Event>) -> UartWriter {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let line = line.trim_end_matches('\r');
+            if let Some(command) = line.strip_prefix(COMMAND_PREFIX) {
+                send_command(&events, command);
+                continue;
+            }
+            for byte in line.bytes().chain(b"\r\n".iter().cloned()) {
+                if events.send(Event::Uart(byte)).is_err() {
+                    return;
+                }
+            }
+        }
+        _ = events.send(Event::InputClosed);
+    });
+    Box::new(io::stdout())
+}
+
+/// UART over a pseudo terminal, the Pi app opens returned path as if it was `/dev/ttyAMA0`.
+/// stdin is used for simulator commands only.
+pub fn pty(events: Sender<Event>) -> io::Result<(UartWriter, PathBuf, OwnedFd)> {
+    let pty = openpty(None, None).map_err(io::Error::from)?;
+    let mut termios = tcgetattr(&pty.slave).map_err(io::Error::from)?;
+    cfmakeraw(&mut termios);
+    tcsetattr(&pty.slave, SetArg::TCSANOW, &termios).map_err(io::Error::from)?;
+    let slave_path = ttyname(&pty.slave).map_err(io::Error::from)?;
+
+    let master = File::from(pty.master);
+    let mut master_reader = master.try_clone()?;
+    let uart_events = events.clone();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 256];
+        loop {
+            match master_reader.read(&mut buffer) {
+                Ok(0) => continue,
+                Ok(count) => {
+                    for byte in &buffer[..count] {
+                        if uart_events.send(Event::Uart(*byte)).is_err() {
+                            return;
+                        }
+                    }
+                }
+                // EIO while nobody has the slave open, keep waiting for the Pi app
+                Err(_) => thread::sleep(std::time::Duration::from_millis(50)),
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) => send_command(&events, line.trim()),
+                Err(_) => break,
+            }
+        }
+        _ = events.send(Event::InputClosed);
+    });
+
+    // slave fd is returned to keep the pty alive between Pi app runs
+    Ok((Box::new(master), slave_path, pty.slave))
+}
+
+fn send_command(events: &Sender<Event>, line: &str) {
+    if line.is_empty() {
+        return;
+    }
+    match parse_command(line) {
+        Ok(command) => {
+            _ = events.send(Event::Command(command));
+        }
+        Err(err) => eprintln!("[sim] {}", err),
+    }
+}
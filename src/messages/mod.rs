@@ -1,2 +0,0 @@
-pub mod pi_2_pico_message;
-pub mod pi_2_pico_test;
\ No newline at end of file
@@ -1,2 +0,0 @@
-pub mod itoa;
-pub mod string_to_kv;